@@ -0,0 +1,731 @@
+# English messages of cyber-toolkit. This catalog is compiled in and has every message; other
+# languages go into /usr/share/cyber-toolkit/locales/<lang>/cyber-toolkit.ftl and may translate
+# any subset of it. Lists and paths are passed preformatted.
+
+## Main command
+
+executing-add = Executing ADD/SYNC command for roles: { $roles }
+executing-remove = Executing REMOVE command for roles: { $roles }
+executing-update = Executing UPDATE command; the configured roles become exactly: { $roles }
+executing-update-keep = Executing UPDATE command with --keep-existing; adding roles: { $roles }
+operation-finished = {"\u000A"}--- Operation finished ---
+roles-file-written = Successfully wrote roles to { $path }
+roles-file-merged = Note: { $path } was changed by another session meanwhile (roles added there: { $added }, removed there: { $removed }); merged those changes with this one instead of overwriting them.
+roles-expire = Roles { $roles } expire at { $until }; run `cyber-toolkit expire` to remove expired roles.
+
+## Fetching roles
+
+fetching-role = Fetching tool list from { $location }...
+role-warning = Warning: { $warning }
+role-skipped-entries = Skipped { $count } entries in { $location } that don't apply to this host:
+role-skipped-entry = {"  "}- { $tool } ({ $reason })
+role-no-tools = No tools found in { $location }.
+role-tools = Found tools in { $location }: { $tools }
+role-failed = Could not fetch role { $role }: { $error }. Skipping this file.
+role-not-cached = role { $role } isn't cached; run once without --offline to fetch it
+role-cache-location = the cache ({ $role })
+role-cache-write-failed = Warning: Could not cache role file { $role }: { $error }
+offline-roles-missing = Offline mode: roles { $roles } aren't cached. Run once without --offline to fetch them.
+role-unknown = Role { $role } doesn't exist in the repository.
+suggest-role-invocation = {"  "}Did you mean the role { $role }? { $invocation }
+suggest-role = {"  "}Did you mean the role { $role }?
+suggest-subcommand = {"  "}Did you mean the { $subcommand } subcommand? cyber-toolkit { $subcommand }
+suggest-configured = {"  "}{ $role } isn't configured; similar configured roles: { $similar }
+add-unknown-roles = Roles { $roles } don't exist in the repository, so nothing was changed. See `cyber-toolkit --list-all` for the available roles.
+
+## Adding roles
+
+roles-config-unreadable = Warning: Could not read existing roles config: { $error }. Starting with an empty list.
+add-fetching = {"\u000A"}Fetching all tools for currently configured roles to ensure system is up to date...
+add-frozen-changed = The definitions of frozen roles { $roles } changed upstream. Review them (e.g. with `cyber-toolkit changelog`) and rerun with --refreeze to accept the changes.
+add-up-to-date = {"\u000A"}Already up to date: all { $count } tools of the configured roles are installed.
+add-total-tools = {"\u000A"}Total unique tools to install/update from all configured roles: { $tools }
+add-user-mode-skipped = User mode: skipping { $count } native packages that need root: { $packages }
+add-no-tools = No tools to install/update based on the current configuration.
+menus-updated = Updated desktop menus for { $count } roles.
+menus-update-failed = Warning: Could not update desktop menus: { $error }
+dotfiles-conflicts = Warning: { $count } dotfiles of role { $role } were not deployed due to conflicts: { $files }
+dotfiles-deploy-failed = Warning: Could not deploy dotfiles for role { $role }: { $error }
+python-tools-available = Python tools of role { $role } available: { $tools }
+venv-setup-failed = Warning: Could not set up the Python virtualenv for role { $role }: { $error }
+running-hooks = {"\u000A"}Running data refresh hooks for configured roles...
+add-failed-new-roles = { $count } tools could not be installed, so roles { $roles } were not added to roles.cnf. See last-failures.json in `cyber-toolkit state export` for details, and rerun the command once the cause is fixed.
+add-failed = { $count } tools could not be installed; all other steps were completed. See last-failures.json in `cyber-toolkit state export` for details, and run `cyber-toolkit retry-failed` to retry just those.
+
+## Updating roles
+
+update-removals = {"\u000A"}!!! --update removes { $count ->
+        [one] this configured role, which isn't listed,
+       *[other] these { $count } configured roles, which aren't listed,
+    } with the tools no remaining role needs:
+update-removal = {"  "}- { $role }
+update-confirm = Remove them and keep only the listed roles?
+update-rolled-back = Removing the tools of { $roles } failed, so the configured roles were set back to those from before the update.
+update-cancelled = Nothing was changed. Use --keep-existing to add roles without removing the others.
+update-unconfirmed = --update would remove the configured roles { $roles }, which needs a confirmation. Pass --yes to confirm it, or --keep-existing to only add roles.
+
+## Removing roles
+
+remove-config-unreadable = Warning: Could not read existing roles config: { $error }. Assuming no roles were configured.
+remove-nothing-configured = No roles currently configured. Nothing to remove.
+remove-not-configured = None of the specified roles to remove were found in the current configuration.
+remove-system-roles = Roles { $roles } are configured system-wide in { $path } for every user, so they can't be removed here. An administrator can remove them with --system.
+remove-other-user = Keeping { $tool }: { $user } installed it, so it belongs to their roles.
+remove-user-installed = Keeping { $tool }: you had installed it yourself before a role listed it.
+remove-user-installed-hint = { $count ->
+        [one] It stays
+       *[other] They stay
+    } installed; pass --remove-user-installed to uninstall { $count ->
+        [one] it
+       *[other] them
+    } too.
+remove-keeping = Roles to keep: { $roles }
+remove-removing = Roles being removed: { $roles }
+remove-tools = {"\u000A"}Tools to uninstall (unique to removed roles): { $tools }
+remove-user-mode-native = User mode: leaving native packages installed.
+remove-no-tools = No tools to uninstall. Either removed roles had no unique tools or no tools at all.
+remove-timed-out = The package manager timed out, so some packages may still be installed. Roles { $roles } stay configured; rerun the removal to finish it.
+menus-remove-failed = Warning: Could not remove desktop menus: { $error }
+firejail-remove-failed = Warning: Could not remove firejail confinement for role { $role }: { $error }
+dotfiles-remove-failed = Warning: Could not remove dotfiles for role { $role }: { $error }
+venv-remove-failed = Warning: Could not remove the Python virtualenv for role { $role }: { $error }
+remove-done = Configuration updated. Roles { $roles } removed.
+
+## Setup wizard
+
+wizard-welcome = Welcome to cyber-toolkit! No roles are configured yet, so let's set things up.{"\u000A"}
+wizard-mode-prompt = How should tools be installed?
+wizard-mode-system = System packages (pacman, asks for your password) plus containers, go tools and Python virtualenvs
+wizard-mode-user = User mode: only containers, go tools and Python virtualenvs (no root needed)
+wizard-helper-prompt = Install packages through an AUR helper?
+wizard-helper-none = None (pacman only)
+wizard-fetching = {"\u000A"}Fetching the available roles...
+wizard-no-roles = The repository lists no roles.
+wizard-roles-prompt = Pick your roles (space to select, enter to confirm)
+wizard-no-selection = No roles selected. Run cyber-toolkit <role> later to add one.
+wizard-resolving = {"\u000A"}Resolving the install plan...
+wizard-plan-roles = {"\u000A"}Roles: { $roles }
+wizard-plan-native = {"  "}System packages: { $count } (via { $via })
+wizard-plan-native-skipped = {"  "}System packages: { $count } (skipped in user mode)
+wizard-plan-isolated = {"  "}Containers:      { $count }
+wizard-plan-go = {"  "}Go tools:        { $count }
+wizard-confirm = Install now?
+nothing-changed = Nothing was changed.
+
+## Containers (isolated:)
+
+isolated-spec-invalid = expected isolated:<name>=<image>
+isolated-image-missing-offline = Container image { $image } for { $tool } isn't available locally (offline mode).
+isolated-pulling = Pulling container image { $image } for { $tool }...
+isolated-pull-failed = Failed to pull { $image }. Exit code: { $code }
+isolated-wrapper-foreign = Not overwriting { $path }: it was not generated by cyber-toolkit.
+isolated-wrapper-created = Created wrapper { $path }.
+isolated-wrapper-removed = Removed wrapper { $path }.
+isolated-wrapper-kept = Leaving { $path } in place: it was not generated by cyber-toolkit.
+isolated-rmi-failed = Warning: Could not remove container image { $image }.
+
+## Fleet mode
+
+fleet-percentage-invalid = expected a percentage from 0% to 100%, got `{ $value }`
+fleet-threshold-invalid = expected a number of hosts or a percentage, got `{ $value }`
+fleet-hosts-file-invalid = Invalid hosts file { $path }: { $error }
+fleet-scp-failed = scp exited with code { $code }
+fleet-address-invalid = Invalid address `{ $address }` of host { $host } in the hosts file: it must not be empty or start with `-`.
+fleet-mktemp-failed = mktemp -d printed no directory: { $output }
+fleet-checksum-mismatch = the copy at { $path } has the SHA-256 { $actual } instead of { $expected }
+fleet-cleanup-failed = Warning: Could not remove { $dir } from { $host }: { $error }
+fleet-command-failed = `{ $command }` exited with code { $code }
+fleet-copying = [{ $host }] Copying binary to { $target }...
+fleet-running = [{ $host }] Running on { $address }: { $command }
+fleet-remote-failed = remote command failed: { $error }
+fleet-verifying = [{ $host }] Verifying: { $command }
+fleet-verify-failed = verification failed: { $error }
+fleet-smoke-test = [{ $host }] Smoke test: { $command }
+fleet-smoke-test-failed = smoke test failed: { $error }
+fleet-host-verified = roles applied and verified
+fleet-host-applied = roles applied
+fleet-canary-tag-unknown = No host in the hosts file has the canary tag `{ $tag }`.
+fleet-canary-stage = {"\u000A"}*** Canary stage: { $count } of { $total } hosts ***
+fleet-canary-passed = {"\u000A"}*** Canary stage passed; rolling out to the other { $count } hosts ***
+fleet-host-header = {"\u000A"}=== { $host } ({ $address }) ===
+fleet-aborted-canary = rollout aborted: { $count } canary host(s) failed
+fleet-aborted = rollout aborted after { $count } failed host(s)
+fleet-canary-failures-allowed = { $count } canary host(s) failed, within the threshold of { $allowed }.
+
+## Purging data of removed tools
+
+home-not-found = Home directory not found.
+purge-path-not-home-relative = Purge path { $path } must start with ~/
+purge-path-outside-home = Purge path { $path } must name something inside the home directory
+purge-path-ignored = Warning: Ignoring purge path of role { $role }: { $error }
+purge-nothing = Nothing to purge: the removed tools left no known data behind.
+purge-list = Data left behind by the removed tools:
+purge-confirm = Delete { $path } ({ $size })?
+purge-no-terminal = Warning: Not purging anything without a terminal to confirm it; rerun with --force or --yes to purge these paths.
+purge-done = Purged { $path }.
+purge-failed = Warning: Could not purge { $path }: { $error }
+
+## Backends
+
+groups-unsupported = Warning: Skipping { $groups }: pacman groups are only supported on Linux
+groups-query-failed = Warning: Could not list pacman group members: { $error }
+group-unknown = Warning: Skipping { $group }: no such pacman group
+entry-skipped = Warning: Skipping { $entry }: { $error }
+choco-name-invalid = invalid chocolatey package name
+
+## Windows
+
+winget-installing = Installing { $package } with winget...
+winget-install-failed = winget install { $package } failed. Exit code: { $code }
+winget-failed = winget could not install: { $packages }
+winget-uninstalling = Uninstalling { $package } with winget...
+winget-uninstall-failed = winget uninstall { $package } failed. Exit code: { $code }
+executing-command = Attempting to execute: { $command }
+choco-failed = choco { $operation } failed. Exit code: { $code }
+
+## Go tools (go:)
+
+go-spec-invalid = expected go:<package>[@<version>]
+go-installing = Running go install { $target }...
+go-install-failed = go install { $target } failed. Exit code: { $code }
+go-not-run = Could not run go install { $target }: { $error }
+go-installed = Installed { $binary } ({ $version }).
+go-version-unknown = unknown version
+path-removed = Removed { $path }.
+
+## Syncing the configuration
+
+git-failed-code = git { $command } failed with exit code { $code }
+git-failed = git { $command } failed: { $error }
+sync-no-remote = No sync remote configured. Set `remote` in the [sync] section of { $path }.
+sync-init = Initializing git repository in { $path }...
+sync-committed = Committed local changes in { $path }.
+sync-nothing-to-commit = No local changes to commit.
+sync-pushed = Pushed roles configuration to { $remote }.
+sync-rebase-conflict = { $error }. Resolve the conflicts in { $path } and run `git rebase --continue`.
+sync-pulled = Pulled roles configuration from { $remote }.
+
+## Wordlists
+
+wordlists-fetch-failed = Failed to fetch { $url }: HTTP Status { $status }
+wordlists-line-invalid = Invalid line { $line_number } in wordlist set { $set }: { $line }
+wordlists-file-name-invalid = Invalid file name { $file } in wordlist set { $set }
+wordlists-set-name-invalid = Invalid wordlist set name { $set }: it must not contain `/` or start with `.`
+bundle-role-name-invalid = Invalid role name { $role } in the bundle: it must not contain `/` or `\` or start with `.`
+wordlists-file-present = {"  "}{ $file } already present.
+wordlists-downloading = {"  "}Downloading { $file } from { $url }...
+wordlists-resuming = {"    "}Resuming at { $bytes } bytes.
+download-no-answer = { $url } did not answer within { $seconds } seconds.
+wordlists-download-failed = Failed to download { $url }: HTTP Status { $status }
+download-stalled = Download of { $url } stalled: no data for { $seconds } seconds.
+wordlists-checksum-mismatch = Checksum mismatch for { $file }: expected { $expected }, got { $actual }. The partial download was discarded.
+wordlists-links-failed = Updating links in { $dir } failed. Exit code: { $code }
+
+## Python virtualenvs
+
+step-failed = { $step } failed. Exit code: { $code }
+venv-creating = Creating Python virtualenv for role { $role } in { $path }...
+venv-create-step = Creating the virtualenv
+venv-entry-point-missing = Warning: Entry point { $entry_point } was not installed in the virtualenv of role { $role }.
+venv-shim-exists = Not creating shim { $path }: the file already exists.
+venv-removed = Removed Python virtualenv of role { $role }.
+
+## Mirrors
+
+mirrors-no-reflector = Warning: Mirror ranking is enabled but reflector is not installed (pacman -S reflector).
+mirrors-ranking = About { $size } to download; ranking mirrors with reflector first...
+mirrors-updated = Mirrorlist updated.
+mirrors-reflector-failed = Warning: reflector failed (exit code { $code }); keeping the current mirrorlist.
+mirrors-reflector-not-run = Warning: Could not run reflector: { $error }
+
+## Data refresh hooks
+
+hook-skipped = Skipping data refresh for { $tool } (not installed).
+hook-running = Refreshing data for { $tool }: { $command }
+hook-failed = Data refresh for { $tool } failed. Exit code: { $code }
+hook-not-started = Data refresh for { $tool } could not be started: { $error }
+
+## Firejail confinement
+
+config-dir-not-found = Config directory not found.
+harden-profile-fetch-failed = Failed to fetch profile from { $url }: HTTP Status { $status }
+harden-no-firejail = firejail is not installed ({ $path } not found).
+harden-not-installed = Skipping { $tool } (not installed).
+harden-link-exists = Skipping { $tool }: { $path } already exists and is not a firejail link.
+harden-no-profile = Skipping { $tool }: no firejail profile in the repository or in { $dir }.
+harden-confined-custom = Confined { $tool } with the cyber-toolkit profile.
+harden-confined-bundled = Confined { $tool } with the firejail bundled profile.
+harden-removed = Removed firejail confinement for { $tool }.
+
+## Dotfiles
+
+dotfile-target-not-home-relative = Dotfile target { $target } must start with ~/
+dotfile-target-outside-home = Dotfile target { $target } must stay inside the home directory
+dotfile-fetch-failed = Failed to fetch dotfile from { $url }: HTTP Status { $status }
+dotfile-conflict = Conflict: { $path } exists and differs from the role's version. Leaving it unchanged.
+dotfile-deployed = Deployed { $path } for role { $role }.
+dotfile-modified = Keeping { $path }: it was modified after deployment.
+
+## State database
+
+state-db-too-new = The state database { $path } has schema version { $version }, but this version of cyber-toolkit only knows { $known }. Please upgrade cyber-toolkit.
+state-db-import-cleanup-failed = Warning: Could not remove { $path } after importing it into the state database: { $error }
+state-import-too-new = The state was exported with schema version { $version }, which this version of cyber-toolkit doesn't know.
+state-import-unknown-document = Unknown state document { $name }; was the state exported by a newer version?
+
+## pacman queries
+
+pacman-query-failed = pacman { $flag } failed: { $error }
+pacman-cache-save-failed = Warning: Could not save the pacman query cache: { $error }
+pacman-bandwidth-limit-invalid = Invalid bandwidth_limit { $limit }; use e.g. "2M" or "500K".
+
+## Role metadata
+
+metadata-not-cached = The metadata of role { $role } isn't cached; run once without --offline to fetch it.
+metadata-cache-failed = Warning: Could not cache the metadata of role { $role }: { $error }
+metadata-fetch-failed = Failed to fetch role metadata from { $url }: HTTP Status { $status }
+metadata-format-invalid = invalid format { $format }
+metadata-invalid = Invalid metadata of role { $role }: { $error }
+metadata-format-too-new = Metadata of role { $role } uses format { $format }, but this version of cyber-toolkit only understands format { $known }; update cyber-toolkit to use it
+metadata-fetch-warning = Warning: Could not fetch metadata for role { $role }: { $error }
+
+## Settings
+
+data-dir-not-found = Data directory not found.
+settings-invalid = Invalid settings in { $path }: { $error }
+settings-toml-invalid = Invalid TOML in { $path }: { $error }
+settings-key-invalid = Invalid key `{ $key }`; use `section.key`, e.g. `pacman.aur_helper`.
+settings-not-a-section = `{ $section }` is not a section.
+
+## Engagements
+
+engage-spec-invalid = Expected <platform>:<tags> (e.g. htb:web,ad), got `{ $spec }`. Platforms: { $platforms }
+engage-platform-unknown = Unknown platform `{ $platform }`. Platforms: { $platforms }
+engage-no-tags = No tags given in `{ $spec }`, e.g. { $platform }:web,ad
+engage-fetch-failed = Failed to fetch the engagement tags from { $url }: HTTP Status { $status }
+engage-tags-invalid = Invalid engagement tags in { $url }: { $error }
+engage-tag-unknown = Warning: Unknown { $platform } tag `{ $tag }`, skipped.
+engage-tag-unknown-similar = Warning: Unknown { $platform } tag `{ $tag }`, skipped. Did you mean { $similar }?
+engage-preset-unknown = Engagement tag `{ $tag }` refers to the unknown preset `{ $preset }`.
+engage-no-known-tags = None of the given tags is known, so there is nothing to install.
+
+## Removal safeguards
+
+removal-guard-too-many = { $count } packages would be uninstalled (more than { $limit })
+removal-guard-protected = protected packages would be uninstalled: { $packages }
+removal-guard-forced = Warning: { $reason }. Continuing because of --force.
+removal-guard-packages = Packages to uninstall: { $packages }
+removal-guard-confirm = Careful: { $reason }. Remove them?
+removal-guard-cancelled = Removal cancelled; nothing was uninstalled.
+removal-guard-refused = Refusing to remove: { $reason }. Rerun with --force to remove them anyway.
+
+## Conflicting packages
+
+conflicts-unresolved = Conflicting packages in the install plan:
+    {"  "}{ $conflicts }
+    Run interactively (without --plain) to choose between them, or remove one of the roles that need them.
+conflicts-found = The install plan contains conflicting packages:
+conflicts-replace = Install { $package } and let pacman replace { $other }
+conflicts-skip = Skip { $package }
+conflicts-choose = Install { $package }, skip { $other }
+conflicts-abort = Abort
+conflicts-aborted = Aborted because of conflicting packages; nothing was installed.
+conflicts-skipped = Skipping conflicting packages: { $packages }
+conflict-installed = { $package } conflicts with the installed package { $other }
+conflict-planned = { $package } and { $other } conflict with each other
+
+## Run notifications
+
+notify-outcome-failed = failed
+notify-outcome-failures = finished with failures
+notify-outcome-succeeded = succeeded
+notify-subject = [cyber-toolkit] { $host }: { $command } { $outcome }
+notify-text-header = cyber-toolkit { $command } on { $host } (user { $user })
+notify-text-roles = Roles: { $roles }
+notify-text-times = Started: { $started }
+    Finished: { $finished } ({ $seconds }s)
+notify-text-failed = Result: failed: { $error }
+notify-text-succeeded = Result: succeeded
+notify-text-configured = Configured roles: { $roles }
+notify-text-failed-tools = {"\u000A"}{ $count } tool(s) failed to install:
+notify-text-attachment = {"\u000A"}The full summary is attached as { $file }.
+notify-serialize-failed = Warning: Could not serialize the run summary: { $error }
+notify-webhook-failed = Warning: Could not send the run summary to { $url }: { $error }
+notify-mail-failed = Warning: Could not mail the run summary to { $recipients }: { $error }
+notify-sendmail-missing = could not run sendmail ({ $error }); install a mail transfer agent or set notify.smtp_url
+notify-no-stdin = { $program } has no stdin
+notify-program-failed = { $program } failed. Exit code: { $code }
+notify-smtp-password-missing = notify.smtp_user is set, but { $variable } isn't
+notify-curl-missing = could not run curl: { $error }
+
+## Inventory
+
+inventory-title = Tool inventory
+inventory-generated = Installed tools as of { $generated }.
+inventory-generated-host = Installed tools on `{ $host }` as of { $generated }.
+inventory-tool = Tool
+inventory-version = Version
+inventory-source = Source
+inventory-roles = Roles
+inventory-installed = Installed
+
+## Tool ownership
+
+attribution-toolkit = installed by cyber-toolkit
+attribution-user = installed by the user
+attribution-dependency = installed as a dependency
+attribution-not-installed = not installed
+
+## Batches
+
+batch-json-invalid = Invalid JSON batch: { $error }
+batch-no-roles = Operation { $operation } of the batch names no roles.
+batch-operation-unknown = Line { $line } of the batch: unknown operation { $operation } (expected add, remove or set).
+
+## Presets
+
+presets-fetch-failed = Failed to fetch presets from { $url }: HTTP Status { $status }
+presets-invalid = Invalid presets in { $url }: { $error }
+preset-unknown = Unknown preset { $preset }. Available presets: { $presets }
+
+## Offline bundles
+
+bundle-download-failed = pacman -Sw failed with exit code { $code }.
+bundle-pack-failed = tar failed to write { $path } (exit code { $code }).
+bundle-unpack-failed = tar failed to unpack { $path } (exit code { $code }).
+bundle-invalid = { $path } is not a cyber-toolkit bundle: { $manifest } is missing ({ $error })
+bundle-package-corrupted = Package file { $file } in the bundle is corrupted (checksum mismatch).
+bundle-package-missing = Package file { $file } is listed in the manifest but missing from the bundle.
+
+## Role checksums
+
+checksums-manifest-unavailable = Warning: Could not fetch { $manifest } ({ $error }); role files are not verified.
+checksums-strict-no-manifest = refusing unverified role { $role }: { $manifest } could not be fetched ({ $error }) and --strict is set
+checksums-mismatch = refusing role { $role }: its content doesn't match its checksum in { $manifest }
+checksums-strict-unlisted = refusing unverified role { $role }: it isn't listed in { $manifest } and --strict is set
+checksums-unlisted = Warning: Role { $role } isn't listed in { $manifest }; using it unverified.
+
+## Role index
+
+role-index-not-cached = The role index isn't cached; run a command that lists roles once without --offline.
+role-index-cache-failed = Warning: Could not cache the role index: { $error }
+role-index-fetch-failed = Failed to fetch role index from { $url }: HTTP Status { $status }
+github-contents-failed = GitHub contents API request to { $url } failed: HTTP Status { $status }
+
+## Package manager log
+
+package-log-open-failed = Warning: Could not open the package manager log: { $error }
+package-log-write-failed = Warning: Could not write the package manager log: { $error }
+
+## Role updates
+
+updates-check-failed = Warning: { $error }
+updates-notification-title = Role updates available
+updates-notify-send-failed = notify-send failed. Exit code: { $code }
+
+## Role file migration
+
+migrate-not-a-repository = { $path } has neither a roles/ nor a meta/ directory; run this in a checkout of the roles repository.
+migrate-newer-format = uses format { $version }, but this version of cyber-toolkit only knows format { $supported }; update it before migrating
+
+## Lockfile
+
+lock-invalid = Invalid lockfile roles.lock: { $error }
+lock-refreezing = Refreezing role { $role } at { $checksum }.
+
+## Role history
+
+github-commits-failed = GitHub commits API request to { $url } failed: HTTP Status { $status }
+changelog-no-history = No history found for role { $role }.
+
+## Batch notes
+
+batch-note-already-configured = add { $role }: already configured
+batch-note-not-configured = remove { $role }: not configured
+
+## Aliases
+
+alias-name-invalid = Invalid alias name `{ $name }`: it must not be empty or contain `+`, `/`, `.` or spaces.
+alias-loop = Alias loop in [aliases]: { $aliases }
+alias-ambiguous = `{ $name }` stands for { $count } roles ({ $roles }), but only one is accepted here.
+
+## Miscellaneous warnings and errors
+
+rate-limited-using-cache = Warning: { $error }. Using cached copies until then.
+project-file-invalid = Invalid project file { $path }: { $error }
+plugin-exec-failed = Failed to execute plugin { $path }: { $error }
+plugin-not-found = No plugin `cyber-toolkit-{ $name }` found in PATH.
+plugin-shadowed-by-role = `{ $name }` is a role as well as a plugin, so the role is used. Run the plugin with `cyber-toolkit plugin { $name }`.
+local-role-name-invalid = Invalid role name `{ $name }` for a local role.
+duration-invalid = Invalid duration { $duration }; use a number followed by m, h, d or w (e.g. 7d).
+audit-write-failed = Warning: Could not write the audit log: { $error }
+elevation-dismissed = The authentication dialog was dismissed.
+elevation-not-authorized = Not authorized. Without a terminal, a polkit authentication agent must be running to show the password dialog.
+pins-refusing-removal = Refusing to remove pinned roles { $roles }. Pass --unpin to remove them anyway, or unpin them first with `cyber-toolkit unpin-role`.
+run-lock-waiting-for = Waiting for another cyber-toolkit run to finish (process { $pid }: { $command })...
+run-lock-waiting = Waiting for other cyber-toolkit runs to finish...
+
+## Learning mode
+
+learn-title = The { $role } toolbox: { $count } tools
+learn-not-installed = {"  "}(not installed)
+
+## Cache
+
+cache-kind-roles = Role definitions
+cache-kind-package-metadata = Package metadata
+cache-kind-bundles = Bundles
+
+## Package operations
+
+pacman-no-tools = No tools specified for pacman { $operation } operation.
+pacman-operation-unsupported = Unsupported pacman operation: { $operation }
+pacman-confirm-note = Note: --confirm flag requires manual 'y/N' input for pacman operations.
+pacman-succeeded = Pacman { $operation } operation completed successfully for tools: { $tools }
+pacman-timed-out = Pacman { $operation } operation timed out after { $minutes } minutes and was stopped. If pacman reports a locked database next time, remove /var/lib/pacman/db.lck.
+pacman-failed = Pacman { $operation } operation failed. Exit code: { $code }. Command: { $command }
+mark-explicit-failed = Warning: Could not mark { $packages } as explicitly installed: { $error }
+choco-skipped = Skipping chocolatey packages (Windows only): { $packages }
+installed-query-failed = Warning: Could not query installed packages: { $error }. Passing all of them to pacman.
+native-up-to-date = Skipping { $count } native packages that are already installed and up to date.
+conflicts-check-failed = Warning: Could not check for conflicting packages: { $error }
+removal-keeping-required = Warning: Keeping { $tool }: required by { $dependents }, which stays installed.
+removal-preview-failed = Warning: Could not preview the removal: { $error }
+removal-also-dependents = Also removing { $count } packages that depend on them or that no other package needs: { $packages }
+removal-also-dependencies = Also removing { $count } dependencies no other package needs: { $packages }
+tool-failed = Failed: { $tool } ({ $reason })
+retry-without-named = pacman's error names { $named }; installing the other { $count } native packages without them...
+retry-without-upgrade = Retrying the { $count } native packages without the system upgrade, in smaller transactions if needed...
+transaction-culprits = The native package transaction failed because of { $count } package(s):
+transaction-culprit-reason = { $error } (isolated as a cause of the failed transaction)
+native-timed-out = package manager timed out
+retry-failed-none = No failed tools recorded.
+retry-failed-retrying = Retrying { $count } tools: { $tools }
+retry-failed-still-failing = { $failed } of { $count } tools still failed.
+retry-failed-succeeded = All { $count } tools installed successfully.
+
+## Commands
+
+removal-strategy = Removal strategy: { $strategy } (pacman -{ $operation }).
+no-roles-configured = No roles currently configured.
+disk-usage-title = {"\u000A"}Disk usage per configured role (shared tools split evenly):
+disk-usage-total = Total: { $size }
+disk-usage-note = Dropping a role frees at most its EXCLUSIVE size; shared tools stay installed for the other roles.
+inventory-written = Wrote the inventory of { $count } tools to { $path }.
+role-metadata-unavailable = Warning: Could not fetch metadata for role { $role }: { $error }
+learn-no-match = No tool of role { $role } mentions `{ $term }`.
+learn-no-tools = Role { $role } has no tools.
+migrate-needed = { $path }: needs migrating to format { $format }
+migrate-declared = { $path }: now declares format { $format }
+migrate-migrated = { $path }: migrated from format { $from } to { $to }
+migrate-error = Error: { $path }: { $error }
+migrate-failed = { $count } file(s) could not be migrated.
+migrate-up-to-date = All role files and metadata are in the current format.
+migrate-check-failed = { $count } file(s) need migrating; run `cyber-toolkit migrate-roles` to migrate them.
+migrate-done = Migrated { $count } file(s).
+licenses-summary = {"\u000A"}{ $commercial } commercial (!), { $review } to review (?), { $open_source } open source.
+licenses-hint = Declare licenses the packages don't know in the [licenses] section of the role's metadata.
+discovery-api-failed = Warning: Role discovery via the GitHub API failed: { $error }. Falling back to the role index.
+roles-available = Available roles ({ $count } configured marked with *):
+fleet-no-hosts = No hosts listed in { $path }.
+fleet-results-title = {"\u000A"}Fleet results:
+fleet-canary-host = { $host } (canary)
+fleet-rollout-aborted = Rollout aborted: { $failed } of { $count } hosts failed, { $skipped } were skipped.
+fleet-hosts-failed = { $failed } of { $count } hosts failed.
+fleet-succeeded = All { $count } hosts updated successfully.
+updates-none = All { $count } configured roles are up to date.
+updates-never-synced = { $role } (never synced on this machine):
+updates-apply-hint = {"\u000A"}Run `cyber-toolkit { $roles }` to apply the changes.
+updates-notification-failed = Warning: Could not show a desktop notification: { $error }
+freeze-done = Froze { $roles } roles and { $packages } package versions in roles.lock.
+lock-no-packages = The lockfile records no package versions.
+
+## Command output
+
+lock-installed = All { $count } locked packages are installed at their locked versions.
+lock-installing = Installing { $count } of { $total } locked packages from the Arch Linux Archive (frozen at { $frozen_at }).
+lock-install-timed-out = Installing the locked packages timed out.
+changelog-no-tool-changes = {"  "}(no tool changes)
+engage-preparing = Preparing for a { $platform } machine tagged { $tags }
+hooks-none-declared = No data refresh hooks declared for roles { $roles }.
+hooks-history-title = {"\u000A"}Data refresh history:
+hooks-failed = Some data refresh hooks failed.
+harden-role-removed = Role { $role } is no longer hardened.
+harden-no-tools = Role { $role } declares no tools to sandbox.
+harden-done = Hardened role { $role }: { $tools } now run under firejail.
+wordlists-available = Available wordlist sets (installed marked with *, stored in { $path }):
+wordlists-installing = Installing wordlist set { $set }...
+wordlists-install-failed = Failed to install wordlist set { $set }: { $error }
+wordlists-some-failed = Some wordlist sets could not be installed.
+wordlists-installed = Wordlist sets { $sets } installed in { $path }.
+wordlists-none-specified-installed = None of the specified wordlist sets are installed.
+wordlists-removed = Wordlist sets { $sets } removed.
+wordlists-none-installed = No wordlist sets installed.
+wordlists-checking = Checking wordlist set { $set }...
+wordlists-file-updated = {"  "}{ $file }: updated
+wordlists-file-up-to-date = {"  "}{ $file }: up to date
+wordlists-file-update-available = {"  "}{ $file }: update available
+current-no-roles = No roles configured.
+current-none-installed = No configured role is fully installed.
+current-all-installed = All tools of the configured roles are installed.
+current-system-role = {"    "}system-wide, set by the administrator
+current-temporary-role = {"    "}temporary, expires at { $until }
+current-missing-tools = {"    "}missing: { $tools }
+current-role-unavailable = { $role } (could not be fetched)
+current-last-sync = {"\u000A"}Last sync: { $time }
+stats-configured-roles = {"\u000A"}Configured roles:   { $count }
+stats-unique-tools = Unique tools:       { $count }
+stats-installed-tools = Installed tools:    { $count } ({ $coverage }% coverage)
+stats-cache-size = Cache size:         { $size }
+stats-last-sync = Last sync:          { $time }
+stats-history-empty = History:            empty
+stats-history = History:            { $counts }
+select-needs-terminal = select needs an interactive terminal (and no --yes); add or remove roles with `cyber-toolkit [-r] <role>...` instead.
+select-plain = select shows an interactive menu, which --plain rules out; add or remove roles with `cyber-toolkit [-r] <role>...` instead.
+select-unchanged = The selection matches the configured roles. Nothing to do.
+roles-to-add = Roles to add: { $roles }
+roles-to-remove = Roles to remove: { $roles }
+batch-read-failed = Could not read the batch { $source }: { $error }
+batch-note = Note: { $note }
+batch-unchanged = The { $count } operation(s) leave the configured roles as they are. Nothing to do.
+role-fetch-failed = Warning: Could not fetch role { $role }: { $error }
+which-role-none = No role provides a tool named like { $tool }.
+which-role-exact = Roles providing { $tool } (configured marked with *):
+which-role-similar = No role provides { $tool } exactly; similar tools (configured roles marked with *):
+which-role-entry = {" "}({ $entry })
+explain-pacman-date = {"  "}pacman:      { $reason } ({ $date })
+explain-pacman = {"  "}pacman:      { $reason }
+explain-toolkit = {"  "}toolkit:     { $origin } ({ $user }, { $time })
+explain-toolkit-event = {"  "}toolkit:     no install record, first install event at { $time }
+explain-toolkit-none = {"  "}toolkit:     no install record
+explain-required-by = {"  "}required by: { $packages }
+explain-roles-none = {"  "}roles:       no configured role lists it
+explain-roles = {"  "}roles:       { $roles }
+explain-kept = Removing these roles keeps it installed.
+
+## More command output
+
+expire-keeping-pinned = Keeping pinned roles past their expiry: { $roles }
+expire-none = No expired roles.
+expire-removing = Removing expired roles: { $roles }
+expire-at = { $role } expires at { $until }.
+project-not-found = No { $file } found in { $path } or its parents.
+project-no-roles = No roles declared in { $path }.
+project-applying = Applying project { $path } (roles: { $roles }, new: { $new })
+auto-sync-on-battery = Skipping the automatic sync: running on battery.
+auto-sync-metered = Skipping the automatic sync: the network connection is metered.
+auto-sync-no-roles = No roles configured; nothing to sync.
+project-not-applied = Project { $path } was not applied. Nothing to remove.
+project-nothing-added = No roles were added for project { $path }; all of them were configured before or are used by other projects.
+project-leaving = Leaving project { $path } (removing roles: { $roles })
+alias-empty = Alias { $name } would stand for no roles.
+alias-unknown = There is no alias { $name }.
+alias-removed = Removed alias { $name }.
+alias-shadowed = Warning: Alias { $alias } still refers to { $name }, which now means a role of that name.
+aliases-none = No aliases defined. Add one with `cyber-toolkit alias add <name> <role>[+<role>...]`.
+verify-no-records = No installs are recorded for { $user } yet; they are recorded by the next add or sync.
+verify-missing-tool = {"    "}missing: { $tool }
+verify-missing = { $count } recorded tools are missing; run `cyber-toolkit install` to reinstall them.
+pins-none = No roles are pinned. Pin one with `cyber-toolkit pin-role <role>`.
+pins-not-configured = Warning: { $role } isn't configured; it is pinned once it is added.
+pins-already-pinned = Already pinned: { $roles }
+pins-pinned = Pinned roles: { $roles }
+local-role-already-lists = Local role { $role } already lists { $tools }.
+local-role-added = Added { $tools } to local role { $role } ({ $path }).
+local-role-tools-unknown = { $tools } isn't in any local role (local roles: { $roles }).
+local-role-removed = Removed { $tools } from local role { $role }.
+local-role-keeping = Keeping { $tool }, another configured role lists it.
+local-role-uninstall-timed-out = Uninstalling { $tools } timed out; they stay in their local roles.
+state-exported = Exported the state to { $path }.
+state-import-invalid = Invalid state export { $path }: { $error }
+state-imported = Imported { $documents } state documents and { $history } history entries from { $path }.
+cache-oldest = , oldest { $days } day(s) old
+cache-stats = { $kind }{ $entries } entry(s), { $size }{ $oldest }
+cache-prunable = `cache prune` would remove { $count } entry(s) ({ $size }).
+cache-would-remove = Would remove { $entry }
+cache-would-free = Would free { $size } from { $count } cache entry(s).
+cache-removed = Removed { $entry }
+cache-remove-failed = Warning: Could not remove { $path }: { $error }
+cache-freed = Freed { $size } from { $count } cache entry(s).
+cache-roles-cleared = Role files are fetched again on the next run; until then --offline can't read them.
+bundle-roles-unavailable = Could not fetch roles { $roles }; no bundle was written.
+bundle-work-dir-remove-failed = Warning: Could not remove the working directory { $path }: { $error }
+bundle-packages-unavailable = Warning: Not bundling { $packages }: they aren't in the sync repositories (e.g. AUR packages).
+bundle-tools-left-out = Warning: Not bundling { $count } container, Go and chocolatey tools; only native packages are bundled.
+bundle-downloading = Downloading { $count } packages and their dependencies...
+bundle-downloading-wordlists = Downloading wordlist set { $set }...
+bundle-written = Wrote { $path } ({ $size }): { $roles } roles, { $packages } package files, { $wordlists } wordlist sets.
+bundle-created = Bundle created { $date } with cyber-toolkit { $version }: roles { $roles }, { $packages } package files, wordlist sets { $wordlists }.
+bundle-architecture-mismatch = The bundle's packages are for { $architecture }, but this machine is { $machine }.
+bundle-packages-skipped = Warning: Skipping the bundle's { $count } package files in user mode.
+bundle-installing = Installing { $count } package files from the bundle...
+bundle-install-timed-out = Installing the bundle's packages timed out.
+config-key-unset = `{ $key }` is not set.
+config-value-invalid = Invalid value for `{ $key }`: { $error }
+config-key-unknown = Unknown setting `{ $key }`. See `cyber-toolkit config show` for the available keys.
+config-set = Set { $key } in { $path }.
+config-key-not-set = `{ $key }` is not set in { $path }.
+config-unset = Unset { $key } in { $path }; the default applies again.
+config-show-header = # { $path } (unset keys show their defaults)
+config-editor-invalid = Invalid editor command `{ $editor }`.
+config-editor-failed = Editor `{ $editor }` failed; your edits are kept in { $path }.
+config-edit-invalid = Invalid settings: { $error }
+    Nothing was changed; fix { $path } and run `cyber-toolkit config edit` again.
+config-key-ignored = Warning: `{ $key }` is not a known setting and will be ignored.
+config-saved = Saved { $path }.
+xdg-moved = Moved { $count } entries from { $path } to the XDG directories:
+xdg-kept = Left { $files } in { $path }; they are rebuilt on the next add/sync, after which you can delete them.
+xdg-move-failed = Warning: Could not move ~/.roles to the XDG directories: { $error }
+headless-settings-unreadable = Warning: Could not read settings: { $error }. Detecting headless mode.
+running-as-root = Warning: cyber-toolkit doesn't need to run as root; it only elevates the package manager itself. Running as root uses root's configuration and state instead of yours.
+install-locked-needs-pacman = install --locked requires pacman.
+preset-applying = Applying preset { $name } (roles: { $roles })
+pins-unpinned = Unpinned roles: { $roles }
+bundle-needs-pacman = Bundles require pacman.
+run-lock-failed = Warning: Could not lock the state directory: { $error }. Not protected against concurrent runs.
+
+## Table columns and states
+
+column-role = ROLE
+column-roles = ROLES
+column-total = TOTAL
+column-exclusive = EXCLUSIVE
+column-shared = SHARED
+column-installed = INSTALLED
+column-missing = MISSING
+column-tool = TOOL
+column-tools = TOOLS
+column-license = LICENSE
+column-vendor = VENDOR
+column-host = HOST
+column-status = STATUS
+column-time = TIME
+column-detail = DETAIL
+column-frozen = FROZEN
+column-source = SOURCE
+status-ok = OK
+status-failed = FAILED
+status-skipped = SKIPPED
+never = never
+
+## Leftover command output
+
+licenses-none-flagged = No installed tool is flagged.
+licenses-none-installed = No tools of the configured roles are installed.
+licenses-unknown = unknown
+lock-not-found = No lockfile found. Run `cyber-toolkit freeze` first.
+preset-roles = roles: { $roles }
+select-prompt = Configured roles (space to toggle, enter to apply)
+select-confirm = Apply these changes?
+explain-explicit = explicitly installed
+explain-dependency = installed as a dependency
+explain-origin-toolkit = installed it
+explain-origin-user = found it explicitly installed
+explain-origin-dependency = found it as a dependency and marked it explicit
+explain-origin-unknown = recorded it
+alias-added = Added alias { $name } for roles: { $roles }
+alias-changed = Changed alias { $name } for roles: { $roles }
+verify-scope-system = System-wide
+verify-scope-user = In your home
+verify-scope-installed = { $scope }: { $present } of { $count } installed
+cache-total = Total
@@ -0,0 +1,34 @@
+# Firejail profile for sqlmap, deployed by `cyber-toolkit harden`.
+include sqlmap.local
+include globals.local
+
+noblacklist ${HOME}/.local/share/sqlmap
+mkdir ${HOME}/.local/share/sqlmap
+whitelist ${HOME}/.local/share/sqlmap
+whitelist ${DOWNLOADS}
+include whitelist-common.inc
+
+include disable-common.inc
+include disable-devel.inc
+include disable-exec.inc
+include disable-programs.inc
+include disable-xdg.inc
+
+caps.drop all
+ipc-namespace
+netfilter
+no3d
+nodvd
+nogroups
+noinput
+nonewprivs
+noroot
+nosound
+notv
+nou2f
+novideo
+protocol unix,inet,inet6
+seccomp
+
+private-dev
+private-tmp
@@ -0,0 +1,119 @@
+//! Role aliases from the `[aliases]` section of `config.toml`.
+//!
+//! ```toml
+//! [aliases]
+//! bt = "blue-teamer"
+//! full = "red-teamer+forensics"
+//! ```
+//!
+//! An alias stands for one or more roles joined with `+`, and may refer to other aliases.
+//! Aliases are expanded wherever role names are given: on the command line (`cyber-toolkit
+//! full`, `-r bt`, subcommands taking roles) and in `.cyber-toolkit.toml`. `+` also joins
+//! plain role names there, e.g. `cyber-toolkit red-teamer+forensics`. An alias named like a
+//! role takes its place. Role names come out canonicalized (see `roles::canonical_name`), so
+//! `Blue-Teamer` and `blue-teamer.txt` both name the role `blue-teamer`.
+
+use std::collections::BTreeMap;
+
+use cyber_toolkit::roles;
+
+use crate::i18n::t;
+use crate::settings;
+
+/// Separates the roles of an alias.
+pub const SEPARATOR: char = '+';
+
+/// Checks that `name` can be used as an alias name.
+pub fn validate_name(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if name.is_empty() || name.contains(SEPARATOR) || name.contains(['/', '.']) || name.contains(char::is_whitespace) {
+        return Err(Box::from(t!("alias-name-invalid", name = name)));
+    }
+    Ok(())
+}
+
+/// Expands the aliases among `names` into the roles they stand for, keeping the order and
+/// dropping duplicates. Fails on aliases that refer to themselves.
+pub fn expand(names: &[String], aliases: &BTreeMap<String, String>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut roles = Vec::new();
+    for name in names {
+        expand_into(name, aliases, &mut Vec::new(), &mut roles)?;
+    }
+    Ok(roles)
+}
+
+fn expand_into(
+    name: &str,
+    aliases: &BTreeMap<String, String>,
+    expanding: &mut Vec<String>,
+    roles: &mut Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for part in name.split(SEPARATOR).map(str::trim).filter(|part| !part.is_empty()) {
+        match aliases.get(part) {
+            Some(_) if expanding.iter().any(|alias| alias == part) => {
+                expanding.push(part.to_string());
+                return Err(Box::from(t!("alias-loop", aliases = expanding.join(" -> "))));
+            }
+            Some(target) => {
+                expanding.push(part.to_string());
+                expand_into(target, aliases, expanding, roles)?;
+                expanding.pop();
+            }
+            None => {
+                let role = roles::canonical_name(part);
+                if !roles.contains(&role) {
+                    roles.push(role);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Expands `names` with the aliases from the settings.
+pub fn expand_configured(names: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    expand(names, &settings::load()?.aliases)
+}
+
+/// Expands a name where exactly one role is expected, e.g. `harden bt`.
+pub fn expand_one(name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut roles = expand_configured(&[name.to_string()])?;
+    if roles.len() != 1 {
+        return Err(Box::from(t!("alias-ambiguous", name = name, count = roles.len(), roles = roles.join(", "))));
+    }
+    Ok(roles.remove(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::expand;
+
+    fn aliases(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries.iter().map(|(name, target)| (name.to_string(), target.to_string())).collect()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn expand_replaces_aliases_recursively() {
+        let aliases = aliases(&[("bt", "blue-teamer"), ("full", "red-teamer+bt+forensics")]);
+        assert_eq!(expand(&names(&["full"]), &aliases).unwrap(), ["red-teamer", "blue-teamer", "forensics"]);
+        assert_eq!(expand(&names(&["bt", "web"]), &aliases).unwrap(), ["blue-teamer", "web"]);
+    }
+
+    #[test]
+    fn expand_joins_plain_roles_and_drops_duplicates() {
+        let aliases = aliases(&[("bt", "blue-teamer")]);
+        assert_eq!(expand(&names(&["Red-Teamer+forensics.txt", "bt", "blue-teamer"]), &aliases).unwrap(), ["red-teamer", "forensics", "blue-teamer"]);
+        assert_eq!(expand(&names(&["+web+"]), &aliases).unwrap(), ["web"]);
+    }
+
+    #[test]
+    fn expand_rejects_loops() {
+        let aliases = aliases(&[("a", "b+web"), ("b", "a")]);
+        assert!(expand(&names(&["a"]), &aliases).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Append-only audit log `~/.local/state/cyber-toolkit/audit.jsonl` for shipping changes to a SIEM.
+//!
+//! Every action that changes the machine or decides what will change it is recorded as one JSON
+//! object per line: role fetches, tool resolution, installs, removals, data refresh hooks and
+//! elevated commands. Each event carries the user and hostname so events from many machines can
+//! be told apart:
+//!
+//! ```json
+//! {"timestamp":"2026-10-16T09:12:03+00:00","user":"alice","hostname":"lab-07","action":"install","backend":"native","packages":["nmap"],"success":true}
+//! ```
+//!
+//! Failing to write the log never fails the action itself; a warning is printed instead.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::t;
+use crate::state;
+
+/// One audit event.
+#[derive(Serialize, Debug)]
+pub struct AuditEvent<'a> {
+    /// RFC 3339 time of the event.
+    pub timestamp: String,
+    pub user: &'a str,
+    pub hostname: &'a str,
+    /// `fetch`, `resolve`, `install`, `remove`, `hook` or `elevation`.
+    pub action: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub roles: &'a [String],
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub packages: &'a [String],
+    /// Outcome of the action; absent for elevations, which are recorded before they run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub success: Option<bool>,
+    /// Free-form detail, such as the command run or the error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<&'a str>,
+}
+
+/// Path of the audit log, `~/.local/state/cyber-toolkit/audit.jsonl`.
+pub fn audit_path() -> Result<PathBuf, io::Error> {
+    Ok(state::state_dir()?.join("audit.jsonl"))
+}
+
+/// Name of the user running the toolkit.
+pub fn user() -> &'static str {
+    static USER: OnceLock<String> = OnceLock::new();
+    USER.get_or_init(|| {
+        ["USER", "LOGNAME", "USERNAME"]
+            .iter()
+            .find_map(|name| std::env::var(name).ok())
+            .or_else(|| {
+                let output = std::process::Command::new("id").arg("-un").output().ok()?;
+                Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            })
+            .unwrap_or_default()
+    })
+}
+
+/// Hostname of the machine, from `/etc/hostname` or `COMPUTERNAME` on Windows.
+pub fn hostname() -> &'static str {
+    static HOSTNAME: OnceLock<String> = OnceLock::new();
+    HOSTNAME.get_or_init(|| {
+        fs::read_to_string("/etc/hostname")
+            .map(|name| name.trim().to_string())
+            .or_else(|_| std::env::var("COMPUTERNAME"))
+            .unwrap_or_default()
+    })
+}
+
+/// The fields of a logged event that `first_installs` reads.
+#[derive(Deserialize, Debug)]
+struct LoggedEvent {
+    timestamp: String,
+    action: String,
+    #[serde(default)]
+    packages: Vec<String>,
+    #[serde(default)]
+    success: Option<bool>,
+}
+
+/// Time (RFC 3339) of the first successful install event naming each of `packages` that has
+/// one. Events are appended in order, so the first one found is the earliest; lines that aren't
+/// events are skipped. A missing log has no events.
+pub fn first_installs(packages: &[String]) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let content = match fs::read_to_string(audit_path()?) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(Box::new(e)),
+    };
+    let mut first = HashMap::new();
+    for event in content.lines().filter_map(|line| serde_json::from_str::<LoggedEvent>(line).ok()) {
+        if event.action != "install" || event.success != Some(true) {
+            continue;
+        }
+        for package in event.packages.into_iter().filter(|package| packages.contains(package)) {
+            first.entry(package).or_insert_with(|| event.timestamp.clone());
+        }
+    }
+    Ok(first)
+}
+
+fn append(event: &AuditEvent) -> Result<(), Box<dyn std::error::Error>> {
+    let path = audit_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    // A single write per line keeps concurrent runs from interleaving events
+    file.write_all((serde_json::to_string(event)? + "\n").as_bytes())?;
+    Ok(())
+}
+
+/// Records an event about the given roles and packages (e.g. a fetch, resolution or hook run).
+pub fn record(action: &str, roles: &[String], packages: &[String], success: bool, detail: Option<&str>) {
+    write_event(action, None, roles, packages, Some(success), detail);
+}
+
+/// Records an event about packages handled by a backend (e.g. an install or removal).
+pub fn record_packages(action: &str, backend: &str, packages: &[String], success: bool, detail: Option<&str>) {
+    if packages.is_empty() {
+        return;
+    }
+    write_event(action, Some(backend), &[], packages, Some(success), detail);
+}
+
+/// Records that a command is about to run with elevated privileges.
+pub fn record_elevation(command: &str) {
+    write_event("elevation", None, &[], &[], None, Some(command));
+}
+
+fn write_event(action: &str, backend: Option<&str>, roles: &[String], packages: &[String], success: Option<bool>, detail: Option<&str>) {
+    let event = AuditEvent {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        user: user(),
+        hostname: hostname(),
+        action,
+        backend,
+        roles,
+        packages,
+        success,
+        detail,
+    };
+    if let Err(e) = append(&event) {
+        eprintln!("{}", t!("audit-write-failed", error = e.to_string()));
+    }
+}
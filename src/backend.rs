@@ -0,0 +1,131 @@
+//! Package backends used by `RoleManager` to install and remove tools.
+
+use std::io;
+use std::process::{ExitStatus, Stdio};
+
+use async_trait::async_trait;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+/// The stream of the package manager a line of its output came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// The package manager operation that printed a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PackageOperation {
+    Install,
+    Remove,
+}
+
+/// Receives the lines a package manager prints, with the stream each came from.
+pub type OutputSink<'a> = &'a (dyn Fn(OutputStream, &str) + Send + Sync);
+
+/// Installs, removes and queries tools.
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// Installs or updates the tools.
+    async fn install(&self, tools: &[String]) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Removes the tools.
+    async fn remove(&self, tools: &[String]) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Returns which of the tools are installed.
+    async fn installed(&self, tools: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>>;
+
+    /// Installs like `install`, passing the lines the package manager prints to `output` as they
+    /// arrive instead of leaving them on the inherited terminal. Defaults to `install`.
+    async fn install_with_output(&self, tools: &[String], _output: OutputSink<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        self.install(tools).await
+    }
+
+    /// Removes like `remove`, passing the package manager's output to `output`. Defaults to `remove`.
+    async fn remove_with_output(&self, tools: &[String], _output: OutputSink<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        self.remove(tools).await
+    }
+}
+
+/// Plain pacman, elevated with `pkexec` unless already running as root. Unlike the command-line
+/// interface, it never prompts and leaves out the special entries (`isolated:`, `go:`, ...).
+/// pacman runs as a Tokio child process, so it needs a Tokio runtime and doesn't block it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PacmanBackend;
+
+impl PacmanBackend {
+    /// Runs pacman for `tools`. With `output`, its stdout and stderr go there line by line
+    /// (without progress bars) instead of to the terminal.
+    async fn run(args: &[&str], tools: &[String], output: Option<OutputSink<'_>>) -> Result<(), Box<dyn std::error::Error>> {
+        let packages: Vec<&String> = tools.iter().filter(|tool| !tool.contains(':')).collect();
+        if packages.is_empty() {
+            return Ok(());
+        }
+        let is_root = Command::new("id").arg("-u").output().await.is_ok_and(|output| output.stdout.trim_ascii() == b"0");
+        let mut command = if is_root { Command::new("pacman") } else { Command::new("pkexec") };
+        if !is_root {
+            command.arg("pacman");
+        }
+        command.args(args).arg("--noconfirm");
+        let status = match output {
+            Some(output) => run_with_output(command.arg("--noprogressbar").arg("--").args(&packages), output).await?,
+            None => command.arg("--").args(&packages).status().await?,
+        };
+        if !status.success() {
+            return Err(Box::from(format!("pacman {} failed. Exit code: {:?}", args.join(" "), status.code())));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Backend for PacmanBackend {
+    async fn install(&self, tools: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        PacmanBackend::run(&["-S", "--needed"], tools, None).await
+    }
+
+    async fn remove(&self, tools: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        // pacman refuses to remove anything if one of the targets isn't installed
+        let installed = self.installed(tools).await?;
+        PacmanBackend::run(&["-Rns"], &installed, None).await
+    }
+
+    async fn install_with_output(&self, tools: &[String], output: OutputSink<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        PacmanBackend::run(&["-S", "--needed"], tools, Some(output)).await
+    }
+
+    async fn remove_with_output(&self, tools: &[String], output: OutputSink<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        let installed = self.installed(tools).await?;
+        PacmanBackend::run(&["-Rns"], &installed, Some(output)).await
+    }
+
+    async fn installed(&self, tools: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let output = Command::new("pacman").arg("-Qq").output().await?;
+        let installed: Vec<&str> = std::str::from_utf8(&output.stdout)?.lines().collect();
+        Ok(tools.iter().filter(|tool| installed.contains(&tool.as_str())).cloned().collect())
+    }
+}
+
+/// Runs `command` with stdout and stderr piped, passing their lines to `output` in the order
+/// they arrive.
+async fn run_with_output(command: &mut Command, output: OutputSink<'_>) -> io::Result<ExitStatus> {
+    let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+    let mut stderr = BufReader::new(child.stderr.take().expect("stderr is piped")).lines();
+    let (mut stdout_open, mut stderr_open) = (true, true);
+    while stdout_open || stderr_open {
+        // `next_line` is cancel safe, so the branch that loses the race keeps its partial line
+        tokio::select! {
+            line = stdout.next_line(), if stdout_open => match line {
+                Ok(Some(line)) => output(OutputStream::Stdout, &line),
+                _ => stdout_open = false,
+            },
+            line = stderr.next_line(), if stderr_open => match line {
+                Ok(Some(line)) => output(OutputStream::Stderr, &line),
+                _ => stderr_open = false,
+            },
+        }
+    }
+    child.wait().await
+}
@@ -0,0 +1,102 @@
+//! Dispatch of role entries to installation backends.
+//!
+//! Plain entries are native packages: pacman packages on Linux, winget package identifiers on
+//! Windows. An entry with a `<backend>:` prefix is handled by that backend instead:
+//!
+//! - `isolated:<name>=<image>`: container image with a wrapper script (see `isolated`).
+//! - `go:<package>[@<version>]`: built with `go install` into a managed `GOBIN` (see `go`).
+//! - `choco:<package>`: chocolatey package, Windows only (see `windows`).
+//!
+//! `group:<name>` entries stand for the members of a pacman group and are replaced by them
+//! when the roles are resolved (see `expand_groups`), before entries are dispatched here.
+
+use crate::go::{self, GoTool};
+use crate::i18n::t;
+use crate::isolated::{self, IsolatedTool};
+use crate::lock::Scope;
+
+/// Entry prefix for chocolatey packages.
+pub const CHOCO_PREFIX: &str = "choco:";
+
+/// Entry prefix for pacman groups, e.g. `group:blackarch-webapp`.
+pub const GROUP_PREFIX: &str = "group:";
+
+/// Replaces `group:<name>` entries with the group's member packages from `pacman -Sg`.
+///
+/// The result is sorted and deduplicated, so members also listed explicitly appear once.
+/// Unknown groups, and all groups where pacman isn't available (e.g. on Windows), are reported and dropped.
+pub fn expand_groups(tools: Vec<String>) -> Vec<String> {
+    let (group_entries, mut expanded): (Vec<String>, Vec<String>) = tools.into_iter().partition(|tool| tool.starts_with(GROUP_PREFIX));
+    if group_entries.is_empty() {
+        return expanded;
+    }
+    if cfg!(windows) {
+        eprintln!("{}", t!("groups-unsupported", groups = format!("{:?}", group_entries)));
+        return expanded;
+    }
+    let groups: Vec<String> = group_entries.iter().map(|entry| entry[GROUP_PREFIX.len()..].to_string()).collect();
+    let members = crate::pacman::query_group_members(&groups).unwrap_or_else(|e| {
+        eprintln!("{}", t!("groups-query-failed", error = e.to_string()));
+        Default::default()
+    });
+    for group in &groups {
+        match members.get(group) {
+            Some(packages) => expanded.extend(packages.iter().cloned()),
+            None => eprintln!("{}", t!("group-unknown", group = format!("{}{}", GROUP_PREFIX, group))),
+        }
+    }
+    expanded.sort_unstable();
+    expanded.dedup();
+    expanded
+}
+
+/// Role entries grouped by the backend that installs them.
+#[derive(Debug, Default)]
+pub struct ToolsByBackend {
+    pub native: Vec<String>,
+    pub isolated: Vec<IsolatedTool>,
+    pub go: Vec<GoTool>,
+    pub choco: Vec<String>,
+}
+
+/// Groups entries by backend. Malformed backend entries are reported and skipped.
+pub fn split_by_backend(tools: &[String]) -> ToolsByBackend {
+    let mut grouped = ToolsByBackend::default();
+    for tool in tools {
+        if let Some(spec) = tool.strip_prefix(isolated::PREFIX) {
+            match isolated::parse_spec(spec) {
+                Ok(isolated_tool) => grouped.isolated.push(isolated_tool),
+                Err(e) => eprintln!("{}", t!("entry-skipped", entry = tool.as_str(), error = e)),
+            }
+        } else if let Some(spec) = tool.strip_prefix(go::PREFIX) {
+            match go::parse_spec(spec) {
+                Ok(go_tool) => grouped.go.push(go_tool),
+                Err(e) => eprintln!("{}", t!("entry-skipped", entry = tool.as_str(), error = e)),
+            }
+        } else if let Some(package) = tool.strip_prefix(CHOCO_PREFIX) {
+            if !package.is_empty() && package.chars().all(|c| c.is_ascii_alphanumeric() || "._-".contains(c)) {
+                grouped.choco.push(package.to_string());
+            } else {
+                eprintln!("{}", t!("entry-skipped", entry = tool.as_str(), error = t!("choco-name-invalid")));
+            }
+        } else {
+            grouped.native.push(tool.clone());
+        }
+    }
+    grouped
+}
+
+/// Returns true for entries installed by the native package manager (no backend prefix).
+pub fn is_native_entry(tool: &str) -> bool {
+    !tool.starts_with(isolated::PREFIX) && !tool.starts_with(go::PREFIX) && !tool.starts_with(CHOCO_PREFIX)
+}
+
+/// Returns where the backend of an entry installs it: native and chocolatey packages for the
+/// whole machine, containers and Go tools for the invoking user.
+pub fn scope(tool: &str) -> Scope {
+    if tool.starts_with(isolated::PREFIX) || tool.starts_with(go::PREFIX) {
+        Scope::User
+    } else {
+        Scope::System
+    }
+}
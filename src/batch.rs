@@ -0,0 +1,157 @@
+//! `cyber-toolkit batch`: applies a list of role operations as one change, for provisioning
+//! scripts that would otherwise run the toolkit once per operation, each time with a full sync.
+//!
+//! The operations come one per line:
+//!
+//! ```text
+//! # Comments and blank lines are skipped
+//! add web osint
+//! remove forensics
+//! set red-teamer blue-teamer
+//! ```
+//!
+//! or as a JSON list, e.g. `[{"op": "add", "roles": ["web"]}, {"op": "remove", "roles": ["forensics"]}]`.
+//!
+//! Nothing is installed or removed per operation. They are applied in order to the configured
+//! roles first (`resolve`), and only the difference to the configuration is carried out, with one
+//! removal and one add.
+
+use serde::Deserialize;
+
+use crate::i18n::t;
+
+/// What an operation does to the roles.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Add,
+    Remove,
+    /// Makes the roles the configured ones.
+    Set,
+}
+
+/// One operation of a batch.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Operation {
+    #[serde(rename = "op")]
+    pub kind: Kind,
+    pub roles: Vec<String>,
+}
+
+/// The change a batch makes to the configured roles.
+#[derive(Debug, Default)]
+pub struct Plan {
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+    /// Operations that had no effect, e.g. removing a role that isn't configured.
+    pub notes: Vec<String>,
+}
+
+/// Parses a batch: a JSON list if it starts with `[`, otherwise one operation per line.
+pub fn parse(content: &str) -> Result<Vec<Operation>, Box<dyn std::error::Error>> {
+    let operations = if content.trim_start().starts_with('[') {
+        serde_json::from_str::<Vec<Operation>>(content).map_err(|e| t!("batch-json-invalid", error = e.to_string()))?
+    } else {
+        parse_lines(content)?
+    };
+    if let Some((index, _)) = operations.iter().enumerate().find(|(_, operation)| operation.roles.is_empty()) {
+        return Err(Box::from(t!("batch-no-roles", operation = index + 1)));
+    }
+    Ok(operations)
+}
+
+fn parse_lines(content: &str) -> Result<Vec<Operation>, Box<dyn std::error::Error>> {
+    let mut operations = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let kind = match words.next() {
+            Some("add") => Kind::Add,
+            Some("remove") => Kind::Remove,
+            Some("set") => Kind::Set,
+            Some(other) => return Err(Box::from(t!("batch-operation-unknown", line = number + 1, operation = format!("{:?}", other)))),
+            None => continue,
+        };
+        operations.push(Operation { kind, roles: words.map(str::to_string).collect() });
+    }
+    Ok(operations)
+}
+
+/// Applies `operations` in order to the `configured` roles and returns the roles to add and to
+/// remove. `set` keeps the `system` roles, which are configured for every user.
+pub fn resolve(configured: &[String], system: &[String], operations: &[Operation]) -> Plan {
+    let mut roles: Vec<String> = configured.to_vec();
+    let mut plan = Plan::default();
+    for operation in operations {
+        match operation.kind {
+            Kind::Add => {
+                for role in &operation.roles {
+                    if roles.contains(role) {
+                        plan.notes.push(t!("batch-note-already-configured", role = role.as_str()));
+                    } else {
+                        roles.push(role.clone());
+                    }
+                }
+            }
+            Kind::Remove => {
+                for role in &operation.roles {
+                    if roles.contains(role) {
+                        roles.retain(|configured| configured != role);
+                    } else {
+                        plan.notes.push(t!("batch-note-not-configured", role = role.as_str()));
+                    }
+                }
+            }
+            Kind::Set => {
+                roles.retain(|role| system.contains(role));
+                for role in &operation.roles {
+                    if !roles.contains(role) {
+                        roles.push(role.clone());
+                    }
+                }
+            }
+        }
+    }
+    plan.add = roles.iter().filter(|role| !configured.contains(role)).cloned().collect();
+    plan.remove = configured.iter().filter(|role| !roles.contains(role)).cloned().collect();
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Kind};
+
+    #[test]
+    fn parse_reads_one_operation_per_line() {
+        let operations = parse("# Lab setup\n\nadd web osint\n  remove forensics\nset red-teamer\n").unwrap();
+        let operations: Vec<(Kind, Vec<String>)> = operations.into_iter().map(|operation| (operation.kind, operation.roles)).collect();
+        assert_eq!(
+            operations,
+            [
+                (Kind::Add, vec!["web".to_string(), "osint".to_string()]),
+                (Kind::Remove, vec!["forensics".to_string()]),
+                (Kind::Set, vec!["red-teamer".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_reads_a_json_list() {
+        let operations = parse(r#"[{"op": "add", "roles": ["web"]}, {"op": "set", "roles": ["blue", "red"]}]"#).unwrap();
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations[1].kind, Kind::Set);
+        assert_eq!(operations[1].roles, ["blue", "red"]);
+    }
+
+    #[test]
+    fn parse_rejects_unknown_operations_and_operations_without_roles() {
+        assert!(parse("add web\ninstall red\n").is_err());
+        assert!(parse("add web\nremove\n").is_err());
+        assert!(parse(r#"[{"op": "add", "roles": []}]"#).is_err());
+        assert!(parse(r#"[{"op": "add", "roles": ["web"], "force": true}]"#).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+//! Synchronous wrappers around the async library functions, for callers without a tokio
+//! runtime such as simple scripts and CLI wrappers. Enabled by the `blocking` cargo feature.
+//!
+//! The wrappers run on a single-threaded runtime owned by this module. It is reused across calls
+//! so pooled HTTP connections stay valid. Don't call them from within an async runtime; use the
+//! async functions there.
+
+use std::sync::OnceLock;
+
+use tokio::runtime::Runtime;
+
+use crate::fetcher::RoleFetcher;
+use crate::report::Reporter;
+use crate::roles::{self, FetchReport, Role};
+
+fn runtime() -> Result<&'static Runtime, Box<dyn std::error::Error>> {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Blocking version of `roles::fetch_role`.
+pub fn fetch_role(name: &str, fetcher: &dyn RoleFetcher) -> Result<Role, Box<dyn std::error::Error>> {
+    runtime()?.block_on(roles::fetch_role(name, fetcher))
+}
+
+/// Blocking version of `roles::fetch_roles`.
+pub fn fetch_roles(
+    names: &[String],
+    fetcher: &dyn RoleFetcher,
+    reporter: &dyn Reporter,
+) -> Result<FetchReport, Box<dyn std::error::Error>> {
+    Ok(runtime()?.block_on(roles::fetch_roles(names, fetcher, reporter)))
+}
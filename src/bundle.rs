@@ -0,0 +1,190 @@
+//! Bundles for air-gapped machines.
+//!
+//! `bundle create` packs everything an offline machine needs to add a set of roles into one tar
+//! file; `bundle install` unpacks it there and adds the roles with `--offline`. A bundle holds:
+//!
+//! - `roles/<role>` and `meta/<role>.toml`: the role definitions and their metadata.
+//! - `packages/`: the package files of the roles' native tools and all their dependencies,
+//!   downloaded with `pacman -Sw`.
+//! - `wordlists/<set>/`: the files of the included wordlist sets.
+//! - `manifest.json`: what the bundle contains, with the SHA-256 of every package file.
+//!
+//! Container images, Go modules and pip packages are not bundled.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+
+use crate::i18n::t;
+use crate::{platform, wordlists, PACMAN_BINARY};
+
+/// Name of the manifest inside a bundle.
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Content of `manifest.json`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    /// RFC 3339 timestamp.
+    pub created_at: String,
+    /// Version of the toolkit that created the bundle.
+    pub toolkit_version: String,
+    /// Architecture of the bundled packages (`uname -m` of the creating machine).
+    pub architecture: String,
+    pub roles: Vec<String>,
+    /// File names under `packages/` with their SHA-256 (lowercase hex).
+    pub packages: BTreeMap<String, String>,
+    pub wordlists: Vec<String>,
+}
+
+/// Name of the working directories, followed by the ID of the process using it, a `-` and a
+/// random suffix.
+pub const WORK_DIR_PREFIX: &str = "cyber-toolkit-bundle-";
+
+/// Creates a new, empty working directory for a bundle in the temporary directory (`$TMPDIR`),
+/// which is deleted with everything in it when dropped. The directory gets a fresh random name
+/// and is only accessible to the user; a path that already exists is never reused, so nothing
+/// placed in `$TMPDIR` beforehand ends up in a bundle or gets installed from one.
+pub fn work_dir() -> Result<TempDir, io::Error> {
+    tempfile::Builder::new()
+        .prefix(&format!("{}{}-", WORK_DIR_PREFIX, std::process::id()))
+        .permissions(fs::Permissions::from_mode(0o700))
+        .tempdir()
+}
+
+/// Downloads `packages` and all their dependencies into `<dir>/packages` with `pacman -Sw`.
+///
+/// pacman skips dependencies that are already installed, so it runs against an empty local
+/// database (sharing the system's sync databases): the target machine may have none of them.
+pub fn download_packages(packages: &[String], dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let package_dir = dir.join("packages");
+    fs::create_dir_all(&package_dir)?;
+    if packages.is_empty() {
+        return Ok(());
+    }
+    let db_dir = dir.join("pacman-db");
+    fs::create_dir_all(db_dir.join("local"))?;
+    std::os::unix::fs::symlink("/var/lib/pacman/sync", db_dir.join("sync"))?;
+
+    let mut args: Vec<String> = vec!["-Sw".into(), "--noconfirm".into()];
+    if platform::is_plain() {
+        args.extend(platform::PLAIN_PACMAN_FLAGS.map(String::from));
+    }
+    args.push("--dbpath".into());
+    args.push(db_dir.to_string_lossy().into_owned());
+    args.push("--cachedir".into());
+    args.push(package_dir.to_string_lossy().into_owned());
+    args.push("--".into());
+    args.extend(packages.iter().cloned());
+    let status = platform::elevated(PACMAN_BINARY, &args).status()?;
+    fs::remove_dir_all(&db_dir)?;
+    if !status.success() {
+        return Err(Box::from(t!("bundle-download-failed", code = format!("{:?}", status.code()))));
+    }
+    Ok(())
+}
+
+/// Returns the SHA-256 of each file in `<dir>/packages`.
+pub fn package_checksums(dir: &Path) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+    let mut checksums = BTreeMap::new();
+    for entry in fs::read_dir(dir.join("packages"))? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            let digest = Sha256::digest(fs::read(entry.path())?);
+            checksums.insert(entry.file_name().to_string_lossy().into_owned(), format!("{:x}", digest));
+        }
+    }
+    Ok(checksums)
+}
+
+/// Writes the manifest and packs `dir` into the tar file `output`.
+pub fn pack(dir: &Path, manifest: &Manifest, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(dir.join(MANIFEST_FILE), serde_json::to_string_pretty(manifest)?)?;
+    let status = Command::new("tar").arg("-cf").arg(output).arg("-C").arg(dir).arg(".").status()?;
+    if !status.success() {
+        return Err(Box::from(t!("bundle-pack-failed", path = format!("{:?}", output), code = format!("{:?}", status.code()))));
+    }
+    Ok(())
+}
+
+/// Unpacks the bundle `file` into `dir` and returns its manifest, after verifying every package
+/// file against its checksum and the names of the roles (see `check_role_name`) and wordlist sets
+/// (see `wordlists::check_set_name`).
+pub fn unpack(file: &Path, dir: &Path) -> Result<Manifest, Box<dyn std::error::Error>> {
+    let status = Command::new("tar").arg("-xf").arg(file).arg("-C").arg(dir).status()?;
+    if !status.success() {
+        return Err(Box::from(t!("bundle-unpack-failed", path = format!("{:?}", file), code = format!("{:?}", status.code()))));
+    }
+    let content = fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|e| t!("bundle-invalid", path = format!("{:?}", file), manifest = MANIFEST_FILE, error = e.to_string()))?;
+    let manifest: Manifest = serde_json::from_str(&content)?;
+    for role in &manifest.roles {
+        check_role_name(role)?;
+    }
+    for set in &manifest.wordlists {
+        wordlists::check_set_name(set)?;
+    }
+
+    let actual = package_checksums(dir)?;
+    for (name, expected) in &manifest.packages {
+        match actual.get(name) {
+            Some(checksum) if checksum == expected => {}
+            Some(_) => return Err(Box::from(t!("bundle-package-corrupted", file = name.as_str()))),
+            None => return Err(Box::from(t!("bundle-package-missing", file = name.as_str()))),
+        }
+    }
+    Ok(manifest)
+}
+
+/// Checks that `role` from a manifest is a plain role name: role names become file names below
+/// the bundle's `roles/` and `meta/` and in the role cache.
+fn check_role_name(role: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if role.is_empty() || role.starts_with('.') || role.contains(['/', '\\']) {
+        return Err(Box::from(t!("bundle-role-name-invalid", role = format!("{:?}", role))));
+    }
+    Ok(())
+}
+
+/// Returns the paths of the bundle's package files, without their signatures.
+pub fn package_files(dir: &Path, manifest: &Manifest) -> Vec<String> {
+    manifest
+        .packages
+        .keys()
+        .filter(|name| !name.ends_with(".sig"))
+        .map(|name| dir.join("packages").join(name).to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Copies the directory `from` to `to`, recursively.
+pub fn copy_dir(from: &Path, to: &Path) -> Result<(), io::Error> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_role_name;
+
+    #[test]
+    fn check_role_name_rejects_paths() {
+        assert!(check_role_name("blue-teamer").is_ok());
+        for role in ["", ".", "..", ".hidden", "../../.bashrc", "a/b", "/etc/passwd", "..\\roles"] {
+            assert!(check_role_name(role).is_err(), "{:?} was accepted", role);
+        }
+    }
+}
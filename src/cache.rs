@@ -0,0 +1,198 @@
+//! `cyber-toolkit cache`: what the toolkit keeps to save downloads and pacman queries, and
+//! cleaning it up. There are three kinds of entries:
+//!
+//! - role definitions: cached role files, their metadata (`<role>.toml`) and the role index, in
+//!   the cache directory. `--offline` and the desktop menus read the configured roles from here.
+//! - package metadata: pacman's answers in `pacman-queries.json` (see `pacman_cache`).
+//! - bundles: the working directories of `bundle create` and `bundle install` in `$TMPDIR`,
+//!   which hold the downloaded package files. A finished run removes its own; an interrupted one
+//!   leaves it behind.
+//!
+//! `prune` applies the `[cache]` policy: entries not refreshed for `max_age_days` go, then the
+//! oldest ones until the cache fits into `max_size_mb`. The files of configured roles are kept,
+//! and leftover bundles are always removed since nothing reuses them. `clear` removes everything
+//! except the bundles of runs still going.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+
+use crate::discovery::ROLE_INDEX_FILE;
+use crate::i18n::t;
+use crate::pacman_cache;
+use crate::state;
+
+/// What a cache entry holds.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    Roles,
+    PackageMetadata,
+    Bundles,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 3] = [Kind::Roles, Kind::PackageMetadata, Kind::Bundles];
+
+    pub fn describe(self) -> String {
+        match self {
+            Kind::Roles => t!("cache-kind-roles"),
+            Kind::PackageMetadata => t!("cache-kind-package-metadata"),
+            Kind::Bundles => t!("cache-kind-bundles"),
+        }
+    }
+}
+
+/// A cached file, or a bundle directory.
+#[derive(Debug)]
+pub struct Entry {
+    pub kind: Kind,
+    pub path: PathBuf,
+    pub size: u64,
+    /// When it was last written, i.e. fetched or refreshed.
+    pub modified: SystemTime,
+    /// A configured role's file, which `prune` keeps.
+    pub configured: bool,
+    /// A bundle directory of a run that is still going, which is never removed.
+    pub in_use: bool,
+}
+
+/// What `prune` keeps; `None` means no limit.
+#[derive(Debug, Clone, Copy)]
+pub struct Policy {
+    pub max_age: Option<Duration>,
+    pub max_size: Option<u64>,
+}
+
+/// Lists the cache entries. `configured` are the configured roles, whose files are marked.
+///
+/// With `CYBER_TOOLKIT_HOME` the cache directory is also the configuration and state directory,
+/// so role files are only recognized by name there: those of configured roles and of the roles
+/// in the cached role index.
+pub fn entries(configured: &[String]) -> Result<Vec<Entry>, io::Error> {
+    let dir = state::cache_dir()?;
+    let shared = dir == state::state_dir()?;
+    let configured: HashSet<String> = configured.iter().map(|role| role.replace('/', "_")).collect();
+    let mut known: HashSet<String> = configured.clone();
+    if shared {
+        let index = state::read_cached_role(ROLE_INDEX_FILE).ok().flatten().unwrap_or_default();
+        known.extend(index.lines().map(|line| line.trim().replace('/', "_")).filter(|line| !line.is_empty()));
+    }
+
+    let mut entries = Vec::new();
+    if dir.is_dir() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let role = name.strip_suffix(".toml").unwrap_or(&name);
+            let kind = if name == pacman_cache::CACHE_FILE {
+                Kind::PackageMetadata
+            } else if name == ROLE_INDEX_FILE || !shared || known.contains(role) {
+                Kind::Roles
+            } else {
+                continue;
+            };
+            entries.push(Entry {
+                kind,
+                path: entry.path(),
+                size: metadata.len(),
+                modified: metadata.modified()?,
+                configured: kind == Kind::Roles && configured.contains(role),
+                in_use: false,
+            });
+        }
+    }
+    entries.extend(bundle_entries()?);
+    entries.sort_by_key(|entry| entry.modified);
+    Ok(entries)
+}
+
+/// Bundle working directories in `$TMPDIR` (see `bundle::work_dir`).
+#[cfg(unix)]
+fn bundle_entries() -> Result<Vec<Entry>, io::Error> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(std::env::temp_dir())? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let pid = name.strip_prefix(crate::bundle::WORK_DIR_PREFIX).and_then(|rest| rest.split_once('-'));
+        let Some(pid) = pid.and_then(|(pid, _)| pid.parse::<u32>().ok()) else {
+            continue;
+        };
+        let metadata = entry.metadata()?;
+        if !metadata.is_dir() {
+            continue;
+        }
+        entries.push(Entry {
+            kind: Kind::Bundles,
+            path: entry.path(),
+            size: dir_size(&entry.path()),
+            modified: metadata.modified()?,
+            configured: false,
+            in_use: pid == std::process::id() || Path::new("/proc").join(pid.to_string()).exists(),
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(windows)]
+fn bundle_entries() -> Result<Vec<Entry>, io::Error> {
+    Ok(Vec::new())
+}
+
+/// Total size of the files below `dir`; unreadable parts count as empty.
+#[cfg(unix)]
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(file_type) if file_type.is_file() => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// The entries `prune` removes under `policy` at `now`: leftover bundles, entries older than
+/// the age limit, then the oldest remaining ones until the total fits the size limit. Files of
+/// configured roles and bundles in use are kept. `entries` must be sorted oldest first.
+pub fn to_prune<'a>(entries: &'a [Entry], policy: &Policy, now: SystemTime) -> Vec<&'a Entry> {
+    let removable = |entry: &&Entry| !entry.configured && !entry.in_use;
+    let expired = |entry: &Entry| {
+        entry.kind == Kind::Bundles
+            || policy.max_age.is_some_and(|max_age| now.duration_since(entry.modified).unwrap_or_default() > max_age)
+    };
+    let mut pruned: Vec<&Entry> = entries.iter().filter(removable).filter(|entry| expired(entry)).collect();
+    if let Some(max_size) = policy.max_size {
+        let mut size: u64 = entries.iter().map(|entry| entry.size).sum::<u64>() - pruned.iter().map(|entry| entry.size).sum::<u64>();
+        for entry in entries.iter().filter(removable) {
+            if size <= max_size {
+                break;
+            }
+            if !pruned.iter().any(|pruned| std::ptr::eq(*pruned, entry)) {
+                size -= entry.size;
+                pruned.push(entry);
+            }
+        }
+    }
+    pruned
+}
+
+/// Removes an entry from the disk.
+pub fn remove(entry: &Entry) -> Result<(), io::Error> {
+    if entry.kind == Kind::Bundles {
+        fs::remove_dir_all(&entry.path)
+    } else {
+        fs::remove_file(&entry.path)
+    }
+}
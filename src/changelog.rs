@@ -0,0 +1,121 @@
+//! Per-role changelogs (`cyber-toolkit changelog <role>`).
+//!
+//! If the repository has a hand-written changelog `changelogs/<role>`, it is shown as is.
+//! Otherwise the changelog is derived from the git history of `roles/<role>`: the GitHub commits
+//! API lists the commits touching the file, and the tool lists before and after each commit are
+//! compared.
+
+use std::collections::BTreeSet;
+
+use reqwest::header::USER_AGENT;
+use serde::{Deserialize, Serialize};
+
+use cyber_toolkit::http;
+
+use crate::i18n::t;
+use crate::role_file;
+
+/// Base URL of hand-written changelog files.
+const CHANGELOGS_RAW_URL: &str = "https://raw.githubusercontent.com/jakubGodula/cyber-toolkit/main/changelogs/";
+
+/// GitHub commits API endpoint of the repository.
+const GITHUB_COMMITS_API_URL: &str = "https://api.github.com/repos/jakubGodula/cyber-toolkit/commits";
+
+/// Base URL for raw files at a specific commit (`<sha>/roles/<role>` is appended).
+const RAW_BASE_URL: &str = "https://raw.githubusercontent.com/jakubGodula/cyber-toolkit/";
+
+/// A role's changelog.
+#[derive(Serialize, Debug)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum Changelog {
+    /// Hand-written changelog file from the repository.
+    File { content: String },
+    /// Changes derived from the git history, newest first.
+    History { entries: Vec<ChangelogEntry> },
+}
+
+/// Changes to a role made by one commit.
+#[derive(Serialize, Debug)]
+pub struct ChangelogEntry {
+    pub date: String,
+    pub commit: String,
+    pub message: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubCommit {
+    sha: String,
+    commit: GitHubCommitDetails,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubCommitDetails {
+    message: String,
+    committer: GitHubCommitter,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubCommitter {
+    date: String,
+}
+
+/// Builds the changelog of a role, covering at most the `limit` most recent commits.
+pub async fn fetch_changelog(role: &str, limit: usize) -> Result<Changelog, Box<dyn std::error::Error>> {
+    let role = role.trim();
+
+    let response = http::get(&format!("{}{}", CHANGELOGS_RAW_URL, role))?.send().await?;
+
+    http::check_response(&response)?;
+    if response.status().is_success() {
+        return Ok(Changelog::File { content: response.text().await? });
+    }
+
+    // One extra commit provides the state before the oldest listed change
+    let url = format!("{}?path=roles/{}&per_page={}", GITHUB_COMMITS_API_URL, role, limit + 1);
+    let response = http::get(&url)?
+        .header(USER_AGENT, concat!("cyber-toolkit/", env!("CARGO_PKG_VERSION"))) // Required by the GitHub API
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?;
+    http::check_response(&response)?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Box::from(t!("github-commits-failed", url = url.as_str(), status = status.to_string())));
+    }
+    let commits: Vec<GitHubCommit> = response.json().await?;
+    if commits.is_empty() {
+        return Err(Box::from(t!("changelog-no-history", role = role)));
+    }
+
+    // Tool lists at each commit, oldest first
+    let mut versions = Vec::with_capacity(commits.len());
+    for commit in commits.iter().rev() {
+        let response = http::get(&format!("{}{}/roles/{}", RAW_BASE_URL, commit.sha, role))?.send().await?;
+        http::check_response(&response)?;
+        let tools: BTreeSet<String> = if response.status().is_success() {
+            role_file::parse_tool_list(&response.text().await?, role).tools.into_iter().collect()
+        } else {
+            BTreeSet::new() // Deleted in this commit
+        };
+        versions.push((commit, tools));
+    }
+
+    // Without the extra commit, the oldest listed commit created the file
+    let start = if commits.len() > limit { 1 } else { 0 };
+    let mut entries = Vec::new();
+    for index in start..versions.len() {
+        let (commit, tools) = &versions[index];
+        let previous = if index == 0 { BTreeSet::new() } else { versions[index - 1].1.clone() };
+        entries.push(ChangelogEntry {
+            date: commit.commit.committer.date.clone(),
+            commit: commit.sha.chars().take(7).collect(),
+            message: commit.commit.message.lines().next().unwrap_or_default().to_string(),
+            added: tools.difference(&previous).cloned().collect(),
+            removed: previous.difference(tools).cloned().collect(),
+        });
+    }
+    entries.reverse();
+    Ok(Changelog::History { entries })
+}
@@ -0,0 +1,136 @@
+//! Verification of downloaded role files against `MANIFEST.sha256` in the repository's `roles/`
+//! directory.
+//!
+//! The manifest is `sha256sum` output for the role files, one `<hex>  <role>` line each. It is
+//! fetched once per run, before the first role file. A role file whose SHA-256 differs from its
+//! manifest entry is always refused, so it never reaches the cache. Role files the manifest
+//! doesn't list, or all of them if there is no manifest, are accepted with a warning, unless
+//! `--strict` is given (see `set_strict`), which refuses everything that can't be verified.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use async_trait::async_trait;
+use cyber_toolkit::{HttpFetcher, RoleFetcher};
+use tokio::sync::OnceCell;
+
+use crate::i18n::t;
+use crate::lock;
+
+/// Name of the manifest in the repository's `roles/` directory.
+pub const MANIFEST_FILE: &str = "MANIFEST.sha256";
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Refuses (`true`) role files that can't be verified against the manifest.
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+fn is_strict() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// Parses `sha256sum` output into a map from role name to lowercase hex SHA-256.
+///
+/// The binary-mode marker (`*`) and a leading `./` or `roles/` are ignored; lines that aren't
+/// checksums are skipped.
+pub fn parse(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| line.trim().split_once(char::is_whitespace))
+        .filter(|(hash, _)| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|(hash, name)| {
+            let name = name.trim_start().trim_start_matches('*');
+            let name = name.strip_prefix("./").unwrap_or(name);
+            let name = name.strip_prefix("roles/").unwrap_or(name);
+            (name.to_string(), hash.to_ascii_lowercase())
+        })
+        .collect()
+}
+
+/// Fetches role files through `inner` and verifies them against the manifest `inner` provides.
+pub struct VerifyingFetcher<F> {
+    inner: F,
+    /// The manifest's checksums, or why it couldn't be fetched.
+    manifest: OnceCell<Result<HashMap<String, String>, String>>,
+}
+
+impl<F: RoleFetcher> VerifyingFetcher<F> {
+    pub fn new(inner: F) -> Self {
+        VerifyingFetcher { inner, manifest: OnceCell::new() }
+    }
+
+    async fn manifest(&self) -> &Result<HashMap<String, String>, String> {
+        self.manifest
+            .get_or_init(|| async {
+                let manifest = self.inner.fetch(MANIFEST_FILE).await.map(|content| parse(&content)).map_err(|e| e.to_string());
+                if let Err(e) = &manifest {
+                    if !is_strict() {
+                        eprintln!("{}", t!("checksums-manifest-unavailable", manifest = MANIFEST_FILE, error = e.as_str()));
+                    }
+                }
+                manifest
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl<F: RoleFetcher> RoleFetcher for VerifyingFetcher<F> {
+    async fn fetch(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let manifest = self.manifest().await;
+        let content = self.inner.fetch(name).await?;
+        let name = name.trim();
+        let checksums = match manifest {
+            Ok(checksums) => checksums,
+            Err(e) if is_strict() => {
+                return Err(Box::from(t!("checksums-strict-no-manifest", role = name, manifest = MANIFEST_FILE, error = e.as_str())));
+            }
+            Err(_) => return Ok(content),
+        };
+        match checksums.get(name) {
+            Some(expected) if *expected == lock::hash_content(&content) => Ok(content),
+            Some(_) => Err(Box::from(t!("checksums-mismatch", role = name, manifest = MANIFEST_FILE))),
+            None if is_strict() => Err(Box::from(t!("checksums-strict-unlisted", role = name, manifest = MANIFEST_FILE))),
+            None => {
+                eprintln!("{}", t!("checksums-unlisted", role = name, manifest = MANIFEST_FILE));
+                Ok(content)
+            }
+        }
+    }
+
+    fn location(&self, name: &str) -> String {
+        self.inner.location(name)
+    }
+}
+
+/// Returns the fetcher for the official repository, verifying role files against its manifest.
+/// The manifest is fetched once per run.
+pub fn repository_fetcher() -> &'static VerifyingFetcher<HttpFetcher> {
+    static FETCHER: OnceLock<VerifyingFetcher<HttpFetcher>> = OnceLock::new();
+    FETCHER.get_or_init(|| VerifyingFetcher::new(HttpFetcher::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    const BLUE: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+    const RED: &str = "60303AE22B998861BCE3B28F33EEC1BE758A213C86C93C076DBE9F558C11C752";
+
+    #[test]
+    fn parse_reads_sha256sum_output() {
+        let manifest = parse(&format!("{}  blue.txt\n{} *./roles/red.txt\n", BLUE, RED));
+        assert_eq!(manifest.len(), 2);
+        assert_eq!(manifest["blue.txt"], BLUE);
+        assert_eq!(manifest["red.txt"], RED.to_ascii_lowercase());
+    }
+
+    #[test]
+    fn parse_skips_lines_that_are_not_checksums() {
+        let manifest = parse(&format!("# Generated by sha256sum\n\nabc123  short.txt\n{}  web.txt\nnot-hex-{}  bad.txt\n", BLUE, &BLUE[8..]));
+        assert_eq!(manifest.keys().collect::<Vec<_>>(), ["web.txt"]);
+    }
+}
@@ -0,0 +1,308 @@
+//! The role configuration file, `roles.cnf` in the configuration directory by default
+//! (`~/.config/cyber-toolkit/roles.cnf`): one configured role per line.
+//!
+//! Lines starting with `#` are comments and blank lines are ignored; both are kept when the file
+//! is rewritten. Writes go to a new temporary file, flushed to disk and then renamed over the
+//! original, so the file is never left half-written, and the previous version is kept as a
+//! single timestamped backup next to it (`roles.cnf.<unix time>.bak`).
+//!
+//! Role names are canonicalized (see `roles::canonical_name`), so `Blue-Teamer` and
+//! `blue-teamer.txt` on separate lines are one role, written back as `blue-teamer`.
+//!
+//! Another process (e.g. a second terminal) may change the file between a read and the write
+//! based on it. A store remembers the content it first read, and if the file differs from it
+//! when writing, the changes are merged instead of overwritten: roles this store added or
+//! removed are applied to the file as it is now, so the other process's changes are kept.
+//! Writers take turns: each holds an advisory lock on `roles.cnf.lock` from reading the current
+//! file to renaming the new one, so no write is based on a file another write is replacing.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::paths;
+use crate::roles;
+
+/// Name of the configuration file inside the state directory.
+pub const CONFIG_FILE_NAME: &str = "roles.cnf";
+
+/// Returns the default configuration file path, `roles.cnf` in the configuration directory
+/// (see `paths::config_dir`).
+pub fn default_config_path() -> Result<PathBuf, io::Error> {
+    Ok(paths::config_dir()?.join(CONFIG_FILE_NAME))
+}
+
+/// Returns the system-wide configuration file, `roles.cnf` in the system configuration directory
+/// (see `paths::system_config_dir`). Its roles are configured for every user, under their own.
+pub fn system_config_path() -> PathBuf {
+    paths::system_config_dir().join(CONFIG_FILE_NAME)
+}
+
+/// Reads and writes the configured roles of one configuration file.
+#[derive(Debug)]
+pub struct ConfigStore {
+    path: PathBuf,
+    /// Content of the file when this store first read it since its last write; a write that
+    /// finds different content merges (see the module documentation).
+    baseline: Mutex<Option<String>>,
+}
+
+/// Changes another process made to the configuration file since this store read it, which a
+/// write merged with its own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExternalChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl Clone for ConfigStore {
+    fn clone(&self) -> Self {
+        ConfigStore { path: self.path.clone(), baseline: Mutex::new(self.baseline()) }
+    }
+}
+
+impl ConfigStore {
+    /// A store for the configuration file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        ConfigStore { path: path.into(), baseline: Mutex::new(None) }
+    }
+
+    /// A store for `roles.cnf` inside `base_dir`, e.g. a temporary directory in tests.
+    pub fn in_dir(base_dir: &Path) -> Self {
+        ConfigStore::new(base_dir.join(CONFIG_FILE_NAME))
+    }
+
+    /// The store for the default configuration file.
+    pub fn open_default() -> Result<Self, io::Error> {
+        Ok(ConfigStore::new(default_config_path()?))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the configured roles, canonicalized and without duplicates, skipping comments and
+    /// blank lines. A missing file means no roles.
+    pub fn read_roles(&self) -> Result<Vec<String>, io::Error> {
+        let content = self.read_content()?;
+        self.baseline.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert_with(|| content.clone());
+        Ok(roles_in(&content))
+    }
+
+    /// Replaces the configured roles.
+    ///
+    /// Comments, blank lines and the lines of roles that stay are kept in place (in canonical
+    /// spelling); lines of dropped roles and duplicates are removed and new roles are appended.
+    /// The previous file becomes the backup.
+    ///
+    /// If another process changed the file since this store read it, only the roles added and
+    /// removed relative to that read are applied to the current file, and the other process's
+    /// changes are returned.
+    pub fn write_roles(&self, roles: &[String]) -> Result<Option<ExternalChanges>, io::Error> {
+        let mut roles = roles::canonical_names(roles);
+        if let Some(parent_dir) = self.path.parent() {
+            fs::create_dir_all(parent_dir)?;
+        }
+        let _lock = self.lock()?;
+        let current = self.read_content()?;
+        let mut external = None;
+        if let Some(baseline) = self.baseline().filter(|baseline| *baseline != current) {
+            let (base_roles, current_roles) = (roles_in(&baseline), roles_in(&current));
+            let added: Vec<&String> = roles.iter().filter(|role| !base_roles.contains(role)).collect();
+            let removed: Vec<&String> = base_roles.iter().filter(|role| !roles.contains(role)).collect();
+            let merged: Vec<String> = current_roles
+                .iter()
+                .filter(|role| !removed.contains(role))
+                .chain(added.into_iter().filter(|role| !current_roles.contains(role)))
+                .cloned()
+                .collect();
+            external = Some(ExternalChanges {
+                added: current_roles.iter().filter(|role| !base_roles.contains(role)).cloned().collect(),
+                removed: base_roles.iter().filter(|role| !current_roles.contains(role)).cloned().collect(),
+            });
+            roles = merged;
+        }
+
+        let previous: Vec<&str> = current.lines().collect();
+        let mut lines = Vec::new();
+        let mut written: Vec<String> = Vec::new();
+        for line in &previous {
+            match role_of(line).map(roles::canonical_name) {
+                Some(role) if roles.contains(&role) && !written.contains(&role) => {
+                    lines.push(role.clone());
+                    written.push(role);
+                }
+                Some(_) => {}
+                None => lines.push(line.to_string()),
+            }
+        }
+        for role in roles {
+            if !written.contains(&role) {
+                lines.push(role.clone());
+                written.push(role);
+            }
+        }
+
+        let content: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        let temp_path = self.write_temporary(&content)?;
+        let replaced = if self.path.exists() { self.replace_backup() } else { Ok(()) };
+        if let Err(e) = replaced.and_then(|()| fs::rename(&temp_path, &self.path)) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+        *self.baseline.lock().unwrap_or_else(|e| e.into_inner()) = Some(content);
+        Ok(external)
+    }
+
+    /// Returns the path of the current backup, if there is one.
+    pub fn backup_path(&self) -> Result<Option<PathBuf>, io::Error> {
+        Ok(self.backups()?.into_iter().max())
+    }
+
+    fn read_content(&self) -> Result<String, io::Error> {
+        if !self.path.exists() {
+            return Ok(String::new()); // No config file means no roles configured yet
+        }
+        fs::read_to_string(&self.path)
+    }
+
+    /// Takes the writers' lock (see the module documentation), waiting while another writer
+    /// holds it. It is released when the returned file is dropped.
+    fn lock(&self) -> Result<File, io::Error> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(self.sibling(".lock"))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    /// Writes `content` to a new temporary file next to the configuration file, flushed to disk,
+    /// and returns its path. The name is unique to this write, and an existing file is never
+    /// written through.
+    fn write_temporary(&self, content: &str) -> Result<PathBuf, io::Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        loop {
+            let path = self.sibling(&format!(".{}-{}.tmp", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+            let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue, // Left over from a crashed run
+                Err(e) => return Err(e),
+            };
+            let written = file.write_all(content.as_bytes()).and_then(|()| file.sync_all());
+            if let Err(e) = written {
+                let _ = fs::remove_file(&path);
+                return Err(e);
+            }
+            return Ok(path);
+        }
+    }
+
+    fn baseline(&self) -> Option<String> {
+        self.baseline.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Copies the current file to a new timestamped backup and deletes the older ones.
+    fn replace_backup(&self) -> Result<(), io::Error> {
+        let old_backups = self.backups()?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or_default();
+        let backup = self.sibling(&format!(".{}.bak", timestamp));
+        fs::copy(&self.path, &backup)?;
+        for old in old_backups.into_iter().filter(|old| *old != backup) {
+            fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+
+    fn backups(&self) -> Result<Vec<PathBuf>, io::Error> {
+        let (Some(dir), Some(name)) = (self.path.parent(), self.path.file_name()) else {
+            return Ok(Vec::new());
+        };
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let prefix = format!("{}.", name.to_string_lossy());
+        let mut backups = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry_name = entry?.file_name().to_string_lossy().into_owned();
+            if entry_name.starts_with(&prefix) && entry_name.ends_with(".bak") {
+                backups.push(dir.join(entry_name));
+            }
+        }
+        Ok(backups)
+    }
+
+    /// A path next to the configuration file, with `suffix` appended to its name.
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(suffix);
+        self.path.with_file_name(name)
+    }
+}
+
+/// The configured roles in the content of a configuration file.
+fn roles_in(content: &str) -> Vec<String> {
+    roles::canonical_names(&content.lines().filter_map(role_of).collect::<Vec<_>>())
+}
+
+/// The role named on a line, or `None` for comments and blank lines.
+fn role_of(line: &str) -> Option<&str> {
+    let line = line.trim();
+    (!line.is_empty() && !line.starts_with('#')).then_some(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempConfig;
+
+    fn roles(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    /// Two stores that both read `initial`, as two processes would.
+    fn two_stores(config: &TempConfig, initial: &[&str]) -> (ConfigStore, ConfigStore) {
+        let (ours, theirs) = (ConfigStore::new(config.path()), ConfigStore::new(config.path()));
+        assert_eq!(ours.read_roles().unwrap(), initial);
+        assert_eq!(theirs.read_roles().unwrap(), initial);
+        (ours, theirs)
+    }
+
+    #[test]
+    fn a_role_added_on_both_sides_is_written_once() {
+        let config = TempConfig::with_roles(&["blue"]).unwrap();
+        let (ours, theirs) = two_stores(&config, &["blue"]);
+        assert_eq!(theirs.write_roles(&roles(&["blue", "web"])).unwrap(), None);
+
+        let external = ours.write_roles(&roles(&["blue", "Web.txt", "red"])).unwrap();
+
+        assert_eq!(external, Some(ExternalChanges { added: roles(&["web"]), removed: Vec::new() }));
+        assert_eq!(fs::read_to_string(config.path()).unwrap(), "blue\nweb\nred\n");
+    }
+
+    #[test]
+    fn a_role_removed_on_both_sides_stays_removed() {
+        let config = TempConfig::with_roles(&["blue", "red", "web"]).unwrap();
+        let (ours, theirs) = two_stores(&config, &["blue", "red", "web"]);
+        assert_eq!(theirs.write_roles(&roles(&["blue", "web"])).unwrap(), None);
+
+        let external = ours.write_roles(&roles(&["web"])).unwrap();
+
+        assert_eq!(external, Some(ExternalChanges { added: Vec::new(), removed: roles(&["red"]) }));
+        assert_eq!(ours.read_roles().unwrap(), ["web"]);
+    }
+
+    #[test]
+    fn merging_keeps_the_other_sides_changes_and_comments() {
+        let config = TempConfig::new().unwrap();
+        fs::write(config.path(), "# Workstation\nBlue-Teamer\nred\n").unwrap();
+        let (ours, theirs) = two_stores(&config, &["blue-teamer", "red"]);
+        assert_eq!(theirs.write_roles(&roles(&["blue-teamer", "web"])).unwrap(), None);
+
+        // Removing a role the other side already removed, and adding one it didn't touch
+        let external = ours.write_roles(&roles(&["blue-teamer", "forensics"])).unwrap();
+
+        assert_eq!(external, Some(ExternalChanges { added: roles(&["web"]), removed: roles(&["red"]) }));
+        assert_eq!(fs::read_to_string(config.path()).unwrap(), "# Workstation\nblue-teamer\nweb\nforensics\n");
+    }
+}
@@ -0,0 +1,114 @@
+//! Detection of conflicting native packages before the pacman transaction starts.
+//!
+//! Roles can bring in packages that can't be installed together, such as competing providers
+//! (`iptables` vs `iptables-nft`), or that conflict with a package already installed. pacman only
+//! notices this in the middle of the transaction, where `--noconfirm` makes it give up. The
+//! conflicts are detected from `pacman -Si` up front and resolved by asking the user instead.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::i18n::t;
+use crate::pacman::{self, SyncPackage};
+use crate::{platform, ui};
+
+/// Two packages that can't be installed together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    /// The planned package declaring the conflict.
+    pub package: String,
+    /// The package it conflicts with: another planned package, or an installed one.
+    pub other: String,
+    /// Whether `other` is already installed rather than planned.
+    pub installed: bool,
+}
+
+/// Finds the conflicts among `plan` and between `plan` and the `installed` packages.
+pub fn find(plan: &[String], installed: &HashMap<String, String>) -> Result<Vec<Conflict>, Box<dyn std::error::Error>> {
+    let relations = pacman::query_sync_packages(plan)?;
+    // A conflict can name a package or anything it provides
+    let mut planned_names: HashMap<&str, &str> = plan.iter().map(|package| (package.as_str(), package.as_str())).collect();
+    for package in plan {
+        for provided in relations.get(package).map(|relation| relation.provides.as_slice()).unwrap_or_default() {
+            planned_names.entry(provided).or_insert(package);
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    let mut seen = HashSet::new();
+    for SyncPackage { name, conflicts: conflicting, .. } in plan.iter().filter_map(|package| relations.get(package)) {
+        for conflict in conflicting {
+            // Providers often declare a conflict with their own provided name, which only
+            // matters if another package of that name is installed
+            let (other, is_installed) = match planned_names.get(conflict.as_str()) {
+                Some(other) if other != name => (other.to_string(), false),
+                _ if conflict != name && installed.contains_key(conflict) => (conflict.clone(), true),
+                _ => continue,
+            };
+            // Each pair is reported once, even if both packages declare the conflict
+            let pair = if *name < other { (name.clone(), other.clone()) } else { (other.clone(), name.clone()) };
+            if seen.insert(pair) {
+                conflicts.push(Conflict { package: name.clone(), other, installed: is_installed });
+            }
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Resolves `conflicts` by asking which package to install, and returns `plan` without the
+/// packages left out. Without a terminal nobody can choose, so any conflict is an error.
+pub fn resolve(mut plan: Vec<String>, conflicts: &[Conflict]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if conflicts.is_empty() {
+        return Ok(plan);
+    }
+    let described: Vec<String> = conflicts.iter().map(describe).collect();
+    // The choice is a menu, which plain output rules out; --yes doesn't pick a side either
+    if !ui().is_interactive() || platform::is_plain() {
+        return Err(Box::from(t!("conflicts-unresolved", conflicts = described.join("\n  "))));
+    }
+
+    println!("{}", t!("conflicts-found"));
+    let mut skipped: HashSet<String> = HashSet::new();
+    for (conflict, description) in conflicts.iter().zip(&described) {
+        if skipped.contains(&conflict.package) || skipped.contains(&conflict.other) {
+            continue; // Already resolved by an earlier choice
+        }
+        let choices = if conflict.installed {
+            vec![
+                t!("conflicts-replace", package = conflict.package.as_str(), other = conflict.other.as_str()),
+                t!("conflicts-skip", package = conflict.package.as_str()),
+                t!("conflicts-abort"),
+            ]
+        } else {
+            vec![
+                t!("conflicts-choose", package = conflict.package.as_str(), other = conflict.other.as_str()),
+                t!("conflicts-choose", package = conflict.other.as_str(), other = conflict.package.as_str()),
+                t!("conflicts-abort"),
+            ]
+        };
+        let choice = ui().select(description, &choices, 0)?;
+        match (choice, conflict.installed) {
+            (Some(0), true) => {}
+            (Some(0), false) => {
+                skipped.insert(conflict.other.clone());
+            }
+            (Some(1), _) => {
+                skipped.insert(conflict.package.clone());
+            }
+            _ => return Err(Box::from(t!("conflicts-aborted"))),
+        }
+    }
+    if !skipped.is_empty() {
+        println!("{}", t!("conflicts-skipped", packages = format!("{:?}", skipped)));
+        plan.retain(|package| !skipped.contains(package));
+    }
+    Ok(plan)
+}
+
+/// Describes a conflict for the user.
+fn describe(conflict: &Conflict) -> String {
+    if conflict.installed {
+        t!("conflict-installed", package = conflict.package.as_str(), other = conflict.other.as_str())
+    } else {
+        t!("conflict-planned", package = conflict.package.as_str(), other = conflict.other.as_str())
+    }
+}
@@ -0,0 +1,136 @@
+//! Desktop menu integration: groups each role's GUI tools under a "Cyber Toolkit" submenu.
+//!
+//! Follows the XDG menu specification. For every role with GUI tools this writes
+//! - `~/.local/share/desktop-directories/cyber-toolkit-<role>.directory` (the submenu's name), and
+//! - `~/.config/menus/applications-merged/cyber-toolkit-<role>.menu` (which launchers it contains),
+//!
+//! placed under a shared `Cyber Toolkit` menu. The launchers themselves are the `.desktop` files
+//! shipped by the tools' packages. Removing a role deletes its files again.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::i18n::t;
+use crate::{pacman, role_file, state};
+
+/// Prefix of every file generated by the toolkit, so they can be recognized and cleaned up.
+const FILE_PREFIX: &str = "cyber-toolkit";
+
+/// Directory holding system launchers; a package's `.desktop` files here are its GUI entry points.
+const APPLICATIONS_DIR: &str = "/usr/share/applications/";
+
+fn menus_dir() -> Result<PathBuf, io::Error> {
+    dirs::config_dir()
+        .map(|dir| dir.join("menus").join("applications-merged"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, t!("config-dir-not-found")))
+}
+
+fn directories_dir() -> Result<PathBuf, io::Error> {
+    dirs::data_dir()
+        .map(|dir| dir.join("desktop-directories"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, t!("data-dir-not-found")))
+}
+
+/// File-name-safe version of a role name.
+fn role_slug(role: &str) -> String {
+    role.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect()
+}
+
+/// Escapes text for use inside XML elements.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Regenerates the submenus of the given roles from their cached role files.
+///
+/// Roles without installed GUI launchers get no submenu (and lose any stale one).
+/// Returns the number of submenus written.
+pub fn update_menus(roles: &[String]) -> Result<usize, Box<dyn std::error::Error>> {
+    let menus_dir = menus_dir()?;
+    let directories_dir = directories_dir()?;
+    let mut written = 0;
+
+    for role in roles {
+        let Some(content) = state::read_cached_role(role)? else {
+            continue; // Role was never fetched successfully
+        };
+        let tools = role_file::parse_tool_list(&content, role).tools;
+        let mut launchers: Vec<String> = pacman::query_files(&tools)?
+            .into_iter()
+            .filter_map(|(_, path)| {
+                let file_name = path.strip_prefix(APPLICATIONS_DIR)?;
+                (file_name.ends_with(".desktop") && !file_name.contains('/')).then(|| file_name.to_string())
+            })
+            .collect();
+        launchers.sort_unstable();
+        launchers.dedup();
+
+        if launchers.is_empty() {
+            remove_menus(std::slice::from_ref(role))?;
+            continue;
+        }
+
+        fs::create_dir_all(&menus_dir)?;
+        fs::create_dir_all(&directories_dir)?;
+        write_parent_directory_file(&directories_dir)?;
+
+        let slug = role_slug(role);
+        fs::write(
+            directories_dir.join(format!("{}-{}.directory", FILE_PREFIX, slug)),
+            format!("[Desktop Entry]\nType=Directory\nName={}\nIcon=applications-system\n", role),
+        )?;
+
+        let includes: String = launchers
+            .iter()
+            .map(|launcher| format!("        <Filename>{}</Filename>\n", xml_escape(launcher)))
+            .collect();
+        let menu = format!(
+            "<!DOCTYPE Menu PUBLIC \"-//freedesktop//DTD Menu 1.0//EN\"\n \"http://www.freedesktop.org/standards/menu-spec/1.0/menu.dtd\">\n\
+             <Menu>\n  <Name>Applications</Name>\n  <Menu>\n    <Name>Cyber Toolkit</Name>\n    <Directory>{prefix}.directory</Directory>\n\
+             \x20   <Menu>\n      <Name>{name}</Name>\n      <Directory>{prefix}-{slug}.directory</Directory>\n      <Include>\n{includes}      </Include>\n\
+             \x20   </Menu>\n  </Menu>\n</Menu>\n",
+            prefix = FILE_PREFIX,
+            name = xml_escape(role),
+            slug = slug,
+            includes = includes,
+        );
+        fs::write(menus_dir.join(format!("{}-{}.menu", FILE_PREFIX, slug)), menu)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Writes the `.directory` file naming the shared "Cyber Toolkit" parent menu.
+fn write_parent_directory_file(directories_dir: &std::path::Path) -> Result<(), io::Error> {
+    fs::write(
+        directories_dir.join(format!("{}.directory", FILE_PREFIX)),
+        "[Desktop Entry]\nType=Directory\nName=Cyber Toolkit\nIcon=security-high\n",
+    )
+}
+
+/// Deletes the submenus of the given roles, and the parent menu once no role submenus are left.
+pub fn remove_menus(roles: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let menus_dir = menus_dir()?;
+    let directories_dir = directories_dir()?;
+    for role in roles {
+        let slug = role_slug(role);
+        for path in [
+            menus_dir.join(format!("{}-{}.menu", FILE_PREFIX, slug)),
+            directories_dir.join(format!("{}-{}.directory", FILE_PREFIX, slug)),
+        ] {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+    }
+
+    let any_menus_left = fs::read_dir(&menus_dir)
+        .map(|entries| entries.flatten().any(|entry| entry.file_name().to_string_lossy().starts_with(FILE_PREFIX)))
+        .unwrap_or(false);
+    let parent = directories_dir.join(format!("{}.directory", FILE_PREFIX));
+    if !any_menus_left && parent.exists() {
+        fs::remove_file(parent)?;
+    }
+    Ok(())
+}
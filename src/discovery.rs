@@ -0,0 +1,118 @@
+//! Discovery of the role files available in the remote repository.
+//!
+//! By default the curated `role_names` index file is used. Alternatively, the GitHub
+//! contents API can list the `roles/` directory directly, so newly added role files
+//! show up without anyone updating the index.
+
+use reqwest::header::{HeaderMap, LINK, USER_AGENT};
+use serde::Deserialize;
+
+use cyber_toolkit::http;
+
+use crate::i18n::t;
+use crate::state;
+use crate::BASE_RAW_URL;
+
+/// GitHub contents API endpoint for the `roles/` directory of the repository.
+const GITHUB_CONTENTS_API_URL: &str = "https://api.github.com/repos/jakubGodula/cyber-toolkit/contents/roles";
+
+/// Branch whose role files are listed; matches the branch used by `BASE_RAW_URL`.
+const GITHUB_REF: &str = "main";
+
+/// Name of the index file listing available roles, one per line.
+pub const ROLE_INDEX_FILE: &str = "role_names";
+
+/// A single entry returned by the GitHub contents API for a directory listing.
+#[derive(Deserialize, Debug)]
+pub struct GitHubContentItem {
+    pub name: String,
+    /// `file`, `dir`, `symlink` or `submodule`.
+    #[serde(rename = "type")]
+    pub item_type: String,
+}
+
+/// Fetches the available role names from the `role_names` index file.
+///
+/// The index is cached like role files; in offline mode, or while the repository rate-limits us,
+/// the cached copy is used instead.
+pub async fn fetch_role_index() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let text = if http::is_offline() {
+        state::read_cached_role(ROLE_INDEX_FILE)?
+            .ok_or_else(|| t!("role-index-not-cached"))?
+    } else {
+        let fetched = fetch_text(&format!("{}{}", BASE_RAW_URL, ROLE_INDEX_FILE)).await;
+        let text = state::or_cached_when_rate_limited(fetched, ROLE_INDEX_FILE)?;
+        if let Err(e) = state::write_cached_role(ROLE_INDEX_FILE, &text) {
+            eprintln!("{}", t!("role-index-cache-failed", error = e.to_string()));
+        }
+        text
+    };
+    let mut roles: Vec<String> = text
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    roles.sort_unstable();
+    roles.dedup();
+    Ok(roles)
+}
+
+async fn fetch_text(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let response = http::get(url)?.send().await?;
+    http::check_response(&response)?;
+    if !response.status().is_success() {
+        return Err(Box::from(t!("role-index-fetch-failed", url = url, status = response.status().to_string())));
+    }
+    Ok(response.text().await?)
+}
+
+/// Lists role files via the GitHub contents API.
+///
+/// - Follows `Link: <...>; rel="next"` headers so large directories are listed completely.
+/// - Only regular files count as roles; the `role_names` index itself is skipped.
+/// - On rate limiting (see `http::check_response`), returns a `RateLimited` error stating when
+///   the limit resets.
+pub async fn discover_roles_via_api() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut roles = Vec::new();
+    let mut next_url = Some(format!("{}?ref={}&per_page=100", GITHUB_CONTENTS_API_URL, GITHUB_REF));
+
+    while let Some(url) = next_url.take() {
+        let response = http::get(&url)?
+            .header(USER_AGENT, concat!("cyber-toolkit/", env!("CARGO_PKG_VERSION"))) // Required by the GitHub API
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await?;
+
+        http::check_response(&response)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Box::from(t!("github-contents-failed", url = url.as_str(), status = status.to_string())));
+        }
+
+        next_url = next_page_url(response.headers());
+        let items: Vec<GitHubContentItem> = response.json().await?;
+        roles.extend(
+            items
+                .into_iter()
+                .filter(|item| item.item_type == "file" && item.name != ROLE_INDEX_FILE)
+                .map(|item| item.name),
+        );
+    }
+
+    roles.sort_unstable();
+    roles.dedup();
+    Ok(roles)
+}
+
+/// Extracts the `rel="next"` URL from a GitHub `Link` header, if present.
+fn next_page_url(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (url, rel) = part.split_once(';')?;
+        if rel.trim() == "rel=\"next\"" {
+            Some(url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+        } else {
+            None
+        }
+    })
+}
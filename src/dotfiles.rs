@@ -0,0 +1,170 @@
+//! Deployment of configuration files ("dotfiles") carried by roles.
+//!
+//! Templates live under `dotfiles/` in the repository and are declared as `[[dotfiles]]` in role
+//! metadata. Before deployment, `{{home}}`, `{{user}}`, `{{hostname}}` and `{{role}}` are replaced
+//! with their values.
+//!
+//! Every deployed file is recorded with its SHA-256 in the `dotfiles.json` state document. That record is
+//! what makes deployment safe:
+//! - An existing file that the toolkit didn't deploy is a conflict and is left untouched.
+//! - A deployed file is only updated or removed while it's unmodified; user edits are kept.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use cyber_toolkit::http;
+
+use crate::i18n::t;
+use crate::metadata::DotfileSpec;
+use crate::state_db;
+
+/// Base URL from which dotfile templates are fetched.
+const DOTFILES_RAW_URL: &str = "https://raw.githubusercontent.com/jakubGodula/cyber-toolkit/main/dotfiles/";
+
+/// A file deployed for a role.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeployedFile {
+    pub target: PathBuf,
+    /// SHA-256 of the content as deployed.
+    pub sha256: String,
+}
+
+/// Counts of what a deployment did.
+#[derive(Debug, Default)]
+pub struct DeployReport {
+    pub written: usize,
+    pub unchanged: usize,
+    /// Targets left alone because they exist and weren't deployed by the toolkit, or were edited.
+    pub conflicts: Vec<PathBuf>,
+}
+
+fn read_record() -> Result<BTreeMap<String, Vec<DeployedFile>>, Box<dyn std::error::Error>> {
+    match state_db::read_document("dotfiles.json")? {
+        Some(content) => Ok(serde_json::from_str(&content)?),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+fn write_record(record: &BTreeMap<String, Vec<DeployedFile>>) -> Result<(), Box<dyn std::error::Error>> {
+    state_db::write_document("dotfiles.json", Some(&serde_json::to_string_pretty(record)?))
+}
+
+fn sha256_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Expands a leading `~/` to the home directory. Only paths inside the home directory are accepted.
+fn resolve_target(target: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let home = dirs::home_dir().ok_or_else(|| t!("home-not-found"))?;
+    let relative = target
+        .strip_prefix("~/")
+        .ok_or_else(|| t!("dotfile-target-not-home-relative", target = format!("{:?}", target)))?;
+    if relative.split('/').any(|part| part == "..") {
+        return Err(Box::from(t!("dotfile-target-outside-home", target = format!("{:?}", target))));
+    }
+    Ok(home.join(relative))
+}
+
+/// Replaces the template variables in a dotfile.
+fn render(template: &str, role: &str) -> String {
+    let home = dirs::home_dir().map(|h| h.to_string_lossy().into_owned()).unwrap_or_default();
+    let user = std::env::var("USER").unwrap_or_default();
+    let hostname = fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+    template
+        .replace("{{home}}", &home)
+        .replace("{{user}}", &user)
+        .replace("{{hostname}}", hostname.trim())
+        .replace("{{role}}", role)
+}
+
+/// Deploys (or updates) the dotfiles of a role.
+pub async fn deploy_role(role: &str, specs: &[DotfileSpec]) -> Result<DeployReport, Box<dyn std::error::Error>> {
+    let mut record = read_record()?;
+    let previously_deployed = record.remove(role).unwrap_or_default();
+    let mut deployed = Vec::new();
+    let mut report = DeployReport::default();
+
+    for spec in specs {
+        let target = resolve_target(&spec.target)?;
+        let url = format!("{}{}", DOTFILES_RAW_URL, spec.source);
+        let response = http::get(&url)?.send().await?;
+        http::check_response(&response)?;
+        if !response.status().is_success() {
+            return Err(Box::from(t!("dotfile-fetch-failed", url = url, status = response.status().to_string())));
+        }
+        let content = render(&response.text().await?, role);
+        let new_hash = sha256_hex(content.as_bytes());
+
+        if target.exists() {
+            let current_hash = sha256_hex(&fs::read(&target)?);
+            if current_hash == new_hash {
+                report.unchanged += 1;
+                deployed.push(DeployedFile { target, sha256: new_hash });
+                continue;
+            }
+            let is_ours = previously_deployed
+                .iter()
+                .chain(record.values().flatten()) // Another role may have deployed the same file
+                .any(|file| file.target == target && file.sha256 == current_hash);
+            if !is_ours {
+                // Not ours, or edited since we deployed it
+                eprintln!("{}", t!("dotfile-conflict", path = format!("{:?}", target)));
+                if let Some(file) = previously_deployed.iter().find(|file| file.target == target) {
+                    deployed.push(file.clone()); // Keep tracking it so it's reported again later
+                }
+                report.conflicts.push(target);
+                continue;
+            }
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, &content)?;
+        println!("{}", t!("dotfile-deployed", path = format!("{:?}", target), role = role));
+        report.written += 1;
+        deployed.push(DeployedFile { target, sha256: new_hash });
+    }
+
+    // Files the role no longer carries are cleaned up like on removal
+    for stale in previously_deployed.iter().filter(|old| !deployed.iter().any(|new| new.target == old.target)) {
+        remove_if_unused(stale, &record)?;
+    }
+
+    if !deployed.is_empty() {
+        record.insert(role.to_string(), deployed);
+    }
+    write_record(&record)?;
+    Ok(report)
+}
+
+/// Removes the dotfiles deployed for a role, keeping any the user has modified since.
+pub fn remove_role(role: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut record = read_record()?;
+    let Some(files) = record.remove(role) else {
+        return Ok(());
+    };
+    for file in &files {
+        remove_if_unused(file, &record)?;
+    }
+    write_record(&record)?;
+    Ok(())
+}
+
+/// Removes a deployed file unless another role still tracks it or the user modified it.
+fn remove_if_unused(file: &DeployedFile, other_roles: &BTreeMap<String, Vec<DeployedFile>>) -> Result<(), Box<dyn std::error::Error>> {
+    if !file.target.exists() || other_roles.values().flatten().any(|other| other.target == file.target) {
+        return Ok(());
+    }
+    if sha256_hex(&fs::read(&file.target)?) == file.sha256 {
+        fs::remove_file(&file.target)?;
+        println!("{}", t!("path-removed", path = format!("{:?}", file.target)));
+    } else {
+        eprintln!("{}", t!("dotfile-modified", path = format!("{:?}", file.target)));
+    }
+    Ok(())
+}
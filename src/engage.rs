@@ -0,0 +1,142 @@
+//! Engagements: preparing the machine for a HackTheBox or TryHackMe box from its tags.
+//!
+//! `cyber-toolkit engage htb:web,ad` maps each tag to roles with `presets/engage.toml` in the
+//! repository:
+//!
+//! ```toml
+//! [tags]                      # Tags of every platform
+//! web = ["web"]
+//! dfir = ["preset:dfir-starter"]
+//!
+//! [htb]                       # Tags of one platform, checked first
+//! sherlock = ["preset:dfir-starter"]
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use cyber_toolkit::http;
+
+use crate::i18n::t;
+use crate::presets;
+use crate::suggest;
+
+/// URL of the tag mapping.
+const ENGAGE_URL: &str = "https://raw.githubusercontent.com/jakubGodula/cyber-toolkit/main/presets/engage.toml";
+
+/// Supported platforms, by prefix.
+pub const PLATFORMS: [(&str, &str); 2] = [("htb", "HackTheBox"), ("thm", "TryHackMe")];
+
+/// Prefix of a mapping target that stands for a preset's roles.
+const PRESET_PREFIX: &str = "preset:";
+
+/// Content of `engage.toml`: tables of tags, each mapped to roles and presets.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct TagMap {
+    /// Tags shared by all platforms.
+    tags: BTreeMap<String, Vec<String>>,
+    /// Platform-specific tags, keyed by platform prefix.
+    #[serde(flatten)]
+    platforms: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+impl TagMap {
+    /// Targets of `tag` on `platform`, if it is known.
+    fn targets(&self, platform: &str, tag: &str) -> Option<&Vec<String>> {
+        self.platforms.get(platform).and_then(|tags| tags.get(tag)).or_else(|| self.tags.get(tag))
+    }
+
+    /// All tags known on `platform`.
+    fn known_tags(&self, platform: &str) -> Vec<String> {
+        self.tags.keys().chain(self.platforms.get(platform).into_iter().flat_map(BTreeMap::keys)).cloned().collect()
+    }
+}
+
+/// An engagement given on the command line, e.g. `htb:web,ad`.
+#[derive(Debug)]
+pub struct Engagement {
+    /// Platform prefix, e.g. `htb`.
+    pub platform: &'static str,
+    /// Platform name, e.g. `HackTheBox`.
+    pub platform_name: &'static str,
+    pub tags: Vec<String>,
+}
+
+/// Normalizes a tag as the mapping spells it: lowercase, with spaces and underscores as dashes.
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase().split([' ', '_', '-']).filter(|word| !word.is_empty()).collect::<Vec<_>>().join("-")
+}
+
+/// Parses `<platform>:<tag>,<tag>...`.
+pub fn parse(spec: &str) -> Result<Engagement, Box<dyn std::error::Error>> {
+    let prefixes: Vec<&str> = PLATFORMS.iter().map(|(prefix, _)| *prefix).collect();
+    let Some((platform, tags)) = spec.split_once(':') else {
+        return Err(Box::from(t!("engage-spec-invalid", spec = spec, platforms = prefixes.join(", "))));
+    };
+    let (platform, platform_name) = PLATFORMS
+        .iter()
+        .find(|(prefix, _)| prefix.eq_ignore_ascii_case(platform.trim()))
+        .copied()
+        .ok_or_else(|| t!("engage-platform-unknown", platform = platform, platforms = prefixes.join(", ")))?;
+    let tags: Vec<String> = tags.split(',').map(normalize_tag).filter(|tag| !tag.is_empty()).collect();
+    if tags.is_empty() {
+        return Err(Box::from(t!("engage-no-tags", spec = spec, platform = platform)));
+    }
+    Ok(Engagement { platform, platform_name, tags })
+}
+
+/// Fetches the tag mapping.
+pub async fn fetch_tag_map() -> Result<TagMap, Box<dyn std::error::Error>> {
+    let response = http::get(ENGAGE_URL)?.send().await?;
+    http::check_response(&response)?;
+    if !response.status().is_success() {
+        return Err(Box::from(t!("engage-fetch-failed", url = ENGAGE_URL, status = response.status().to_string())));
+    }
+    let content = response.text().await?;
+    toml::from_str(&content).map_err(|e| Box::from(t!("engage-tags-invalid", url = ENGAGE_URL, error = e.to_string())))
+}
+
+/// Resolves the tags of `engagements` to roles, returning each known tag (as `<platform>:<tag>`)
+/// with its roles. Unknown tags are reported with the closest known ones and skipped; it is an
+/// error if no tag is known.
+pub async fn resolve(map: &TagMap, engagements: &[Engagement]) -> Result<Vec<(String, Vec<String>)>, Box<dyn std::error::Error>> {
+    let mut presets = None;
+    let mut per_tag = Vec::new();
+    for engagement in engagements {
+        for tag in &engagement.tags {
+            let Some(targets) = map.targets(engagement.platform, tag) else {
+                let known = map.known_tags(engagement.platform);
+                let similar = suggest::nearest(tag, &known);
+                if similar.is_empty() {
+                    eprintln!("{}", t!("engage-tag-unknown", platform = engagement.platform, tag = tag.as_str()));
+                } else {
+                    eprintln!("{}", t!("engage-tag-unknown-similar", platform = engagement.platform, tag = tag.as_str(), similar = similar.join(", ")));
+                }
+                continue;
+            };
+            let mut tag_roles = Vec::new();
+            for target in targets {
+                match target.strip_prefix(PRESET_PREFIX) {
+                    Some(name) => {
+                        if presets.is_none() {
+                            presets = Some(presets::fetch_presets().await?);
+                        }
+                        let preset = presets
+                            .as_ref()
+                            .and_then(|presets| presets.get(name))
+                            .ok_or_else(|| t!("engage-preset-unknown", tag = tag.as_str(), preset = name))?;
+                        tag_roles.extend(preset.roles.iter().cloned());
+                    }
+                    None => tag_roles.push(target.clone()),
+                }
+            }
+            per_tag.push((format!("{}:{}", engagement.platform, tag), tag_roles));
+        }
+    }
+    if per_tag.is_empty() {
+        return Err(Box::from(t!("engage-no-known-tags")));
+    }
+    Ok(per_tag)
+}
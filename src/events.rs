@@ -0,0 +1,158 @@
+//! Progress of `RoleManager` operations as a stream of events, for GUI and TUI front-ends.
+//!
+//! [`channel`] returns a [`Reporter`] to give to the manager and the [`EventStream`] it feeds.
+//! The package manager's output is captured instead of going to the inherited terminal: lines
+//! recognized as download or install/remove steps become typed events, the rest arrive as
+//! [`ToolkitEvent::Output`], tagged with the operation and stream they came from.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use cyber_toolkit::{events, RoleManager};
+//!
+//! let (reporter, mut events) = events::channel();
+//! let manager = RoleManager::builder().reporter(reporter).build()?;
+//! // Moved in, so the manager (and with it the reporter) is dropped once the operation is done
+//! let operation = async move { manager.add(&["web".to_string()]).await };
+//! let render = async {
+//!     while let Some(event) = events.next().await {
+//!         println!("{:?}", event);
+//!     }
+//! };
+//! let (report, ()) = tokio::join!(operation, render);
+//! println!("{:?}", report?.tools_installed);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The stream ends when the reporter is dropped, i.e. together with the manager.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::backend::{OutputStream, PackageOperation};
+use crate::report::Reporter;
+use crate::roles::Role;
+
+/// Something that happened during an operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ToolkitEvent {
+    /// A role file is being fetched from `location`.
+    Resolving { role: String, location: String },
+    /// A role file was fetched and resolved to `tools`.
+    Resolved { role: String, tools: Vec<String> },
+    /// A role file couldn't be fetched or resolved.
+    ResolveFailed { role: String, error: String },
+    /// `tools` are about to be installed.
+    Installing { tools: Vec<String> },
+    /// `tools` are about to be removed.
+    Removing { tools: Vec<String> },
+    /// The package manager started downloading `package`.
+    Downloading { package: String },
+    /// The package manager reached step `index` of `total` (both counted from 1), e.g. installing
+    /// or removing `package`.
+    PackageStep { action: String, package: String, index: usize, total: usize },
+    /// Any other line the package manager printed on `stream` during `operation`.
+    Output { operation: PackageOperation, stream: OutputStream, line: String },
+}
+
+/// Returns a reporter for `RoleManager::builder().reporter(...)` and the stream of its events.
+pub fn channel() -> (EventReporter, EventStream) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (EventReporter { sender }, EventStream { receiver })
+}
+
+/// Turns reported progress into `ToolkitEvent`s.
+#[derive(Debug, Clone)]
+pub struct EventReporter {
+    sender: UnboundedSender<ToolkitEvent>,
+}
+
+impl EventReporter {
+    fn send(&self, event: ToolkitEvent) {
+        // Nobody listening any more is not the operation's problem
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Reporter for EventReporter {
+    fn fetching_role(&self, role: &str, location: &str) {
+        self.send(ToolkitEvent::Resolving { role: role.to_string(), location: location.to_string() });
+    }
+
+    fn role_fetched(&self, role: &Role) {
+        self.send(ToolkitEvent::Resolved { role: role.name.clone(), tools: role.tools.clone() });
+    }
+
+    fn role_failed(&self, role: &str, error: &str) {
+        self.send(ToolkitEvent::ResolveFailed { role: role.to_string(), error: error.to_string() });
+    }
+
+    fn installing_tools(&self, tools: &[String]) {
+        self.send(ToolkitEvent::Installing { tools: tools.to_vec() });
+    }
+
+    fn removing_tools(&self, tools: &[String]) {
+        self.send(ToolkitEvent::Removing { tools: tools.to_vec() });
+    }
+
+    fn captures_package_output(&self) -> bool {
+        true
+    }
+
+    fn package_output(&self, operation: PackageOperation, stream: OutputStream, line: &str) {
+        self.send(parse_package_output(operation, stream, line));
+    }
+}
+
+/// Steps of pacman's transaction that name a package.
+const PACKAGE_ACTIONS: [&str; 5] = ["installing", "upgrading", "reinstalling", "downgrading", "removing"];
+
+/// Recognizes pacman's progress lines: ` nmap-7.95-1-x86_64 downloading...` and
+/// `(1/3) installing nmap`.
+fn parse_package_output(operation: PackageOperation, stream: OutputStream, line: &str) -> ToolkitEvent {
+    let trimmed = line.trim();
+    if let Some(package) = trimmed.strip_suffix(" downloading...") {
+        return ToolkitEvent::Downloading { package: package.to_string() };
+    }
+    let step = trimmed.strip_prefix('(').and_then(|rest| rest.split_once(") ")).and_then(|(counter, rest)| {
+        let (index, total) = counter.trim().split_once('/')?;
+        let (action, package) = rest.split_once(' ')?;
+        // Not "(1/3) checking keys in keyring" and the like
+        if !PACKAGE_ACTIONS.contains(&action) {
+            return None;
+        }
+        let package = package.split_whitespace().next()?;
+        Some(ToolkitEvent::PackageStep {
+            action: action.to_string(),
+            package: package.to_string(),
+            index: index.trim().parse().ok()?,
+            total: total.trim().parse().ok()?,
+        })
+    });
+    step.unwrap_or_else(|| ToolkitEvent::Output { operation, stream, line: line.to_string() })
+}
+
+/// The events of an `EventReporter`, in the order they happened.
+#[derive(Debug)]
+pub struct EventStream {
+    receiver: UnboundedReceiver<ToolkitEvent>,
+}
+
+impl EventStream {
+    /// Waits for the next event. Returns `None` once the reporter is dropped and all events
+    /// were received.
+    pub async fn next(&mut self) -> Option<ToolkitEvent> {
+        self.receiver.recv().await
+    }
+}
+
+impl futures_core::Stream for EventStream {
+    type Item = ToolkitEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<ToolkitEvent>> {
+        self.receiver.poll_recv(cx)
+    }
+}
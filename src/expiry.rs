@@ -0,0 +1,83 @@
+//! Temporary roles added with `--temp <DURATION>`, whose expiry times are kept in the
+//! `expiry.json` state document.
+//!
+//! `cyber-toolkit expire` removes the roles whose time is up, so engagement-specific tools don't
+//! accumulate. Adding a role again without `--temp` makes it permanent.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::i18n::t;
+use crate::state_db;
+
+/// Parses a duration such as `30m`, `12h`, `7d` or `2w`.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let invalid = || t!("duration-invalid", duration = format!("{:?}", text));
+    let split = text.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (number, unit) = text.split_at(split);
+    let number: i64 = number.parse().map_err(|_| invalid())?;
+    let duration = match unit {
+        "m" => Duration::try_minutes(number),
+        "h" => Duration::try_hours(number),
+        "d" => Duration::try_days(number),
+        "w" => Duration::try_weeks(number),
+        _ => None,
+    };
+    duration.filter(|duration| *duration > Duration::zero()).ok_or_else(invalid)
+}
+
+/// Parses the `expiry.json` state document. Times are stored in RFC 3339 format.
+fn parse(content: Option<String>) -> Result<BTreeMap<String, DateTime<Utc>>, Box<dyn std::error::Error>> {
+    let Some(content) = content else {
+        return Ok(BTreeMap::new());
+    };
+    let stored: BTreeMap<String, String> = serde_json::from_str(&content)?;
+    Ok(stored
+        .into_iter()
+        .filter_map(|(role, until)| Some((role, DateTime::parse_from_rfc3339(&until).ok()?.with_timezone(&Utc))))
+        .collect())
+}
+
+/// Reads the expiry time of every temporary role.
+pub fn read() -> Result<BTreeMap<String, DateTime<Utc>>, Box<dyn std::error::Error>> {
+    parse(state_db::read_document("expiry.json")?)
+}
+
+/// Changes the recorded expiry times with `change`, in one state database transaction.
+/// An empty map removes the document.
+fn update(change: impl FnOnce(&mut BTreeMap<String, DateTime<Utc>>)) -> Result<(), Box<dyn std::error::Error>> {
+    state_db::update_document("expiry.json", |content| {
+        let mut expiries = parse(content)?;
+        change(&mut expiries);
+        if expiries.is_empty() {
+            return Ok(None);
+        }
+        let stored: BTreeMap<&String, String> = expiries.iter().map(|(role, until)| (role, until.to_rfc3339())).collect();
+        Ok(Some(serde_json::to_string_pretty(&stored)?))
+    })
+}
+
+/// Marks `roles` as temporary until `until`, replacing earlier expiry times.
+pub fn set(roles: &[String], until: DateTime<Utc>) -> Result<(), Box<dyn std::error::Error>> {
+    update(|expiries| {
+        for role in roles.iter().map(|role| role.trim()).filter(|role| !role.is_empty()) {
+            expiries.insert(role.to_string(), until);
+        }
+    })
+}
+
+/// Forgets the expiry times of `roles`, e.g. because they were removed or added permanently.
+pub fn clear(roles: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    update(|expiries| {
+        for role in roles {
+            expiries.remove(role.trim());
+        }
+    })
+}
+
+/// Returns the temporary roles whose expiry time is before `now`.
+pub fn expired(now: DateTime<Utc>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    Ok(read()?.into_iter().filter(|(_, until)| *until <= now).map(|(role, _)| role).collect())
+}
@@ -0,0 +1,155 @@
+//! Detection of host facts (architecture, GPU, memory, virtualization, wireless, headless) used when
+//! resolving role files.
+//!
+//! Facts are detected once per run from `/proc`, `/sys` and `systemd-detect-virt`; anything that
+//! can't be determined falls back to a neutral value (`"none"`, `0`).
+
+use std::fs;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Facts about the machine the toolkit runs on.
+#[derive(Debug, Clone)]
+pub struct HostFacts {
+    /// CPU architecture as reported by Rust (`x86_64`, `aarch64`, ...).
+    pub arch: String,
+    /// Vendor of the primary GPU: `nvidia`, `amd`, `intel` or `none`.
+    pub gpu: String,
+    /// Total memory in MiB.
+    pub ram_mb: u64,
+    /// Virtualization technology (`kvm`, `vmware`, `oracle`, ...) or `none` on bare metal.
+    pub virtualization: String,
+    /// Whether a wireless network adapter is present.
+    pub wifi: bool,
+    /// Whether the machine has no graphical session, so GUI tools should be skipped.
+    /// Set with `configure_headless` (the CLI passes `headless` from the `[profile]` settings),
+    /// or detected if unset.
+    pub headless: bool,
+}
+
+impl HostFacts {
+    /// Returns true if the host runs inside a virtual machine or container.
+    pub fn is_vm(&self) -> bool {
+        self.virtualization != "none"
+    }
+
+    /// Evaluates a role entry condition such as `wifi`, `vm`, `baremetal`, `gpu`, a GPU vendor or `gui`.
+    ///
+    /// A leading `!` negates the condition. Returns `None` for unknown conditions.
+    pub fn satisfies(&self, condition: &str) -> Option<bool> {
+        if let Some(negated) = condition.strip_prefix('!') {
+            return self.satisfies(negated).map(|result| !result);
+        }
+        Some(match condition {
+            "wifi" => self.wifi,
+            "vm" => self.is_vm(),
+            "baremetal" => !self.is_vm(),
+            "gpu" => self.gpu != "none",
+            "nvidia" | "amd" | "intel" => self.gpu == condition,
+            "gui" => !self.headless,
+            _ => return None,
+        })
+    }
+}
+
+/// Headless mode chosen by the caller instead of detecting it, see `configure_headless`.
+static CONFIGURED_HEADLESS: OnceLock<Option<bool>> = OnceLock::new();
+
+/// Overrides headless detection, e.g. with `headless` from the `[profile]` settings. `None`
+/// keeps detection. Has to be called before the facts are first used; later calls are ignored.
+pub fn configure_headless(headless: Option<bool>) {
+    let _ = CONFIGURED_HEADLESS.set(headless);
+}
+
+/// Returns the facts of the current host, detecting them on first use.
+pub fn current() -> &'static HostFacts {
+    static FACTS: OnceLock<HostFacts> = OnceLock::new();
+    FACTS.get_or_init(detect)
+}
+
+fn detect() -> HostFacts {
+    let virtualization = detect_virtualization();
+    let configured_headless = CONFIGURED_HEADLESS.get().copied().flatten();
+    HostFacts {
+        arch: std::env::consts::ARCH.to_string(),
+        gpu: detect_gpu(),
+        ram_mb: detect_ram_mb(),
+        headless: configured_headless.unwrap_or_else(|| detect_headless(&virtualization)),
+        virtualization,
+        wifi: detect_wifi(),
+    }
+}
+
+/// Reads PCI vendor IDs of DRM devices. The first discrete vendor found wins over Intel integrated graphics.
+fn detect_gpu() -> String {
+    let mut vendors = Vec::new();
+    if let Ok(entries) = fs::read_dir("/sys/class/drm") {
+        for entry in entries.flatten() {
+            if let Ok(vendor) = fs::read_to_string(entry.path().join("device/vendor")) {
+                vendors.push(match vendor.trim() {
+                    "0x10de" => "nvidia",
+                    "0x1002" => "amd",
+                    "0x8086" => "intel",
+                    _ => continue,
+                });
+            }
+        }
+    }
+    ["nvidia", "amd", "intel"]
+        .into_iter()
+        .find(|vendor| vendors.contains(vendor))
+        .unwrap_or("none")
+        .to_string()
+}
+
+fn detect_ram_mb() -> u64 {
+    fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|meminfo| {
+            meminfo
+                .lines()
+                .find(|line| line.starts_with("MemTotal:"))
+                .and_then(|line| line.split_whitespace().nth(1))
+                .and_then(|kib| kib.parse::<u64>().ok())
+        })
+        .map(|kib| kib / 1024)
+        .unwrap_or(0)
+}
+
+/// Uses `systemd-detect-virt`, falling back to the `hypervisor` CPU flag.
+fn detect_virtualization() -> String {
+    if let Ok(output) = Command::new("systemd-detect-virt").output() {
+        let detected = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !detected.is_empty() {
+            return detected;
+        }
+    }
+    let has_hypervisor_flag = fs::read_to_string("/proc/cpuinfo")
+        .map(|cpuinfo| cpuinfo.lines().any(|line| line.starts_with("flags") && line.contains(" hypervisor")))
+        .unwrap_or(false);
+    if has_hypervisor_flag { "vm".to_string() } else { "none".to_string() }
+}
+
+/// A wireless adapter shows up as a network interface with a `wireless` (or `phy80211`) entry in sysfs.
+fn detect_wifi() -> bool {
+    fs::read_dir("/sys/class/net")
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.path().join("wireless").exists() || entry.path().join("phy80211").exists())
+        })
+        .unwrap_or(false)
+}
+
+/// Containers are always headless; otherwise a machine without any installed X11 or Wayland
+/// session files is assumed to be a server.
+fn detect_headless(virtualization: &str) -> bool {
+    const CONTAINERS: &[&str] = &["docker", "podman", "lxc", "lxc-libvirt", "systemd-nspawn", "openvz", "wsl"];
+    if CONTAINERS.contains(&virtualization) {
+        return true;
+    }
+    let has_sessions = ["/usr/share/xsessions", "/usr/share/wayland-sessions"]
+        .iter()
+        .any(|dir| fs::read_dir(dir).map(|mut entries| entries.next().is_some()).unwrap_or(false));
+    !has_sessions
+}
@@ -0,0 +1,101 @@
+//! Machine-readable report of the tools that failed to install in the last run, kept in the
+//! `last-failures.json` state document (see `state_db`; `cyber-toolkit state export` prints it).
+//!
+//! Each entry names the tool, its backend, and, where a command failed, the command line, exit
+//! code and the last lines of its stderr, so automation and bug reports get actionable data.
+//! `cyber-toolkit retry-failed` reattempts just these tools.
+
+use std::fmt;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::state_db;
+
+/// A role entry that could not be installed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FailedTool {
+    /// The entry as written in the role file, including any backend prefix.
+    pub tool: String,
+    /// Backend that tried to install it: `native`, `choco`, `isolated` or `go`.
+    #[serde(default)]
+    pub backend: String,
+    pub reason: String,
+    /// The failing command line, if a command failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// The last lines the failing command wrote to stderr.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<String>,
+}
+
+impl FailedTool {
+    /// A failure that isn't tied to a command.
+    pub fn new(tool: String, backend: &str, reason: String) -> Self {
+        FailedTool { tool, backend: backend.to_string(), reason, command: None, exit_code: None, stderr: None }
+    }
+
+    /// A failure caused by a command. Other errors are recorded with their message only.
+    pub fn from_error(tool: String, backend: &str, error: &(dyn std::error::Error + 'static)) -> Self {
+        match error.downcast_ref::<CommandFailure>() {
+            Some(failure) => failure.for_tool(tool, backend),
+            None => FailedTool::new(tool, backend, error.to_string()),
+        }
+    }
+}
+
+/// A command that exited unsuccessfully.
+#[derive(Debug, Clone)]
+pub struct CommandFailure {
+    pub reason: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub stderr: String,
+}
+
+impl CommandFailure {
+    pub fn new(reason: String, command: &Command, exit_code: Option<i32>, stderr: String) -> Self {
+        let words: Vec<String> = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|word| word.to_string_lossy().into_owned())
+            .collect();
+        let command = shlex::try_join(words.iter().map(String::as_str)).unwrap_or_default();
+        CommandFailure { reason, command, exit_code, stderr }
+    }
+
+    /// Records this failure against a tool.
+    pub fn for_tool(&self, tool: String, backend: &str) -> FailedTool {
+        FailedTool {
+            tool,
+            backend: backend.to_string(),
+            reason: self.reason.clone(),
+            command: Some(self.command.clone()),
+            exit_code: self.exit_code,
+            stderr: Some(self.stderr.clone()).filter(|stderr| !stderr.is_empty()),
+        }
+    }
+}
+
+impl fmt::Display for CommandFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl std::error::Error for CommandFailure {}
+
+/// Reads the failures of the last run. No `last-failures.json` state document means nothing failed.
+pub fn read() -> Result<Vec<FailedTool>, Box<dyn std::error::Error>> {
+    match state_db::read_document("last-failures.json")? {
+        Some(content) => Ok(serde_json::from_str(&content)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Replaces the recorded failures. An empty list removes the document.
+pub fn write(failures: &[FailedTool]) -> Result<(), Box<dyn std::error::Error>> {
+    let content = if failures.is_empty() { None } else { Some(serde_json::to_string_pretty(failures)?) };
+    state_db::write_document("last-failures.json", content.as_deref())
+}
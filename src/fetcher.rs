@@ -0,0 +1,84 @@
+//! Where role files come from.
+//!
+//! Role files are read through a `RoleFetcher`. The default, `HttpFetcher`, downloads them from
+//! the repository with the shared HTTP client (`remote` feature); library users can supply their own fetcher to
+//! read from a mirror, add a caching layer, or serve fixed content in tests.
+
+use async_trait::async_trait;
+
+#[cfg(feature = "remote")]
+use crate::http;
+#[cfg(feature = "remote")]
+use crate::roles::BASE_RAW_URL;
+
+/// Source of raw role files.
+#[async_trait]
+pub trait RoleFetcher: Send + Sync {
+    /// Returns the raw content of the role file `name`.
+    async fn fetch(&self, name: &str) -> Result<String, Box<dyn std::error::Error>>;
+
+    /// Describes where the role file `name` is read from, for progress reports.
+    fn location(&self, name: &str) -> String {
+        name.to_string()
+    }
+}
+
+/// Fetches role files over HTTP from a base URL the role name is appended to. Fails without
+/// connecting while network access is forbidden or the host rate-limits us (see `http`).
+#[cfg(feature = "remote")]
+#[derive(Debug, Clone)]
+pub struct HttpFetcher {
+    client: reqwest::Client,
+    base_url: String,
+    /// Total timeout per request; `None` leaves it to the client.
+    timeout: Option<std::time::Duration>,
+}
+
+#[cfg(feature = "remote")]
+impl HttpFetcher {
+    /// Fetches from the official repository with the shared client.
+    pub fn new() -> Self {
+        HttpFetcher::with_base_url(BASE_RAW_URL)
+    }
+
+    /// Fetches from another base URL (e.g. a mirror) with the shared client and its request timeout.
+    pub fn with_base_url(base_url: &str) -> Self {
+        let timeout = Some(http::settings().request_timeout);
+        HttpFetcher { timeout, ..HttpFetcher::with_client(http::client().clone(), base_url) }
+    }
+
+    /// Fetches with a custom client, e.g. one configured with a proxy. Its own timeouts apply.
+    pub fn with_client(client: reqwest::Client, base_url: &str) -> Self {
+        HttpFetcher { client, base_url: base_url.to_string(), timeout: None }
+    }
+}
+
+#[cfg(feature = "remote")]
+impl Default for HttpFetcher {
+    fn default() -> Self {
+        HttpFetcher::new()
+    }
+}
+
+#[cfg(feature = "remote")]
+#[async_trait]
+impl RoleFetcher for HttpFetcher {
+    async fn fetch(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let url = self.location(name);
+        http::check_request(&url)?;
+        let mut request = self.client.get(&url);
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await?;
+        http::check_response(&response)?;
+        if !response.status().is_success() {
+            return Err(Box::from(format!("Failed to fetch tool list from {}: HTTP Status {}", url, response.status())));
+        }
+        Ok(response.text().await?)
+    }
+
+    fn location(&self, name: &str) -> String {
+        format!("{}{}", self.base_url, name.trim())
+    }
+}
@@ -0,0 +1,370 @@
+//! Fleet mode: applying roles to several remote machines over SSH.
+//!
+//! Hosts are listed in a TOML file:
+//!
+//! ```toml
+//! [[hosts]]
+//! name = "lab-01"
+//! address = "student@10.0.0.11"
+//! port = 22                       # optional
+//! identity_file = "~/.ssh/lab"    # optional
+//! tags = ["canary"]               # optional, for `--canary-tag`
+//! ```
+//!
+//! Each host runs `cyber-toolkit <roles...>` itself, so the usual add/sync logic applies remotely.
+//! Hosts are processed one after another because the remote `pkexec` prompts need the terminal.
+//!
+//! A rollout can be staged (see `Rollout`): a canary subset of the hosts (a percentage, or the
+//! hosts with a tag) goes first, and each host is verified after applying with `cyber-toolkit
+//! verify` and the given smoke tests. The other hosts only follow if the canaries stayed within
+//! the failure threshold, and the rollout stops as soon as the threshold is exceeded.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::i18n::t;
+
+/// File name of the local binary on remote hosts when `--copy-binary` is used, inside a new
+/// directory made with `mktemp -d` for each run.
+const REMOTE_BINARY_NAME: &str = "cyber-toolkit";
+
+/// Top-level structure of a hosts file.
+#[derive(Deserialize, Debug)]
+pub struct HostsFile {
+    pub hosts: Vec<Host>,
+}
+
+/// A single remote machine.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Host {
+    /// Display name used in reports.
+    pub name: String,
+    /// SSH destination, e.g. `user@hostname`.
+    pub address: String,
+    pub port: Option<u16>,
+    pub identity_file: Option<String>,
+    /// Labels for selecting hosts, e.g. as canaries.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// What happened on a host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostStatus {
+    Succeeded,
+    Failed,
+    /// Not attempted because the rollout was aborted.
+    Skipped,
+}
+
+/// Outcome of applying roles to one host.
+#[derive(Debug)]
+pub struct HostResult {
+    pub host: String,
+    pub status: HostStatus,
+    /// Whether the host was in the canary stage.
+    pub canary: bool,
+    pub detail: String,
+    pub duration: Duration,
+}
+
+/// Hosts that go first in a staged rollout.
+#[derive(Debug, Clone)]
+pub enum Canary {
+    /// This percentage of the hosts (rounded up), in the order of the hosts file.
+    Percent(u8),
+    /// The hosts with this tag.
+    Tag(String),
+}
+
+/// How many failed hosts abort a rollout: a number, or a percentage of all hosts (`10%`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureThreshold {
+    Hosts(usize),
+    Percent(u8),
+}
+
+impl FailureThreshold {
+    /// The most failed hosts tolerated among `total`.
+    fn allowed(self, total: usize) -> usize {
+        match self {
+            FailureThreshold::Hosts(hosts) => hosts,
+            FailureThreshold::Percent(percent) => total * usize::from(percent) / 100,
+        }
+    }
+}
+
+impl FromStr for FailureThreshold {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().strip_suffix('%') {
+            Some(percent) => match percent.trim().parse::<u8>() {
+                Ok(percent) if percent <= 100 => Ok(FailureThreshold::Percent(percent)),
+                _ => Err(t!("fleet-percentage-invalid", value = value)),
+            },
+            None => value.trim().parse().map(FailureThreshold::Hosts).map_err(|_| t!("fleet-threshold-invalid", value = value)),
+        }
+    }
+}
+
+/// How roles are rolled out to the hosts.
+#[derive(Debug, Clone, Default)]
+pub struct Rollout {
+    /// Hosts that go first; the others only follow if they succeed. `None` applies to all hosts
+    /// in one stage.
+    pub canary: Option<Canary>,
+    /// Commands run on each host after applying (e.g. `nmap --version`); a failing one fails the host.
+    pub smoke_tests: Vec<String>,
+    /// Failed hosts beyond which the rollout is aborted. Defaults to none with a canary stage and
+    /// to no limit without one.
+    pub max_failures: Option<FailureThreshold>,
+}
+
+impl Rollout {
+    /// Whether hosts are verified after applying: always in a staged rollout, otherwise only if
+    /// there are smoke tests.
+    fn verifies(&self) -> bool {
+        self.canary.is_some() || !self.smoke_tests.is_empty()
+    }
+}
+
+/// Reads and parses a hosts file.
+pub fn load_hosts(path: &Path) -> Result<Vec<Host>, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(path)?;
+    let hosts_file: HostsFile =
+        toml::from_str(&content).map_err(|e| t!("fleet-hosts-file-invalid", path = format!("{:?}", path), error = e.to_string()))?;
+    // ssh and scp would take such an address for an option, e.g. `-oProxyCommand=...`
+    if let Some(host) = hosts_file.hosts.iter().find(|host| host.address.is_empty() || host.address.starts_with('-')) {
+        return Err(Box::from(t!("fleet-address-invalid", host = host.name.as_str(), address = host.address.as_str())));
+    }
+    Ok(hosts_file.hosts)
+}
+
+impl Host {
+    /// Common SSH options (port, identity) for `ssh`, or for `scp` when `for_scp` is set.
+    fn ssh_options(&self, for_scp: bool) -> Vec<String> {
+        let mut options = Vec::new();
+        if let Some(port) = self.port {
+            options.push(if for_scp { "-P" } else { "-p" }.to_string());
+            options.push(port.to_string());
+        }
+        if let Some(identity_file) = &self.identity_file {
+            options.push("-i".to_string());
+            options.push(identity_file.clone());
+        }
+        options
+    }
+}
+
+/// Copies the currently running binary into a new directory on the host, made with `mktemp -d`
+/// so other users of the host can neither predict nor replace it, and checks the SHA-256 of the
+/// copy before anything runs it. Returns the directory and the path of the copy; the directory
+/// is removed again if the copy fails.
+fn copy_binary(host: &Host) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let dir = remote_output(host, "mktemp -d")?.trim().to_string();
+    if !dir.starts_with('/') {
+        return Err(Box::from(t!("fleet-mktemp-failed", output = dir.as_str())));
+    }
+    let path = format!("{}/{}", dir, REMOTE_BINARY_NAME);
+    println!("{}", t!("fleet-copying", host = host.name.as_str(), target = format!("{}:{}", host.address, path)));
+    match upload_binary(host, &path) {
+        Ok(()) => Ok((dir, path)),
+        Err(e) => {
+            remove_remote_dir(host, &dir);
+            Err(e)
+        }
+    }
+}
+
+/// Copies the currently running binary to `path` on the host and compares the checksums.
+fn upload_binary(host: &Host, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let local_binary = std::env::current_exe()?;
+    let status = Command::new("scp")
+        .args(host.ssh_options(true))
+        .arg("--")
+        .arg(&local_binary)
+        .arg(format!("{}:{}", host.address, path))
+        .status()?;
+    if !status.success() {
+        return Err(Box::from(t!("fleet-scp-failed", code = format!("{:?}", status.code()))));
+    }
+    let expected = format!("{:x}", Sha256::digest(std::fs::read(&local_binary)?));
+    let output = remote_output(host, &format!("sha256sum -- {}", shlex::try_quote(path)?))?;
+    let actual = output.split_whitespace().next().unwrap_or_default();
+    if actual != expected {
+        return Err(Box::from(t!("fleet-checksum-mismatch", path = path, expected = expected.as_str(), actual = actual)));
+    }
+    Ok(())
+}
+
+/// Removes the directory of a copied binary from the host, warning if that fails.
+fn remove_remote_dir(host: &Host, dir: &str) {
+    let command = match shlex::try_quote(dir) {
+        Ok(dir) => format!("rm -rf -- {}", dir),
+        Err(_) => return,
+    };
+    if let Err(e) = run_remote(host, &command, false) {
+        eprintln!("{}", t!("fleet-cleanup-failed", host = host.name.as_str(), dir = dir, error = e.to_string()));
+    }
+}
+
+/// `ssh` with the host's options for running `command`. The address follows `--`, so it can't
+/// be taken for an option.
+fn ssh(host: &Host, command: &str, tty: bool) -> Command {
+    let mut ssh = Command::new("ssh");
+    if tty {
+        ssh.arg("-t");
+    }
+    ssh.args(host.ssh_options(false)).arg("--").arg(&host.address).arg(command);
+    ssh
+}
+
+/// Runs `command` on the host over SSH. With `tty`, a terminal is allocated so the remote
+/// `pkexec` can prompt.
+fn run_remote(host: &Host, command: &str, tty: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let status = ssh(host, command, tty).status()?;
+    if !status.success() {
+        return Err(Box::from(t!("fleet-command-failed", command = command, code = format!("{:?}", status.code()))));
+    }
+    Ok(())
+}
+
+/// Runs `command` on the host over SSH and returns what it printed.
+fn remote_output(host: &Host, command: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let output = ssh(host, command, false).stderr(Stdio::inherit()).output()?;
+    if !output.status.success() {
+        return Err(Box::from(t!("fleet-command-failed", command = command, code = format!("{:?}", output.status.code()))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Applies the roles to a single host and reports the outcome. Never fails; errors are captured in the result.
+fn apply_to_host(host: &Host, roles: &[String], copy: bool, rollout: &Rollout) -> HostResult {
+    let started = Instant::now();
+    // Directory of the copied binary, removed once the host is done
+    let mut remote_dir = None;
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        let binary = if copy {
+            let (dir, path) = copy_binary(host)?;
+            remote_dir = Some(dir);
+            shlex::try_quote(&path)?.into_owned()
+        } else {
+            "cyber-toolkit".to_string()
+        };
+
+        let mut remote_command = vec![binary.clone()];
+        for role in roles {
+            remote_command.push(shlex::try_quote(role)?.into_owned());
+        }
+        let remote_command = remote_command.join(" ");
+        println!("{}", t!("fleet-running", host = host.name.as_str(), address = host.address.as_str(), command = remote_command.as_str()));
+
+        run_remote(host, &remote_command, true).map_err(|e| t!("fleet-remote-failed", error = e.to_string()))?;
+
+        if rollout.verifies() {
+            let verify_command = format!("{} verify", binary);
+            println!("{}", t!("fleet-verifying", host = host.name.as_str(), command = verify_command.as_str()));
+            run_remote(host, &verify_command, false).map_err(|e| t!("fleet-verify-failed", error = e.to_string()))?;
+            for smoke_test in &rollout.smoke_tests {
+                println!("{}", t!("fleet-smoke-test", host = host.name.as_str(), command = smoke_test.as_str()));
+                run_remote(host, smoke_test, false).map_err(|e| t!("fleet-smoke-test-failed", error = e.to_string()))?;
+            }
+        }
+        Ok(())
+    })();
+    if let Some(dir) = remote_dir {
+        remove_remote_dir(host, &dir);
+    }
+
+    HostResult {
+        host: host.name.clone(),
+        status: if result.is_ok() { HostStatus::Succeeded } else { HostStatus::Failed },
+        canary: false,
+        detail: match result {
+            Ok(()) if rollout.verifies() => t!("fleet-host-verified"),
+            Ok(()) => t!("fleet-host-applied"),
+            Err(e) => e.to_string(),
+        },
+        duration: started.elapsed(),
+    }
+}
+
+/// Indices of the canary hosts. A tag no host has is an error.
+fn canary_hosts(hosts: &[Host], canary: &Canary) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    match canary {
+        Canary::Percent(percent) => {
+            let count = (hosts.len() * usize::from(*percent)).div_ceil(100).max(1);
+            Ok((0..count.min(hosts.len())).collect())
+        }
+        Canary::Tag(tag) => {
+            let indices: Vec<usize> = (0..hosts.len()).filter(|&index| hosts[index].tags.contains(tag)).collect();
+            if indices.is_empty() {
+                return Err(Box::from(t!("fleet-canary-tag-unknown", tag = tag.as_str())));
+            }
+            Ok(indices)
+        }
+    }
+}
+
+/// Applies the roles to the hosts in turn, canaries first, and returns the per-host results in
+/// the order the hosts were processed. Once more hosts failed than the rollout allows, the
+/// remaining ones are skipped.
+pub fn apply(hosts: &[Host], roles: &[String], copy: bool, rollout: &Rollout) -> Result<Vec<HostResult>, Box<dyn std::error::Error>> {
+    let canaries = match &rollout.canary {
+        Some(canary) => canary_hosts(hosts, canary)?,
+        None => Vec::new(),
+    };
+    let allowed_failures = match (rollout.max_failures, &rollout.canary) {
+        (Some(threshold), _) => threshold.allowed(hosts.len()),
+        (None, Some(_)) => 0,
+        (None, None) => usize::MAX,
+    };
+    let order: Vec<usize> = canaries.iter().copied().chain((0..hosts.len()).filter(|index| !canaries.contains(index))).collect();
+
+    let mut results = Vec::with_capacity(hosts.len());
+    let mut failures = 0;
+    // Why the remaining hosts are skipped, once the rollout is aborted
+    let mut aborted: Option<String> = None;
+    for (position, &index) in order.iter().enumerate() {
+        let host = &hosts[index];
+        let canary = position < canaries.len();
+        if let Some(reason) = &aborted {
+            results.push(HostResult {
+                host: host.name.clone(),
+                status: HostStatus::Skipped,
+                canary,
+                detail: reason.clone(),
+                duration: Duration::ZERO,
+            });
+            continue;
+        }
+        if position == 0 && canary {
+            println!("{}", t!("fleet-canary-stage", count = canaries.len(), total = hosts.len()));
+        } else if position == canaries.len() && !canaries.is_empty() {
+            println!("{}", t!("fleet-canary-passed", count = hosts.len() - canaries.len()));
+        }
+        println!("{}", t!("fleet-host-header", host = host.name.as_str(), address = host.address.as_str()));
+        let mut result = apply_to_host(host, roles, copy, rollout);
+        result.canary = canary;
+        if result.status == HostStatus::Failed {
+            failures += 1;
+        }
+        results.push(result);
+        if failures > allowed_failures {
+            aborted = Some(if canary {
+                t!("fleet-aborted-canary", count = failures)
+            } else {
+                t!("fleet-aborted", count = failures)
+            });
+        } else if canary && position + 1 == canaries.len() && failures > 0 {
+            println!("{}", t!("fleet-canary-failures-allowed", count = failures, allowed = allowed_failures));
+        }
+    }
+    Ok(results)
+}
@@ -0,0 +1,43 @@
+//! Typo-tolerant matching of names, e.g. for finding tools across roles.
+
+/// Edit distance (insertions, deletions, substitutions) between `a` and `b`, ignoring case.
+pub fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// How well a candidate matches a query; lower is better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Match {
+    /// Same name, ignoring case.
+    Exact,
+    /// One name contains the other, e.g. `bloodhound` and `bloodhound-python`.
+    Partial,
+    /// A likely typo, with the edit distance.
+    Close(usize),
+}
+
+/// Matches `candidate` against `query`. Typos are accepted up to about one edit per three characters.
+pub fn matches(query: &str, candidate: &str) -> Option<Match> {
+    let (query, candidate) = (query.to_lowercase(), candidate.to_lowercase());
+    if query == candidate {
+        return Some(Match::Exact);
+    }
+    // Very short names would be contained in almost anything
+    let shorter = query.chars().count().min(candidate.chars().count());
+    if shorter >= 3 && (candidate.contains(&query) || query.contains(&candidate)) {
+        return Some(Match::Partial);
+    }
+    let edits = distance(&query, &candidate);
+    (edits <= (query.chars().count() / 3).max(1)).then_some(Match::Close(edits))
+}
@@ -0,0 +1,182 @@
+//! The `go:` backend: tools built from source with `go install`.
+//!
+//! An entry `go:github.com/projectdiscovery/subfinder/v2/cmd/subfinder@latest` is installed with
+//! `go install` into the managed `GOBIN` `~/.local/state/cyber-toolkit/go/bin`, which has to be on `PATH`. The version
+//! is optional and defaults to `latest`.
+//!
+//! Each installed binary is recorded in `~/.local/state/cyber-toolkit/go.lock` together with the module version it
+//! was built from, as reported by `go version -m`. Removal deletes exactly the recorded binaries.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use cyber_toolkit::http;
+
+use crate::failures::{CommandFailure, FailedTool};
+use crate::i18n::t;
+use crate::{platform, state};
+
+/// Entry prefix selecting this backend.
+pub const PREFIX: &str = "go:";
+
+/// A tool installed with `go install`.
+#[derive(Debug, Clone)]
+pub struct GoTool {
+    /// Package path of the main package, e.g. `github.com/projectdiscovery/subfinder/v2/cmd/subfinder`.
+    pub package: String,
+    /// Requested version (`latest`, a tag or a commit).
+    pub version: String,
+    /// Name of the binary `go install` produces.
+    pub binary: String,
+}
+
+/// Lockfile entry for an installed binary.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockedTool {
+    pub package: String,
+    pub requested: String,
+    /// Module version the binary was built from, if `go version -m` could tell.
+    pub version: Option<String>,
+}
+
+impl GoTool {
+    /// The role file entry describing this tool.
+    pub fn entry(&self) -> String {
+        format!("{}{}@{}", PREFIX, self.package, self.version)
+    }
+}
+
+/// Parses the part after `go:`, i.e. `<package>[@<version>]`.
+pub fn parse_spec(spec: &str) -> Result<GoTool, String> {
+    let (package, version) = match spec.trim().split_once('@') {
+        Some((package, version)) => (package.trim(), version.trim()),
+        None => (spec.trim(), "latest"),
+    };
+    if package.is_empty() || version.is_empty() || !package.contains('/') {
+        return Err(t!("go-spec-invalid"));
+    }
+    // go names the binary after the last path element, skipping a major version suffix
+    let mut elements = package.rsplit('/');
+    let mut binary = elements.next().unwrap_or_default();
+    if is_major_version_suffix(binary) {
+        binary = elements.next().unwrap_or_default();
+    }
+    Ok(GoTool { package: package.to_string(), version: version.to_string(), binary: binary.to_string() })
+}
+
+fn is_major_version_suffix(element: &str) -> bool {
+    element.strip_prefix('v').is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// The managed `GOBIN`, `~/.local/state/cyber-toolkit/go/bin`.
+pub fn bin_dir() -> Result<PathBuf, io::Error> {
+    Ok(state::state_dir()?.join("go").join("bin"))
+}
+
+fn lock_path() -> Result<PathBuf, io::Error> {
+    Ok(state::state_dir()?.join("go.lock"))
+}
+
+/// Reads the installed binaries from `~/.local/state/cyber-toolkit/go.lock`, keyed by binary name.
+pub fn read_lock() -> Result<BTreeMap<String, LockedTool>, Box<dyn std::error::Error>> {
+    let path = lock_path()?;
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn write_lock(lock: &BTreeMap<String, LockedTool>) -> Result<(), Box<dyn std::error::Error>> {
+    let path = lock_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(lock)?)?;
+    Ok(())
+}
+
+/// Runs `go install` for each tool and records the results. Returns the tools that failed.
+pub fn install(tools: &[GoTool]) -> Result<Vec<FailedTool>, Box<dyn std::error::Error>> {
+    let dir = bin_dir()?;
+    fs::create_dir_all(&dir)?;
+    let mut lock = read_lock()?;
+    let mut failed = Vec::new();
+
+    for tool in tools {
+        let target = format!("{}@{}", tool.package, tool.version);
+        println!("{}", t!("go-installing", target = target.as_str()));
+        let mut command = Command::new("go");
+        command.env("GOBIN", &dir).arg("install").arg(&target);
+        if http::is_offline() {
+            command.env("GOPROXY", "off"); // Only modules already in the module cache
+        }
+        match platform::status_with_stderr(&mut command) {
+            Ok((status, _)) if status.success() => {}
+            Ok((status, stderr)) => {
+                eprintln!("{}", t!("go-install-failed", target = target.as_str(), code = format!("{:?}", status.code())));
+                let failure = CommandFailure::new("go install failed".to_string(), &command, status.code(), stderr);
+                failed.push(failure.for_tool(tool.entry(), "go"));
+                continue;
+            }
+            Err(e) => {
+                eprintln!("{}", t!("go-not-run", target = target.as_str(), error = e.to_string()));
+                failed.push(FailedTool::new(tool.entry(), "go", format!("could not run go install: {}", e)));
+                continue;
+            }
+        }
+        let version = built_version(&dir.join(&tool.binary));
+        println!("{}", t!("go-installed", binary = tool.binary.as_str(), version = version.clone().unwrap_or_else(|| t!("go-version-unknown"))));
+        lock.insert(
+            tool.binary.clone(),
+            LockedTool { package: tool.package.clone(), requested: tool.version.clone(), version },
+        );
+    }
+    write_lock(&lock)?;
+    Ok(failed)
+}
+
+/// Reads the main module version embedded in a Go binary (`go version -m`).
+fn built_version(binary: &Path) -> Option<String> {
+    let output = Command::new("go").arg("version").arg("-m").arg(binary).output().ok()?;
+    String::from_utf8_lossy(&output.stdout).lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some("mod"), Some(_module), Some(version)) => Some(version.to_string()),
+            _ => None,
+        }
+    })
+}
+
+/// Deletes the recorded binaries of the given tools. Untracked binaries are left alone.
+pub fn remove(tools: &[GoTool]) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = bin_dir()?;
+    let mut lock = read_lock()?;
+    for tool in tools {
+        if lock.remove(&tool.binary).is_none() {
+            continue;
+        }
+        let path = dir.join(&tool.binary);
+        if path.exists() {
+            fs::remove_file(&path)?;
+            println!("{}", t!("path-removed", path = format!("{:?}", path)));
+        }
+    }
+    write_lock(&lock)?;
+    Ok(())
+}
+
+/// Returns the names of the tools that are recorded in the lockfile and present in `GOBIN`.
+pub fn installed(tools: &[GoTool]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let dir = bin_dir()?;
+    let lock = read_lock()?;
+    Ok(tools
+        .iter()
+        .filter(|tool| lock.contains_key(&tool.binary) && dir.join(&tool.binary).exists())
+        .map(|tool| tool.binary.clone())
+        .collect())
+}
@@ -0,0 +1,171 @@
+//! Sandboxing a role's network-facing tools with firejail (`cyber-toolkit harden <role>`).
+//!
+//! For each tool listed under `[sandbox] firejail` in the role metadata:
+//! - the profile `profiles/firejail/<tool>.profile` is fetched from the repository into
+//!   `~/.config/firejail/`, falling back to the profile bundled with firejail if the repository
+//!   has none, and
+//! - `~/.local/bin/<tool>` is symlinked to firejail, which then runs the real tool confined
+//!   (this requires `~/.local/bin` to come before `/usr/bin` in `PATH`).
+//!
+//! Hardened roles are recorded in the `hardened.json` state document so everything can be undone when the
+//! role is removed. Tools shared with another hardened role stay confined.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use cyber_toolkit::http;
+
+use crate::i18n::t;
+use crate::{pacman, platform, state_db};
+
+/// Base URL from which firejail profiles are fetched.
+const PROFILES_RAW_URL: &str = "https://raw.githubusercontent.com/jakubGodula/cyber-toolkit/main/profiles/firejail/";
+
+/// Location of the firejail binary that tool symlinks point to.
+const FIREJAIL_BINARY: &str = "/usr/bin/firejail";
+
+/// Directory of profiles bundled with firejail.
+const SYSTEM_PROFILES_DIR: &str = "/etc/firejail";
+
+/// A tool confined as part of a hardened role.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HardenedTool {
+    pub tool: String,
+    /// Whether a profile from the repository was written to `~/.config/firejail`.
+    pub custom_profile: bool,
+}
+
+/// Reads the hardened roles and their confined tools.
+pub fn read_hardened() -> Result<BTreeMap<String, Vec<HardenedTool>>, Box<dyn std::error::Error>> {
+    match state_db::read_document("hardened.json")? {
+        Some(content) => Ok(serde_json::from_str(&content)?),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+fn write_hardened(record: &BTreeMap<String, Vec<HardenedTool>>) -> Result<(), Box<dyn std::error::Error>> {
+    state_db::write_document("hardened.json", Some(&serde_json::to_string_pretty(record)?))
+}
+
+fn user_profiles_dir() -> Result<PathBuf, io::Error> {
+    dirs::config_dir()
+        .map(|dir| dir.join("firejail"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, t!("config-dir-not-found")))
+}
+
+fn user_bin_dir() -> Result<PathBuf, io::Error> {
+    dirs::home_dir()
+        .map(|home_dir| home_dir.join(".local").join("bin"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, t!("home-not-found")))
+}
+
+/// Fetches a tool's profile from the repository. Returns `None` if the repository has none.
+async fn fetch_profile(tool: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let url = format!("{}{}.profile", PROFILES_RAW_URL, tool);
+    let response = http::get(&url)?.send().await?;
+    http::check_response(&response)?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(Box::from(t!("harden-profile-fetch-failed", url = url, status = response.status().to_string())));
+    }
+    Ok(Some(response.text().await?))
+}
+
+/// Returns true if `path` is a symlink pointing at the firejail binary.
+fn is_firejail_link(path: &Path) -> bool {
+    fs::read_link(path).map(|target| target == Path::new(FIREJAIL_BINARY)).unwrap_or(false)
+}
+
+/// Confines the given tools of a role and records them. Returns the tools that were confined.
+///
+/// Tools that aren't installed, have no profile anywhere, or whose `~/.local/bin` entry is a
+/// user file are reported and skipped.
+pub async fn harden_role(role: &str, tools: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if !Path::new(FIREJAIL_BINARY).exists() {
+        return Err(Box::from(t!("harden-no-firejail", path = FIREJAIL_BINARY)));
+    }
+    let installed = pacman::query_installed(tools)?;
+    let profiles_dir = user_profiles_dir()?;
+    let bin_dir = user_bin_dir()?;
+    fs::create_dir_all(&profiles_dir)?;
+    fs::create_dir_all(&bin_dir)?;
+
+    let mut hardened = Vec::new();
+    for tool in tools {
+        if !installed.contains_key(tool) {
+            println!("{}", t!("harden-not-installed", tool = tool.as_str()));
+            continue;
+        }
+        let link_path = bin_dir.join(tool);
+        if link_path.exists() && !is_firejail_link(&link_path) {
+            eprintln!("{}", t!("harden-link-exists", tool = tool.as_str(), path = format!("{:?}", link_path)));
+            continue;
+        }
+
+        let custom_profile = match fetch_profile(tool).await? {
+            Some(profile) => {
+                fs::write(profiles_dir.join(format!("{}.profile", tool)), profile)?;
+                true
+            }
+            None if Path::new(SYSTEM_PROFILES_DIR).join(format!("{}.profile", tool)).exists() => false,
+            None => {
+                eprintln!("{}", t!("harden-no-profile", tool = tool.as_str(), dir = SYSTEM_PROFILES_DIR));
+                continue;
+            }
+        };
+
+        if !link_path.exists() {
+            platform::symlink_file(Path::new(FIREJAIL_BINARY), &link_path)?;
+        }
+        if custom_profile {
+            println!("{}", t!("harden-confined-custom", tool = tool.as_str()));
+        } else {
+            println!("{}", t!("harden-confined-bundled", tool = tool.as_str()));
+        }
+        hardened.push(HardenedTool { tool: tool.clone(), custom_profile });
+    }
+
+    let mut record = read_hardened()?;
+    let names = hardened.iter().map(|h| h.tool.clone()).collect();
+    record.insert(role.to_string(), hardened);
+    write_hardened(&record)?;
+    Ok(names)
+}
+
+/// Undoes `harden_role` for a role. Tools still confined by another hardened role are kept.
+///
+/// Does nothing if the role was never hardened.
+pub fn unharden_role(role: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut record = read_hardened()?;
+    let Some(tools) = record.remove(role) else {
+        return Ok(());
+    };
+    let profiles_dir = user_profiles_dir()?;
+    let bin_dir = user_bin_dir()?;
+
+    for hardened_tool in tools {
+        let still_needed = record.values().flatten().any(|other| other.tool == hardened_tool.tool);
+        if still_needed {
+            continue;
+        }
+        let link_path = bin_dir.join(&hardened_tool.tool);
+        if is_firejail_link(&link_path) {
+            fs::remove_file(&link_path)?;
+        }
+        if hardened_tool.custom_profile {
+            let profile_path = profiles_dir.join(format!("{}.profile", hardened_tool.tool));
+            if profile_path.exists() {
+                fs::remove_file(profile_path)?;
+            }
+        }
+        println!("{}", t!("harden-removed", tool = hardened_tool.tool.as_str()));
+    }
+    write_hardened(&record)?;
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Running data refresh hooks declared in role metadata, and tracking their results.
+//!
+//! Results are recorded in the `hooks.json` state document, keyed by command, so `refresh-data` can show
+//! when each tool's data was last refreshed.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::t;
+use crate::metadata::{RoleMetadata, UpdateHook};
+use crate::{audit, pacman, platform, state_db};
+
+/// Outcome of the most recent run of a hook.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HookRun {
+    pub tool: String,
+    /// RFC 3339 timestamp of the run.
+    pub last_run: String,
+    pub success: bool,
+}
+
+/// Reads the recorded hook runs, keyed by command.
+pub fn read_runs() -> Result<BTreeMap<String, HookRun>, Box<dyn std::error::Error>> {
+    match state_db::read_document("hooks.json")? {
+        Some(content) => Ok(serde_json::from_str(&content)?),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+fn write_runs(runs: &BTreeMap<String, HookRun>) -> Result<(), Box<dyn std::error::Error>> {
+    state_db::write_document("hooks.json", Some(&serde_json::to_string_pretty(runs)?))
+}
+
+/// Collects the hooks declared in the given roles' metadata, without duplicate commands.
+pub fn collect_hooks(roles_metadata: &[(String, RoleMetadata)]) -> Vec<UpdateHook> {
+    let mut hooks: Vec<UpdateHook> = Vec::new();
+    for (_, role_metadata) in roles_metadata {
+        for hook in &role_metadata.hooks {
+            if !hooks.iter().any(|known| known.command == hook.command) {
+                hooks.push(hook.clone());
+            }
+        }
+    }
+    hooks
+}
+
+/// Runs the hooks whose tools are installed, records the results, and returns them.
+///
+/// Hooks for tools that aren't installed are skipped. A failing hook doesn't stop the others.
+pub fn run_hooks(hooks: &[UpdateHook]) -> Result<Vec<HookRun>, Box<dyn std::error::Error>> {
+    let tools: Vec<String> = hooks.iter().map(|hook| hook.tool.clone()).collect();
+    let installed = pacman::query_installed(&tools)?;
+    let mut runs = read_runs()?;
+    let mut results = Vec::new();
+
+    for hook in hooks {
+        if !installed.contains_key(&hook.tool) {
+            println!("{}", t!("hook-skipped", tool = hook.tool.as_str()));
+            continue;
+        }
+        println!("{}", t!("hook-running", tool = hook.tool.as_str(), command = hook.command.as_str()));
+        let status = if hook.root {
+            platform::elevated_shell(&hook.command).status()
+        } else {
+            platform::shell(&hook.command).status()
+        };
+        let success = match status {
+            Ok(status) if status.success() => true,
+            Ok(status) => {
+                eprintln!("{}", t!("hook-failed", tool = hook.tool.as_str(), code = format!("{:?}", status.code())));
+                false
+            }
+            Err(e) => {
+                eprintln!("{}", t!("hook-not-started", tool = hook.tool.as_str(), error = e.to_string()));
+                false
+            }
+        };
+        audit::record("hook", &[], std::slice::from_ref(&hook.tool), success, Some(&hook.command));
+        let run = HookRun { tool: hook.tool.clone(), last_run: chrono::Utc::now().to_rfc3339(), success };
+        runs.insert(hook.command.clone(), run.clone());
+        results.push(run);
+    }
+
+    write_runs(&runs)?;
+    Ok(results)
+}
@@ -0,0 +1,168 @@
+//! The HTTP client shared by all requests.
+//!
+//! It is built once, so connections to the repository and GitHub are pooled and reused across
+//! requests instead of being set up again for each one. Its timeouts and keep-alive come from
+//! `configure` (see `ClientSettings`), so a stalled server can't hang a headless run forever.
+//!
+//! With `set_offline`, all network access through `get` and `HttpFetcher` is refused, e.g. on
+//! air-gapped workstations; requests then fail with an error instead of connecting.
+//!
+//! Rate limits are respected: once a host answers with HTTP 429, or 403 with
+//! `X-RateLimit-Remaining: 0` (GitHub), `check_response` records when the limit resets and
+//! further requests to that host fail right away with a `RateLimited` error until then, instead
+//! of each being refused by the server.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Hosts that rate-limited us, with the time their limit resets (`None`: unknown, for the rest of the run).
+static RATE_LIMITED: Mutex<Option<HashMap<String, Option<SystemTime>>>> = Mutex::new(None);
+
+/// Timeouts and keep-alive of the shared client.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientSettings {
+    /// Longest wait for a connection to be established.
+    pub connect_timeout: Duration,
+    /// Longest time a request made with `get` may take in total, including the response body.
+    pub request_timeout: Duration,
+    /// Longest wait for the next data of a streamed download (see `read_timeout`).
+    pub read_timeout: Duration,
+    /// TCP keep-alive interval, and how long idle connections are kept for reuse.
+    pub keepalive: Duration,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        ClientSettings {
+            connect_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(60),
+            read_timeout: Duration::from_secs(30),
+            keepalive: Duration::from_secs(60),
+        }
+    }
+}
+
+static SETTINGS: OnceLock<ClientSettings> = OnceLock::new();
+
+/// Sets the timeouts and keep-alive of the shared client. Has to be called before the client is
+/// first used; later calls are ignored.
+pub fn configure(settings: ClientSettings) {
+    let _ = SETTINGS.set(settings);
+}
+
+/// Returns the settings of the shared client (the defaults unless `configure` was called).
+pub fn settings() -> ClientSettings {
+    *SETTINGS.get_or_init(ClientSettings::default)
+}
+
+/// Returns the shared HTTP client, building it on first use.
+pub fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        let settings = settings();
+        reqwest::Client::builder()
+            .user_agent(concat!("cyber-toolkit/", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(settings.connect_timeout)
+            .tcp_keepalive(settings.keepalive)
+            .pool_idle_timeout(settings.keepalive)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// Returns the read timeout for streamed downloads, which have no total timeout: a download may
+/// take as long as it needs, as long as data keeps arriving.
+pub fn read_timeout() -> Duration {
+    settings().read_timeout
+}
+
+/// Forbids (`true`) or allows all network access for the rest of the process.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Returns true if network access is forbidden (see `set_offline`).
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// A request refused because the host rate-limited us.
+#[derive(Debug, Clone)]
+pub struct RateLimited {
+    pub host: String,
+    /// When the limit resets, if the host said so.
+    pub reset: Option<SystemTime>,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} rate limit exceeded", self.host)?;
+        if let Some(reset) = self.reset {
+            let wait = reset.duration_since(SystemTime::now()).unwrap_or_default();
+            write!(f, "; it resets in {} minutes", wait.as_secs().div_ceil(60))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Returns true if `error` is a `RateLimited` error.
+pub fn is_rate_limited(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.downcast_ref::<RateLimited>().is_some()
+}
+
+fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)).unwrap_or_default()
+}
+
+/// Returns an error naming `url` if network access is forbidden, or a `RateLimited` error if its
+/// host rate-limited us and the limit hasn't reset yet.
+pub fn check_request(url: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if is_offline() {
+        return Err(Box::from(format!("Network access is disabled (offline mode); not fetching {}", url)));
+    }
+    let host = host_of(url);
+    let mut limited = RATE_LIMITED.lock().unwrap_or_else(|e| e.into_inner());
+    let hosts = limited.get_or_insert_with(HashMap::new);
+    match hosts.get(&host) {
+        Some(Some(reset)) if *reset <= SystemTime::now() => {
+            hosts.remove(&host);
+            Ok(())
+        }
+        Some(reset) => Err(Box::new(RateLimited { host, reset: *reset })),
+        None => Ok(()),
+    }
+}
+
+/// Checks a response for a rate limit: HTTP 429, or 403 with `X-RateLimit-Remaining: 0`. If the
+/// host rate-limited us, records until when (from `Retry-After` or `X-RateLimit-Reset`) and
+/// returns a `RateLimited` error.
+pub fn check_response(response: &reqwest::Response) -> Result<(), RateLimited> {
+    let headers = response.headers();
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).and_then(|value| value.trim().parse::<u64>().ok());
+    let exhausted = header("x-ratelimit-remaining") == Some(0);
+    let status = response.status().as_u16();
+    if !(status == 429 || (status == 403 && exhausted)) {
+        return Ok(());
+    }
+    let reset = header("retry-after")
+        .map(|seconds| SystemTime::now() + Duration::from_secs(seconds))
+        .or_else(|| header("x-ratelimit-reset").map(|epoch| UNIX_EPOCH + Duration::from_secs(epoch)));
+    let host = response.url().host_str().unwrap_or_default().to_string();
+    let mut limited = RATE_LIMITED.lock().unwrap_or_else(|e| e.into_inner());
+    limited.get_or_insert_with(HashMap::new).insert(host.clone(), reset);
+    Err(RateLimited { host, reset })
+}
+
+/// Starts a GET request for `url` with the shared client and the request timeout, unless
+/// `check_request` refuses it.
+pub fn get(url: &str) -> Result<reqwest::RequestBuilder, Box<dyn std::error::Error>> {
+    check_request(url)?;
+    Ok(client().get(url).timeout(settings().request_timeout))
+}
@@ -0,0 +1,152 @@
+//! Translations of user-facing messages, with [Fluent](https://projectfluent.org).
+//!
+//! Messages are looked up by id with the `t!` macro, e.g. `t!("add-up-to-date", count = 12)`.
+//! The English catalog, `locales/en/cyber-toolkit.ftl`, is compiled in and has every message.
+//! Other languages are read at runtime from `<LOCALE_DIR>/<lang>/cyber-toolkit.ftl`, so
+//! distributions can ship translations without rebuilding; they may translate any subset, and
+//! missing messages fall back to English.
+//!
+//! What other programs read stays in English: the JSON and CSV outputs, metrics, logs, commit
+//! messages and the failure reasons kept in the state directory.
+//!
+//! The language comes from `--lang` (see `configure`), or else from `LC_ALL`, `LC_MESSAGES` or
+//! `LANG` (`de_DE.UTF-8` selects `de-DE`, then `de`).
+
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use unic_langid::LanguageIdentifier;
+
+/// Directory holding translations, one `<lang>/cyber-toolkit.ftl` per language.
+pub const LOCALE_DIR: &str = "/usr/share/cyber-toolkit/locales";
+
+/// File name of a catalog inside its language directory.
+const CATALOG_FILE: &str = "cyber-toolkit.ftl";
+
+/// The compiled-in English catalog.
+const ENGLISH: &str = include_str!("../locales/en/cyber-toolkit.ftl");
+
+static LANGUAGE: OnceLock<Option<String>> = OnceLock::new();
+
+/// Selects the language instead of detecting it from the environment. Has to be called before
+/// the first message is looked up; later calls are ignored.
+pub fn configure(language: Option<&str>) {
+    let _ = LANGUAGE.set(language.map(str::to_string));
+}
+
+/// Returns the value of `--lang` among the command-line arguments `args` (program name first).
+/// Messages are printed before the arguments are parsed, and by the parser itself, so the
+/// language is configured from this quick scan first. Arguments after `--` don't count.
+pub fn language_arg(args: &[String]) -> Option<&str> {
+    let mut args = args.iter().skip(1).take_while(|arg| *arg != "--");
+    while let Some(arg) = args.next() {
+        if arg == "--lang" {
+            return args.next().map(String::as_str);
+        }
+        if let Some(language) = arg.strip_prefix("--lang=") {
+            return Some(language);
+        }
+    }
+    None
+}
+
+/// Returns the requested language: the configured one, or the one from the locale environment variables.
+fn requested_language() -> Option<LanguageIdentifier> {
+    let configured = LANGUAGE.get().cloned().flatten();
+    let raw = configured.or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"].iter().filter_map(|name| std::env::var(name).ok()).find(|value| !value.is_empty())
+    })?;
+    // `de_DE.UTF-8@euro` -> `de-DE`
+    let tag = raw.split(['.', '@']).next().unwrap_or_default().replace('_', "-");
+    if tag == "C" || tag == "POSIX" {
+        return None;
+    }
+    tag.parse().ok()
+}
+
+fn bundle(language: LanguageIdentifier, source: String) -> Option<FluentBundle<FluentResource>> {
+    let resource = match FluentResource::try_new(source) {
+        Ok(resource) => resource,
+        Err((resource, errors)) => {
+            // About the catalog itself, so it can't come from it
+            eprintln!("Warning: Ignoring {} invalid entries in the {} translation.", errors.len(), language);
+            resource
+        }
+    };
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // Unicode isolation marks around arguments only show up as garbage in terminals
+    bundle.set_use_isolating(false);
+    bundle.add_resource(resource).ok()?;
+    Some(bundle)
+}
+
+/// Loads the translation for `language` from `LOCALE_DIR`, trying the full tag, then the language alone.
+fn load_translation(language: &LanguageIdentifier) -> Option<FluentBundle<FluentResource>> {
+    let candidates = [language.to_string(), language.language.to_string()];
+    candidates.iter().find_map(|name| {
+        let source = fs::read_to_string(Path::new(LOCALE_DIR).join(name).join(CATALOG_FILE)).ok()?;
+        bundle(language.clone(), source)
+    })
+}
+
+/// Returns the catalogs to look messages up in, in order: the translation (if any), then English.
+fn bundles() -> &'static [FluentBundle<FluentResource>] {
+    static BUNDLES: OnceLock<Vec<FluentBundle<FluentResource>>> = OnceLock::new();
+    BUNDLES.get_or_init(|| {
+        let english: LanguageIdentifier = "en".parse().unwrap_or_default();
+        let mut bundles = Vec::new();
+        if let Some(language) = requested_language().filter(|language| language.language != english.language) {
+            bundles.extend(load_translation(&language));
+        }
+        bundles.extend(bundle(english, ENGLISH.to_string()));
+        bundles
+    })
+}
+
+/// Formats the message `id` with `args`. An unknown id is returned as is.
+pub fn message(id: &str, args: &[(&str, FluentValue)]) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.clone());
+    }
+    for bundle in bundles() {
+        let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) else {
+            continue;
+        };
+        let mut errors = Vec::new();
+        return bundle.format_pattern(pattern, Some(&fluent_args), &mut errors).into_owned();
+    }
+    id.to_string()
+}
+
+/// Looks up a translated message: `t!("id")` or `t!("id", name = value, ...)`. Values can be
+/// strings or numbers; format lists and paths into a string first.
+macro_rules! t {
+    ($id:literal) => {
+        crate::i18n::message($id, &[])
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        crate::i18n::message($id, &[$((stringify!($name), fluent_bundle::FluentValue::from($value))),+])
+    };
+}
+pub(crate) use t;
+
+#[cfg(test)]
+mod tests {
+    use super::language_arg;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn language_arg_reads_both_forms() {
+        assert_eq!(language_arg(&args(&["cyber-toolkit", "--lang", "de", "list"])), Some("de"));
+        assert_eq!(language_arg(&args(&["cyber-toolkit", "list", "--lang=pt-BR"])), Some("pt-BR"));
+        assert_eq!(language_arg(&args(&["cyber-toolkit", "plugin", "scan", "--", "--lang", "de"])), None);
+        assert_eq!(language_arg(&args(&["cyber-toolkit", "--lang"])), None);
+    }
+}
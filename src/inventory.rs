@@ -0,0 +1,102 @@
+//! Tool inventory for `report`: the installed tools of the configured roles as a Markdown table or
+//! CSV, ready to paste into the appendix of a pentest report.
+
+use crate::i18n::t;
+
+/// Output formats of `report`.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Markdown table with a heading naming the host and date.
+    Md,
+    /// Comma-separated values with a header row (RFC 4180).
+    Csv,
+}
+
+/// One installed tool.
+#[derive(Debug)]
+pub struct Row {
+    pub tool: String,
+    /// Package version, container image or Go module version; empty if unknown.
+    pub version: String,
+    /// Where the tool comes from, e.g. `pacman`, `container docker.io/parrotsec/sqlmap` or
+    /// `go github.com/projectdiscovery/subfinder/v2/cmd/subfinder`.
+    pub source: String,
+    pub roles: Vec<String>,
+    /// Date (`YYYY-MM-DD`) the tool was installed, if known.
+    pub installed: Option<String>,
+}
+
+const HEADERS: [&str; 5] = ["Tool", "Version", "Source", "Roles", "Installed"];
+
+impl Row {
+    fn cells(&self) -> [String; 5] {
+        [
+            self.tool.clone(),
+            self.version.clone(),
+            self.source.clone(),
+            self.roles.join(", "),
+            self.installed.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+/// Converts pacman's C-locale date (`Tue Oct  1 10:00:00 2024`) or an RFC 3339 timestamp to
+/// `YYYY-MM-DD`.
+pub fn date_of(timestamp: &str) -> Option<String> {
+    // pacman pads single-digit days with a space
+    let pacman_date = timestamp.split_whitespace().collect::<Vec<_>>().join(" ");
+    let date = chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| time.date_naive())
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(&pacman_date, "%a %b %d %H:%M:%S %Y").map(|time| time.date()))
+        .ok()?;
+    Some(date.format("%Y-%m-%d").to_string())
+}
+
+/// Renders `rows` in `format`. `host` and `generated` (a date) head the Markdown document.
+pub fn render(rows: &[Row], format: Format, host: &str, generated: &str) -> String {
+    match format {
+        Format::Md => render_markdown(rows, host, generated),
+        Format::Csv => render_csv(rows),
+    }
+}
+
+fn render_markdown(rows: &[Row], host: &str, generated: &str) -> String {
+    let mut text = format!("## {}\n\n", t!("inventory-title"));
+    if host.is_empty() {
+        text.push_str(&format!("{}\n\n", t!("inventory-generated", generated = generated)));
+    } else {
+        text.push_str(&format!("{}\n\n", t!("inventory-generated-host", host = host, generated = generated)));
+    }
+    // The CSV keeps the English column names, which scripts rely on
+    let headers = [t!("inventory-tool"), t!("inventory-version"), t!("inventory-source"), t!("inventory-roles"), t!("inventory-installed")];
+    text.push_str(&format!("| {} |\n", headers.join(" | ")));
+    text.push_str(&format!("|{}\n", "---|".repeat(HEADERS.len())));
+    for row in rows {
+        let cells: Vec<String> = row.cells().iter().map(|cell| markdown_cell(cell)).collect();
+        text.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    text
+}
+
+/// Escapes what would end a table cell or break the row.
+fn markdown_cell(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|").replace('\n', " ")
+}
+
+fn render_csv(rows: &[Row]) -> String {
+    let mut text = format!("{}\r\n", HEADERS.map(|header| header.to_lowercase()).join(","));
+    for row in rows {
+        let cells: Vec<String> = row.cells().iter().map(|cell| csv_field(cell)).collect();
+        text.push_str(&format!("{}\r\n", cells.join(",")));
+    }
+    text
+}
+
+/// Quotes a field if it contains a separator, quote or line break, doubling inner quotes.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,144 @@
+//! The `isolated:` backend: runs tools from container images instead of installing them natively.
+//!
+//! An entry `isolated:sqlmap=docker.io/parrotsec/sqlmap` pulls the image with podman and writes
+//! a wrapper script `~/.local/bin/sqlmap` that runs the image with the current directory mounted
+//! at `/work` and host networking. The host package set is never touched. Offline, images are
+//! never pulled; they have to be in podman's local storage already.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+
+use cyber_toolkit::http;
+
+use crate::failures::{CommandFailure, FailedTool};
+use crate::i18n::t;
+use crate::platform;
+
+/// Entry prefix selecting this backend.
+pub const PREFIX: &str = "isolated:";
+
+/// Marker line identifying wrappers generated by the toolkit, so user files are never deleted.
+const WRAPPER_MARKER: &str = "# Generated by cyber-toolkit (isolated backend)";
+
+/// A tool provided by a container image.
+#[derive(Debug, Clone)]
+pub struct IsolatedTool {
+    /// Command name of the wrapper script.
+    pub name: String,
+    /// Container image reference.
+    pub image: String,
+}
+
+impl IsolatedTool {
+    /// The role file entry describing this tool.
+    pub fn entry(&self) -> String {
+        format!("{}{}={}", PREFIX, self.name, self.image)
+    }
+}
+
+/// Parses the part after `isolated:`, i.e. `<name>=<image>`.
+pub fn parse_spec(spec: &str) -> Result<IsolatedTool, String> {
+    let (name, image) = spec
+        .split_once('=')
+        .ok_or_else(|| t!("isolated-spec-invalid"))?;
+    let (name, image) = (name.trim(), image.trim());
+    if name.is_empty() || image.is_empty() || name.contains('/') {
+        return Err(t!("isolated-spec-invalid"));
+    }
+    Ok(IsolatedTool { name: name.to_string(), image: image.to_string() })
+}
+
+/// Directory receiving wrapper scripts, `~/.local/bin`.
+fn wrapper_dir() -> Result<PathBuf, io::Error> {
+    dirs::home_dir()
+        .map(|home_dir| home_dir.join(".local").join("bin"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, t!("home-not-found")))
+}
+
+/// Pulls the images and writes the wrapper scripts. Returns the tools that failed.
+pub fn install(tools: &[IsolatedTool]) -> Result<Vec<FailedTool>, Box<dyn std::error::Error>> {
+    let dir = wrapper_dir()?;
+    fs::create_dir_all(&dir)?;
+    let mut failed = Vec::new();
+
+    for tool in tools {
+        if http::is_offline() {
+            // Offline, the image must already be in local storage
+            let exists = Command::new("podman").args(["image", "exists", &tool.image]).status().is_ok_and(|status| status.success());
+            if !exists {
+                eprintln!("{}", t!("isolated-image-missing-offline", image = tool.image.as_str(), tool = tool.name.as_str()));
+                failed.push(FailedTool::new(tool.entry(), "isolated", format!("{} isn't available locally and offline mode forbids pulling it", tool.image)));
+                continue;
+            }
+        } else {
+            println!("{}", t!("isolated-pulling", image = tool.image.as_str(), tool = tool.name.as_str()));
+            let mut pull = Command::new("podman");
+            pull.arg("pull").arg(&tool.image);
+            if platform::is_plain() {
+                pull.arg("--quiet"); // No per-layer progress bars
+            }
+            let (status, stderr) = platform::status_with_stderr(&mut pull)?;
+            if !status.success() {
+                eprintln!("{}", t!("isolated-pull-failed", image = tool.image.as_str(), code = format!("{:?}", status.code())));
+                let failure = CommandFailure::new(format!("could not pull {}", tool.image), &pull, status.code(), stderr);
+                failed.push(failure.for_tool(tool.entry(), "isolated"));
+                continue;
+            }
+        }
+
+        let wrapper_path = dir.join(&tool.name);
+        if wrapper_path.exists() && !is_generated_wrapper(&wrapper_path) {
+            eprintln!("{}", t!("isolated-wrapper-foreign", path = format!("{:?}", wrapper_path)));
+            let reason = format!("{:?} exists and was not generated by cyber-toolkit", wrapper_path);
+            failed.push(FailedTool::new(tool.entry(), "isolated", reason));
+            continue;
+        }
+        let image = shlex::try_quote(&tool.image)?;
+        let script = format!(
+            "#!/bin/sh\n{}\ntty_flag=\nif [ -t 0 ] && [ -t 1 ]; then tty_flag=-t; fi\n\
+             exec podman run --rm -i $tty_flag --network host -v \"$PWD:/work:Z\" -w /work {} \"$@\"\n",
+            WRAPPER_MARKER, image
+        );
+        fs::write(&wrapper_path, script)?;
+        platform::make_executable(&wrapper_path)?;
+        println!("{}", t!("isolated-wrapper-created", path = format!("{:?}", wrapper_path)));
+    }
+    Ok(failed)
+}
+
+/// Deletes the wrapper scripts and removes the images. Missing wrappers or images are not errors.
+pub fn remove(tools: &[IsolatedTool]) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = wrapper_dir()?;
+    for tool in tools {
+        let wrapper_path = dir.join(&tool.name);
+        if wrapper_path.exists() {
+            if is_generated_wrapper(&wrapper_path) {
+                fs::remove_file(&wrapper_path)?;
+                println!("{}", t!("isolated-wrapper-removed", path = format!("{:?}", wrapper_path)));
+            } else {
+                eprintln!("{}", t!("isolated-wrapper-kept", path = format!("{:?}", wrapper_path)));
+            }
+        }
+        let status = Command::new("podman").arg("rmi").arg(&tool.image).status();
+        if !matches!(status, Ok(status) if status.success()) {
+            eprintln!("{}", t!("isolated-rmi-failed", image = tool.image.as_str()));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the names of the tools whose generated wrapper script exists.
+pub fn installed(tools: &[IsolatedTool]) -> Result<Vec<String>, io::Error> {
+    let dir = wrapper_dir()?;
+    Ok(tools
+        .iter()
+        .filter(|tool| is_generated_wrapper(&dir.join(&tool.name)))
+        .map(|tool| tool.name.clone())
+        .collect())
+}
+
+fn is_generated_wrapper(path: &std::path::Path) -> bool {
+    fs::read_to_string(path).map(|content| content.contains(WRAPPER_MARKER)).unwrap_or(false)
+}
@@ -0,0 +1,118 @@
+//! Teaching mode: what each tool of a role is for, shown by `learn`.
+//!
+//! Every tool gets the one-line description of its package (`pacman -Qi`, or `pacman -Si` for
+//! tools not installed yet) and, if the role's metadata has one, a longer explanation from its
+//! `[learn]` section. The text is shown in `$PAGER` (`less` by default), whose `/` searches it.
+
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use serde::Serialize;
+
+use crate::i18n::t;
+use crate::platform;
+
+/// Column at which explanations are wrapped.
+const WRAP_WIDTH: usize = 76;
+
+/// A tool with what there is to learn about it.
+#[derive(Serialize, Debug)]
+pub struct Lesson {
+    pub tool: String,
+    pub installed: bool,
+    /// Package description, empty if the package manager doesn't know the tool.
+    pub description: String,
+    /// Longer explanation from the role metadata.
+    pub explanation: Option<String>,
+}
+
+impl Lesson {
+    /// Whether `term` appears (case-insensitively) in the tool name, description or explanation.
+    pub fn matches(&self, term: &str) -> bool {
+        let term = term.to_lowercase();
+        [Some(&self.tool), Some(&self.description), self.explanation.as_ref()]
+            .into_iter()
+            .flatten()
+            .any(|text| text.to_lowercase().contains(&term))
+    }
+}
+
+/// Renders the lessons of `role` as text.
+pub fn render(role: &str, lessons: &[Lesson]) -> String {
+    let mut text = t!("learn-title", role = role, count = lessons.len()) + "\n";
+    for lesson in lessons {
+        text.push('\n');
+        text.push_str(&lesson.tool);
+        if !lesson.installed {
+            text.push_str(&t!("learn-not-installed"));
+        }
+        text.push('\n');
+        if !lesson.description.is_empty() {
+            text.push_str(&format!("    {}\n", lesson.description));
+        }
+        if let Some(explanation) = &lesson.explanation {
+            for paragraph in explanation.split("\n\n") {
+                text.push('\n');
+                for line in wrap(paragraph, WRAP_WIDTH) {
+                    text.push_str(&format!("    {}\n", line));
+                }
+            }
+        }
+    }
+    text
+}
+
+/// Breaks `text` into lines of at most `width` characters at spaces; longer words get their own line.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Shows `text` in the pager when stdout is a terminal (and not `--plain`), otherwise prints it.
+/// Falls back to printing if the pager can't be started.
+pub fn page(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if platform::is_plain() || !io::stdout().is_terminal() {
+        print!("{}", text);
+        return Ok(());
+    }
+    let pager = std::env::var("PAGER").ok().filter(|pager| !pager.trim().is_empty()).unwrap_or_else(|| "less".to_string());
+    let Some((program, args)) = shlex::split(&pager).and_then(|words| {
+        let (program, args) = words.split_first()?;
+        Some((program.clone(), args.to_vec()))
+    }) else {
+        print!("{}", text);
+        return Ok(());
+    };
+    let mut command = Command::new(program);
+    command.args(args).stdin(Stdio::piped());
+    // Like git: quit at once if the text fits on the screen, and don't clear it on exit
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    let Ok(mut child) = command.spawn() else {
+        print!("{}", text);
+        return Ok(());
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closes its input when the user quits early, which isn't an error
+        match stdin.write_all(text.as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(Box::new(e)),
+            _ => {}
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! # Cyber Toolkit library
+//!
+//! The reusable core of the `cyber-toolkit` command: fetching role files (tool lists) from the
+//! repository and resolving them against the facts of the current host. Role files are read
+//! through a [`RoleFetcher`], which callers can replace (see [`fetcher`]).
+//!
+//! [`RoleManager`] is the main entry point: configured with a builder, it adds, removes and sets
+//! roles, installing and removing their tools with a [`Backend`], and returns typed reports.
+//!
+//! Library functions never print or exit. They return data such as [`roles::Role`] and
+//! [`roles::FetchReport`], and report progress through a [`Reporter`] supplied by the caller, so
+//! embedders (e.g. a provisioning daemon) stay in control of all output. Questions to the user
+//! go through a [`UserInterface`] the same way. The command-line interface in `main.rs` is one
+//! such caller.
+//!
+//! The API is async (tokio). With the `blocking` cargo feature, [`blocking`] offers synchronous
+//! wrappers for callers without a runtime. With the `events` cargo feature, [`events`] turns
+//! progress into a stream of events, including the package manager's output, for front-ends
+//! that render it live. With the `queue` feature, [`queue`] runs operations one at a time for
+//! daemons that receive overlapping requests. With the `test-support` feature, [`test_support`] provides an in-memory
+//! fetcher, a recording backend and fixtures for testing code built on the library.
+//!
+//! Cargo features keep the dependency tree small for embedders: `remote` (HTTP fetching with
+//! reqwest and tokio), `cli` (the command-line interface), and the TLS backend `native-tls` or
+//! `rustls`. All are on by default except `rustls`; with `default-features = false`, role
+//! files are read through your own [`RoleFetcher`].
+
+pub mod backend;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod config;
+#[cfg(feature = "events")]
+pub mod events;
+pub mod facts;
+pub mod fetcher;
+#[cfg(feature = "remote")]
+pub mod http;
+pub mod manager;
+pub mod paths;
+#[cfg(feature = "queue")]
+pub mod queue;
+pub mod report;
+pub mod role_file;
+pub mod roles;
+pub mod scripting;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+pub mod ui;
+
+pub use backend::{Backend, OutputStream, PackageOperation, PacmanBackend};
+#[cfg(feature = "remote")]
+pub use fetcher::HttpFetcher;
+pub use fetcher::RoleFetcher;
+pub use manager::{ChangeReport, RoleManager, RoleManagerBuilder, RoleStatus};
+pub use report::{Reporter, SilentReporter};
+pub use ui::{NonInteractive, UserInterface};
@@ -0,0 +1,99 @@
+//! License classification for the `licenses` subcommand: which installed tools are open source,
+//! and which need a license or a closer look (e.g. commercial tools such as Burp Suite or Nessus).
+//!
+//! Packages declare SPDX identifiers (`GPL-2.0-or-later`, `MIT OR Apache-2.0`) or, in older
+//! packages, Arch's short names (`GPL2`, `custom:BSD`). A tool counts as open source only if every
+//! license it declares is a known open source license. `custom` licenses, `LicenseRef-`
+//! identifiers and tools without any license information can be anything, so they are flagged
+//! for review; licenses naming proprietary terms are flagged as commercial.
+
+use serde::Serialize;
+
+/// How a tool's licenses are classified, from least to most attention needed.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum LicenseClass {
+    OpenSource,
+    /// Custom or unknown licenses that someone has to read.
+    Review,
+    /// Proprietary or commercial terms.
+    Commercial,
+}
+
+impl LicenseClass {
+    /// Marker shown in front of flagged tools.
+    pub fn marker(self) -> &'static str {
+        match self {
+            LicenseClass::OpenSource => " ",
+            LicenseClass::Review => "?",
+            LicenseClass::Commercial => "!",
+        }
+    }
+}
+
+/// Prefixes of open source license identifiers, both SPDX (`Apache-2.0`) and Arch's legacy names
+/// (`APACHE`, `PerlArtistic`). Compared case-insensitively.
+const OPEN_SOURCE_PREFIXES: &[&str] = &[
+    "0BSD", "AGPL", "Apache", "Artistic", "BSD", "BSL-1.0", "Boost", "CC0", "CDDL", "curl", "EPL", "EUPL", "FDL", "FTL", "GFDL",
+    "GPL", "HPND", "IJG", "ISC", "LGPL", "Libpng", "LPPL", "MIT", "MPL", "NCSA", "OFL", "OpenSSL", "Perl", "PHP", "PostgreSQL",
+    "PSF", "Python", "Ruby", "Sleepycat", "Unicode", "Unlicense", "Vim", "W3C", "WTFPL", "X11", "Zlib", "ZPL",
+];
+
+/// Words in a license name that mean it isn't open source.
+const COMMERCIAL_MARKERS: &[&str] = &["proprietary", "commercial", "eula", "freeware", "shareware", "non-free", "nonfree"];
+
+/// Classifies the licenses a tool declares. No licenses at all need review.
+pub fn classify(licenses: &[String]) -> LicenseClass {
+    licenses.iter().map(|license| classify_one(license)).max().unwrap_or(LicenseClass::Review)
+}
+
+/// Classifies one license, which may be an SPDX expression such as
+/// `GPL-2.0-or-later WITH Classpath-exception-2.0`.
+fn classify_one(license: &str) -> LicenseClass {
+    let lowercase = license.to_lowercase();
+    if COMMERCIAL_MARKERS.iter().any(|marker| lowercase.contains(marker)) {
+        return LicenseClass::Commercial;
+    }
+    let mut class = LicenseClass::OpenSource;
+    let mut exception = false;
+    for token in license.split(|c: char| c.is_whitespace() || c == '(' || c == ')').filter(|token| !token.is_empty()) {
+        // The exception after WITH only grants more rights
+        if std::mem::take(&mut exception) {
+            continue;
+        }
+        match token.to_ascii_uppercase().as_str() {
+            "AND" | "OR" => continue,
+            "WITH" => {
+                exception = true;
+                continue;
+            }
+            _ => {}
+        }
+        // Old packages name well-known licenses as `custom:BSD`; a bare `custom` stays unknown
+        let id = token.strip_prefix("custom:").unwrap_or(token);
+        if !is_open_source(id) {
+            class = LicenseClass::Review;
+        }
+    }
+    class
+}
+
+fn is_open_source(id: &str) -> bool {
+    OPEN_SOURCE_PREFIXES
+        .iter()
+        .any(|prefix| id.get(..prefix.len()).is_some_and(|start| start.eq_ignore_ascii_case(prefix)))
+}
+
+/// Derives a vendor from a project URL: the host, or the account on code hosting sites
+/// (`https://github.com/sqlmapproject/sqlmap` is `github.com/sqlmapproject`).
+pub fn vendor_from_url(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    match url.path_segments().and_then(|mut segments| segments.next()).filter(|account| !account.is_empty()) {
+        Some(account) if ["github.com", "gitlab.com", "codeberg.org", "bitbucket.org"].contains(&host) => {
+            Some(format!("{}/{}", host, account))
+        }
+        _ => Some(host.to_string()),
+    }
+}
@@ -0,0 +1,125 @@
+//! Local roles: role files kept in `~/.config/cyber-toolkit/local-roles/` instead of the
+//! repository, written by `tool add` and `tool remove`.
+//!
+//! A local role is configured and resolved like any other role, so its tools are tracked,
+//! verified and only uninstalled when no other role needs them. A local role hides a
+//! repository role of the same name.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use cyber_toolkit::{paths, roles};
+
+use crate::i18n::t;
+
+/// Role that `tool add` puts tools into unless told otherwise.
+pub const DEFAULT_ROLE: &str = "extras";
+
+/// Directory of the local role files.
+fn dir() -> Result<PathBuf, io::Error> {
+    Ok(paths::config_dir()?.join("local-roles"))
+}
+
+/// Path of the local role file `name`.
+pub fn path(name: &str) -> Result<PathBuf, io::Error> {
+    Ok(dir()?.join(roles::canonical_name(name)))
+}
+
+/// Checks that `name` can be used as the name of a local role file.
+pub fn validate_name(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let name = roles::canonical_name(name);
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\', '+']) || name.contains(char::is_whitespace) {
+        return Err(Box::from(t!("local-role-name-invalid", name = name)));
+    }
+    Ok(())
+}
+
+/// Returns the content of the local role `name`, or `None` if there is no such local role.
+pub fn read(name: &str) -> Result<Option<String>, io::Error> {
+    let path = path(name)?;
+    if !path.is_file() {
+        return Ok(None);
+    }
+    fs::read_to_string(path).map(Some)
+}
+
+/// Names of the local roles, sorted.
+pub fn list() -> Result<Vec<String>, io::Error> {
+    let dir = dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // Dotfiles are temporary files of `write`
+        if entry.file_type()?.is_file() && !name.starts_with('.') {
+            names.push(name);
+        }
+    }
+    names.sort_unstable();
+    Ok(names)
+}
+
+/// Tool entries on the lines of a local role file; comments and blank lines are kept in the file
+/// but aren't entries.
+fn entry_of(line: &str) -> Option<&str> {
+    let line = line.trim();
+    (!line.is_empty() && !line.starts_with('#')).then_some(line)
+}
+
+/// Appends `tools` to the local role `name`, creating it if needed. Returns the tools that
+/// weren't in it yet.
+pub fn add_tools(name: &str, tools: &[String]) -> Result<Vec<String>, io::Error> {
+    let existing = read(name)?.unwrap_or_default();
+    let mut lines: Vec<String> = existing.lines().map(str::to_string).collect();
+    let mut added = Vec::new();
+    for tool in tools.iter().map(|tool| tool.trim()).filter(|tool| !tool.is_empty()) {
+        if !lines.iter().any(|line| entry_of(line) == Some(tool)) {
+            lines.push(tool.to_string());
+            added.push(tool.to_string());
+        }
+    }
+    if !added.is_empty() {
+        write(name, &lines)?;
+    }
+    Ok(added)
+}
+
+/// Removes `tools` from the local role `name`; the file is deleted once it lists no tools.
+/// Returns the tools that were in it.
+pub fn remove_tools(name: &str, tools: &[String]) -> Result<Vec<String>, io::Error> {
+    let Some(existing) = read(name)? else {
+        return Ok(Vec::new());
+    };
+    let mut removed = Vec::new();
+    let mut lines = Vec::new();
+    for line in existing.lines() {
+        match entry_of(line) {
+            Some(entry) if tools.iter().any(|tool| tool.trim() == entry) => removed.push(entry.to_string()),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    if removed.is_empty() {
+        return Ok(removed);
+    }
+    if lines.iter().any(|line| entry_of(line).is_some()) {
+        write(name, &lines)?;
+    } else {
+        fs::remove_file(path(name)?)?;
+    }
+    Ok(removed)
+}
+
+fn write(name: &str, lines: &[String]) -> Result<(), io::Error> {
+    let path = path(name)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    let temp_path = path.with_file_name(format!(".{}.tmp", roles::canonical_name(name)));
+    fs::write(&temp_path, content)?;
+    fs::rename(temp_path, path)
+}
@@ -0,0 +1,190 @@
+//! The lockfile `roles.lock`, written by `cyber-toolkit freeze` and kept in the state database
+//! (see `state_db`).
+//!
+//! It records the SHA-256 of each configured role's definition. While a role is frozen, add/sync
+//! refuses to install from a definition that changed upstream unless `--refreeze` is given,
+//! which protects against tampered or unexpected role edits.
+//!
+//! It also records the exact version of every installed native package of those roles, so
+//! `cyber-toolkit install --locked` can reproduce the same set of packages later.
+//!
+//! Every add/sync also records the tools it installed with their scope: native packages are
+//! installed system-wide, containers and Go tools in the home of the user who ran it. Removal
+//! and `verify` only touch the tools recorded for the invoking user, which matters where users
+//! share a state directory (`CYBER_TOOLKIT_HOME`) on a lab machine.
+//!
+//! The install records also keep why a tool is there: the toolkit installed it, or it was already
+//! installed before a role asked for it, explicitly by the user or as a dependency of another
+//! package. To pacman, everything a role lists ends up explicitly installed, so `why-installed`
+//! and removal go by the record (see `ownership`).
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::i18n::t;
+use crate::{backends, state, state_db};
+
+/// Contents of `roles.lock`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct RolesLock {
+    /// When the lockfile was written (RFC 3339).
+    pub frozen_at: String,
+    pub roles: BTreeMap<String, LockedRole>,
+    /// Installed native packages of the frozen roles, keyed by name.
+    pub packages: BTreeMap<String, LockedPackage>,
+    /// Tools installed by add/sync, keyed by role entry.
+    pub installs: BTreeMap<String, InstalledTool>,
+}
+
+/// Where a tool is installed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// For the whole machine, by the package manager.
+    System,
+    /// In the home of the user who installed it.
+    User,
+}
+
+/// Who put a tool on the machine.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    /// The toolkit installed it for a role.
+    Toolkit,
+    /// The user had explicitly installed it before a role listed it.
+    User,
+    /// It was installed as a dependency of another package before a role listed it.
+    Dependency,
+}
+
+/// Lock entry of a tool installed by add/sync.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstalledTool {
+    pub scope: Scope,
+    /// User who installed it.
+    pub user: String,
+    /// When it was first installed (RFC 3339).
+    pub installed_at: String,
+    /// Who installed it; `None` in records written before origins were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<Origin>,
+}
+
+/// Lock entry of a native package.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockedPackage {
+    pub version: String,
+    pub architecture: String,
+}
+
+/// Lock entry of a role.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockedRole {
+    /// SHA-256 of the role file content (lowercase hex).
+    pub sha256: String,
+}
+
+/// Reads the lockfile, or `None` if nothing was frozen.
+pub fn read() -> Result<Option<RolesLock>, Box<dyn std::error::Error>> {
+    let Some(content) = state_db::read_document("roles.lock")? else {
+        return Ok(None);
+    };
+    toml::from_str(&content).map(Some).map_err(|e| Box::from(t!("lock-invalid", error = e.to_string())))
+}
+
+/// Writes the lockfile.
+pub fn write(lock: &RolesLock) -> Result<(), Box<dyn std::error::Error>> {
+    state_db::write_document("roles.lock", Some(&toml::to_string_pretty(lock)?))
+}
+
+/// Computes the SHA-256 of a role definition as lowercase hex.
+pub fn hash_content(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Writes a new lockfile recording the hash of each `(role, content)` definition and the given
+/// package versions.
+pub fn freeze(
+    definitions: &[(String, String)],
+    packages: BTreeMap<String, LockedPackage>,
+) -> Result<RolesLock, Box<dyn std::error::Error>> {
+    let installs = read()?.map(|lock| lock.installs).unwrap_or_default();
+    let mut lock = RolesLock { frozen_at: chrono::Utc::now().to_rfc3339(), packages, installs, ..RolesLock::default() };
+    for (role, content) in definitions {
+        lock.roles.insert(role.clone(), LockedRole { sha256: hash_content(content) });
+    }
+    write(&lock)?;
+    Ok(lock)
+}
+
+/// Checks the freshly fetched (cached) definitions of frozen roles against the lockfile.
+///
+/// Returns the roles whose definition changed, with the hash of their new definition. Roles
+/// that aren't frozen are not checked. Nothing is written; see `refreeze`.
+pub fn verify(roles: &[String]) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let Some(lock) = read()? else {
+        return Ok(Vec::new());
+    };
+    let mut changed = Vec::new();
+    for role in roles {
+        let (Some(locked), Some(content)) = (lock.roles.get(role), state::read_cached_role(role)?) else {
+            continue;
+        };
+        let actual = hash_content(&content);
+        if actual != locked.sha256 {
+            changed.push((role.clone(), actual));
+        }
+    }
+    Ok(changed)
+}
+
+/// Records the new hashes of `changed` roles, as returned by `verify`, accepting their new definitions.
+pub fn refreeze(changed: &[(String, String)]) -> Result<(), Box<dyn std::error::Error>> {
+    if changed.is_empty() {
+        return Ok(());
+    }
+    let mut lock = read()?.unwrap_or_default();
+    for (role, sha256) in changed {
+        println!("{}", t!("lock-refreezing", role = role.as_str(), checksum = sha256.as_str()));
+        lock.roles.insert(role.clone(), LockedRole { sha256: sha256.clone() });
+    }
+    write(&lock)
+}
+
+/// Records `entries` as installed by `user`, in the scope of their backend. Entries in
+/// `preinstalled` were on the machine before and get the origin given there; the others were
+/// installed by the toolkit. Entries recorded before keep their user, time and origin.
+pub fn record_installs(entries: &[String], user: &str, preinstalled: &HashMap<String, Origin>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut lock = read()?.unwrap_or_default();
+    let before = lock.installs.len();
+    let now = chrono::Utc::now().to_rfc3339();
+    for entry in entries {
+        lock.installs.entry(entry.clone()).or_insert_with(|| InstalledTool {
+            scope: backends::scope(entry),
+            user: user.to_string(),
+            installed_at: now.clone(),
+            origin: Some(preinstalled.get(entry).copied().unwrap_or(Origin::Toolkit)),
+        });
+    }
+    if lock.installs.len() == before {
+        return Ok(());
+    }
+    write(&lock)
+}
+
+/// Forgets the install records of `entries`, e.g. because they were uninstalled.
+pub fn forget_installs(entries: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(mut lock) = read()? else {
+        return Ok(());
+    };
+    let before = lock.installs.len();
+    lock.installs.retain(|entry, _| !entries.contains(entry));
+    if lock.installs.len() == before {
+        return Ok(());
+    }
+    write(&lock)
+}
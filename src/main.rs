@@ -3,340 +3,4195 @@
 //! 
 //! A command-line utility to manage collections of cybersecurity tools (roles) on Arch Linux-based systems.
 //! It fetches tool lists from a predefined GitHub repository, installs/uninstalls them using `pacman`,
-//! and manages a local configuration file (`~/.roles/roles.cnf`) to keep track of active roles.
+//! and manages a local configuration file (`~/.config/cyber-toolkit/roles.cnf`) to keep track of active roles.
 
-use std::collections::HashSet;
-use std::process::Command;
-use clap::Parser;
-use std::fs;
-use std::io::{self, Write, BufReader, BufRead};
-use shlex; // For safely quoting arguments for shell commands
+use std::collections::{BTreeMap, HashMap, HashSet};
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use serde::Serialize;
+
+use cyber_toolkit::roles::{self, Role, BASE_RAW_URL};
+use cyber_toolkit::config::{self, ConfigStore};
+use cyber_toolkit::{facts, http, paths, role_file, NonInteractive, Reporter, RoleFetcher, SilentReporter, UserInterface};
+use i18n::t;
+
+/// Set when `--json` is given. Informational messages then go to stderr so stdout stays valid JSON.
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Prints an informational message to stdout, or to stderr in `--json` mode.
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if crate::JSON_OUTPUT.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+mod aliases;
+mod audit;
+mod backends;
+mod batch;
+#[cfg(unix)]
+mod bundle;
+mod cache;
+mod changelog;
+mod checksums;
+#[cfg(unix)]
+mod conflicts;
+mod desktop;
+mod discovery;
+mod dotfiles;
+mod engage;
+mod expiry;
+mod failures;
+mod fuzzy;
+mod fleet;
+mod go;
+mod harden;
+mod hooks;
+mod i18n;
+mod inventory;
+mod isolated;
+mod learn;
+mod licenses;
+mod local_roles;
+mod lock;
+mod metadata;
+mod metrics;
+mod migrate;
+#[cfg(unix)]
+mod mirrors;
+mod notify;
+mod ownership;
+#[cfg(unix)]
+mod package_log;
+mod pacman;
+mod pacman_cache;
+#[cfg(feature = "pacman-db")]
+mod pacman_db;
+#[cfg(unix)]
+mod pacman_config;
+mod pins;
+mod platform;
+mod power;
+mod plugins;
+mod presets;
+mod purge;
+mod removal_guard;
+mod project;
+mod run_lock;
+mod settings;
+mod state;
+mod state_db;
+mod suggest;
+mod sync;
+mod terminal_ui;
+mod updates;
+mod venv;
+#[cfg(windows)]
+mod windows;
+mod wizard;
+mod wordlists;
+
+/// Absolute path of pacman; the shipped polkit policy matches on it.
+#[cfg(unix)]
+const PACMAN_BINARY: &str = "/usr/bin/pacman";
 
-/// Base URL from which role files (tool lists) are fetched.
-const BASE_RAW_URL: &str = "https://raw.githubusercontent.com/jakubGodula/cyber-toolkit/main/roles/";
 
 /// Defines the command-line arguments accepted by the application.
 #[derive(Parser, Debug)]
 #[clap(author, version, about = "Manages roles and associated tools for Athena OS.", long_about = None)]
+#[clap(subcommand_negates_reqs = true)]
+#[clap(group(ArgGroup::new("listing").args(["current", "list_all"])))]
 struct Cli {
+    /// Optional subcommand. When omitted, the listed `role_files` are added (or removed with `--remove`).
+    #[clap(subcommand)]
+    command: Option<Commands>,
+
+    /// Print machine-readable JSON instead of human-readable output (for commands that support it).
+    #[clap(long, global = true)]
+    json: bool,
+
+    /// Use this roles file instead of `~/.config/cyber-toolkit/roles.cnf`.
+    #[clap(long, global = true, value_name = "PATH")]
+    config: Option<std::path::PathBuf>,
+
+    /// Change the system-wide roles (`/etc/cyber-toolkit/roles.cnf`), which every user gets under
+    /// their own, instead of yours. Needs write access to that file, usually root.
+    #[clap(long, global = true, conflicts_with = "config")]
+    system: bool,
+
+    /// User mode: only use backends that don't need root (containers, `go:` tools, Python
+    /// virtualenvs). Native packages are skipped and recorded in `~/.local/state/cyber-toolkit/skipped_native`.
+    #[clap(long, global = true)]
+    user: bool,
+
     /// Flag to indicate removal of roles and their unique tools.
     /// If present, the listed `role_files` will be removed.
     #[clap(short, long)]
     remove: bool,
 
+    /// Make the listed roles exactly the configured ones: add the new ones and remove the other
+    /// configured roles with their unique tools. The removals are shown and need a confirmation
+    /// (or `--yes`).
+    #[clap(short, long, conflicts_with = "remove")]
+    update: bool,
+
+    /// With `--update`, keep the configured roles that aren't listed, so it only adds.
+    #[clap(long, requires = "update")]
+    keep_existing: bool,
+
+    /// Add the listed roles only temporarily, e.g. `--temp 7d` (units: m, h, d, w).
+    /// `cyber-toolkit expire` removes them once the time is up.
+    #[clap(long, value_name = "DURATION", value_parser = expiry::parse_duration, conflicts_with_all = ["remove", "update", "list_all"])]
+    temp: Option<chrono::Duration>,
+
+    /// With `--remove`, also delete the configuration and data directories the role metadata
+    /// lists for the uninstalled tools, after a preview and a confirmation per path.
+    #[clap(long, requires = "remove")]
+    purge: bool,
+
+    /// Never access the network: read roles, their metadata and the role index from the cache
+    /// (as fetched by earlier runs) and fail if something isn't cached. For air-gapped machines.
+    #[clap(long, global = true)]
+    offline: bool,
+
+    /// Language of the output, e.g. `de` or `pt-BR`, instead of the one from `LC_ALL`,
+    /// `LC_MESSAGES` or `LANG`. Untranslated messages are shown in English.
+    #[clap(long, global = true, value_name = "LANG")]
+    lang: Option<String>,
+
+    /// Plain, line-oriented output for screen readers and log collectors: no colors, progress
+    /// bars or spinners, also from pacman and the other tools run, and no interactive menus.
+    #[clap(long, global = true)]
+    plain: bool,
+
+    /// Answer every question without asking: confirmations with yes, menus with their default
+    /// choice. Also passes `--noconfirm` to pacman.
+    #[clap(long, short = 'y', global = true)]
+    yes: bool,
+
+    /// Refuse role files that can't be verified against the repository's `MANIFEST.sha256`
+    /// (no manifest, or not listed in it). Files that don't match it are always refused.
+    #[clap(long, global = true)]
+    strict: bool,
+
+    /// Remove without asking, even if the removal is large or includes protected packages
+    /// (see the `[removal]` settings).
+    #[clap(long, global = true)]
+    force: bool,
+
+    /// Allow removing pinned roles (see `pin-role`), and unpin them.
+    #[clap(long, global = true)]
+    unpin: bool,
+
+    /// Also uninstall the packages you had installed yourself before a role listed them, which
+    /// removing roles keeps otherwise (see `why-installed`).
+    #[clap(long, global = true)]
+    remove_user_installed: bool,
+
+    /// Accept upstream changes to roles frozen with `freeze` and record their new hashes.
+    #[clap(long)]
+    refreeze: bool,
+
+    /// List all roles available in the repository, marking the ones currently configured.
+    #[clap(long)]
+    list_all: bool,
+
+    /// Show the configured roles with their tool counts, install state, source and freeze state.
+    #[clap(long, conflicts_with_all = ["list_all", "remove"])]
+    current: bool,
+
+    /// With `--current` or `--list-all`, only show configured roles whose tools are all installed.
+    #[clap(long, requires = "listing", conflicts_with = "missing_only")]
+    installed_only: bool,
+
+    /// With `--current`, only show roles with tools that aren't installed, and those tools; with
+    /// `--list-all`, only show roles that aren't configured or not fully installed.
+    #[clap(long, requires = "listing")]
+    missing_only: bool,
+
+    /// With `--list-all`, discover role files through the GitHub contents API instead of
+    /// the `role_names` index, so newly added roles appear without updating the index.
+    #[clap(long, requires = "list_all")]
+    discover: bool,
+
     /// Names of the role files to process (e.g., blue-teamer.txt).
     /// These files are expected to be located in the repository defined by `BASE_RAW_URL`.
     /// - If `--remove` is used, these are the roles to remove from the configuration and system.
     /// - Otherwise (default), these roles are added to the configuration, and their tools are installed/synced.
-    #[clap(required = true, num_args = 1..)]
+    #[clap(required_unless_present_any = ["list_all", "current"], num_args = 1..)]
     role_files: Vec<String>,
 }
 
-/// Reads the list of currently configured role file names from `~/.roles/roles.cnf`.
+/// Subcommands for operations beyond adding and removing roles.
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Show installed disk usage per configured role.
+    ///
+    /// Sizes come from `pacman -Qi`; tools shared by several roles are split evenly between them.
+    Du,
+
+    /// Show a summary of configured roles, tool install coverage, cache size, last sync and history.
+    Stats,
+
+    /// Print health metrics (configured roles, installed and missing tools, last sync, failures
+    /// of the last run) in the Prometheus text format, e.g. for node_exporter's textfile collector.
+    ///
+    /// Computed from the cached role files and local state only; no network is used.
+    Metrics {
+        /// Write the metrics to this file instead of stdout, replacing it atomically, e.g.
+        /// `/var/lib/node_exporter/textfile_collector/cyber_toolkit.prom`.
+        #[clap(long, value_name = "FILE")]
+        textfile: Option<std::path::PathBuf>,
+    },
+
+    /// Synchronize the configuration directory (roles list and settings) with a git remote.
+    ///
+    /// The remote is configured as `remote` in the `[sync]` section of `~/.config/cyber-toolkit/config.toml`.
+    Sync {
+        #[clap(subcommand)]
+        action: SyncAction,
+    },
+
+    /// Run the data refresh hooks (e.g. `searchsploit -u`) declared in role metadata.
+    RefreshData {
+        /// Roles whose hooks to run. Defaults to all configured roles.
+        role_files: Vec<String>,
+    },
+
+    /// Confine a role's network-facing tools with firejail profiles.
+    ///
+    /// The tools are listed under `[sandbox] firejail` in the role metadata. Confinement is
+    /// undone automatically when the role is removed, or explicitly with `--undo`.
+    Harden {
+        role_file: String,
+
+        /// Remove the role's confinement instead of applying it.
+        #[clap(long)]
+        undo: bool,
+    },
+
+    /// Manage wordlist sets (SecLists, rockyou, dirb, ...) alongside roles.
+    Wordlists {
+        #[clap(subcommand)]
+        action: WordlistsAction,
+    },
+
+    /// Manage roles on multiple remote machines over SSH (e.g. classroom labs).
+    Fleet {
+        #[clap(subcommand)]
+        action: FleetAction,
+    },
+
+    /// Report tools added or removed upstream in the configured roles since the last sync.
+    ///
+    /// Compares the current role files with the copies cached at the last sync, without
+    /// installing anything or updating the cache.
+    CheckUpdates {
+        /// Also show a desktop notification if there are changes.
+        #[clap(long)]
+        notify: bool,
+    },
+
+    /// Install the tools of the configured roles (like adding no new roles).
+    Install {
+        /// Install exactly the package versions recorded in `roles.lock` by `freeze`,
+        /// using the Arch Linux Archive for versions no longer in the repositories.
+        #[clap(long)]
+        locked: bool,
+    },
+
+    /// Reattempt only the tools that failed in the last add/sync, as recorded in
+    /// `last-failures.json` in the state database.
+    RetryFailed,
+
+    /// Record a content hash of each configured role's definition in `roles.lock` in the state database.
+    ///
+    /// Later adds/syncs refuse to install a frozen role whose definition changed upstream,
+    /// unless `--refreeze` is given.
+    Freeze,
+
+    /// Show how a role's tool list changed over time.
+    ///
+    /// Uses the repository's `changelogs/<role>` file if there is one, and otherwise derives the
+    /// changes from the git history of the role file.
+    Changelog {
+        role_file: String,
+
+        /// Number of most recent changes to show.
+        #[clap(long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Curated groups of roles for certifications and common goals (OSCP, eJPT, ...).
+    Preset {
+        #[clap(subcommand)]
+        action: PresetAction,
+    },
+
+    /// Prepare this machine for a HackTheBox or TryHackMe box: maps its tags (web, ad, pwn,
+    /// crypto, ...) to roles and adds them.
+    ///
+    /// The mapping is `presets/engage.toml` in the repository.
+    Engage {
+        /// `<platform>:<tags>`, e.g. `htb:web,ad` or `thm:"active directory"`. Platforms: htb, thm.
+        #[clap(required = true)]
+        targets: Vec<String>,
+    },
+
+    /// Choose the configured roles from a checklist of all available roles, then add and remove
+    /// roles to match the selection.
+    Select,
+
+    /// Apply a list of add/remove/set operations as one change: one removal and one add/sync for
+    /// the difference to the configured roles, instead of one run per operation.
+    ///
+    /// One operation per line (`add web osint`, `remove forensics`, `set red-teamer`, `#`
+    /// comments) or a JSON list such as `[{"op": "add", "roles": ["web"]}]`.
+    Batch {
+        /// File with the operations, or `-` for stdin.
+        source: String,
+    },
+
+    /// Find the roles that provide a tool, tolerating typos in its name.
+    ///
+    /// Searches the tool lists of all available roles, e.g. `which-role bloodhund`.
+    WhichRole {
+        /// Tool to look for (package, container or Go binary name).
+        tool: String,
+    },
+
+    /// Tell why a package is installed: by cyber-toolkit for a role, by the user, or as a
+    /// dependency of another package.
+    ///
+    /// Combines pacman's install reason with the toolkit's install records; removing a role keeps
+    /// packages the user had installed themselves.
+    WhyInstalled {
+        /// Package, or role entry such as `isolated:...` or `go:...`.
+        package: String,
+    },
+
+    /// Sync the configured roles unattended, unless the machine is on battery or a metered
+    /// connection (see `[auto_sync]` in `config.toml`).
+    ///
+    /// Meant to be run from a systemd timer, e.g. nightly.
+    AutoSync,
+
+    /// Remove temporary roles (added with `--temp`) whose time is up, with their unique tools.
+    ///
+    /// Meant to be run regularly, e.g. from a systemd user timer.
+    Expire,
+
+    /// Add the roles declared in `.cyber-toolkit.toml` in the current directory or its parents.
+    ///
+    /// Roles that weren't configured before are remembered, so `leave` can remove them again.
+    Apply,
+
+    /// Remove the roles that `apply` added for the project in the current directory.
+    ///
+    /// Roles that were configured before, or that another applied project declares, are kept.
+    Leave,
+
+    /// Inspect and change the settings in `~/.config/cyber-toolkit/config.toml`.
+    ///
+    /// Keys are written as `section.key`, e.g. `pacman.aur_helper`. Values are checked
+    /// against the known settings before the file is changed.
+    Config {
+        #[clap(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Export or import the toolkit's state database (`~/.local/state/cyber-toolkit/state.db`) as JSON.
+    State {
+        #[clap(subcommand)]
+        action: StateAction,
+    },
+
+    /// Show, prune or clear the cache: role definitions, package metadata and leftover bundle
+    /// downloads.
+    Cache {
+        #[clap(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Carry roles to an air-gapped machine: pack their definitions, package files and
+    /// wordlists into one file, and install from it without network access.
+    Bundle {
+        #[clap(subcommand)]
+        action: BundleAction,
+    },
+
+    /// Manage role aliases (`[aliases]` in `~/.config/cyber-toolkit/config.toml`), which can be
+    /// used wherever a role name is accepted.
+    Alias {
+        #[clap(subcommand)]
+        action: AliasAction,
+    },
+
+    /// Check that the tools add/sync installed for you are still installed: native packages
+    /// system-wide, containers and Go tools in your home. Fails if any are missing.
+    Verify,
+
+    /// Write an inventory of the installed tools of the configured roles (name, version, source,
+    /// roles, install date) for pentest report appendices.
+    Report {
+        #[clap(long, value_enum, default_value = "md")]
+        format: inventory::Format,
+        /// File to write to instead of stdout.
+        #[clap(long, short)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Explain what each tool of a role does: its package description and, where the role's
+    /// metadata has one, a longer explanation. Shown in `$PAGER`, whose `/` searches.
+    Learn {
+        /// The role to learn about.
+        role: String,
+        /// Only show tools whose name, description or explanation contains this text.
+        #[clap(long)]
+        search: Option<String>,
+    },
+
+    /// Report the license and vendor of every installed tool of the configured roles, flagging
+    /// commercial tools and licenses that need review.
+    ///
+    /// Licenses come from `pacman -Qi`, overridden by the `[licenses]` section of role metadata.
+    Licenses {
+        /// Only list the flagged tools.
+        #[clap(long)]
+        flagged: bool,
+    },
+
+    /// For maintainers: bring the role files and metadata of a checkout of the roles repository
+    /// to the current formats, declaring the format in every file.
+    MigrateRoles {
+        /// Checkout of the repository, containing `roles/` and `meta/`.
+        #[clap(default_value = ".")]
+        repository: std::path::PathBuf,
+        /// Only list the files that need migrating, and fail if there are any.
+        #[clap(long)]
+        check: bool,
+    },
+
+    /// Protect roles from being removed by accident: removing a pinned role needs `--unpin`.
+    /// Without roles, lists the pinned ones.
+    PinRole { roles: Vec<String> },
+
+    /// Unpin roles, so they can be removed again without `--unpin`.
+    UnpinRole {
+        #[clap(required = true)]
+        roles: Vec<String>,
+    },
+
+    /// Install or uninstall single tools without a role of their own. They are kept in local
+    /// roles (`~/.config/cyber-toolkit/local-roles/`), so they are tracked like role tools.
+    Tool {
+        #[clap(subcommand)]
+        action: ToolAction,
+    },
+
+    /// Run the plugin `cyber-toolkit-<NAME>` from `PATH`, also when a role has the same name and
+    /// `cyber-toolkit <NAME>` therefore adds the role.
+    Plugin {
+        name: String,
+        /// Arguments passed to the plugin.
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+/// Tool operations.
+#[derive(Subcommand, Debug)]
+enum ToolAction {
+    /// Add tools to a local role, configure it and install them.
+    Add {
+        #[clap(required = true)]
+        tools: Vec<String>,
+        /// Local role to keep the tools in.
+        #[clap(long, default_value = local_roles::DEFAULT_ROLE)]
+        role: String,
+    },
+    /// Remove tools from local roles and uninstall them unless another role needs them.
+    Remove {
+        #[clap(required = true)]
+        tools: Vec<String>,
+        /// Only remove them from this local role (default: from all local roles).
+        #[clap(long)]
+        role: Option<String>,
+    },
+}
+
+/// Alias operations.
+#[derive(Subcommand, Debug)]
+enum AliasAction {
+    /// Define an alias, or change its roles, e.g. `alias add full red-teamer+forensics`.
+    Add {
+        name: String,
+        /// Roles (or other aliases) the alias stands for, joined with `+`.
+        roles: String,
+    },
+    /// Delete an alias.
+    Remove { name: String },
+    /// List the aliases with the roles they expand to.
+    List,
+}
+
+/// State database operations.
+#[derive(Subcommand, Debug)]
+enum StateAction {
+    /// Write the whole state (lockfile, failures, history, ...) as JSON.
+    Export {
+        /// File to write to instead of stdout.
+        #[clap(long, short)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Replace the whole state with a JSON file written by `state export`.
+    Import { file: std::path::PathBuf },
+}
+
+/// Cache operations.
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// Show the entries, size and age of each kind of cache.
+    Stats,
+    /// Remove what the `[cache]` policy in config.toml doesn't keep. The files of configured
+    /// roles are always kept.
+    Prune {
+        /// Only list what would be removed.
+        #[clap(long)]
+        dry_run: bool,
+        /// Override `max_age_days`; 0 means no limit.
+        #[clap(long, value_name = "DAYS")]
+        max_age_days: Option<u64>,
+        /// Override `max_size_mb`; 0 means no limit.
+        #[clap(long, value_name = "MB")]
+        max_size_mb: Option<u64>,
+    },
+    /// Remove everything cached; role files are fetched again on the next run.
+    Clear,
+}
+
+/// Offline bundle operations.
+#[derive(Subcommand, Debug)]
+enum BundleAction {
+    /// Pack roles, the package files of their native tools (with all dependencies) and wordlist sets.
+    Create {
+        #[clap(required = true, num_args = 1..)]
+        role_files: Vec<String>,
+        /// Wordlist set to include; can be repeated.
+        #[clap(long = "wordlist", value_name = "SET")]
+        wordlists: Vec<String>,
+        /// File to write.
+        #[clap(long, short, default_value = "cyber-toolkit-bundle.tar")]
+        output: std::path::PathBuf,
+    },
+    /// Install a bundle: its packages, wordlists and roles, without network access.
+    Install { file: std::path::PathBuf },
+}
+
+/// Settings operations.
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print the effective value of a setting or section, including defaults.
+    Get { key: String },
+    /// Change a setting. The value is read as TOML (`true`, `4`, `["Germany", "Poland"]`)
+    /// and otherwise taken as a string.
+    Set { key: String, value: String },
+    /// Remove a setting from the file, restoring its default.
+    Unset { key: String },
+    /// Print all effective settings, including defaults.
+    Show,
+    /// Open the settings file in `$VISUAL`/`$EDITOR` and check it before saving.
+    Edit,
+}
+
+/// Preset operations.
+#[derive(Subcommand, Debug)]
+enum PresetAction {
+    /// List the available presets and their roles.
+    List,
+    /// Add all roles of a preset, like `cyber-toolkit <role>...`.
+    Apply {
+        /// Name of the preset (e.g. `oscp`).
+        name: String,
+    },
+}
+
+/// Wordlist operations.
+#[derive(Subcommand, Debug)]
+enum WordlistsAction {
+    /// List available wordlist sets, marking installed ones.
+    List,
+    /// Download wordlist sets and record them as installed.
+    Add {
+        #[clap(required = true, num_args = 1..)]
+        sets: Vec<String>,
+    },
+    /// Delete wordlist sets and their system links.
+    Remove {
+        #[clap(required = true, num_args = 1..)]
+        sets: Vec<String>,
+    },
+    /// Re-download installed wordlists that changed upstream.
+    Update {
+        /// Only report which files have updates, without downloading them.
+        #[clap(long)]
+        check: bool,
+    },
+}
+
+/// Fleet operations on remote hosts.
+#[derive(Subcommand, Debug)]
+enum FleetAction {
+    /// Add the given roles on every host listed in the hosts file, over SSH.
+    Apply {
+        /// TOML file listing the hosts (`[[hosts]]` entries with `name` and `address`).
+        #[clap(long)]
+        hosts: std::path::PathBuf,
+
+        /// Copy this binary to each host first instead of using the `cyber-toolkit` installed there.
+        #[clap(long)]
+        copy_binary: bool,
+
+        /// Apply to this percentage of the hosts first (in hosts file order), and to the others
+        /// only if they succeed.
+        #[clap(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(1..=100), conflicts_with = "canary_tag")]
+        canary_percent: Option<u8>,
+
+        /// Apply to the hosts with this tag first, and to the others only if they succeed.
+        #[clap(long, value_name = "TAG")]
+        canary_tag: Option<String>,
+
+        /// Command to run on each host after applying; a failing one fails the host. Can be
+        /// repeated. Hosts are also checked with `cyber-toolkit verify` in a canary rollout.
+        #[clap(long, value_name = "COMMAND")]
+        smoke_test: Vec<String>,
+
+        /// Abort the rollout once more hosts failed than this number or percentage of all hosts
+        /// (e.g. `2` or `10%`). Default: 0 with a canary stage, no limit otherwise.
+        #[clap(long, value_name = "N|N%")]
+        max_failures: Option<fleet::FailureThreshold>,
+
+        /// Role files to add on every host.
+        #[clap(required = true, num_args = 1..)]
+        role_files: Vec<String>,
+    },
+}
+
+/// Direction of a `sync` operation.
+#[derive(Subcommand, Debug)]
+enum SyncAction {
+    /// Commit local changes and push them to the remote.
+    Push,
+    /// Pull the remote state, rebasing local changes on top.
+    Pull,
+}
+
+/// Reads the list of currently configured role file names from `~/.config/cyber-toolkit/roles.cnf`,
+/// after the system-wide roles from `/etc/cyber-toolkit/roles.cnf` (see `system_roles`).
 /// 
 /// Returns a `Vec<String>` of role names. If the config file doesn't exist, an empty vector is returned.
 /// Errors during file reading are propagated.
 fn read_roles_from_config_file() -> Result<Vec<String>, io::Error> {
-    // Construct path to ~/.roles/roles.cnf
-    let config_file_path = dirs::home_dir()
-        .map(|home_dir| home_dir.join(".roles").join("roles.cnf"))
-        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Home directory not found."))?;
-
-    if !config_file_path.exists() {
-        return Ok(Vec::new()); // No config file means no roles configured yet
-    }
-
-    let file = fs::File::open(config_file_path)?;
-    let reader = BufReader::new(file);
-    reader.lines()
-        .map(|line| line.map(|s| s.trim().to_string())) // Trim whitespace from each role name
-        .filter(|res| match res { // Filter out empty lines
-            Ok(s) => !s.is_empty(),
-            Err(_) => true, // Keep errors to allow them to be propagated by .collect()
-        })
-        .collect() // Collect into a Result<Vec<String>, io::Error>
+    let mut roles = system_roles()?;
+    for role in config_store()?.read_roles()? {
+        if !roles.contains(&role) {
+            roles.push(role);
+        }
+    }
+    Ok(roles)
 }
 
-/// Writes the given list of role file names to `~/.roles/roles.cnf`, one role per line.
+/// The roles of the system-wide layer, configured for every user. Empty with `--system`, where
+/// that file is the one read and written.
+fn system_roles() -> Result<Vec<String>, io::Error> {
+    if SYSTEM_MODE.load(Ordering::Relaxed) {
+        return Ok(Vec::new());
+    }
+    ConfigStore::new(config::system_config_path()).read_roles()
+}
+
+/// Writes the given list of role file names to `~/.config/cyber-toolkit/roles.cnf`, one role per line.
 /// 
-/// This function overwrites the existing file. It ensures the `~/.roles` directory exists.
+/// The file is replaced atomically and its comments are kept; the previous version is kept as a
+/// timestamped backup (see `ConfigStore`). It ensures the configuration directory exists.
+/// If another session changed the file since it was read, its changes are merged and reported.
 /// Errors during directory creation or file writing are propagated.
 fn write_roles_to_config_file(roles: &[String]) -> Result<(), io::Error> {
-    let config_file_path = dirs::home_dir()
-        .map(|home_dir| home_dir.join(".roles").join("roles.cnf"))
-        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Home directory not found."))?;
+    let store = config_store()?;
+    // System-wide roles stay in their own file (their removal is refused before getting here)
+    let system = system_roles()?;
+    let own_roles: Vec<String> = roles.iter().filter(|role| !system.contains(role)).cloned().collect();
+    if let Some(external) = store.write_roles(&own_roles)? {
+        eprintln!(
+            "{}",
+            t!(
+                "roles-file-merged",
+                path = format!("{:?}", store.path()),
+                added = format!("{:?}", external.added),
+                removed = format!("{:?}", external.removed)
+            )
+        );
+    }
+    info!("{}", t!("roles-file-written", path = format!("{:?}", store.path())));
+    Ok(())
+}
+
+/// The roles file given with `--config` or `--system`; unset means `~/.config/cyber-toolkit/roles.cnf`.
+static CONFIG_STORE: OnceLock<ConfigStore> = OnceLock::new();
+
+/// Set by `--system`: the system-wide roles file is the one changed, without a layer under it.
+static SYSTEM_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Returns the store of the roles file in use.
+fn config_store() -> Result<&'static ConfigStore, io::Error> {
+    if let Some(store) = CONFIG_STORE.get() {
+        return Ok(store);
+    }
+    let store = ConfigStore::open_default()?;
+    Ok(CONFIG_STORE.get_or_init(|| store))
+}
+
+/// Where questions are asked; set from `--yes` once the arguments are parsed.
+static USER_INTERFACE: OnceLock<Box<dyn UserInterface + Send + Sync>> = OnceLock::new();
 
-    // Ensure the .roles directory exists
-    if let Some(parent_dir) = config_file_path.parent() {
-        fs::create_dir_all(parent_dir)?;
+/// Returns the interface questions go through: answering them all with `--yes`, asking in the
+/// terminal if there is one, and leaving them unanswered otherwise.
+fn ui() -> &'static dyn UserInterface {
+    USER_INTERFACE
+        .get_or_init(|| {
+            if io::IsTerminal::is_terminal(&io::stdin()) {
+                Box::new(terminal_ui::TerminalUi::default())
+            } else {
+                Box::new(NonInteractive::default())
+            }
+        })
+        .as_ref()
+}
+
+/// Presents the progress of library operations on the terminal.
+struct ConsoleReporter;
+
+impl Reporter for ConsoleReporter {
+    fn fetching_role(&self, _role: &str, location: &str) {
+        info!("{}", t!("fetching-role", location = location));
+    }
+
+    fn role_fetched(&self, role: &Role) {
+        for warning in &role.warnings {
+            eprintln!("{}", t!("role-warning", warning = warning.as_str()));
+        }
+        if !role.skipped.is_empty() {
+            info!("{}", t!("role-skipped-entries", count = role.skipped.len(), location = role.location.as_str()));
+            for skipped in &role.skipped {
+                info!("{}", t!("role-skipped-entry", tool = skipped.tool.as_str(), reason = skipped.reason.as_str()));
+            }
+        }
+        if role.tools.is_empty() {
+            info!("{}", t!("role-no-tools", location = role.location.as_str()));
+        } else {
+            info!("{}", t!("role-tools", location = role.location.as_str(), tools = format!("{:?}", role.tools)));
+        }
     }
 
-    let mut file = fs::File::create(&config_file_path)?;
-    for role_name in roles {
-        writeln!(file, "{}", role_name)?;
+    fn role_failed(&self, role: &str, error: &str) {
+        eprintln!("{}", t!("role-failed", role = role, error = error));
+    }
+}
+
+/// Reads role files from the local cache only, for `--offline`.
+struct CacheFetcher;
+
+#[async_trait::async_trait]
+impl RoleFetcher for CacheFetcher {
+    async fn fetch(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        if let Some(content) = local_roles::read(name)? {
+            return Ok(content);
+        }
+        state::read_cached_role(name.trim())?
+            .ok_or_else(|| Box::from(t!("role-not-cached", role = name.trim())))
+    }
+
+    fn location(&self, name: &str) -> String {
+        match local_role_location(name) {
+            Some(location) => location,
+            None => t!("role-cache-location", role = name.trim()),
+        }
+    }
+}
+
+/// Reads role files from the repository, verified against its `MANIFEST.sha256` (see
+/// `checksums`), and from the cache while the repository rate-limits us.
+struct RepositoryFetcher;
+
+#[async_trait::async_trait]
+impl RoleFetcher for RepositoryFetcher {
+    async fn fetch(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        if let Some(content) = local_roles::read(name)? {
+            return Ok(content);
+        }
+        state::or_cached_when_rate_limited(checksums::repository_fetcher().fetch(name).await, name.trim())
+    }
+
+    fn location(&self, name: &str) -> String {
+        local_role_location(name).unwrap_or_else(|| checksums::repository_fetcher().location(name))
+    }
+}
+
+/// The path of the local role `name` (see `local_roles`), which both fetchers read first.
+fn local_role_location(name: &str) -> Option<String> {
+    let path = local_roles::path(name).ok().filter(|path| path.is_file())?;
+    Some(path.display().to_string())
+}
+
+/// Returns where role files are read from: the cache with `--offline`, the repository otherwise.
+fn role_fetcher() -> &'static dyn RoleFetcher {
+    if http::is_offline() {
+        &CacheFetcher
+    } else {
+        &RepositoryFetcher
     }
-    println!("Successfully wrote roles to {:?}", config_file_path);
-    Ok(())
 }
 
 /// Fetches tool lists for the given role file names from the `BASE_RAW_URL`.
-/// 
-/// For each role file:
-/// - Constructs the full URL.
-/// - Fetches the content.
-/// - Parses each line as a tool name, handling trailing commas and surrounding quotes.
-/// - Collects all unique tools from all specified role files.
-/// 
+///
+/// Fetching and parsing is done by `roles::fetch_roles`; this reports the progress, stores the
+/// raw content of each role in the local cache (`~/.cache/cyber-toolkit`) and records the fetches in the
+/// audit log. Roles that can't be fetched are skipped; with `--offline`, roles are read from the
+/// cache and a role missing there is an error. `group:` entries are expanded to the
+/// group's packages (see `backends::expand_groups`).
+///
 /// Returns a `Result` containing a deduplicated `Vec<String>` of tool names, or an error.
 async fn fetch_tools_for_role_files(role_files: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let mut collected_tools = Vec::new();
-    if role_files.is_empty() {
-        return Ok(collected_tools); // No roles, no tools
-    }
+    let (report, _) = fetch_role_report(role_files).await?;
+    Ok(backends::expand_groups(report.tools()))
+}
 
-    for role_file_name in role_files {
-        let trimmed_role_file_name = role_file_name.trim();
-        if trimmed_role_file_name.is_empty() {
-            continue; // Skip empty role file names
+/// Fetches role files like `fetch_tools_for_role_files`, returning the report and the failed
+/// roles that don't exist in the repository. For those, similar roles and subcommands are suggested.
+async fn fetch_role_report(role_files: &[String]) -> Result<(roles::FetchReport, Vec<String>), Box<dyn std::error::Error>> {
+    let report = roles::fetch_roles(role_files, role_fetcher(), &ConsoleReporter).await;
+    for role in &report.roles {
+        if let Err(e) = state::write_cached_role(&role.name, &role.content) {
+            eprintln!("{}", t!("role-cache-write-failed", role = role.name.as_str(), error = e.to_string()));
         }
-        let full_tool_list_url = format!("{}{}", BASE_RAW_URL, trimmed_role_file_name);
-        println!("Fetching tool list from {}...", full_tool_list_url);
+        audit::record("fetch", std::slice::from_ref(&role.name), &role.tools, true, None);
+    }
+    for failure in &report.failed {
+        audit::record("fetch", std::slice::from_ref(&failure.role), &[], false, Some(&failure.error));
+    }
+    let unknown = suggest_unknown_roles(&report.failed).await;
+    // Offline there's no later run to pick up the skipped roles, so stop instead of installing a
+    // partial set; roles that don't exist won't turn up later anyway
+    let missing: Vec<&str> =
+        report.failed.iter().map(|failure| failure.role.as_str()).filter(|role| !unknown.iter().any(|name| name == role)).collect();
+    if http::is_offline() && !missing.is_empty() {
+        return Err(Box::from(t!("offline-roles-missing", roles = format!("{:?}", missing))));
+    }
+    Ok((report, unknown))
+}
 
-        let response = reqwest::get(&full_tool_list_url).await;
-        match response {
-            Ok(res) => {
-                if !res.status().is_success() {
-                    eprintln!("Failed to fetch tool list from {}: HTTP Status {}. Skipping this file.", full_tool_list_url, res.status());
-                    continue; // Skip this role file on HTTP error
-                }
-                let tool_list_text = res.text().await?;
-                let tools_from_current_file: Vec<String> = tool_list_text
-                    .lines()
-                    .map(|line| {
-                        // Normalize tool names: trim whitespace, remove trailing commas, and strip surrounding quotes.
-                        let s = line.trim();
-                        let s_no_comma = s.trim_end_matches(',');
-                        let mut final_s = s_no_comma.trim(); // Trim again after comma removal
-                        if final_s.len() >= 2 {
-                            if (final_s.starts_with('"') && final_s.ends_with('"')) ||
-                               (final_s.starts_with('\'') && final_s.ends_with('\'')) {
-                                final_s = &final_s[1..final_s.len() - 1]; // Strip quotes
-                            }
-                        }
-                        final_s.to_string()
-                    })
-                    .filter(|s| !s.is_empty()) // Filter out empty lines after processing
-                    .collect();
-                
-                if tools_from_current_file.is_empty() {
-                    println!("No tools found in {}.", full_tool_list_url);
-                } else {
-                    println!("Found tools in {}: {:?}", full_tool_list_url, tools_from_current_file);
-                    collected_tools.extend(tools_from_current_file);
-                }
-            }
-            Err(e) => {
-                // Log error and continue with other role files if possible
-                eprintln!("Error fetching tool list from {}: {}. Skipping this file.", full_tool_list_url, e);
-                continue;
-            }
+/// Prints suggestions for the failed roles that the role index doesn't list, and returns them.
+/// Without the index (e.g. no network), nothing is known to be unknown.
+async fn suggest_unknown_roles(failed: &[roles::RoleFetchError]) -> Vec<String> {
+    if failed.is_empty() {
+        return Vec::new();
+    }
+    let Ok(available) = discovery::fetch_role_index().await else {
+        return Vec::new();
+    };
+    let subcommands: Vec<String> = Cli::command().get_subcommands().map(|sub| sub.get_name().to_string()).collect();
+    let mut unknown = Vec::new();
+    for failure in failed.iter().filter(|failure| !available.contains(&failure.role)) {
+        for line in suggest::unknown_role(&failure.role, &available, &subcommands) {
+            eprintln!("{}", line);
         }
+        unknown.push(failure.role.clone());
     }
+    unknown
+}
 
-    // Deduplicate the final list of tools
-    if !collected_tools.is_empty() {
-        collected_tools.sort_unstable();
-        collected_tools.dedup();
-    }
-    Ok(collected_tools)
+/// Result of a package manager run that didn't fail outright.
+#[derive(Debug, PartialEq)]
+enum PackageRun {
+    Completed,
+    /// Stopped by the `timeout_minutes` setting; the caller records it and carries on.
+    TimedOut,
 }
 
 /// Executes a pacman command (`-Syu` or `-Runs`) for the given list of tools.
 /// 
-/// Uses `pkexec` to run `sudo pacman`.
-/// Tools are quoted using `shlex` for safe shell execution.
-/// Pacman flags `--confirm --overwrite` are used as per user specification.
+/// Only this step runs with root privileges: pacman is started through `pkexec` as a short-lived
+/// child process (directly when the toolkit already runs as root), without a shell in between.
+/// Fetching, parsing and planning stay in the unprivileged parent. Pacman asks for confirmation
+/// (`--confirm`) on a terminal; without one, or with `--yes`, it runs with `--noconfirm` so GUI
+/// launchers don't hang on an invisible prompt. The targets always follow `--`.
+/// If an AUR helper is configured, it runs unprivileged in place of `pkexec pacman`.
+/// Download tuning from the `[pacman]` settings applies through a generated `--config` file, and
+/// `timeout_minutes` stops runs that hang (see `platform::with_timeout`). Everything pacman
+/// prints is also appended to the package manager log (see `package_log`).
 /// 
 /// # Arguments
-/// * `operation_flag`: Either "Syu" (for install/update) or "Rcns" (for remove - mapped to -Runs for pacman).
+/// * `operation_flag`: "Syu" (for install/update), "S" (install without refreshing or upgrading, with `--needed`), "R", "Runs" or "Rcns" (for remove, see `settings::RemovalStrategy`),
+///   "U" (install package files) or "D" (mark packages explicitly installed).
 /// * `tools`: A slice of tool names to process.
 /// 
-/// Returns `Ok(())` on success, or an error if the pacman command fails.
-async fn run_pacman_command(operation_flag: &str, tools: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+/// Returns whether the run completed or timed out, or an error if the pacman command fails.
+#[cfg(unix)]
+async fn run_pacman_command(operation_flag: &str, tools: &[String]) -> Result<PackageRun, Box<dyn std::error::Error>> {
     if tools.is_empty() {
-        println!("No tools specified for pacman {} operation.", operation_flag);
-        return Ok(());
+        println!("{}", t!("pacman-no-tools", operation = operation_flag));
+        return Ok(PackageRun::Completed);
     }
 
     // Map the logical operation_flag to the actual pacman argument string
     let pacman_op_arg = match operation_flag {
         "Syu" => "-Syu",
-        "Rcns" => "-Runs", // Maps to -Runs for pacman as per user's previous edit
-        _ => return Err(Box::from(format!("Unsupported pacman operation: {}", operation_flag))),
+        "S" => "-S",
+        "R" => "-R",
+        "Runs" => "-Runs",
+        "Rcns" => "-Rcns",
+        "U" => "-U", // Install package files or URLs (used by `install --locked`)
+        "D" => "-D", // Mark installed packages as explicitly installed (see `mark_explicit`)
+        _ => return Err(Box::from(t!("pacman-operation-unsupported", operation = operation_flag))),
     };
 
-    let mut quoted_tools: Vec<String> = Vec::with_capacity(tools.len());
-    for tool in tools {
-        match shlex::try_quote(tool) {
-            Ok(quoted_tool) => quoted_tools.push(quoted_tool.into_owned()),
-            Err(e) => {
-                eprintln!("Warning: Could not quote tool name '{}' due to error: {}. Skipping this tool.", tool, e);
-                // Optionally, we could add it to a list of skipped tools and report at the end.
+    // Without a terminal (e.g. launched from a GUI) nobody can answer pacman's prompts;
+    // the polkit dialog is the confirmation then. With --yes nobody is asked either.
+    let interactive = ui().is_interactive();
+    let confirm_flag = if interactive { "--confirm" } else { "--noconfirm" };
+    let mut args = vec![pacman_op_arg.to_string(), confirm_flag.to_string()];
+    if platform::is_plain() {
+        // -D isn't a transaction, so pacman rejects --noprogressbar for it
+        args.extend(platform::PLAIN_PACMAN_FLAGS.into_iter().filter(|flag| pacman_op_arg != "-D" || *flag != "--noprogressbar").map(String::from));
+    }
+    match pacman_op_arg {
+        "-S" => args.push("--needed".to_string()),
+        "-D" => args.push("--asexplicit".to_string()),
+        _ => {}
+    }
+    // Targets come from role files; after `--`, an entry like `--hookdir=...` can't become an option
+    args.push("--".to_string());
+    args.extend(tools.iter().cloned());
+    let pacman_settings = settings::load()?.pacman;
+    let timeout = pacman_settings.timeout_minutes;
+    let mut command = match &pacman_settings.aur_helper {
+        Some(helper) => {
+            let (program, args) = platform::with_timeout(helper, args, timeout, interactive);
+            let mut helper_command = std::process::Command::new(program);
+            helper_command.args(&args);
+            helper_command
+        }
+        None => {
+            if let Some(config) = pacman_config::write_tuned_config(&pacman_settings)? {
+                args.splice(1..1, ["--config".to_string(), config.to_string_lossy().into_owned()]);
             }
+            let (program, args) = platform::with_timeout(PACMAN_BINARY, args, timeout, interactive);
+            platform::elevated(&program, &args)
         }
-    }
+    };
 
-    if quoted_tools.is_empty() {
-        println!("No tools could be safely quoted for pacman {} operation.", operation_flag);
-        return Ok(());
+    println!("{}", t!("executing-command", command = format!("{:?}", command)));
+    if interactive {
+        println!("{}", t!("pacman-confirm-note"));
     }
 
-    let tools_string = quoted_tools.join(" ");
-    // Using --confirm --overwrite as per user's edit
-    let command_str = format!("sudo pacman {} --confirm --overwrite {}", pacman_op_arg, tools_string);
-    
-    println!("Attempting to execute: {}", command_str);
-    println!("Note: --confirm flag requires manual 'y/N' input for pacman operations.");
-
-    let status = Command::new("pkexec")
-        .arg("sh")
-        .arg("-c")
-        .arg(&command_str)
-        .status()?;
+    let mut log = package_log::PackageLog::start(pacman_op_arg, &format!("{:?}", command));
+    let (status, stderr) = platform::status_with_lines(&mut command, &mut |stream, line| log.line(stream, line))?;
+    log.finish(status.code());
 
     if status.success() {
-        println!("Pacman {} operation completed successfully for tools: {:?}", operation_flag, tools);
+        println!("{}", t!("pacman-succeeded", operation = operation_flag, tools = format!("{:?}", tools)));
+    } else if timeout.is_some() && platform::timed_out(status.code()) {
+        eprintln!("{}", t!("pacman-timed-out", operation = operation_flag, minutes = timeout.unwrap_or_default()));
+        state::append_history("timeout", tools)?;
+        return Ok(PackageRun::TimedOut);
     } else {
-        eprintln!("Pacman {} operation failed. Exit code: {:?}. Command: {}", operation_flag, status.code(), command_str);
-        return Err(Box::from(format!("Pacman {} operation failed for tools: {:?}", operation_flag, tools)));
+        eprintln!("{}", t!("pacman-failed", operation = operation_flag, code = format!("{:?}", status.code()), command = format!("{:?}", command)));
+        if let Some(reason) = platform::elevation_failure(status.code()) {
+            eprintln!("{}", reason);
+        }
+        let reason = format!("Pacman {} operation failed for tools: {:?}", operation_flag, tools);
+        return Err(Box::new(failures::CommandFailure::new(reason, &command, status.code(), stderr)));
     }
-    Ok(())
+    Ok(PackageRun::Completed)
 }
 
-/// Handles the logic for adding roles and syncing tools.
-/// 
-/// - Reads existing roles from `~/.roles/roles.cnf`.
-/// - Appends new roles provided in `roles_to_add_from_args`.
-/// - Deduplicates and writes the updated list back to the config file.
-/// - Fetches tools for *all* currently configured roles.
-/// - Installs/updates these tools using `pacman -Syu`.
-async fn handle_add_command(roles_to_add_from_args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
-    let mut current_roles = read_roles_from_config_file().unwrap_or_else(|e| {
-        eprintln!("Warning: Could not read existing roles config: {}. Starting with an empty list.", e);
-        Vec::new()
-    });
-
-    // Add new roles from arguments
-    for role_arg in roles_to_add_from_args {
-        let trimmed_role = role_arg.trim().to_string();
-        if !trimmed_role.is_empty() {
-            current_roles.push(trimmed_role);
-        }
+/// Installs or updates native packages: pacman on Linux, winget on Windows.
+///
+/// On Linux, the mirrorlist is refreshed first if the `[mirrors]` settings ask for it. Offline,
+/// pacman installs with `-S --needed` from the databases and packages it already has, since
+/// `-Syu` would refresh the databases over the network.
+async fn install_native_packages(tools: &[String]) -> Result<PackageRun, Box<dyn std::error::Error>> {
+    #[cfg(unix)]
+    {
+        mirrors::rank_if_needed(&settings::load()?.mirrors, tools);
+        let operation = if http::is_offline() { "S" } else { "Syu" };
+        run_pacman_command(operation, tools).await
     }
-    // Deduplicate and save updated roles list
-    current_roles.sort_unstable();
-    current_roles.dedup();
-    write_roles_to_config_file(&current_roles)?;
-
-    println!("\nFetching all tools for currently configured roles to ensure system is up to date...");
-    let all_tools_for_configured_roles = fetch_tools_for_role_files(&current_roles).await?;
-    
-    if !all_tools_for_configured_roles.is_empty() {
-        println!("\nTotal unique tools to install/update from all configured roles: {:?}", all_tools_for_configured_roles);
-        run_pacman_command("Syu", &all_tools_for_configured_roles).await?;
-    } else {
-        println!("No tools to install/update based on the current configuration.");
+    #[cfg(windows)]
+    {
+        windows::winget_install(tools).map(|_| PackageRun::Completed)
     }
-    Ok(())
 }
 
-/// Handles the logic for removing roles and their unique tools.
-/// 
-/// - Reads existing roles from `~/.roles/roles.cnf`.
-/// - Identifies roles to keep and roles to remove based on `roles_to_remove_from_args`.
-/// - Fetches tools for kept roles and for removed roles separately.
-/// - Determines tools unique to the removed roles (tools not present in any kept role).
-/// - Uninstalls these unique tools using `pacman -Runs`.
-/// - Writes the updated list of (kept) roles back to the config file.
-async fn handle_remove_command(roles_to_remove_from_args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
-    let configured_roles_before_removal = read_roles_from_config_file().unwrap_or_else(|e| {
-        eprintln!("Warning: Could not read existing roles config: {}. Assuming no roles were configured.", e);
-        Vec::new()
-    });
-
-    if configured_roles_before_removal.is_empty() {
-        println!("No roles currently configured. Nothing to remove.");
-        return Ok(());
+/// Marks native packages that were installed as dependencies as explicitly installed, once a
+/// role lists them, so pacman no longer reports them as orphans when the package that pulled them
+/// in goes. `--asexplicit` isn't passed to the install itself, where it would mark every package
+/// of the transaction, upgrades and new dependencies included. Failing only prints a warning.
+#[cfg(unix)]
+async fn mark_explicit(packages: &[String]) {
+    if packages.is_empty() {
+        return;
+    }
+    if let Err(e) = run_pacman_command("D", packages).await {
+        eprintln!("{}", t!("mark-explicit-failed", packages = format!("{:?}", packages), error = e.to_string()));
     }
+}
 
-    // Determine which roles to keep and which are actually being removed
-    let roles_to_remove_set: HashSet<_> = roles_to_remove_from_args.iter().map(|s| s.trim().to_string()).collect();
-    let roles_to_keep: Vec<String> = configured_roles_before_removal
-        .iter()
-        .filter(|r| !roles_to_remove_set.contains(*r))
-        .cloned()
-        .collect();
-    
-    let roles_actually_removed: Vec<String> = configured_roles_before_removal
-        .iter()
-        .filter(|r| roles_to_remove_set.contains(*r))
-        .cloned()
-        .collect();
+/// Removes native packages: pacman on Linux, with the pacman operation of `strategy`, and winget
+/// on Windows.
+async fn remove_native_packages(tools: &[String], strategy: settings::RemovalStrategy) -> Result<PackageRun, Box<dyn std::error::Error>> {
+    #[cfg(unix)]
+    {
+        run_pacman_command(strategy.operation(), tools).await
+    }
+    #[cfg(windows)]
+    {
+        let _ = strategy;
+        windows::winget_uninstall(tools).map(|_| PackageRun::Completed)
+    }
+}
 
-    if roles_actually_removed.is_empty() {
-        println!("None of the specified roles to remove were found in the current configuration.");
-        write_roles_to_config_file(&roles_to_keep)?; // Still write, to ensure config is clean
+/// Installs (`install = true`) or uninstalls chocolatey packages. They are skipped outside Windows.
+fn run_choco(packages: &[String], install: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if packages.is_empty() {
         return Ok(());
     }
+    #[cfg(windows)]
+    {
+        windows::choco(if install { "upgrade" } else { "uninstall" }, packages)
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = install;
+        println!("{}", t!("choco-skipped", packages = format!("{:?}", packages)));
+        Ok(())
+    }
+}
 
-    println!("Roles to keep: {:?}", roles_to_keep);
-    println!("Roles being removed: {:?}", roles_actually_removed);
-
-    // Fetch tools for kept roles and removed roles
-    let tools_for_kept_roles = fetch_tools_for_role_files(&roles_to_keep).await?;
-    let tools_for_removed_roles = fetch_tools_for_role_files(&roles_actually_removed).await?;
-
-    // Identify tools unique to the removed roles
-    let tools_for_kept_roles_set: HashSet<_> = tools_for_kept_roles.into_iter().collect();
-    let mut tools_to_uninstall = Vec::new();
+/// Counts the installed native and chocolatey packages among the given ones.
+fn count_installed_native(grouped: &backends::ToolsByBackend) -> Result<usize, Box<dyn std::error::Error>> {
+    #[cfg(unix)]
+    {
+        Ok(pacman::query_installed(&grouped.native)?.len())
+    }
+    #[cfg(windows)]
+    {
+        Ok(windows::winget_installed(&grouped.native)?.len() + windows::choco_installed(&grouped.choco)?.len())
+    }
+}
 
-    for tool in tools_for_removed_roles {
-        if !tools_for_kept_roles_set.contains(&tool) {
-            tools_to_uninstall.push(tool);
+/// Works out which native packages to pass to the package manager.
+///
+/// Packages that are installed and up to date are dropped, so reruns don't start a transaction for
+/// nothing; if the installed packages can't be queried, all are kept. On Linux, conflicts among the
+/// remaining packages or with installed ones are then resolved with the user (see `conflicts`).
+fn plan_native_install(tools: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    #[cfg(unix)]
+    {
+        let install_state = pacman::query_install_state().unwrap_or_else(|e| {
+            eprintln!("{}", t!("installed-query-failed", error = e.to_string()));
+            pacman::InstallState::default()
+        });
+        let (current, pending): (Vec<String>, Vec<String>) = tools.iter().cloned().partition(|tool| install_state.is_current(tool));
+        if !current.is_empty() {
+            println!("{}", t!("native-up-to-date", count = current.len()));
+        }
+        match conflicts::find(&pending, &install_state.installed) {
+            Ok(found) => conflicts::resolve(pending, &found),
+            Err(e) => {
+                eprintln!("{}", t!("conflicts-check-failed", error = e.to_string()));
+                Ok(pending)
+            }
         }
     }
-
-    // Uninstall unique tools
-    if !tools_to_uninstall.is_empty() {
-        println!("\nTools to uninstall (unique to removed roles): {:?}", tools_to_uninstall);
-        run_pacman_command("Rcns", &tools_to_uninstall).await?;
-    } else {
-        println!("No tools to uninstall. Either removed roles had no unique tools or no tools at all.");
+    #[cfg(windows)]
+    {
+        Ok(tools.to_vec())
     }
+}
 
-    // Update the configuration file with the kept roles
-    write_roles_to_config_file(&roles_to_keep)?;
-    println!("Configuration updated. Roles {:?} removed.", roles_actually_removed);
-    Ok(())
+/// Whether all `tools` are installed and native packages are up to date, so an add/sync has
+/// nothing to install. Entries the run would skip anyway (native packages in user mode,
+/// chocolatey packages outside Windows) don't count. If anything can't be checked, the tools
+/// don't count as up to date, so the caller falls back to a full run.
+fn tools_up_to_date(tools: &[String], user_mode: bool) -> bool {
+    let checked: Vec<String> = tools
+        .iter()
+        .filter(|tool| !(user_mode && (backends::is_native_entry(tool) || tool.starts_with(backends::CHOCO_PREFIX))))
+        .filter(|tool| cfg!(windows) || !tool.starts_with(backends::CHOCO_PREFIX))
+        .cloned()
+        .collect();
+    let Ok(installed) = installed_entries(&checked) else {
+        return false;
+    };
+    if checked.iter().any(|tool| !installed.contains(tool)) {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        let Ok(install_state) = pacman::query_install_state() else {
+            return false;
+        };
+        checked.iter().filter(|tool| backends::is_native_entry(tool)).all(|tool| install_state.is_current(tool))
+    }
+    #[cfg(windows)]
+    {
+        true
+    }
 }
 
-/// Main entry point of the application.
-/// 
-/// Parses command-line arguments and dispatches to either `handle_add_command`
-/// or `handle_remove_command` based on the presence of the `--remove` flag.
+/// Works out which native packages to uninstall with `strategy`, and previews the impact of the
+/// removal.
+///
+/// Packages that aren't installed are dropped. Unless the strategy is `cascade`, packages that
+/// installed packages outside the removal still depend on (including ones the toolkit doesn't
+/// manage) are kept, so removing a role never cascades into other software. The other packages
+/// pacman will remove along with them (dependencies, and with `cascade` the packages depending on
+/// them) are listed.
+///
+/// Returns the packages to uninstall and the other packages pacman removes with them.
+fn plan_native_removal(tools: &[String], strategy: settings::RemovalStrategy) -> Result<(Vec<String>, Vec<String>), Box<dyn std::error::Error>> {
+    #[cfg(unix)]
+    {
+        let installed = pacman::query_installed(tools)?;
+        let mut removal: Vec<String> = tools.iter().filter(|tool| installed.contains_key(*tool)).cloned().collect();
+        // Packages others depend on stay, unless the removal cascades into those others
+        if strategy != settings::RemovalStrategy::Cascade {
+            // Keeping one package can make its own dependencies needed again, so repeat until stable
+            loop {
+                let needed: Vec<(String, Vec<String>)> = removal
+                    .iter()
+                    .filter_map(|tool| {
+                        let dependents: Vec<String> =
+                            installed[tool].required_by.iter().filter(|dependent| !removal.contains(dependent)).cloned().collect();
+                        (!dependents.is_empty()).then(|| (tool.clone(), dependents))
+                    })
+                    .collect();
+                if needed.is_empty() {
+                    break;
+                }
+                for (tool, dependents) in needed {
+                    eprintln!("{}", t!("removal-keeping-required", tool = tool.as_str(), dependents = dependents.join(", ")));
+                    removal.retain(|candidate| *candidate != tool);
+                }
+            }
+        }
+        let dependencies: Vec<String> = match pacman::preview_removal(&removal, strategy.operation()) {
+            Ok(preview) => preview.into_iter().filter(|package| !removal.contains(package)).collect(),
+            Err(e) => {
+                eprintln!("{}", t!("removal-preview-failed", error = e.to_string()));
+                Vec::new()
+            }
+        };
+        if !dependencies.is_empty() {
+            if strategy == settings::RemovalStrategy::Cascade {
+                println!("{}", t!("removal-also-dependents", count = dependencies.len(), packages = format!("{:?}", dependencies)));
+            } else {
+                println!("{}", t!("removal-also-dependencies", count = dependencies.len(), packages = format!("{:?}", dependencies)));
+            }
+        }
+        Ok((removal, dependencies))
+    }
+    #[cfg(windows)]
+    {
+        let _ = strategy;
+        Ok((tools.to_vec(), Vec::new()))
+    }
+}
+
+/// Installs the grouped tools with their backends and returns the ones that failed.
+///
+/// Native packages that are installed and up to date are skipped, and conflicts between native
+/// packages are resolved before anything is installed (see `plan_native_install`).
+/// A failing backend doesn't stop the others. pacman installs all native packages in one
+/// transaction; if it fails over some of the packages, smaller transactions isolate them (see
+/// `isolate_failing_natives`), and if it times out, all of them count as failed. In user mode,
+/// native and chocolatey packages are left out.
+async fn install_tools(
+    grouped: &backends::ToolsByBackend,
+    user_mode: bool,
+) -> Result<Vec<failures::FailedTool>, Box<dyn std::error::Error>> {
+    let mut failed = Vec::new();
+    let fail_all = |entries: Vec<String>, backend: &str, error: &(dyn std::error::Error + 'static)| {
+        entries.into_iter().map(|tool| failures::FailedTool::from_error(tool, backend, error)).collect::<Vec<_>>()
+    };
+
+    if !user_mode {
+        let native = plan_native_install(&grouped.native)?;
+        if !native.is_empty() {
+            match install_native_packages(&native).await {
+                Ok(PackageRun::Completed) => {}
+                Ok(PackageRun::TimedOut) => failed.extend(timed_out_natives(native)),
+                #[cfg(unix)]
+                Err(e) if native.len() > 1 && is_package_error(&*e) => failed.extend(isolate_failing_natives(native, &*e).await),
+                Err(e) => failed.extend(fail_all(native, "native", &*e)),
+            }
+        }
+        if let Err(e) = run_choco(&grouped.choco, true) {
+            let entries = grouped.choco.iter().map(|package| format!("{}{}", backends::CHOCO_PREFIX, package)).collect();
+            failed.extend(fail_all(entries, "choco", &*e));
+        }
+    }
+    if !grouped.isolated.is_empty() {
+        match isolated::install(&grouped.isolated) {
+            Ok(failed_tools) => failed.extend(failed_tools),
+            Err(e) => failed.extend(fail_all(grouped.isolated.iter().map(|tool| tool.entry()).collect(), "isolated", &*e)),
+        }
+    }
+    if !grouped.go.is_empty() {
+        match go::install(&grouped.go) {
+            Ok(failed_tools) => failed.extend(failed_tools),
+            Err(e) => failed.extend(fail_all(grouped.go.iter().map(|tool| tool.entry()).collect(), "go", &*e)),
+        }
+    }
+
+    for failure in &failed {
+        eprintln!("{}", t!("tool-failed", tool = failure.tool.as_str(), reason = failure.reason.as_str()));
+    }
+    audit_installs(grouped, user_mode, &failed);
+    Ok(failed)
+}
+
+fn timed_out_natives(tools: Vec<String>) -> impl Iterator<Item = failures::FailedTool> {
+    tools.into_iter().map(|tool| failures::FailedTool::new(tool, "native", t!("native-timed-out")))
+}
+
+/// Whether a failed pacman run failed because of the packages (pacman reported an `error:`), as
+/// opposed to a dismissed authentication dialog or an answered-no prompt, which retrying smaller
+/// transactions wouldn't change.
+#[cfg(unix)]
+fn is_package_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.downcast_ref::<failures::CommandFailure>().is_some_and(|failure| {
+        platform::elevation_failure(failure.exit_code).is_none() && failure.stderr.lines().any(|line| line.starts_with("error:"))
+    })
+}
+
+/// Returns the packages among `tools` that pacman's error output blames: `error: target not
+/// found: x`, and under `error: failed to prepare transaction` or `error: failed to commit
+/// transaction`, the package of each listed problem (`x: /usr/bin/y exists in filesystem`,
+/// `:: x and y are in conflict`, `:: unable to satisfy dependency 'z' required by x`). Other
+/// words in the output, such as a path or a dependency, don't count.
+#[cfg(unix)]
+fn named_packages(stderr: &str, tools: &[String]) -> Vec<String> {
+    let mut named = Vec::new();
+    let mut in_problems = false;
+    for line in stderr.lines() {
+        if let Some(target) = line.strip_prefix("error: target not found: ") {
+            named.push(target.trim());
+        } else if line.starts_with("error: failed to prepare transaction") || line.starts_with("error: failed to commit transaction") {
+            in_problems = true;
+        } else if line.starts_with("error: ") {
+            in_problems = false;
+        } else if in_problems {
+            named.extend(problem_packages(line));
+        }
+    }
+    tools.iter().filter(|tool| named.iter().any(|name| is_package(name, tool))).cloned().collect()
+}
+
+/// The packages a problem line under pacman's transaction error is about.
+#[cfg(unix)]
+fn problem_packages(line: &str) -> Vec<&str> {
+    if let Some(problem) = line.strip_prefix(":: ") {
+        if let Some((_, package)) = problem.rsplit_once(" required by ") {
+            return vec![package.trim()];
+        }
+        if let Some((first, rest)) = problem.split_once(" and ") {
+            if let Some((second, _)) = rest.split_once(" are in conflict") {
+                return vec![first, second];
+            }
+        }
+        return Vec::new();
+    }
+    match line.split_once(": ") {
+        Some((package, problem)) if problem.contains(" exists in ") || problem.contains("conflicts with") => vec![package],
+        _ => Vec::new(),
+    }
+}
+
+/// Whether `name` from pacman's output is the package `tool`, possibly with its version
+/// (`x-1.0-1`), as older pacman versions print conflicts.
+#[cfg(unix)]
+fn is_package(name: &str, tool: &str) -> bool {
+    name == tool
+        || name
+            .strip_prefix(tool)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// pacman's error message in a failure's stderr, without the `error: ` prefix.
+#[cfg(unix)]
+fn pacman_error(failure: &failures::CommandFailure) -> Option<String> {
+    failure.stderr.lines().rev().find_map(|line| line.strip_prefix("error: ")).map(str::to_string)
+}
+
+/// Installs native packages after the transaction for all of them failed with `error`, and
+/// reports the packages that caused it.
+///
+/// pacman installs everything in one transaction, so a single bad package (a missing target, a
+/// file conflict, a broken signature) fails all of them. The retries run `pacman -S --needed`,
+/// without refreshing the databases or upgrading the system again, so a failing upgrade doesn't
+/// fail every retry (see `isolate_failures`).
+#[cfg(unix)]
+async fn isolate_failing_natives(tools: Vec<String>, error: &(dyn std::error::Error + 'static)) -> Vec<failures::FailedTool> {
+    isolate_failures(tools, error, |chunk| async move { run_pacman_command("S", &chunk).await }).await
+}
+
+/// Finds the packages among `tools` that make their transaction fail, installing the others
+/// with `install`.
+///
+/// If pacman's error names some of the packages, they are set aside and the others installed in
+/// one go. Whatever still fails is split in halves, also when the error names none of its
+/// packages (e.g. a corrupted package file), and each half retried the same way, so `k` bad
+/// packages among `n` are found in about `2k·log2(n)` transactions instead of `n`
+/// single-package runs, and the good packages are installed along the way. The packages that
+/// fail on their own are the root cause. A timeout, or a failure that isn't about the packages,
+/// stops the retries: everything not installed by then counts as failed.
+#[cfg(unix)]
+async fn isolate_failures<F, Fut>(tools: Vec<String>, error: &(dyn std::error::Error + 'static), install: F) -> Vec<failures::FailedTool>
+where
+    F: Fn(Vec<String>) -> Fut,
+    Fut: std::future::Future<Output = Result<PackageRun, Box<dyn std::error::Error>>>,
+{
+    let named_in = |error: &(dyn std::error::Error + 'static), chunk: &[String]| {
+        error.downcast_ref::<failures::CommandFailure>().map(|failure| named_packages(&failure.stderr, chunk)).unwrap_or_default()
+    };
+    // The named packages on their own and the others, or halves if that doesn't split the chunk
+    let split = |mut chunk: Vec<String>, named: Vec<String>| {
+        if !named.is_empty() && named.len() < chunk.len() {
+            let others: Vec<String> = chunk.iter().filter(|tool| !named.contains(tool)).cloned().collect();
+            return vec![named, others];
+        }
+        let second = chunk.split_off(chunk.len() / 2);
+        vec![second, chunk]
+    };
+    let named = named_in(error, &tools);
+    // Chunks are taken from the end, depth-first, so each culprit is pinned down before moving on
+    let mut chunks = if !named.is_empty() && named.len() < tools.len() {
+        println!("{}", t!("retry-without-named", named = format!("{:?}", named), count = tools.len() - named.len()));
+        split(tools, named)
+    } else {
+        println!("{}", t!("retry-without-upgrade", count = tools.len()));
+        vec![tools]
+    };
+
+    let mut failed = Vec::new();
+    let mut culprits = Vec::new();
+    while let Some(chunk) = chunks.pop() {
+        match install(chunk.clone()).await {
+            Ok(PackageRun::Completed) => {}
+            Ok(PackageRun::TimedOut) => {
+                failed.extend(timed_out_natives(chunks.drain(..).flatten().chain(chunk).collect()));
+            }
+            Err(e) if is_package_error(&*e) => {
+                if let [tool] = chunk.as_slice() {
+                    let mut failure = failures::FailedTool::from_error(tool.clone(), "native", &*e);
+                    if let Some(message) = e.downcast_ref::<failures::CommandFailure>().and_then(pacman_error) {
+                        failure.reason = t!("transaction-culprit-reason", error = message);
+                    }
+                    culprits.push(failure);
+                } else {
+                    let named = named_in(&*e, &chunk);
+                    chunks.extend(split(chunk, named));
+                }
+            }
+            Err(e) => {
+                let remaining: Vec<String> = chunks.drain(..).flatten().chain(chunk).collect();
+                failed.extend(remaining.into_iter().map(|tool| failures::FailedTool::from_error(tool, "native", &*e)));
+            }
+        }
+    }
+    if !culprits.is_empty() {
+        println!("{}", t!("transaction-culprits", count = culprits.len()));
+        for culprit in &culprits {
+            println!("    {}: {}", culprit.tool, culprit.reason);
+        }
+    }
+    culprits.extend(failed);
+    culprits
+}
+
+/// Records the outcome of `install_tools` in the audit log, one event per backend and failure.
+fn audit_installs(grouped: &backends::ToolsByBackend, user_mode: bool, failed: &[failures::FailedTool]) {
+    let mut attempted = vec![
+        ("isolated", grouped.isolated.iter().map(|tool| tool.entry()).collect::<Vec<_>>()),
+        ("go", grouped.go.iter().map(|tool| tool.entry()).collect()),
+    ];
+    if !user_mode {
+        attempted.push(("native", grouped.native.clone()));
+        attempted.push(("choco", grouped.choco.iter().map(|package| format!("{}{}", backends::CHOCO_PREFIX, package)).collect()));
+    }
+    for (backend, entries) in attempted {
+        let (failures, succeeded): (Vec<String>, Vec<String>) =
+            entries.into_iter().partition(|entry| failed.iter().any(|failure| &failure.tool == entry));
+        audit::record_packages("install", backend, &succeeded, true, None);
+        for failure in failed.iter().filter(|failure| failures.contains(&failure.tool)) {
+            audit::record_packages("install", backend, std::slice::from_ref(&failure.tool), false, Some(&failure.reason));
+        }
+    }
+}
+
+/// Handles the `plugin` subcommand: runs the plugin `name` even if a role shadows it. Only
+/// returns if there is no such plugin or it couldn't be executed.
+fn handle_plugin_command(name: &str, args: &[String]) -> Box<dyn std::error::Error> {
+    match plugins::find_plugin(name) {
+        Some(plugin) => plugins::exec_plugin(&plugin, args),
+        None => Box::from(t!("plugin-not-found", name = name)),
+    }
+}
+
+/// Handles the `retry-failed` subcommand: reattempts only the tools that failed in the last run
+/// and records the ones that still fail.
+async fn handle_retry_failed_command(user_mode: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let previous = failures::read()?;
+    if previous.is_empty() {
+        println!("{}", t!("retry-failed-none"));
+        return Ok(());
+    }
+    let tools: Vec<String> = previous.iter().map(|failure| failure.tool.clone()).collect();
+    println!("{}", t!("retry-failed-retrying", count = tools.len(), tools = format!("{:?}", tools)));
+    let still_failed = install_tools(&backends::split_by_backend(&tools), user_mode).await?;
+    failures::write(&still_failed)?;
+    if !still_failed.is_empty() {
+        return Err(Box::from(t!("retry-failed-still-failing", failed = still_failed.len(), count = tools.len())));
+    }
+    println!("{}", t!("retry-failed-succeeded", count = tools.len()));
+    Ok(())
+}
+
+/// Handles the logic for adding roles and syncing tools.
+/// 
+/// - Reads existing roles from `~/.config/cyber-toolkit/roles.cnf` and adds the new roles provided in `roles_to_add_from_args`.
+/// - Fetches tools for *all* of these roles and checks frozen roles against the lockfile.
+/// - Stops early with "already up to date" if no role is new and every tool is installed and current.
+/// - Installs/updates these tools using `pacman -Syu`, sets up `isolated:` tools as containers and builds `go:` tools.
+/// - Only if every tool was installed, writes the updated roles list to the config file and
+///   records refrozen roles in the lockfile. After a failure both stay as they were, so the
+///   configuration never lists roles whose tools aren't there.
+/// - Groups each role's GUI tools into a desktop submenu (see `desktop`).
+/// - Deploys the configuration files carried by the roles (see `dotfiles`).
+/// - Installs the roles' pip requirements into per-role virtualenvs (see `venv`).
+/// - Runs the roles' data refresh hooks if `run_on_sync` is enabled in the `[hooks]` settings.
+/// - Records the sync time and appends the operation to the history.
+async fn handle_add_command(roles_to_add_from_args: &[String], user_mode: bool, refreeze: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let configured_roles = read_roles_from_config_file().unwrap_or_else(|e| {
+        eprintln!("{}", t!("roles-config-unreadable", error = e.to_string()));
+        Vec::new()
+    });
+
+    // Add new roles from arguments
+    let mut current_roles = configured_roles.clone();
+    current_roles.extend(roles::canonical_names(roles_to_add_from_args));
+    current_roles.sort_unstable();
+    current_roles.dedup();
+    sync_roles(&configured_roles, current_roles, roles_to_add_from_args, user_mode, refreeze).await
+}
+
+/// Installs the tools of `current_roles` and makes them the configured roles, which were
+/// `configured_roles` until now: the steps of `handle_add_command` after deciding on the roles.
+/// Roles that are dropped by this keep their tools (`handle_update_command` removes them).
+/// `roles_from_args` is what the history records.
+async fn sync_roles(
+    configured_roles: &[String],
+    current_roles: Vec<String>,
+    roles_from_args: &[String],
+    user_mode: bool,
+    refreeze: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let new_roles: Vec<String> = current_roles.iter().filter(|role| !configured_roles.contains(role)).cloned().collect();
+    let dropped_roles = configured_roles.iter().any(|role| !current_roles.contains(role));
+
+    println!("{}", t!("add-fetching"));
+    let (report, unknown_roles) = fetch_role_report(&current_roles).await?;
+    // Configured roles may have been dropped upstream, but new ones must exist
+    let unknown_new: Vec<&String> = new_roles.iter().filter(|role| unknown_roles.contains(role)).collect();
+    if !unknown_new.is_empty() {
+        return Err(Box::from(t!("add-unknown-roles", roles = format!("{:?}", unknown_new))));
+    }
+    let all_tools_for_configured_roles = backends::expand_groups(report.tools());
+    audit::record("resolve", &current_roles, &all_tools_for_configured_roles, true, None);
+    let changed_roles = lock::verify(&current_roles)?;
+    if !changed_roles.is_empty() && !refreeze {
+        let frozen: Vec<_> = changed_roles.iter().map(|(role, _)| role).collect();
+        return Err(Box::from(t!("add-frozen-changed", roles = format!("{:?}", frozen))));
+    }
+
+    // Rerunning an add for configured roles whose tools are all in place changes nothing
+    let unchanged = new_roles.is_empty() && !dropped_roles && changed_roles.is_empty() && !all_tools_for_configured_roles.is_empty();
+    if unchanged && tools_up_to_date(&all_tools_for_configured_roles, user_mode) {
+        println!("{}", t!("add-up-to-date", count = all_tools_for_configured_roles.len()));
+        failures::write(&[])?;
+        state::record_sync()?;
+        return Ok(());
+    }
+
+    let mut failed_tools = Vec::new();
+    if !all_tools_for_configured_roles.is_empty() {
+        println!("{}", t!("add-total-tools", tools = format!("{:?}", all_tools_for_configured_roles)));
+        let grouped = backends::split_by_backend(&all_tools_for_configured_roles);
+        if user_mode {
+            let skipped: Vec<String> = grouped.native.iter().chain(&grouped.choco).cloned().collect();
+            if !skipped.is_empty() {
+                println!("{}", t!("add-user-mode-skipped", count = skipped.len(), packages = format!("{:?}", skipped)));
+            }
+            state::write_skipped_native(&skipped)?;
+        } else {
+            state::write_skipped_native(&[])?;
+        }
+        // What was installed before, by the user or as a dependency, isn't the toolkit's
+        #[cfg(unix)]
+        let preinstalled = if user_mode { HashMap::new() } else { ownership::preinstalled(&pacman::query_installed(&grouped.native)?) };
+        #[cfg(windows)]
+        let preinstalled = HashMap::new();
+        failed_tools = install_tools(&grouped, user_mode).await?;
+        // User mode leaves system-wide packages alone, so they aren't this user's installs
+        let installed: Vec<String> = all_tools_for_configured_roles
+            .iter()
+            .filter(|tool| !failed_tools.iter().any(|failed| failed.tool == **tool))
+            .filter(|tool| !user_mode || backends::scope(tool) == lock::Scope::User)
+            .cloned()
+            .collect();
+        #[cfg(unix)]
+        {
+            let dependencies: Vec<String> =
+                installed.iter().filter(|tool| preinstalled.get(*tool) == Some(&lock::Origin::Dependency)).cloned().collect();
+            mark_explicit(&dependencies).await;
+        }
+        lock::record_installs(&installed, audit::user(), &preinstalled)?;
+    } else {
+        println!("{}", t!("add-no-tools"));
+    }
+
+    // Commit the configuration only once the packages are in place
+    failures::write(&failed_tools)?;
+    let committed_roles = if failed_tools.is_empty() {
+        write_roles_to_config_file(&current_roles)?;
+        lock::refreeze(&changed_roles)?;
+        current_roles
+    } else {
+        configured_roles.to_vec()
+    };
+
+    match desktop::update_menus(&committed_roles) {
+        Ok(count) if count > 0 => println!("{}", t!("menus-updated", count = count)),
+        Ok(_) => {}
+        Err(e) => eprintln!("{}", t!("menus-update-failed", error = e.to_string())),
+    }
+
+    let roles_metadata = metadata::fetch_all(&committed_roles).await;
+    for (role, role_metadata) in &roles_metadata {
+        match dotfiles::deploy_role(role, &role_metadata.dotfiles).await {
+            Ok(report) if !report.conflicts.is_empty() => {
+                eprintln!("{}", t!("dotfiles-conflicts", count = report.conflicts.len(), role = role.as_str(), files = format!("{:?}", report.conflicts)));
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("{}", t!("dotfiles-deploy-failed", role = role.as_str(), error = e.to_string())),
+        }
+        if !role_metadata.python.requirements.is_empty() {
+            match venv::sync_role(role, &role_metadata.python) {
+                Ok(exposed) if !exposed.is_empty() => println!("{}", t!("python-tools-available", role = role.as_str(), tools = format!("{:?}", exposed))),
+                Ok(_) => {}
+                Err(e) => eprintln!("{}", t!("venv-setup-failed", role = role.as_str(), error = e.to_string())),
+            }
+        }
+    }
+
+    if settings::load()?.hooks.run_on_sync {
+        println!("{}", t!("running-hooks"));
+        let mut hooks_to_run = hooks::collect_hooks(&roles_metadata);
+        if user_mode {
+            hooks_to_run.retain(|hook| !hook.root);
+        }
+        hooks::run_hooks(&hooks_to_run)?;
+    }
+
+    if !failed_tools.is_empty() {
+        if !new_roles.is_empty() {
+            return Err(Box::from(t!("add-failed-new-roles", count = failed_tools.len(), roles = format!("{:?}", new_roles))));
+        }
+        return Err(Box::from(t!("add-failed", count = failed_tools.len())));
+    }
+    state::record_sync()?;
+    state::append_history("add", roles_from_args)?;
+    Ok(())
+}
+
+/// Handles `--update`: makes `role_files` the configured roles.
+///
+/// Configured roles that aren't listed are shown prominently and removed (with their unique
+/// tools) only after a confirmation, which `--yes` gives; without an answer nothing changes. With
+/// `keep_existing`, nothing is removed and this is a plain add.
+///
+/// The change is planned once, as a net diff: the tools of the listed roles are installed, and
+/// only the tools of the removed roles that no listed role needs are removed, so shared tools are
+/// neither removed nor reinstalled. pacman can't install and remove in one transaction, so each
+/// side runs exactly once, installs first (see `sync_roles`), and removals only once they
+/// succeeded (see `remove_role_tools`). `roles.cnf` only changes if the install succeeded, and
+/// is rolled back to the roles from before the update if the removal fails.
+async fn handle_update_command(
+    role_files: &[String],
+    user_mode: bool,
+    refreeze: bool,
+    force: bool,
+    unpin: bool,
+    keep_existing: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let roles_list = format!("{:?}", role_files);
+    if keep_existing {
+        println!("{}", t!("executing-update-keep", roles = roles_list));
+    } else {
+        println!("{}", t!("executing-update", roles = roles_list));
+        let configured = read_roles_from_config_file()?;
+        // The system-wide roles stay whatever is listed
+        let system = system_roles()?;
+        let to_remove: Vec<String> =
+            configured.into_iter().filter(|role| !role_files.contains(role) && !system.contains(role)).collect();
+        if !to_remove.is_empty() {
+            let pinned_removed = pins::check_removal(&to_remove, unpin)?;
+            println!("{}", t!("update-removals", count = to_remove.len()));
+            for role in &to_remove {
+                println!("{}", t!("update-removal", role = role.as_str()));
+            }
+            match ui().confirm(&t!("update-confirm"), false)? {
+                Some(true) => {}
+                Some(false) => {
+                    println!("{}", t!("update-cancelled"));
+                    return Ok(());
+                }
+                None => return Err(Box::from(t!("update-unconfirmed", roles = format!("{:?}", to_remove)))),
+            }
+            return update_roles(role_files, &to_remove, &pinned_removed, user_mode, refreeze, force).await;
+        }
+    }
+    handle_add_command(role_files, user_mode, refreeze).await?;
+    expiry::clear(role_files)?;
+    Ok(())
+}
+
+/// Replaces the configured roles `to_remove` with `role_files` (see `handle_update_command`),
+/// unpinning `pinned_removed`, the pinned roles among them, once they are gone.
+async fn update_roles(
+    role_files: &[String],
+    to_remove: &[String],
+    pinned_removed: &[String],
+    user_mode: bool,
+    refreeze: bool,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let configured = read_roles_from_config_file()?;
+    let mut target: Vec<String> = configured.iter().filter(|role| !to_remove.contains(role)).cloned().collect();
+    target.extend(roles::canonical_names(role_files));
+    target.sort_unstable();
+    target.dedup();
+
+    let tools_for_target: HashSet<String> = fetch_tools_for_role_files(&target).await?.into_iter().collect();
+    let tools_to_uninstall: Vec<String> =
+        fetch_tools_for_role_files(to_remove).await?.into_iter().filter(|tool| !tools_for_target.contains(tool)).collect();
+
+    sync_roles(&configured, target, role_files, user_mode, refreeze).await?;
+    // The listed roles are in place and configured now; if the removal fails, the file goes back
+    // to the roles from before, since the removed ones still have their tools
+    if let Err(e) = remove_role_tools(to_remove, &tools_to_uninstall, user_mode, force, false).await {
+        write_roles_to_config_file(&configured)?;
+        eprintln!("{}", t!("update-rolled-back", roles = format!("{:?}", to_remove)));
+        return Err(e);
+    }
+    expiry::clear(role_files)?;
+    expiry::clear(to_remove)?;
+    pins::unpin(pinned_removed)?;
+    state::append_history("remove", to_remove)?;
+    println!("{}", t!("remove-done", roles = format!("{:?}", to_remove)));
+    Ok(())
+}
+
+/// Handles the logic for removing roles and their unique tools.
+/// 
+/// - Reads existing roles from `~/.config/cyber-toolkit/roles.cnf`.
+/// - Identifies roles to keep and roles to remove based on `roles_to_remove_from_args`.
+/// - Fetches tools for kept roles and for removed roles separately.
+/// - Determines tools unique to the removed roles (tools not present in any kept role, or in
+///   `roles_being_added` when the removal is followed by an add).
+/// - Keeps tools that other installed packages depend on, unless the removal strategy is `cascade` (see `plan_native_removal`).
+/// - Refuses to remove pinned roles unless `unpin` is set, in which case they are unpinned (see `pins`).
+/// - Asks for confirmation of large removals and protected packages unless `force` is set (see `removal_guard`).
+/// - Uninstalls these unique tools using pacman with the configured removal strategy, `pacman -Runs` by default (or the `isolated:` and `go:` backends) and deletes the removed roles' desktop submenus, firejail confinement, unmodified dotfiles and virtualenvs.
+/// - With `purge`, deletes the data the role metadata lists for the uninstalled tools (see `purge`).
+/// - Writes the updated list of (kept) roles back to the config file and appends the operation to the history.
+///   If uninstalling fails or times out, the roles stay configured and nothing else is cleaned up.
+async fn handle_remove_command(
+    roles_to_remove_from_args: &[String],
+    roles_being_added: &[String],
+    user_mode: bool,
+    force: bool,
+    purge: bool,
+    unpin: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let configured_roles_before_removal = read_roles_from_config_file().unwrap_or_else(|e| {
+        eprintln!("{}", t!("remove-config-unreadable", error = e.to_string()));
+        Vec::new()
+    });
+
+    if configured_roles_before_removal.is_empty() {
+        println!("{}", t!("remove-nothing-configured"));
+        return Ok(());
+    }
+
+    // Determine which roles to keep and which are actually being removed
+    let roles_to_remove_set: HashSet<_> = roles::canonical_names(roles_to_remove_from_args).into_iter().collect();
+    let roles_to_keep: Vec<String> = configured_roles_before_removal
+        .iter()
+        .filter(|r| !roles_to_remove_set.contains(*r))
+        .cloned()
+        .collect();
+    
+    let roles_actually_removed: Vec<String> = configured_roles_before_removal
+        .iter()
+        .filter(|r| roles_to_remove_set.contains(*r))
+        .cloned()
+        .collect();
+
+    if roles_actually_removed.is_empty() {
+        println!("{}", t!("remove-not-configured"));
+        for line in suggest::not_configured(roles_to_remove_from_args, &configured_roles_before_removal) {
+            println!("{}", line);
+        }
+        write_roles_to_config_file(&roles_to_keep)?; // Still write, to ensure config is clean
+        return Ok(());
+    }
+
+    let system = system_roles()?;
+    let mandated: Vec<&String> = roles_actually_removed.iter().filter(|role| system.contains(role)).collect();
+    if !mandated.is_empty() {
+        return Err(Box::from(t!(
+            "remove-system-roles",
+            roles = format!("{:?}", mandated),
+            path = format!("{:?}", config::system_config_path())
+        )));
+    }
+    let pinned_removed = pins::check_removal(&roles_actually_removed, unpin)?;
+
+    println!("{}", t!("remove-keeping", roles = format!("{:?}", roles_to_keep)));
+    println!("{}", t!("remove-removing", roles = format!("{:?}", roles_actually_removed)));
+
+    // Fetch tools for kept roles and removed roles. Roles about to be added count as kept, so
+    // tools they share with the removed roles aren't uninstalled only to be reinstalled.
+    let roles_needing_tools: Vec<String> = roles_to_keep.iter().chain(roles_being_added).cloned().collect();
+    let tools_for_kept_roles = fetch_tools_for_role_files(&roles_needing_tools).await?;
+    let tools_for_removed_roles = fetch_tools_for_role_files(&roles_actually_removed).await?;
+
+    // Identify tools unique to the removed roles
+    let tools_for_kept_roles_set: HashSet<_> = tools_for_kept_roles.into_iter().collect();
+    let mut tools_to_uninstall = Vec::new();
+
+    for tool in tools_for_removed_roles {
+        if !tools_for_kept_roles_set.contains(&tool) {
+            tools_to_uninstall.push(tool);
+        }
+    }
+
+    // The configuration keeps the roles until their tools are really gone
+    remove_role_tools(&roles_actually_removed, &tools_to_uninstall, user_mode, force, purge).await?;
+
+    // Update the configuration file with the kept roles
+    write_roles_to_config_file(&roles_to_keep)?;
+    expiry::clear(&roles_actually_removed)?;
+    pins::unpin(&pinned_removed)?;
+    state::append_history("remove", &roles_actually_removed)?;
+    println!("{}", t!("remove-done", roles = format!("{:?}", roles_actually_removed)));
+    Ok(())
+}
+
+/// Uninstalls `tools_to_uninstall`, the tools only the removed `roles` needed, and deletes what
+/// else was set up for those roles: desktop submenus, firejail confinement, unmodified dotfiles
+/// and virtualenvs, and with `purge` the data their metadata lists. Fails without cleaning up if
+/// uninstalling fails or times out.
+async fn remove_role_tools(roles: &[String], tools_to_uninstall: &[String], user_mode: bool, force: bool, purge: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let (uninstalled_tools, removal_timed_out) = uninstall_tools(tools_to_uninstall, roles, user_mode, force).await?;
+    if removal_timed_out {
+        return Err(Box::from(t!("remove-timed-out", roles = format!("{:?}", roles))));
+    }
+
+    if purge {
+        let roles_metadata = metadata::fetch_all(roles).await;
+        purge::run(&purge::collect(&uninstalled_tools, &roles_metadata), force)?;
+    }
+
+    if let Err(e) = desktop::remove_menus(roles) {
+        eprintln!("{}", t!("menus-remove-failed", error = e.to_string()));
+    }
+    for role in roles {
+        if let Err(e) = harden::unharden_role(role) {
+            eprintln!("{}", t!("firejail-remove-failed", role = role.as_str(), error = e.to_string()));
+        }
+        if let Err(e) = dotfiles::remove_role(role) {
+            eprintln!("{}", t!("dotfiles-remove-failed", role = role.as_str(), error = e.to_string()));
+        }
+        if let Err(e) = venv::remove_role(role) {
+            eprintln!("{}", t!("venv-remove-failed", role = role.as_str(), error = e.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Uninstalls `tools_to_uninstall` with their backends, after the `removal_guard` check.
+///
+/// Native packages that other installed packages depend on are kept (see `plan_native_removal`),
+/// and so are native and chocolatey packages in user mode, tools another user installed and,
+/// unless `--remove-user-installed` is given, packages the user had installed themselves (see
+/// `ownership::user_installed`). Native packages are removed with the strategy configured for
+/// `roles`, the roles the tools are removed with (see `settings::RemovalSettings::strategy_for`).
+/// Returns the tools that were really uninstalled, and whether pacman timed out.
+async fn uninstall_tools(
+    tools_to_uninstall: &[String],
+    roles: &[String],
+    user_mode: bool,
+    force: bool,
+) -> Result<(Vec<String>, bool), Box<dyn std::error::Error>> {
+    // Tools another user installed are theirs: in their home, or system-wide for their roles
+    let installs = lock::read()?.unwrap_or_default().installs;
+    let (others, tools_to_uninstall): (Vec<String>, Vec<String>) = tools_to_uninstall
+        .iter()
+        .cloned()
+        .partition(|tool| installs.get(tool).is_some_and(|record| record.user != audit::user()));
+    for tool in &others {
+        println!("{}", t!("remove-other-user", tool = tool.as_str(), user = installs[tool].user.as_str()));
+    }
+    // Tools the user had installed before a role listed them stay, without the role's record
+    #[cfg(unix)]
+    let packages = pacman::query_installed(&backends::split_by_backend(&tools_to_uninstall).native)?;
+    #[cfg(windows)]
+    let packages: HashMap<String, pacman::InstalledPackage> = HashMap::new();
+    let first_installs = audit::first_installs(&tools_to_uninstall)?;
+    let (user_installed, tools_to_uninstall): (Vec<String>, Vec<String>) = tools_to_uninstall.into_iter().partition(|tool| {
+        !ownership::removes_user_installed()
+            && ownership::user_installed(packages.get(tool), installs.get(tool), first_installs.get(tool).map(String::as_str))
+    });
+    for tool in &user_installed {
+        println!("{}", t!("remove-user-installed", tool = tool.as_str()));
+    }
+    if !user_installed.is_empty() {
+        println!("{}", t!("remove-user-installed-hint", count = user_installed.len()));
+    }
+    let tools_to_uninstall = tools_to_uninstall.as_slice();
+
+    let mut removal_timed_out = false;
+    let mut uninstalled_tools = Vec::new();
+    if !tools_to_uninstall.is_empty() {
+        println!("{}", t!("remove-tools", tools = format!("{:?}", tools_to_uninstall)));
+        let grouped = backends::split_by_backend(tools_to_uninstall);
+        let removal_settings = settings::load()?.removal;
+        let strategy = removal_settings.strategy_for(roles);
+        if strategy != settings::RemovalStrategy::default() && !user_mode && !grouped.native.is_empty() {
+            println!("{}", t!("removal-strategy", strategy = strategy.name(), operation = strategy.operation()));
+        }
+        let (native, native_dependencies) = if user_mode { (Vec::new(), Vec::new()) } else { plan_native_removal(&grouped.native, strategy)? };
+        let mut uninstall_set: Vec<String> = native.iter().chain(&native_dependencies).cloned().collect();
+        if !user_mode {
+            uninstall_set.extend(grouped.choco.iter().map(|package| format!("{}{}", backends::CHOCO_PREFIX, package)));
+        }
+        uninstall_set.extend(grouped.isolated.iter().map(|tool| tool.entry()));
+        uninstall_set.extend(grouped.go.iter().map(|tool| tool.entry()));
+        removal_guard::check(&uninstall_set, &removal_settings, force)?;
+        if user_mode {
+            if !grouped.native.is_empty() || !grouped.choco.is_empty() {
+                println!("{}", t!("remove-user-mode-native"));
+            }
+        } else {
+            if !native.is_empty() {
+                let native_run = remove_native_packages(&native, strategy).await;
+                let removed = matches!(native_run, Ok(PackageRun::Completed));
+                audit::record_packages("remove", "native", &native, removed, None);
+                if native_run? == PackageRun::TimedOut {
+                    removal_timed_out = true;
+                }
+            }
+            let choco_run = run_choco(&grouped.choco, false);
+            audit::record_packages("remove", "choco", &grouped.choco, choco_run.is_ok(), None);
+            choco_run?;
+        }
+        let isolated_run = isolated::remove(&grouped.isolated);
+        let isolated_entries: Vec<String> = grouped.isolated.iter().map(|tool| tool.entry()).collect();
+        audit::record_packages("remove", "isolated", &isolated_entries, isolated_run.is_ok(), None);
+        isolated_run?;
+        let go_run = go::remove(&grouped.go);
+        let go_entries: Vec<String> = grouped.go.iter().map(|tool| tool.entry()).collect();
+        audit::record_packages("remove", "go", &go_entries, go_run.is_ok(), None);
+        go_run?;
+        // Native packages that were kept (user mode, other packages depend on them) weren't uninstalled
+        uninstalled_tools = tools_to_uninstall
+            .iter()
+            .filter(|tool| if backends::is_native_entry(tool) { native.contains(tool) } else { !user_mode || !tool.starts_with(backends::CHOCO_PREFIX) })
+            .cloned()
+            .collect();
+    } else {
+        println!("{}", t!("remove-no-tools"));
+    }
+    lock::forget_installs(&[uninstalled_tools.as_slice(), user_installed.as_slice()].concat())?;
+    Ok((uninstalled_tools, removal_timed_out))
+}
+
+/// Handles the `du` subcommand: attributes installed disk usage to each configured role.
+/// 
+/// - Fetches the tool list of every configured role separately.
+/// - Queries installed sizes for all of those tools with `pacman -Qi`.
+/// - Charges tools unique to a role fully to that role, and splits shared tools evenly
+///   between all roles that list them, so the per-role totals add up to the real usage.
+/// - Prints roles sorted by total size, largest first.
+async fn handle_du_command() -> Result<(), Box<dyn std::error::Error>> {
+    let configured_roles = read_roles_from_config_file()?;
+    if configured_roles.is_empty() {
+        println!("{}", t!("no-roles-configured"));
+        return Ok(());
+    }
+
+    // Fetch each role on its own so tools can be attributed to the roles that list them
+    let mut tools_per_role: Vec<(String, Vec<String>)> = Vec::with_capacity(configured_roles.len());
+    for role in &configured_roles {
+        let mut tools = fetch_tools_for_role_files(std::slice::from_ref(role)).await?;
+        tools.retain(|tool| backends::is_native_entry(tool)); // Only pacman packages have an installed size
+        tools_per_role.push((role.clone(), tools));
+    }
+
+    // Count how many roles share each tool
+    let mut role_count_per_tool: HashMap<&str, u64> = HashMap::new();
+    for (_, tools) in &tools_per_role {
+        for tool in tools {
+            *role_count_per_tool.entry(tool.as_str()).or_insert(0) += 1;
+        }
+    }
+
+    let all_tools: Vec<String> = role_count_per_tool.keys().map(|t| t.to_string()).collect();
+    let installed = pacman::query_installed(&all_tools)?;
+
+    struct RoleUsage {
+        role: String,
+        exclusive: u64,
+        shared: u64,
+        installed: usize,
+        missing: usize,
+    }
+
+    let mut usage: Vec<RoleUsage> = tools_per_role
+        .iter()
+        .map(|(role, tools)| {
+            let mut entry = RoleUsage { role: role.clone(), exclusive: 0, shared: 0, installed: 0, missing: 0 };
+            for tool in tools {
+                match installed.get(tool) {
+                    Some(pkg) => {
+                        entry.installed += 1;
+                        let sharing_roles = role_count_per_tool[tool.as_str()];
+                        if sharing_roles > 1 {
+                            entry.shared += pkg.installed_size / sharing_roles;
+                        } else {
+                            entry.exclusive += pkg.installed_size;
+                        }
+                    }
+                    None => entry.missing += 1,
+                }
+            }
+            entry
+        })
+        .collect();
+    usage.sort_by_key(|entry| std::cmp::Reverse(entry.exclusive + entry.shared));
+
+    println!("{}", t!("disk-usage-title"));
+    println!(
+        "{:<20} {:>12} {:>12} {:>12} {:>10} {:>8}",
+        t!("column-role"),
+        t!("column-total"),
+        t!("column-exclusive"),
+        t!("column-shared"),
+        t!("column-installed"),
+        t!("column-missing")
+    );
+    let mut grand_total = 0;
+    for entry in &usage {
+        let total = entry.exclusive + entry.shared;
+        grand_total += total;
+        println!(
+            "{:<20} {:>12} {:>12} {:>12} {:>10} {:>8}",
+            entry.role,
+            pacman::format_size(total),
+            pacman::format_size(entry.exclusive),
+            pacman::format_size(entry.shared),
+            entry.installed,
+            entry.missing
+        );
+    }
+    println!("{}", t!("disk-usage-total", size = pacman::format_size(grand_total)));
+    println!("{}", t!("disk-usage-note"));
+    Ok(())
+}
+
+/// Handles the `report` subcommand: writes the inventory of installed tools of the configured
+/// roles to `output`, or to stdout (keeping progress messages off it).
+///
+/// - Versions and install dates of native packages come from `pacman -Qi`.
+/// - Containers are reported with their image, Go tools with their module version.
+/// - Install dates of other tools come from the install records in the lockfile.
+async fn handle_report_command(format: inventory::Format, output: Option<std::path::PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    if output.is_none() {
+        JSON_OUTPUT.store(true, Ordering::Relaxed);
+    }
+    let configured_roles = read_roles_from_config_file()?;
+    let mut roles_per_tool: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for role in &configured_roles {
+        for tool in fetch_tools_for_role_files(std::slice::from_ref(role)).await? {
+            roles_per_tool.entry(tool).or_default().push(role.clone());
+        }
+    }
+    let all_tools: Vec<String> = roles_per_tool.keys().cloned().collect();
+    let installed = installed_entries(&all_tools)?;
+    #[cfg(unix)]
+    let packages = pacman::query_installed(&backends::split_by_backend(&all_tools).native)?;
+    #[cfg(windows)]
+    let packages: HashMap<String, pacman::InstalledPackage> = HashMap::new();
+    let installs = lock::read()?.unwrap_or_default().installs;
+    let go_lock = go::read_lock()?;
+
+    let mut rows = Vec::new();
+    for (entry, roles) in roles_per_tool.into_iter().filter(|(entry, _)| installed.contains(entry)) {
+        let recorded = installs.get(&entry).and_then(|record| inventory::date_of(&record.installed_at));
+        let (tool, version, source, installed) = if let Some(spec) = entry.strip_prefix(isolated::PREFIX) {
+            let isolated_tool = isolated::parse_spec(spec)?;
+            (isolated_tool.name, String::new(), format!("container {}", isolated_tool.image), recorded)
+        } else if let Some(spec) = entry.strip_prefix(go::PREFIX) {
+            let go_tool = go::parse_spec(spec)?;
+            let version = go_lock.get(&go_tool.binary).and_then(|locked| locked.version.clone()).unwrap_or(go_tool.version);
+            (go_tool.binary, version, format!("go {}", go_tool.package), recorded)
+        } else if let Some(package) = entry.strip_prefix(backends::CHOCO_PREFIX) {
+            (package.to_string(), String::new(), "chocolatey".to_string(), recorded)
+        } else {
+            let package = packages.get(&entry);
+            let version = package.map(|package| package.version.clone()).unwrap_or_default();
+            let installed = package.and_then(|package| package.install_date.as_deref()).and_then(inventory::date_of).or(recorded);
+            (entry, version, if cfg!(windows) { "winget" } else { "pacman" }.to_string(), installed)
+        };
+        rows.push(inventory::Row { tool, version, source, roles, installed });
+    }
+    rows.sort_by(|a, b| a.tool.cmp(&b.tool));
+
+    let generated = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let content = inventory::render(&rows, format, audit::hostname(), &generated);
+    match output {
+        Some(path) => {
+            std::fs::write(&path, content)?;
+            println!("{}", t!("inventory-written", count = rows.len(), path = format!("{:?}", path)));
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// Handles the `learn` subcommand: shows every tool of `role` with its package description and
+/// the explanation from the role's `[learn]` metadata, optionally only those matching `search`.
+///
+/// Descriptions of installed packages come from `pacman -Qi`, of the others from `pacman -Si`.
+async fn handle_learn_command(role: &str, search: Option<&str>, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let tools = fetch_tools_for_role_files(&[role.to_string()]).await?;
+    let mut role_metadata = metadata::fetch_role_metadata(role).await.unwrap_or_else(|e| {
+        eprintln!("{}", t!("role-metadata-unavailable", role = role, error = e.to_string()));
+        metadata::RoleMetadata::default()
+    });
+    let installed = installed_entries(&tools)?;
+    #[cfg(unix)]
+    let descriptions: HashMap<String, String> = {
+        let native = backends::split_by_backend(&tools).native;
+        let mut descriptions: HashMap<String, String> =
+            pacman::query_installed(&native)?.into_iter().map(|(name, package)| (name, package.description)).collect();
+        let not_installed: Vec<String> = native.into_iter().filter(|tool| !descriptions.contains_key(tool)).collect();
+        descriptions.extend(pacman::query_sync_packages(&not_installed)?.into_iter().map(|(name, package)| (name, package.description)));
+        descriptions
+    };
+    #[cfg(windows)]
+    let descriptions: HashMap<String, String> = HashMap::new();
+
+    let lessons: Vec<learn::Lesson> = tools
+        .iter()
+        .map(|tool| learn::Lesson {
+            tool: tool.clone(),
+            installed: installed.contains(tool),
+            description: descriptions.get(tool).cloned().unwrap_or_default(),
+            explanation: role_metadata.learn.remove(tool),
+        })
+        .filter(|lesson| search.is_none_or(|term| lesson.matches(term)))
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&lessons)?);
+        return Ok(());
+    }
+    if lessons.is_empty() {
+        match search {
+            Some(term) => println!("{}", t!("learn-no-match", role = role, term = term)),
+            None => println!("{}", t!("learn-no-tools", role = role)),
+        }
+        return Ok(());
+    }
+    learn::page(&learn::render(role, &lessons))
+}
+
+/// An installed tool with its licenses, as reported by `licenses`.
+#[derive(Serialize, Debug)]
+struct LicensedTool {
+    tool: String,
+    roles: Vec<String>,
+    licenses: Vec<String>,
+    vendor: Option<String>,
+    class: licenses::LicenseClass,
+}
+
+/// Handles the `migrate-roles` subcommand: migrates the files of a repository checkout (or with
+/// `check`, lists those that need it) and fails if any can't be migrated.
+fn handle_migrate_roles_command(repository: &std::path::Path, check: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = migrate::run(repository, check)?;
+    for migration in &outcome.migrations {
+        let path = migration.path.strip_prefix(repository).unwrap_or(&migration.path).display().to_string();
+        match (check, migration.from == migration.to) {
+            (true, _) => println!("{}", t!("migrate-needed", path = path, format = migration.to)),
+            (false, true) => println!("{}", t!("migrate-declared", path = path, format = migration.to)),
+            (false, false) => println!("{}", t!("migrate-migrated", path = path, from = migration.from, to = migration.to)),
+        }
+    }
+    for (path, error) in &outcome.errors {
+        eprintln!("{}", t!("migrate-error", path = path.strip_prefix(repository).unwrap_or(path).display().to_string(), error = error.as_str()));
+    }
+    if !outcome.errors.is_empty() {
+        return Err(Box::from(t!("migrate-failed", count = outcome.errors.len())));
+    }
+    if outcome.migrations.is_empty() {
+        println!("{}", t!("migrate-up-to-date"));
+    } else if check {
+        return Err(Box::from(t!("migrate-check-failed", count = outcome.migrations.len())));
+    } else {
+        println!("{}", t!("migrate-done", count = outcome.migrations.len()));
+    }
+    Ok(())
+}
+
+/// Handles the `licenses` subcommand: reports the license of each installed tool of the
+/// configured roles, for organizations that must track the use of commercial tools.
+///
+/// - Fetches each configured role separately, so every tool lists the roles that install it.
+/// - Takes licenses and the vendor (from the project URL) of native packages from `pacman -Qi`.
+/// - Lets the `[licenses]` section of role metadata override both, which is the only source for
+///   containers, Go tools and wrappers of commercial tools whose package says `custom`.
+/// - Prints commercial tools first, then those to review, then the open source ones.
+async fn handle_licenses_command(flagged_only: bool, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let configured_roles = read_roles_from_config_file()?;
+    if configured_roles.is_empty() {
+        println!("{}", t!("no-roles-configured"));
+        return Ok(());
+    }
+
+    let mut roles_per_tool: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for role in &configured_roles {
+        for tool in fetch_tools_for_role_files(std::slice::from_ref(role)).await? {
+            roles_per_tool.entry(tool).or_default().push(role.clone());
+        }
+    }
+    let all_tools: Vec<String> = roles_per_tool.keys().cloned().collect();
+    let installed = installed_entries(&all_tools)?;
+    #[cfg(unix)]
+    let packages = pacman::query_installed(&backends::split_by_backend(&all_tools).native)?;
+    #[cfg(windows)]
+    let packages: HashMap<String, pacman::InstalledPackage> = HashMap::new();
+
+    // The first role declaring a tool's license wins
+    let mut declared: HashMap<String, metadata::LicenseMetadata> = HashMap::new();
+    for (_, role_metadata) in metadata::fetch_all(&configured_roles).await {
+        for (tool, license) in role_metadata.licenses {
+            declared.entry(tool).or_insert(license);
+        }
+    }
+
+    let mut tools: Vec<LicensedTool> = roles_per_tool
+        .into_iter()
+        .filter(|(tool, _)| installed.contains(tool))
+        .map(|(tool, roles)| {
+            let package = packages.get(&tool);
+            let declared = declared.get(&tool);
+            let licenses = match declared.and_then(|declared| declared.license.clone()) {
+                Some(license) => vec![license],
+                None => package.map(|package| package.licenses.clone()).unwrap_or_default(),
+            };
+            let vendor = declared
+                .and_then(|declared| declared.vendor.clone())
+                .or_else(|| package.and_then(|package| package.url.as_deref()).and_then(licenses::vendor_from_url));
+            let class = licenses::classify(&licenses);
+            LicensedTool { tool, roles, licenses, vendor, class }
+        })
+        .filter(|tool| !flagged_only || tool.class != licenses::LicenseClass::OpenSource)
+        .collect();
+    tools.sort_by(|a, b| b.class.cmp(&a.class).then_with(|| a.tool.cmp(&b.tool)));
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tools)?);
+        return Ok(());
+    }
+    if tools.is_empty() {
+        println!("{}", if flagged_only { t!("licenses-none-flagged") } else { t!("licenses-none-installed") });
+        return Ok(());
+    }
+    println!("  {:<28} {:<32} {:<28} {}", t!("column-tool"), t!("column-license"), t!("column-vendor"), t!("column-roles"));
+    for tool in &tools {
+        let licenses = if tool.licenses.is_empty() { t!("licenses-unknown") } else { tool.licenses.join(", ") };
+        println!(
+            "{} {:<28} {:<32} {:<28} {}",
+            tool.class.marker(),
+            tool.tool,
+            licenses,
+            tool.vendor.as_deref().unwrap_or("-"),
+            tool.roles.join(", ")
+        );
+    }
+    let count = |class| tools.iter().filter(|tool| tool.class == class).count();
+    println!(
+        "{}",
+        t!(
+            "licenses-summary",
+            commercial = count(licenses::LicenseClass::Commercial),
+            review = count(licenses::LicenseClass::Review),
+            open_source = count(licenses::LicenseClass::OpenSource)
+        )
+    );
+    println!("{}", t!("licenses-hint"));
+    Ok(())
+}
+
+/// Handles the `--list-all` flag: lists the roles available in the repository.
+/// 
+/// - Uses the `role_names` index by default.
+/// - With `discover`, lists the repository's `roles/` directory through the GitHub contents API,
+///   falling back to the index if the API request fails (e.g. when rate limited).
+/// - Marks roles that are currently configured in `~/.config/cyber-toolkit/roles.cnf`.
+/// - With a `filter`, keeps only the fully installed configured roles, or all others.
+/// - Shows the approximate installed size and one-line description from each role's metadata, if any.
+async fn handle_list_all_command(discover: bool, filter: ListFilter, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut available_roles = if discover {
+        match discovery::discover_roles_via_api().await {
+            Ok(roles) => roles,
+            Err(e) => {
+                eprintln!("{}", t!("discovery-api-failed", error = e.to_string()));
+                discovery::fetch_role_index().await?
+            }
+        }
+    } else {
+        discovery::fetch_role_index().await?
+    };
+
+    if filter != ListFilter::All {
+        let fully_installed: HashSet<String> =
+            current_state().await?.roles.into_iter().filter(|role| role.missing.is_empty()).map(|role| role.role).collect();
+        available_roles.retain(|role| fully_installed.contains(role) == (filter == ListFilter::InstalledOnly));
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&available_roles)?);
+        return Ok(());
+    }
+
+    let configured_roles: HashSet<String> = read_roles_from_config_file().unwrap_or_default().into_iter().collect();
+    println!("{}", t!("roles-available", count = configured_roles.len()));
+    for role in &available_roles {
+        let marker = if configured_roles.contains(role) { "*" } else { " " };
+        // Size and description are only hints, so roles without (reachable) metadata are listed plainly
+        let role_metadata = metadata::fetch_role_metadata(role).await.unwrap_or_default();
+        let size = role_metadata.installed_size_mb.map(|size| format!("~{}", pacman::format_size(size.saturating_mul(1024 * 1024)))).unwrap_or_default();
+        let description = role_metadata.description.unwrap_or_default();
+        println!(" {} {:<24} {:>12}  {}", marker, role, size, description.trim());
+    }
+    Ok(())
+}
+
+/// Handles `fleet apply`: adds roles on every host in the hosts file, staged as `rollout` says,
+/// and summarizes the results.
+/// 
+/// Returns an error if any host failed, after all hosts have been attempted or the rollout was
+/// aborted.
+fn handle_fleet_apply_command(
+    hosts_file: &std::path::Path,
+    role_files: &[String],
+    copy_binary: bool,
+    rollout: &fleet::Rollout,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let hosts = fleet::load_hosts(hosts_file)?;
+    if hosts.is_empty() {
+        println!("{}", t!("fleet-no-hosts", path = format!("{:?}", hosts_file)));
+        return Ok(());
+    }
+
+    let results = fleet::apply(&hosts, role_files, copy_binary, rollout)?;
+
+    println!("{}", t!("fleet-results-title"));
+    println!("{:<20} {:<8} {:>8}  {}", t!("column-host"), t!("column-status"), t!("column-time"), t!("column-detail"));
+    for result in &results {
+        let status = match result.status {
+            fleet::HostStatus::Succeeded => t!("status-ok"),
+            fleet::HostStatus::Failed => t!("status-failed"),
+            fleet::HostStatus::Skipped => t!("status-skipped"),
+        };
+        let host = if result.canary { t!("fleet-canary-host", host = result.host.as_str()) } else { result.host.clone() };
+        println!("{:<20} {:<8} {:>7}s  {}", host, status, result.duration.as_secs(), result.detail);
+    }
+
+    let failed = results.iter().filter(|r| r.status == fleet::HostStatus::Failed).count();
+    let skipped = results.iter().filter(|r| r.status == fleet::HostStatus::Skipped).count();
+    if skipped > 0 {
+        return Err(Box::from(t!("fleet-rollout-aborted", failed = failed, count = results.len(), skipped = skipped)));
+    }
+    if failed > 0 {
+        return Err(Box::from(t!("fleet-hosts-failed", failed = failed, count = results.len())));
+    }
+    println!("{}", t!("fleet-succeeded", count = results.len()));
+    Ok(())
+}
+
+/// Handles the `check-updates` subcommand: prints the upstream changes per configured role
+/// (or JSON with `--json`) and optionally sends a desktop notification.
+async fn handle_check_updates_command(notify: bool, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let roles = read_roles_from_config_file()?;
+    let updates = updates::check(&roles).await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&updates)?);
+    } else if !updates.iter().any(|update| update.has_changes()) {
+        println!("{}", t!("updates-none", count = roles.len()));
+    } else {
+        for update in updates.iter().filter(|update| update.has_changes()) {
+            if update.not_cached {
+                println!("{}", t!("updates-never-synced", role = update.role.as_str()));
+            } else {
+                println!("{}:", update.role);
+            }
+            for tool in &update.added {
+                println!("  + {}", tool);
+            }
+            for tool in &update.removed {
+                println!("  - {}", tool);
+            }
+        }
+        println!("{}", t!("updates-apply-hint", roles = roles.join(" ")));
+    }
+
+    if notify {
+        if let Err(e) = updates::notify(&updates) {
+            eprintln!("{}", t!("updates-notification-failed", error = e.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Handles the `freeze` subcommand: fetches each configured role and records its hash, along
+/// with the versions of the roles' installed native packages.
+async fn handle_freeze_command() -> Result<(), Box<dyn std::error::Error>> {
+    let roles = read_roles_from_config_file()?;
+    let mut definitions = Vec::with_capacity(roles.len());
+    let mut tools = Vec::new();
+    for role in &roles {
+        let content = updates::fetch_role_content(role).await?;
+        tools.extend(role_file::parse_tool_list(&content, role).tools);
+        definitions.push((role.clone(), content));
+    }
+    tools.sort_unstable();
+    tools.dedup();
+
+    let mut packages = BTreeMap::new();
+    if cfg!(unix) {
+        for (name, package) in pacman::query_installed(&backends::split_by_backend(&tools).native)? {
+            packages.insert(name, lock::LockedPackage { version: package.version, architecture: package.architecture });
+        }
+    }
+
+    let lock = lock::freeze(&definitions, packages)?;
+    for (role, locked) in &lock.roles {
+        println!("{:<20} {}", role, locked.sha256);
+    }
+    println!("{}", t!("freeze-done", roles = lock.roles.len(), packages = lock.packages.len()));
+    Ok(())
+}
+
+/// Handles `install --locked`: installs exactly the package versions recorded in `roles.lock`.
+///
+/// Packages already installed at the locked version are left alone. The others are installed
+/// with `pacman -U` from the Arch Linux Archive, so this works months after the versions left
+/// the repositories. Packages that never were in the official repositories (e.g. from the AUR)
+/// can't be reproduced this way and make the command fail.
+#[cfg(unix)]
+async fn handle_install_locked_command() -> Result<(), Box<dyn std::error::Error>> {
+    let lock = lock::read()?.ok_or_else(|| t!("lock-not-found"))?;
+    if lock.packages.is_empty() {
+        println!("{}", t!("lock-no-packages"));
+        return Ok(());
+    }
+    let names: Vec<String> = lock.packages.keys().cloned().collect();
+    let installed = pacman::query_installed(&names)?;
+
+    let urls: Vec<String> = lock
+        .packages
+        .iter()
+        .filter(|(name, locked)| installed.get(*name).map(|package| &package.version) != Some(&locked.version))
+        .map(|(name, locked)| pacman::archive_url(name, &locked.version, &locked.architecture))
+        .collect();
+    if urls.is_empty() {
+        println!("{}", t!("lock-installed", count = names.len()));
+        return Ok(());
+    }
+    println!("{}", t!("lock-installing", count = urls.len(), total = names.len(), frozen_at = lock.frozen_at));
+    match run_pacman_command("U", &urls).await? {
+        PackageRun::Completed => Ok(()),
+        PackageRun::TimedOut => Err(Box::from(t!("lock-install-timed-out"))),
+    }
+}
+
+/// Handles the `changelog` subcommand: prints the role's changelog, or JSON with `--json`.
+async fn handle_changelog_command(role_file: &str, limit: usize, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let changelog = changelog::fetch_changelog(role_file, limit).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&changelog)?);
+        return Ok(());
+    }
+    match changelog {
+        changelog::Changelog::File { content } => print!("{}", content),
+        changelog::Changelog::History { entries } => {
+            for entry in entries {
+                println!("{}  {}  {}", entry.date, entry.commit, entry.message);
+                for tool in &entry.added {
+                    println!("  + {}", tool);
+                }
+                for tool in &entry.removed {
+                    println!("  - {}", tool);
+                }
+                if entry.added.is_empty() && entry.removed.is_empty() {
+                    println!("{}", t!("changelog-no-tool-changes"));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handles `preset list`: prints each preset with its description and roles, or JSON with `--json`.
+async fn handle_preset_list_command(json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let presets = presets::fetch_presets().await?;
+    if json {
+        let value: BTreeMap<&String, serde_json::Value> = presets
+            .iter()
+            .map(|(name, preset)| (name, serde_json::json!({ "description": preset.description, "roles": preset.roles })))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+    for (name, preset) in &presets {
+        println!("{:<15} {}", name, preset.description);
+        println!("{:<15} {}", "", t!("preset-roles", roles = preset.roles.join(", ")));
+    }
+    Ok(())
+}
+
+/// Handles the `engage` subcommand: resolves the machine tags of each target to roles, shows
+/// which tag brought in which roles, and adds those roles like `cyber-toolkit <role>...`.
+async fn handle_engage_command(targets: &[String], user_mode: bool, refreeze: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let engagements = targets.iter().map(|target| engage::parse(target)).collect::<Result<Vec<_>, _>>()?;
+    let tag_map = engage::fetch_tag_map().await?;
+    let per_tag = engage::resolve(&tag_map, &engagements).await?;
+
+    for engagement in &engagements {
+        println!("{}", t!("engage-preparing", platform = engagement.platform_name, tags = engagement.tags.join(", ")));
+    }
+    let mut roles = Vec::new();
+    for (tag, tag_roles) in &per_tag {
+        println!("    {:<28} -> {}", tag, tag_roles.join(", "));
+        roles.extend(tag_roles.iter().cloned());
+    }
+    handle_add_command(&aliases::expand_configured(&roles)?, user_mode, refreeze).await
+}
+
+/// Handles the `refresh-data` subcommand: runs the data refresh hooks of the given roles
+/// (or of all configured roles) and prints when each hook last ran. In user mode, hooks that need
+/// root are skipped.
+async fn handle_refresh_data_command(role_files: &[String], user_mode: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let roles = if role_files.is_empty() { read_roles_from_config_file()? } else { role_files.to_vec() };
+    let mut role_hooks = hooks::collect_hooks(&metadata::fetch_all(&roles).await);
+    if user_mode {
+        role_hooks.retain(|hook| !hook.root);
+    }
+    if role_hooks.is_empty() {
+        println!("{}", t!("hooks-none-declared", roles = format!("{:?}", roles)));
+        return Ok(());
+    }
+
+    let results = hooks::run_hooks(&role_hooks)?;
+
+    println!("{}", t!("hooks-history-title"));
+    for (command, run) in hooks::read_runs()? {
+        println!("  {:<20} {:<8} {}  ({})", run.tool, if run.success { t!("status-ok") } else { t!("status-failed") }, run.last_run, command);
+    }
+    if results.iter().any(|run| !run.success) {
+        return Err(Box::from(t!("hooks-failed")));
+    }
+    Ok(())
+}
+
+/// Handles the `harden` subcommand: confines the role's network-facing tools with firejail,
+/// or undoes that with `--undo`.
+async fn handle_harden_command(role_file: &str, undo: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let role = role_file.trim();
+    if undo {
+        harden::unharden_role(role)?;
+        println!("{}", t!("harden-role-removed", role = role));
+        return Ok(());
+    }
+
+    let role_metadata = metadata::fetch_role_metadata(role).await?;
+    if role_metadata.sandbox.firejail.is_empty() {
+        println!("{}", t!("harden-no-tools", role = role));
+        return Ok(());
+    }
+    let hardened = harden::harden_role(role, &role_metadata.sandbox.firejail).await?;
+    println!("{}", t!("harden-done", role = role, tools = format!("{:?}", hardened)));
+    Ok(())
+}
+
+/// Handles the `wordlists` subcommands.
+/// 
+/// - `list`: Shows the sets from the repository's wordlist index, marking installed ones.
+/// - `add`: Downloads each set (resuming partial downloads, verifying checksums), records it in
+///   `~/.config/cyber-toolkit/wordlists.cnf` and symlinks it into `/usr/share/wordlists` if enabled (never in user mode).
+/// - `remove`: Deletes the sets' files and system links and forgets them.
+/// - `update`: Compares installed files with the server and re-downloads changed ones.
+async fn handle_wordlists_command(action: WordlistsAction, user_mode: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut settings = settings::load()?.wordlists;
+    settings.link_system &= !user_mode;
+    let dir = settings.dir()?;
+    let mut installed_sets = wordlists::read_installed_sets()?;
+    let client = http::client();
+
+    match action {
+        WordlistsAction::List => {
+            let available = wordlists::fetch_set_names().await?;
+            println!("{}", t!("wordlists-available", path = format!("{:?}", dir)));
+            for set in &available {
+                let marker = if installed_sets.contains(set) { "*" } else { " " };
+                println!(" {} {}", marker, set);
+            }
+        }
+        WordlistsAction::Add { sets } => {
+            let mut added = Vec::new();
+            for set in sets.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                println!("{}", t!("wordlists-installing", set = set));
+                match wordlists::install_set(client, set, &dir).await {
+                    Ok(()) => added.push(set.to_string()),
+                    Err(e) => eprintln!("{}", t!("wordlists-install-failed", set = set, error = e.to_string())),
+                }
+            }
+            installed_sets.extend(added.iter().cloned());
+            installed_sets.sort_unstable();
+            installed_sets.dedup();
+            wordlists::write_installed_sets(&installed_sets)?;
+            if settings.link_system {
+                wordlists::update_system_links(&added, &dir, true)?;
+            }
+            if added.len() < sets.len() {
+                return Err(Box::from(t!("wordlists-some-failed")));
+            }
+            println!("{}", t!("wordlists-installed", sets = format!("{:?}", added), path = format!("{:?}", dir)));
+        }
+        WordlistsAction::Remove { sets } => {
+            let removed: Vec<String> = installed_sets.iter().filter(|s| sets.contains(s)).cloned().collect();
+            if removed.is_empty() {
+                println!("{}", t!("wordlists-none-specified-installed"));
+                return Ok(());
+            }
+            if settings.link_system {
+                wordlists::update_system_links(&removed, &dir, false)?;
+            }
+            for set in &removed {
+                wordlists::remove_set_files(set, &dir)?;
+            }
+            installed_sets.retain(|s| !removed.contains(s));
+            wordlists::write_installed_sets(&installed_sets)?;
+            println!("{}", t!("wordlists-removed", sets = format!("{:?}", removed)));
+        }
+        WordlistsAction::Update { check } => {
+            if installed_sets.is_empty() {
+                println!("{}", t!("wordlists-none-installed"));
+                return Ok(());
+            }
+            for set in &installed_sets {
+                println!("{}", t!("wordlists-checking", set = set));
+                for (file, status) in wordlists::update_set(client, set, &dir, check).await? {
+                    match status {
+                        wordlists::FileStatus::Downloaded => println!("{}", t!("wordlists-file-updated", file = file)),
+                        wordlists::FileStatus::UpToDate => println!("{}", t!("wordlists-file-up-to-date", file = file)),
+                        wordlists::FileStatus::UpdateAvailable => println!("{}", t!("wordlists-file-update-available", file = file)),
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the entries among `tools` that are installed, across all backends.
+fn installed_entries(tools: &[String]) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
+    let grouped = backends::split_by_backend(tools);
+    #[cfg(unix)]
+    let native: HashSet<String> = pacman::query_installed(&grouped.native)?.into_keys().collect();
+    #[cfg(windows)]
+    let native: HashSet<String> = windows::winget_installed(&grouped.native)?
+        .into_iter()
+        .chain(windows::choco_installed(&grouped.choco)?.into_iter().map(|package| format!("{}{}", backends::CHOCO_PREFIX, package)))
+        .collect();
+    let isolated_names: HashSet<String> = isolated::installed(&grouped.isolated)?.into_iter().collect();
+    let go_binaries: HashSet<String> = go::installed(&grouped.go)?.into_iter().collect();
+    Ok(tools
+        .iter()
+        .filter(|tool| {
+            if let Some(spec) = tool.strip_prefix(isolated::PREFIX) {
+                isolated::parse_spec(spec).is_ok_and(|isolated_tool| isolated_names.contains(&isolated_tool.name))
+            } else if let Some(spec) = tool.strip_prefix(go::PREFIX) {
+                go::parse_spec(spec).is_ok_and(|go_tool| go_binaries.contains(&go_tool.binary))
+            } else {
+                native.contains(*tool)
+            }
+        })
+        .cloned()
+        .collect())
+}
+
+/// Which roles `--current` and `--list-all` show.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ListFilter {
+    All,
+    /// `--installed-only`: configured roles whose tools are all installed.
+    InstalledOnly,
+    /// `--missing-only`: roles with tools that aren't installed.
+    MissingOnly,
+}
+
+impl ListFilter {
+    fn from_flags(installed_only: bool, missing_only: bool) -> Self {
+        match (installed_only, missing_only) {
+            (true, _) => ListFilter::InstalledOnly,
+            (_, true) => ListFilter::MissingOnly,
+            _ => ListFilter::All,
+        }
+    }
+}
+
+/// A configured role as shown by `--current`.
+#[derive(Serialize, Debug)]
+struct CurrentRole {
+    role: String,
+    /// Where the role file is fetched from.
+    source: String,
+    tools: usize,
+    installed: usize,
+    /// Tools of the role that aren't installed.
+    missing: Vec<String>,
+    /// SHA-256 recorded by `freeze`, if the role is frozen.
+    frozen_sha256: Option<String>,
+    /// Whether the role comes from the system-wide roles file.
+    system: bool,
+    /// RFC 3339 expiry time of a role added with `--temp`.
+    expires: Option<String>,
+}
+
+/// Output of `--current`.
+#[derive(Serialize, Debug)]
+struct CurrentState {
+    /// RFC 3339 timestamp of the last successful add/sync, if any.
+    last_sync: Option<String>,
+    roles: Vec<CurrentRole>,
+    /// Roles that are configured but couldn't be fetched.
+    unavailable: Vec<String>,
+}
+
+/// Handles `--current`: shows each configured role's tool count, how many of its tools are
+/// installed or missing, where it comes from and whether it is frozen or temporary.
+/// `--installed-only` and `--missing-only` narrow the roles shown (see `ListFilter`).
+async fn handle_current_command(filter: ListFilter, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut current = current_state().await?;
+    match filter {
+        ListFilter::All => {}
+        ListFilter::InstalledOnly => current.roles.retain(|role| role.missing.is_empty()),
+        ListFilter::MissingOnly => current.roles.retain(|role| !role.missing.is_empty()),
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&current)?);
+        return Ok(());
+    }
+
+    if current.roles.is_empty() && current.unavailable.is_empty() {
+        match filter {
+            ListFilter::All => println!("{}", t!("current-no-roles")),
+            ListFilter::InstalledOnly => println!("{}", t!("current-none-installed")),
+            ListFilter::MissingOnly => println!("{}", t!("current-all-installed")),
+        }
+        return Ok(());
+    }
+    println!(
+        "\n{:<24} {:>6} {:>10} {:>8}  {:<14} {}",
+        t!("column-role"),
+        t!("column-tools"),
+        t!("column-installed"),
+        t!("column-missing"),
+        t!("column-frozen"),
+        t!("column-source")
+    );
+    for role in &current.roles {
+        let frozen = role.frozen_sha256.as_deref().map(|sha| format!("sha256:{}", &sha[..sha.len().min(7)])).unwrap_or_else(|| "-".to_string());
+        println!("{:<24} {:>6} {:>10} {:>8}  {:<14} {}", role.role, role.tools, role.installed, role.missing.len(), frozen, role.source);
+        if role.system {
+            println!("{}", t!("current-system-role"));
+        }
+        if let Some(expires) = &role.expires {
+            println!("{}", t!("current-temporary-role", until = expires));
+        }
+        if !role.missing.is_empty() {
+            println!("{}", t!("current-missing-tools", tools = role.missing.join(", ")));
+        }
+    }
+    for role in &current.unavailable {
+        println!("{}", t!("current-role-unavailable", role = format!("{:<24}", role)));
+    }
+    println!("{}", t!("current-last-sync", time = current.last_sync.clone().unwrap_or_else(|| t!("never"))));
+    Ok(())
+}
+
+/// Fetches the configured roles and works out which of their tools are installed.
+async fn current_state() -> Result<CurrentState, Box<dyn std::error::Error>> {
+    let configured_roles = read_roles_from_config_file()?;
+    let report = roles::fetch_roles(&configured_roles, role_fetcher(), &ConsoleReporter).await;
+    let lock = lock::read()?.unwrap_or_default();
+    let expiries = expiry::read()?;
+    let system = system_roles()?;
+
+    let mut current = CurrentState {
+        last_sync: state::last_sync()?.map(|time| time.to_rfc3339()),
+        roles: Vec::new(),
+        unavailable: report.failed.iter().map(|failure| failure.role.clone()).collect(),
+    };
+    for role in &report.roles {
+        let tools = backends::expand_groups(role.tools.clone());
+        let installed = installed_entries(&tools)?;
+        current.roles.push(CurrentRole {
+            role: role.name.clone(),
+            source: role.location.clone(),
+            tools: tools.len(),
+            installed: installed.len(),
+            missing: tools.iter().filter(|tool| !installed.contains(*tool)).cloned().collect(),
+            frozen_sha256: lock.roles.get(&role.name).map(|locked| locked.sha256.clone()),
+            system: system.contains(&role.name),
+            expires: expiries.get(&role.name).map(|until| until.to_rfc3339()),
+        });
+    }
+
+    Ok(current)
+}
+
+/// Summary produced by the `stats` subcommand.
+#[derive(Serialize, Debug)]
+struct Stats {
+    configured_roles: usize,
+    unique_tools: usize,
+    installed_tools: usize,
+    /// Percentage of `unique_tools` that are currently installed.
+    install_coverage_percent: f64,
+    cache_size_bytes: u64,
+    /// RFC 3339 timestamp of the last successful add/sync, if any.
+    last_sync: Option<String>,
+    /// Number of history entries per operation (`add`, `remove`).
+    history: BTreeMap<String, usize>,
+}
+
+/// Handles the `stats` subcommand: gathers a summary of the local toolkit state.
+/// 
+/// - Counts configured roles and the unique tools they list (fetched from the repository).
+/// - Computes install coverage by checking which of those tools `pacman -Qi` reports as installed.
+/// - Reports cache size, last sync time and per-operation history counts from the cache and state directories.
+/// - Prints the result as a table, or as a single JSON object with `--json`.
+async fn handle_stats_command(json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let configured_roles = read_roles_from_config_file()?;
+    let tools = fetch_tools_for_role_files(&configured_roles).await?;
+    let grouped = backends::split_by_backend(&tools);
+    let installed_tools = count_installed_native(&grouped)?
+        + isolated::installed(&grouped.isolated)?.len()
+        + go::installed(&grouped.go)?.len();
+    let install_coverage_percent = if tools.is_empty() {
+        100.0
+    } else {
+        installed_tools as f64 * 100.0 / tools.len() as f64
+    };
+
+    let stats = Stats {
+        configured_roles: configured_roles.len(),
+        unique_tools: tools.len(),
+        installed_tools,
+        install_coverage_percent,
+        cache_size_bytes: state::cache_size()?,
+        last_sync: state::last_sync()?.map(|time| time.to_rfc3339()),
+        history: state::history_counts()?,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    println!("{}", t!("stats-configured-roles", count = stats.configured_roles));
+    println!("{}", t!("stats-unique-tools", count = stats.unique_tools));
+    println!("{}", t!("stats-installed-tools", count = stats.installed_tools, coverage = format!("{:.1}", stats.install_coverage_percent)));
+    println!("{}", t!("stats-cache-size", size = pacman::format_size(stats.cache_size_bytes)));
+    println!("{}", t!("stats-last-sync", time = stats.last_sync.clone().unwrap_or_else(|| t!("never"))));
+    if stats.history.is_empty() {
+        println!("{}", t!("stats-history-empty"));
+    } else {
+        let counts: Vec<String> = stats.history.iter().map(|(op, count)| format!("{} {}", count, op)).collect();
+        println!("{}", t!("stats-history", counts = counts.join(", ")));
+    }
+    Ok(())
+}
+
+/// Handles the `metrics` subcommand: computes the metrics from the cached role files, so it works
+/// without network access, and prints them or writes them to `textfile`.
+async fn handle_metrics_command(textfile: Option<&std::path::Path>) -> Result<(), Box<dyn std::error::Error>> {
+    if textfile.is_none() {
+        JSON_OUTPUT.store(true, Ordering::Relaxed);
+    }
+    let configured_roles = read_roles_from_config_file()?;
+    let report = roles::fetch_roles(&configured_roles, &CacheFetcher, &SilentReporter).await;
+    let tools = backends::expand_groups(report.tools());
+    let installed = installed_entries(&tools)?;
+    let mut collected = metrics::Metrics {
+        unavailable_roles: report.failed.len(),
+        tools: tools.len(),
+        installed_tools: installed.len(),
+        last_sync: state::last_sync()?.map(|time| time.timestamp()),
+        last_run_failures: failures::read()?.len(),
+        cache_size_bytes: state::cache_size()?,
+        ..Default::default()
+    };
+    for role in &report.roles {
+        let role_tools = backends::expand_groups(role.tools.clone());
+        let role_installed = role_tools.iter().filter(|tool| installed.contains(*tool)).count();
+        collected.roles.push(metrics::RoleMetrics { role: role.name.clone(), tools: role_tools.len(), installed: role_installed });
+    }
+
+    let text = metrics::render(&collected);
+    match textfile {
+        Some(path) => metrics::write_textfile(path, &text)?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Handles the `select` subcommand: shows the available roles as a checklist with the configured
+/// ones checked, then removes the unchecked and adds the newly checked roles.
+async fn handle_select_command(user_mode: bool, refreeze: bool, force: bool, unpin: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if !ui().is_interactive() {
+        return Err(Box::from(t!("select-needs-terminal")));
+    }
+    if platform::is_plain() {
+        return Err(Box::from(t!("select-plain")));
+    }
+    let configured = read_roles_from_config_file()?;
+    let mut available = discovery::fetch_role_index().await?;
+    // Configured roles the index doesn't list stay selectable, so they aren't removed by accident
+    for role in &configured {
+        if !available.contains(role) {
+            available.push(role.clone());
+        }
+    }
+    let checked: Vec<bool> = available.iter().map(|role| configured.contains(role)).collect();
+    let Some(selected) = ui().multi_select(&t!("select-prompt"), &available, &checked)? else {
+        println!("{}", t!("nothing-changed"));
+        return Ok(());
+    };
+
+    let selected: Vec<String> = selected.into_iter().map(|index| available[index].clone()).collect();
+    let to_add: Vec<String> = selected.iter().filter(|role| !configured.contains(role)).cloned().collect();
+    let to_remove: Vec<String> = configured.iter().filter(|role| !selected.contains(role)).cloned().collect();
+    if to_add.is_empty() && to_remove.is_empty() {
+        println!("{}", t!("select-unchanged"));
+        return Ok(());
+    }
+    println!("{}", t!("roles-to-add", roles = format!("{:?}", to_add)));
+    println!("{}", t!("roles-to-remove", roles = format!("{:?}", to_remove)));
+    pins::check_removal(&to_remove, unpin)?;
+    if ui().confirm(&t!("select-confirm"), true)? != Some(true) {
+        println!("{}", t!("nothing-changed"));
+        return Ok(());
+    }
+
+    // Removing first means the add doesn't upgrade tools that are about to go, while the removal
+    // keeps the tools the added roles need, so every tool is installed or removed at most once
+    if !to_remove.is_empty() {
+        handle_remove_command(&to_remove, &to_add, user_mode, force, false, unpin).await?;
+    }
+    if !to_add.is_empty() {
+        handle_add_command(&to_add, user_mode, refreeze).await?;
+        expiry::clear(&to_add)?;
+    }
+    Ok(())
+}
+
+/// Handles the `batch` subcommand: reads the operations from `source` (`-` for stdin), applies
+/// them to the configured roles (see `batch::resolve`) and carries out the difference. Like
+/// `select`, it removes first, keeping the tools the added roles need, and then adds, so every
+/// tool is installed or removed at most once and there is a single sync.
+async fn handle_batch_command(source: &str, user_mode: bool, refreeze: bool, force: bool, unpin: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let content = if source == "-" {
+        io::read_to_string(io::stdin())?
+    } else {
+        std::fs::read_to_string(source).map_err(|e| t!("batch-read-failed", source = source, error = e.to_string()))?
+    };
+    let mut operations = batch::parse(&content)?;
+    for operation in &mut operations {
+        operation.roles = roles::canonical_names(&aliases::expand_configured(&operation.roles)?);
+    }
+    let configured = read_roles_from_config_file()?;
+    let plan = batch::resolve(&configured, &system_roles()?, &operations);
+    for note in &plan.notes {
+        println!("{}", t!("batch-note", note = note));
+    }
+    if plan.add.is_empty() && plan.remove.is_empty() {
+        println!("{}", t!("batch-unchanged", count = operations.len()));
+        return Ok(());
+    }
+    println!("{}", t!("roles-to-add", roles = format!("{:?}", plan.add)));
+    println!("{}", t!("roles-to-remove", roles = format!("{:?}", plan.remove)));
+    pins::check_removal(&plan.remove, unpin)?;
+
+    if !plan.remove.is_empty() && !plan.add.is_empty() {
+        // A misspelled role would otherwise only fail the add, after the removal went through
+        let (_, unknown) = fetch_role_report(&plan.add).await?;
+        if !unknown.is_empty() {
+            return Err(Box::from(t!("add-unknown-roles", roles = format!("{:?}", unknown))));
+        }
+    }
+    if !plan.remove.is_empty() {
+        handle_remove_command(&plan.remove, &plan.add, user_mode, force, false, unpin).await?;
+    }
+    if !plan.add.is_empty() {
+        handle_add_command(&plan.add, user_mode, refreeze).await?;
+    }
+    Ok(())
+}
+
+/// A role entry matching the tool searched by `which-role`.
+#[derive(Serialize, Debug)]
+struct ToolMatch {
+    role: String,
+    /// The role entry, e.g. `isolated:bloodhound=...`.
+    entry: String,
+    /// The tool name the search matched.
+    tool: String,
+    /// Whether the name matched exactly.
+    exact: bool,
+}
+
+/// Returns the name a role entry is known by: the container name of `isolated:` entries, the
+/// binary of `go:` entries, the group of `group:` entries and the package otherwise.
+fn entry_tool_name(entry: &str) -> String {
+    if let Some(spec) = entry.strip_prefix(isolated::PREFIX) {
+        isolated::parse_spec(spec).map(|tool| tool.name).unwrap_or_else(|_| spec.to_string())
+    } else if let Some(spec) = entry.strip_prefix(go::PREFIX) {
+        go::parse_spec(spec).map(|tool| tool.binary).unwrap_or_else(|_| spec.to_string())
+    } else {
+        let name = entry.strip_prefix(backends::GROUP_PREFIX).or_else(|| entry.strip_prefix(backends::CHOCO_PREFIX)).unwrap_or(entry);
+        name.to_string()
+    }
+}
+
+/// Handles the `which-role` subcommand: fetches all roles of the index and lists the entries
+/// whose tool name matches `tool` exactly, partially or with a typo (see `fuzzy::matches`),
+/// best matches first.
+async fn handle_which_role_command(tool: &str, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let available_roles = discovery::fetch_role_index().await?;
+    let report = roles::fetch_roles(&available_roles, role_fetcher(), &SilentReporter).await;
+    for failure in &report.failed {
+        eprintln!("{}", t!("role-fetch-failed", role = failure.role.as_str(), error = failure.error.as_str()));
+    }
+
+    let mut matches: Vec<(fuzzy::Match, ToolMatch)> = Vec::new();
+    for role in &report.roles {
+        for entry in &role.tools {
+            let name = entry_tool_name(entry);
+            if let Some(quality) = fuzzy::matches(tool, &name) {
+                let exact = quality == fuzzy::Match::Exact;
+                matches.push((quality, ToolMatch { role: role.name.clone(), entry: entry.clone(), tool: name, exact }));
+            }
+        }
+    }
+    matches.sort_by(|(a, a_match), (b, b_match)| a.cmp(b).then_with(|| a_match.tool.cmp(&b_match.tool)).then_with(|| a_match.role.cmp(&b_match.role)));
+    let matches: Vec<ToolMatch> = matches.into_iter().map(|(_, tool_match)| tool_match).collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&matches)?);
+        return Ok(());
+    }
+    if matches.is_empty() {
+        println!("{}", t!("which-role-none", tool = format!("{:?}", tool)));
+        return Ok(());
+    }
+    if matches.iter().any(|tool_match| tool_match.exact) {
+        println!("{}", t!("which-role-exact", tool = format!("{:?}", tool)));
+    } else {
+        println!("{}", t!("which-role-similar", tool = format!("{:?}", tool)));
+    }
+    let configured_roles: HashSet<String> = read_roles_from_config_file().unwrap_or_default().into_iter().collect();
+    for tool_match in &matches {
+        let marker = if configured_roles.contains(&tool_match.role) { "*" } else { " " };
+        let entry = if tool_match.entry == tool_match.tool { String::new() } else { t!("which-role-entry", entry = tool_match.entry.as_str()) };
+        println!(" {} {:<24} {}{}", marker, tool_match.role, tool_match.tool, entry);
+    }
+    Ok(())
+}
+
+/// What `why-installed` found out about a package.
+#[derive(Serialize, Debug)]
+struct InstallAttribution {
+    package: String,
+    attribution: ownership::Attribution,
+    /// pacman's install reason, `None` if pacman doesn't have the package.
+    explicit: Option<bool>,
+    install_date: Option<String>,
+    required_by: Vec<String>,
+    /// The toolkit's install record.
+    record: Option<lock::InstalledTool>,
+    /// Time of the first install event in the audit log.
+    first_install: Option<String>,
+    /// Configured roles listing the package.
+    roles: Vec<String>,
+}
+
+/// Handles the `why-installed` subcommand: attributes `package` to the toolkit, the user or a
+/// dependency (see `ownership::attribute`) from pacman's install reason and the install records,
+/// and names the configured roles listing it. Roles are read from the cache, so no network is used.
+async fn handle_why_installed_command(package: &str, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let entry = package.to_string();
+    #[cfg(unix)]
+    let pacman_package = if backends::is_native_entry(&entry) { pacman::query_installed(std::slice::from_ref(&entry))?.remove(&entry) } else { None };
+    #[cfg(windows)]
+    let pacman_package: Option<pacman::InstalledPackage> = None;
+    let installed = pacman_package.is_some() || installed_entries(std::slice::from_ref(&entry))?.contains(&entry);
+    let record = lock::read()?.unwrap_or_default().installs.remove(&entry);
+    let first_install = audit::first_installs(std::slice::from_ref(&entry))?.remove(&entry);
+
+    let configured_roles = read_roles_from_config_file()?;
+    let report = roles::fetch_roles(&configured_roles, &CacheFetcher, &SilentReporter).await;
+    let roles: Vec<String> =
+        report.roles.iter().filter(|role| backends::expand_groups(role.tools.clone()).contains(&entry)).map(|role| role.name.clone()).collect();
+
+    let attribution = InstallAttribution {
+        package: entry,
+        attribution: ownership::attribute(installed, pacman_package.as_ref(), record.as_ref(), first_install.as_deref()),
+        explicit: pacman_package.as_ref().map(|package| package.explicit),
+        install_date: pacman_package.as_ref().and_then(|package| package.install_date.clone()),
+        required_by: pacman_package.map(|package| package.required_by).unwrap_or_default(),
+        record,
+        first_install,
+        roles,
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&attribution)?);
+        return Ok(());
+    }
+
+    println!("{}: {}", attribution.package, attribution.attribution.describe());
+    if let Some(explicit) = attribution.explicit {
+        let reason = if explicit { t!("explain-explicit") } else { t!("explain-dependency") };
+        match &attribution.install_date {
+            Some(date) => println!("{}", t!("explain-pacman-date", reason = reason.as_str(), date = date)),
+            None => println!("{}", t!("explain-pacman", reason = reason)),
+        }
+    }
+    match &attribution.record {
+        Some(record) => {
+            let origin = match record.origin {
+                Some(lock::Origin::Toolkit) => t!("explain-origin-toolkit"),
+                Some(lock::Origin::User) => t!("explain-origin-user"),
+                Some(lock::Origin::Dependency) => t!("explain-origin-dependency"),
+                None => t!("explain-origin-unknown"),
+            };
+            println!("{}", t!("explain-toolkit", origin = origin, user = record.user.as_str(), time = record.installed_at.as_str()));
+        }
+        None => match &attribution.first_install {
+            Some(time) => println!("{}", t!("explain-toolkit-event", time = time)),
+            None => println!("{}", t!("explain-toolkit-none")),
+        },
+    }
+    if !attribution.required_by.is_empty() {
+        println!("{}", t!("explain-required-by", packages = attribution.required_by.join(", ")));
+    }
+    if attribution.roles.is_empty() {
+        println!("{}", t!("explain-roles-none"));
+    } else {
+        println!("{}", t!("explain-roles", roles = attribution.roles.join(", ")));
+    }
+    if attribution.attribution == ownership::Attribution::User && !attribution.roles.is_empty() {
+        println!("{}", t!("explain-kept"));
+    }
+    Ok(())
+}
+
+/// Handles the `expire` subcommand: removes expired temporary roles and lists the remaining ones.
+async fn handle_expire_command(user_mode: bool, force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let now = chrono::Utc::now();
+    let configured = read_roles_from_config_file()?;
+    let (expired, gone): (Vec<String>, Vec<String>) = expiry::expired(now)?.into_iter().partition(|role| configured.contains(role));
+    // Roles removed by other means no longer need an expiry
+    expiry::clear(&gone)?;
+    // Pinning a temporary role overrides its expiry; it stays recorded for when it's unpinned
+    let pinned = pins::pinned()?;
+    let (kept, expired): (Vec<String>, Vec<String>) = expired.into_iter().partition(|role| pinned.contains(role));
+    if !kept.is_empty() {
+        println!("{}", t!("expire-keeping-pinned", roles = format!("{:?}", kept)));
+    }
+    if expired.is_empty() {
+        println!("{}", t!("expire-none"));
+    } else {
+        println!("{}", t!("expire-removing", roles = format!("{:?}", expired)));
+        handle_remove_command(&expired, &[], user_mode, force, false, false).await?;
+    }
+    for (role, until) in expiry::read()? {
+        println!("{}", t!("expire-at", role = role, until = until.format("%Y-%m-%d %H:%M UTC").to_string()));
+    }
+    Ok(())
+}
+
+/// Returns the directory of the project file for the current directory.
+fn current_project_dir() -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+    let cwd = std::env::current_dir()?;
+    project::find(&cwd).ok_or_else(|| Box::from(t!("project-not-found", file = project::PROJECT_FILE_NAME.to_string(), path = format!("{:?}", cwd))))
+}
+
+/// Handles the `apply` subcommand: adds the roles of the current project like `cyber-toolkit <role>...`
+/// and records which of them are new, for `leave`.
+async fn handle_apply_command(user_mode: bool, refreeze: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = current_project_dir()?;
+    let roles = aliases::expand_configured(&project::load(&dir)?.roles)?;
+    if roles.is_empty() {
+        println!("{}", t!("project-no-roles", path = format!("{:?}", dir.join(project::PROJECT_FILE_NAME))));
+        return Ok(());
+    }
+    let configured = read_roles_from_config_file().unwrap_or_default();
+    let newly_added: Vec<String> = roles.iter().filter(|role| !configured.contains(role)).cloned().collect();
+    println!("{}", t!("project-applying", path = format!("{:?}", dir), roles = format!("{:?}", roles), new = format!("{:?}", newly_added)));
+    handle_add_command(&roles, user_mode, refreeze).await?;
+    project::record_apply(&dir, &roles, &newly_added)
+}
+
+/// Handles the `auto-sync` subcommand: syncs the configured roles like `install`, unless the
+/// `[auto_sync]` settings say to wait for mains power or an unmetered connection. Skipping is
+/// not an error, so the timer's next run simply tries again.
+async fn handle_auto_sync_command(user_mode: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = settings::load()?.auto_sync;
+    if settings.skip_on_battery && power::on_battery() {
+        println!("{}", t!("auto-sync-on-battery"));
+        return Ok(());
+    }
+    if settings.skip_on_metered && power::on_metered_connection() {
+        println!("{}", t!("auto-sync-metered"));
+        return Ok(());
+    }
+    if read_roles_from_config_file()?.is_empty() {
+        println!("{}", t!("auto-sync-no-roles"));
+        return Ok(());
+    }
+    handle_add_command(&[], user_mode, false).await
+}
+
+/// Handles the `leave` subcommand: removes the roles `apply` added for the current project.
+async fn handle_leave_command(user_mode: bool, force: bool, unpin: bool) -> Result<(), Box<dyn std::error::Error>> {
+    // The project file may be gone already; the directory is enough to find the applied project
+    let dir = match current_project_dir() {
+        Ok(dir) => dir,
+        Err(_) => std::env::current_dir()?,
+    };
+    let Some(roles) = project::roles_to_leave(&dir)? else {
+        println!("{}", t!("project-not-applied", path = format!("{:?}", dir)));
+        return Ok(());
+    };
+    if roles.is_empty() {
+        println!("{}", t!("project-nothing-added", path = format!("{:?}", dir)));
+    } else {
+        println!("{}", t!("project-leaving", path = format!("{:?}", dir), roles = format!("{:?}", roles)));
+        handle_remove_command(&roles, &[], user_mode, force, false, unpin).await?;
+    }
+    project::forget(&dir)
+}
+
+/// Handles the `state` subcommand: moves the state database in and out as JSON.
+/// Handles the `alias` subcommand: edits `[aliases]` in `config.toml`, keeping the rest of the file.
+fn handle_alias_command(action: AliasAction, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let aliases = settings::load()?.aliases;
+    match action {
+        AliasAction::Add { name, roles } => {
+            aliases::validate_name(&name)?;
+            let mut changed = aliases.clone();
+            changed.insert(name.clone(), roles.clone());
+            let expanded = aliases::expand(std::slice::from_ref(&name), &changed)?;
+            if expanded.is_empty() {
+                return Err(Box::from(t!("alias-empty", name = name)));
+            }
+            let mut table = settings::load_table()?;
+            settings::set_key(&mut table, &format!("aliases.{}", name), toml::Value::String(roles))?;
+            settings::save_table(&table)?;
+            let roles = expanded.join(", ");
+            if aliases.contains_key(&name) {
+                println!("{}", t!("alias-changed", name = name.as_str(), roles = roles.as_str()));
+            } else {
+                println!("{}", t!("alias-added", name = name.as_str(), roles = roles.as_str()));
+            }
+        }
+        AliasAction::Remove { name } => {
+            let mut table = settings::load_table()?;
+            if !settings::unset_key(&mut table, &format!("aliases.{}", name)) {
+                return Err(Box::from(t!("alias-unknown", name = name)));
+            }
+            settings::save_table(&table)?;
+            println!("{}", t!("alias-removed", name = name.as_str()));
+            for (other, roles) in &aliases {
+                if roles.split(aliases::SEPARATOR).any(|part| part.trim() == name) {
+                    eprintln!("{}", t!("alias-shadowed", alias = other, name = name.as_str()));
+                }
+            }
+        }
+        AliasAction::List => {
+            let mut expanded = BTreeMap::new();
+            for name in aliases.keys() {
+                expanded.insert(name.clone(), aliases::expand(std::slice::from_ref(name), &aliases)?);
+            }
+            if json {
+                println!("{}", serde_json::to_string_pretty(&expanded)?);
+            } else if expanded.is_empty() {
+                println!("{}", t!("aliases-none"));
+            } else {
+                for (name, roles) in &expanded {
+                    println!("{} = {} ({})", name, aliases[name], roles.join(", "));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A tool recorded in the lockfile, as checked by `verify`.
+#[derive(Serialize, Debug)]
+struct VerifiedTool {
+    tool: String,
+    scope: lock::Scope,
+    installed: bool,
+}
+
+/// Handles the `verify` subcommand: checks the tools recorded for the invoking user in the
+/// lockfile, each where its scope says it is installed. Tools other users installed (in a shared
+/// state directory) are left to them.
+fn handle_verify_command(json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let installs = lock::read()?.unwrap_or_default().installs;
+    let mine: Vec<String> = installs.iter().filter(|(_, record)| record.user == audit::user()).map(|(tool, _)| tool.clone()).collect();
+    let installed = installed_entries(&mine)?;
+    let tools: Vec<VerifiedTool> =
+        mine.iter().map(|tool| VerifiedTool { tool: tool.clone(), scope: installs[tool].scope, installed: installed.contains(tool) }).collect();
+    let missing = tools.iter().filter(|tool| !tool.installed).count();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tools)?);
+    } else if tools.is_empty() {
+        println!("{}", t!("verify-no-records", user = audit::user()));
+    } else {
+        for scope in [lock::Scope::System, lock::Scope::User] {
+            let in_scope: Vec<&VerifiedTool> = tools.iter().filter(|tool| tool.scope == scope).collect();
+            if in_scope.is_empty() {
+                continue;
+            }
+            let label = match scope {
+                lock::Scope::System => t!("verify-scope-system"),
+                lock::Scope::User => t!("verify-scope-user"),
+            };
+            let present = in_scope.iter().filter(|tool| tool.installed).count();
+            println!("{}", t!("verify-scope-installed", scope = label, present = present, count = in_scope.len()));
+            for tool in in_scope.iter().filter(|tool| !tool.installed) {
+                println!("{}", t!("verify-missing-tool", tool = tool.tool.as_str()));
+            }
+        }
+    }
+    if missing > 0 {
+        return Err(Box::from(t!("verify-missing", count = missing)));
+    }
+    Ok(())
+}
+
+/// Handles the `pin-role` subcommand: pins `roles`, or lists the pinned roles if none are given.
+fn handle_pin_role_command(roles: &[String], json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if roles.is_empty() {
+        let pinned = pins::pinned()?;
+        if json {
+            println!("{}", serde_json::to_string_pretty(&pinned)?);
+        } else if pinned.is_empty() {
+            println!("{}", t!("pins-none"));
+        } else {
+            for role in &pinned {
+                println!("{}", role);
+            }
+        }
+        return Ok(());
+    }
+    let configured = read_roles_from_config_file()?;
+    for role in roles.iter().filter(|role| !configured.contains(role)) {
+        eprintln!("{}", t!("pins-not-configured", role = role));
+    }
+    let pinned = pins::pin(roles)?;
+    if pinned.is_empty() {
+        println!("{}", t!("pins-already-pinned", roles = format!("{:?}", roles)));
+    } else {
+        println!("{}", t!("pins-pinned", roles = format!("{:?}", pinned)));
+    }
+    Ok(())
+}
+
+/// Handles the `tool` subcommand.
+///
+/// `tool add` appends the tools to the local role and then adds that role like
+/// `cyber-toolkit <role>`, which installs them. `tool remove` takes the tools out of the local
+/// roles, drops local roles left without tools from the configuration, and uninstalls the
+/// tools no configured role lists any more (with the same confirmation as `--remove`).
+async fn handle_tool_command(action: ToolAction, user_mode: bool, refreeze: bool, force: bool, unpin: bool) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        ToolAction::Add { tools, role } => {
+            local_roles::validate_name(&role)?;
+            let role = roles::canonical_name(&role);
+            let added = local_roles::add_tools(&role, &tools)?;
+            if added.is_empty() {
+                println!("{}", t!("local-role-already-lists", role = role.as_str(), tools = format!("{:?}", tools)));
+            } else {
+                println!("{}", t!("local-role-added", tools = format!("{:?}", added), role = role.as_str(), path = format!("{:?}", local_roles::path(&role)?)));
+            }
+            handle_add_command(std::slice::from_ref(&role), user_mode, refreeze).await?;
+            state::append_history("tool-add", &added)?;
+        }
+        ToolAction::Remove { tools, role } => {
+            let local = match role {
+                Some(role) => vec![roles::canonical_name(&role)],
+                None => local_roles::list()?,
+            };
+            let mut removed_from = Vec::new();
+            for role in &local {
+                let removed = local_roles::remove_tools(role, &tools)?;
+                if !removed.is_empty() {
+                    removed_from.push((role.clone(), removed));
+                }
+            }
+            if removed_from.is_empty() {
+                return Err(Box::from(t!("local-role-tools-unknown", tools = format!("{:?}", tools), roles = format!("{:?}", local_roles::list()?))));
+            }
+            let removed: Vec<String> = removed_from.iter().flat_map(|(_, removed)| removed.iter().cloned()).collect();
+            for (role, removed) in &removed_from {
+                println!("{}", t!("local-role-removed", tools = format!("{:?}", removed), role = role));
+            }
+
+            // Local roles without tools are gone; the others still count
+            let configured = read_roles_from_config_file()?;
+            let emptied: Vec<&String> = removed_from.iter().map(|(role, _)| role).filter(|role| local_roles::read(role).ok().flatten().is_none()).collect();
+            let dropped: Vec<String> = configured.iter().filter(|role| emptied.contains(role)).cloned().collect();
+            if let Err(e) = pins::check_removal(&dropped, unpin) {
+                for (role, removed) in &removed_from {
+                    local_roles::add_tools(role, removed)?;
+                }
+                return Err(e);
+            }
+            let kept_roles: Vec<String> = configured.iter().filter(|role| !emptied.contains(role)).cloned().collect();
+            let kept_tools: HashSet<String> = fetch_tools_for_role_files(&kept_roles).await?.into_iter().collect();
+            let tools_to_uninstall: Vec<String> = removed.iter().filter(|tool| !kept_tools.contains(*tool)).cloned().collect();
+            for tool in removed.iter().filter(|tool| kept_tools.contains(*tool)) {
+                println!("{}", t!("local-role-keeping", tool = tool));
+            }
+
+            let roles: Vec<String> = removed_from.iter().map(|(role, _)| role.clone()).collect();
+            let (_, timed_out) = uninstall_tools(&tools_to_uninstall, &roles, user_mode, force).await?;
+            if timed_out {
+                // Keep listing the tools until they are really gone
+                for (role, removed) in &removed_from {
+                    local_roles::add_tools(role, removed)?;
+                }
+                return Err(Box::from(t!("local-role-uninstall-timed-out", tools = format!("{:?}", tools_to_uninstall))));
+            }
+            if !dropped.is_empty() {
+                write_roles_to_config_file(&kept_roles)?;
+                pins::unpin(&dropped)?;
+            }
+            state::append_history("tool-remove", &removed)?;
+        }
+    }
+    Ok(())
+}
+
+/// Handles the `state` subcommand.
+fn handle_state_command(action: StateAction) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        StateAction::Export { output } => {
+            let content = serde_json::to_string_pretty(&state_db::export()?)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, content + "\n")?;
+                    println!("{}", t!("state-exported", path = format!("{:?}", path)));
+                }
+                None => {
+                    // Keep stdout valid JSON, like `--json` does
+                    JSON_OUTPUT.store(true, Ordering::Relaxed);
+                    println!("{}", content);
+                }
+            }
+        }
+        StateAction::Import { file } => {
+            let state: state_db::Export = serde_json::from_str(&std::fs::read_to_string(&file)?)
+                .map_err(|e| t!("state-import-invalid", path = format!("{:?}", file), error = e.to_string()))?;
+            state_db::import(&state)?;
+            println!("{}", t!("state-imported", documents = state.documents.len(), history = state.history.len(), path = format!("{:?}", file)));
+        }
+    }
+    Ok(())
+}
+
+/// Statistics of one kind of cache, as `cache stats --json` prints them.
+#[derive(Serialize, Debug)]
+struct CacheKindStats {
+    kind: cache::Kind,
+    entries: usize,
+    size_bytes: u64,
+    /// RFC 3339 time the oldest entry was written.
+    oldest: Option<String>,
+}
+
+/// Handles `cache stats|prune|clear`; see `cache`.
+fn handle_cache_command(action: CacheAction, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = cache::entries(&read_roles_from_config_file()?)?;
+    let describe = |entry: &cache::Entry| format!("{} ({})", entry.path.display(), pacman::format_size(entry.size));
+    match action {
+        CacheAction::Stats => {
+            let stats: Vec<CacheKindStats> = cache::Kind::ALL
+                .iter()
+                .map(|kind| {
+                    let of_kind: Vec<&cache::Entry> = entries.iter().filter(|entry| entry.kind == *kind).collect();
+                    CacheKindStats {
+                        kind: *kind,
+                        entries: of_kind.len(),
+                        size_bytes: of_kind.iter().map(|entry| entry.size).sum(),
+                        oldest: of_kind.first().map(|entry| chrono::DateTime::<chrono::Utc>::from(entry.modified).to_rfc3339()),
+                    }
+                })
+                .collect();
+            let prunable = cache::to_prune(&entries, &settings::load()?.cache.policy(), std::time::SystemTime::now());
+            let prunable_bytes: u64 = prunable.iter().map(|entry| entry.size).sum();
+            if json {
+                let output = serde_json::json!({
+                    "kinds": stats,
+                    "size_bytes": entries.iter().map(|entry| entry.size).sum::<u64>(),
+                    "prunable_entries": prunable.len(),
+                    "prunable_bytes": prunable_bytes,
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+                return Ok(());
+            }
+            for (kind, stats) in cache::Kind::ALL.iter().zip(&stats) {
+                let oldest = entries
+                    .iter()
+                    .find(|entry| entry.kind == *kind)
+                    .map(|entry| t!("cache-oldest", days = entry.modified.elapsed().unwrap_or_default().as_secs() / (24 * 60 * 60)))
+                    .unwrap_or_default();
+                let kind = format!("{:<18}", kind.describe());
+                println!("{}", t!("cache-stats", kind = kind, entries = stats.entries, size = pacman::format_size(stats.size_bytes), oldest = oldest));
+            }
+            println!("{:<18}{}", t!("cache-total"), pacman::format_size(entries.iter().map(|entry| entry.size).sum()));
+            println!("{}", t!("cache-prunable", count = prunable.len(), size = pacman::format_size(prunable_bytes)));
+        }
+        CacheAction::Prune { dry_run, max_age_days, max_size_mb } => {
+            let mut settings = settings::load()?.cache;
+            settings.max_age_days = max_age_days.unwrap_or(settings.max_age_days);
+            settings.max_size_mb = max_size_mb.unwrap_or(settings.max_size_mb);
+            let pruned = cache::to_prune(&entries, &settings.policy(), std::time::SystemTime::now());
+            if dry_run {
+                for entry in &pruned {
+                    println!("{}", t!("cache-would-remove", entry = describe(entry)));
+                }
+                let size = pruned.iter().map(|entry| entry.size).sum();
+                println!("{}", t!("cache-would-free", size = pacman::format_size(size), count = pruned.len()));
+                return Ok(());
+            }
+            let (mut freed, mut removed) = (0, 0);
+            for entry in &pruned {
+                match cache::remove(entry) {
+                    Ok(()) => {
+                        println!("{}", t!("cache-removed", entry = describe(entry)));
+                        freed += entry.size;
+                        removed += 1;
+                    }
+                    Err(e) => eprintln!("{}", t!("cache-remove-failed", path = format!("{:?}", entry.path), error = e.to_string())),
+                }
+            }
+            println!("{}", t!("cache-freed", size = pacman::format_size(freed), count = removed));
+        }
+        CacheAction::Clear => {
+            let (mut freed, mut removed) = (0, 0);
+            for entry in entries.iter().filter(|entry| !entry.in_use) {
+                match cache::remove(entry) {
+                    Ok(()) => {
+                        freed += entry.size;
+                        removed += 1;
+                    }
+                    Err(e) => eprintln!("{}", t!("cache-remove-failed", path = format!("{:?}", entry.path), error = e.to_string())),
+                }
+            }
+            println!("{}", t!("cache-freed", size = pacman::format_size(freed), count = removed));
+            if entries.iter().any(|entry| entry.kind == cache::Kind::Roles) {
+                println!("{}", t!("cache-roles-cleared"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handles `bundle create`: fetches the roles and their metadata, downloads the package files of
+/// their native tools and the wordlist sets, and packs everything with a manifest.
+///
+/// Tools that aren't in the sync repositories (e.g. AUR packages) and non-native tools are left
+/// out with a warning.
+#[cfg(unix)]
+async fn handle_bundle_create_command(
+    role_files: &[String],
+    wordlist_sets: &[String],
+    output: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let report = roles::fetch_roles(role_files, role_fetcher(), &ConsoleReporter).await;
+    if !report.failed.is_empty() {
+        let failed: Vec<&str> = report.failed.iter().map(|failure| failure.role.as_str()).collect();
+        return Err(Box::from(t!("bundle-roles-unavailable", roles = format!("{:?}", failed))));
+    }
+    let dir = bundle::work_dir()?;
+    let result = create_bundle(&report.roles, report.tools(), wordlist_sets, dir.path(), output).await;
+    remove_bundle_work_dir(dir);
+    result
+}
+
+/// Fills the working directory `dir` with the bundle's content and packs it into `output`.
+#[cfg(unix)]
+async fn create_bundle(
+    fetched_roles: &[Role],
+    tools: Vec<String>,
+    wordlist_sets: &[String],
+    dir: &std::path::Path,
+    output: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(dir.join("roles"))?;
+    std::fs::create_dir_all(dir.join("meta"))?;
+    for role in fetched_roles {
+        std::fs::write(dir.join("roles").join(&role.name), &role.content)?;
+        let role_metadata = metadata::fetch_role_metadata_source(&role.name).await?;
+        std::fs::write(dir.join("meta").join(format!("{}.toml", role.name)), role_metadata)?;
+    }
+
+    let grouped = backends::split_by_backend(&backends::expand_groups(tools));
+    let available = pacman::query_sync_packages(&grouped.native)?;
+    let (packages, unavailable): (Vec<String>, Vec<String>) =
+        grouped.native.into_iter().partition(|package| available.contains_key(package));
+    if !unavailable.is_empty() {
+        eprintln!("{}", t!("bundle-packages-unavailable", packages = format!("{:?}", unavailable)));
+    }
+    let left_out = grouped.isolated.len() + grouped.go.len() + grouped.choco.len();
+    if left_out > 0 {
+        eprintln!("{}", t!("bundle-tools-left-out", count = left_out));
+    }
+    println!("{}", t!("bundle-downloading", count = packages.len()));
+    bundle::download_packages(&packages, dir)?;
+
+    let wordlist_dir = dir.join("wordlists");
+    for set in wordlist_sets {
+        println!("{}", t!("bundle-downloading-wordlists", set = set));
+        wordlists::install_set(http::client(), set, &wordlist_dir).await?;
+    }
+
+    let manifest = bundle::Manifest {
+        created_at: chrono::Utc::now().to_rfc3339(),
+        toolkit_version: env!("CARGO_PKG_VERSION").to_string(),
+        architecture: facts::current().arch.clone(),
+        roles: fetched_roles.iter().map(|role| role.name.clone()).collect(),
+        packages: bundle::package_checksums(dir)?,
+        wordlists: wordlist_sets.to_vec(),
+    };
+    bundle::pack(dir, &manifest, output)?;
+    let size = std::fs::metadata(output).map(|metadata| metadata.len()).unwrap_or(0);
+    println!(
+        "{}",
+        t!(
+            "bundle-written",
+            path = format!("{:?}", output),
+            size = pacman::format_size(size),
+            roles = manifest.roles.len(),
+            packages = manifest.packages.len(),
+            wordlists = manifest.wordlists.len()
+        )
+    );
+    Ok(())
+}
+
+/// Handles `bundle install`: unpacks and verifies the bundle, installs its package files with
+/// `pacman -U`, installs its wordlist sets, caches its role definitions and metadata, and then
+/// adds the roles in offline mode, so nothing is fetched from the network.
+#[cfg(unix)]
+async fn handle_bundle_install_command(file: &std::path::Path, user_mode: bool, refreeze: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = bundle::work_dir()?;
+    let result = install_bundle(file, dir.path(), user_mode, refreeze).await;
+    remove_bundle_work_dir(dir);
+    result
+}
+
+/// Deletes a bundle working directory, warning if that fails.
+#[cfg(unix)]
+fn remove_bundle_work_dir(dir: tempfile::TempDir) {
+    let path = dir.path().to_path_buf();
+    if let Err(e) = dir.close() {
+        eprintln!("{}", t!("bundle-work-dir-remove-failed", path = format!("{:?}", path), error = e.to_string()));
+    }
+}
+
+/// Installs the bundle `file`, unpacked into the working directory `dir`.
+#[cfg(unix)]
+async fn install_bundle(file: &std::path::Path, dir: &std::path::Path, user_mode: bool, refreeze: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = bundle::unpack(file, dir)?;
+    println!(
+        "{}",
+        t!(
+            "bundle-created",
+            date = manifest.created_at.as_str(),
+            version = manifest.toolkit_version.as_str(),
+            roles = format!("{:?}", manifest.roles),
+            packages = manifest.packages.len(),
+            wordlists = format!("{:?}", manifest.wordlists)
+        )
+    );
+    if manifest.architecture != facts::current().arch {
+        let machine = facts::current().arch.to_string();
+        return Err(Box::from(t!("bundle-architecture-mismatch", architecture = manifest.architecture.as_str(), machine = machine)));
+    }
+    http::set_offline(true);
+
+    for role in &manifest.roles {
+        state::write_cached_role(role, &std::fs::read_to_string(dir.join("roles").join(role))?)?;
+        let meta_file = format!("{}.toml", role);
+        if let Ok(content) = std::fs::read_to_string(dir.join("meta").join(&meta_file)) {
+            state::write_cached_role(&meta_file, &content)?;
+        }
+    }
+
+    let package_files = bundle::package_files(dir, &manifest);
+    if user_mode {
+        eprintln!("{}", t!("bundle-packages-skipped", count = package_files.len()));
+    } else if !package_files.is_empty() {
+        println!("{}", t!("bundle-installing", count = package_files.len()));
+        if run_pacman_command("U", &package_files).await? == PackageRun::TimedOut {
+            return Err(Box::from(t!("bundle-install-timed-out")));
+        }
+    }
+
+    if !manifest.wordlists.is_empty() {
+        let mut settings = settings::load()?.wordlists;
+        settings.link_system &= !user_mode;
+        let wordlist_dir = settings.dir()?;
+        for set in &manifest.wordlists {
+            bundle::copy_dir(&dir.join("wordlists").join(set), &wordlist_dir.join(set))?;
+        }
+        let mut installed_sets = wordlists::read_installed_sets()?;
+        installed_sets.extend(manifest.wordlists.iter().cloned());
+        installed_sets.sort_unstable();
+        installed_sets.dedup();
+        wordlists::write_installed_sets(&installed_sets)?;
+        if settings.link_system {
+            wordlists::update_system_links(&manifest.wordlists, &wordlist_dir, true)?;
+        }
+        println!("{}", t!("wordlists-installed", sets = format!("{:?}", manifest.wordlists), path = format!("{:?}", wordlist_dir)));
+    }
+
+    handle_add_command(&manifest.roles, user_mode, refreeze).await
+}
+
+/// Handles the `config` subcommand: reads or changes `config.toml`.
+///
+/// `set` and `edit` only replace the file once the result parses as settings; keys that aren't
+/// settings are rejected by `set` and reported by `edit`.
+fn handle_config_command(action: &ConfigAction, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let path = settings::settings_path()?;
+    match action {
+        ConfigAction::Get { key } => {
+            let effective = toml::Value::try_from(settings::validate(&settings::load_table()?)?)?;
+            let table = effective.as_table().expect("settings serialize to a table");
+            let value = settings::get_key(table, key).ok_or_else(|| t!("config-key-unset", key = key))?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(value)?);
+            } else {
+                match value {
+                    toml::Value::String(text) => println!("{}", text),
+                    toml::Value::Table(section) => print!("{}", toml::to_string_pretty(section)?),
+                    other => println!("{}", other),
+                }
+            }
+        }
+        ConfigAction::Set { key, value } => {
+            let mut table = settings::load_table()?;
+            let parsed = settings::parse_value(value);
+            let is_string = parsed.is_str();
+            settings::set_key(&mut table, key, parsed)?;
+            // `sync.branch 123` means the string: retry unquoted values as strings before failing
+            if let Err(e) = settings::validate(&table) {
+                if is_string {
+                    return Err(Box::from(t!("config-value-invalid", key = key, error = e.to_string())));
+                }
+                settings::set_key(&mut table, key, toml::Value::String(value.clone()))?;
+                settings::validate(&table).map_err(|_| t!("config-value-invalid", key = key, error = e.to_string()))?;
+            }
+            if settings::unknown_keys(&table)?.contains(key) {
+                return Err(Box::from(t!("config-key-unknown", key = key)));
+            }
+            settings::save_table(&table)?;
+            println!("{}", t!("config-set", key = key, path = format!("{:?}", path)));
+        }
+        ConfigAction::Unset { key } => {
+            let mut table = settings::load_table()?;
+            if !settings::unset_key(&mut table, key) {
+                return Err(Box::from(t!("config-key-not-set", key = key, path = format!("{:?}", path))));
+            }
+            settings::save_table(&table)?;
+            println!("{}", t!("config-unset", key = key, path = format!("{:?}", path)));
+        }
+        ConfigAction::Show => {
+            let effective = settings::validate(&settings::load_table()?)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&effective)?);
+            } else {
+                println!("{}", t!("config-show-header", path = format!("{:?}", path)));
+                print!("{}", toml::to_string_pretty(&effective)?);
+            }
+        }
+        ConfigAction::Edit => {
+            // Edit a copy so a broken file never replaces the working one
+            let draft = path.with_extension("edit.toml");
+            if !draft.exists() {
+                if let Some(parent) = draft.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&draft, std::fs::read_to_string(&path).unwrap_or_default())?;
+            }
+            let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string());
+            let words = shlex::split(&editor).filter(|words| !words.is_empty()).ok_or_else(|| t!("config-editor-invalid", editor = editor.as_str()))?;
+            let status = std::process::Command::new(&words[0]).args(&words[1..]).arg(&draft).status()?;
+            if !status.success() {
+                return Err(Box::from(t!("config-editor-failed", editor = editor.as_str(), path = format!("{:?}", draft))));
+            }
+            let table = std::fs::read_to_string(&draft)?
+                .parse::<toml::Table>()
+                .map_err(|e| e.to_string())
+                .and_then(|table| settings::validate(&table).map(|_| table).map_err(|e| e.to_string()))
+                .map_err(|e| t!("config-edit-invalid", error = e.to_string(), path = format!("{:?}", draft)))?;
+            for key in settings::unknown_keys(&table)? {
+                eprintln!("{}", t!("config-key-ignored", key = key));
+            }
+            std::fs::rename(&draft, &path)?;
+            println!("{}", t!("config-saved", path = format!("{:?}", path)));
+        }
+    }
+    Ok(())
+}
+
+/// Main entry point of the application.
+/// 
+/// Runs an external plugin if the first argument names one (see `plugins`), otherwise
+/// parses command-line arguments and dispatches to a subcommand handler if one was given,
+/// to `handle_current_command` for `--current`, to `handle_list_all_command` for `--list-all`, and otherwise to either `handle_add_command`
+/// or `handle_remove_command` based on the presence of the `--remove` flag. Runs that change the
+/// machine end with the summary configured in `[notify]`.
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    i18n::configure(i18n::language_arg(&args));
+
+    match paths::migrate_legacy_dir() {
+        Ok(Some(migration)) => {
+            eprintln!("{}", t!("xdg-moved", count = migration.moved.len(), path = format!("{:?}", migration.from)));
+            for path in &migration.moved {
+                eprintln!("  {:?}", path);
+            }
+            if !migration.kept.is_empty() {
+                eprintln!("{}", t!("xdg-kept", files = format!("{:?}", migration.kept), path = format!("{:?}", migration.from)));
+            }
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("{}", t!("xdg-move-failed", error = e.to_string())),
+    }
+
+    match settings::load() {
+        Ok(settings) => {
+            facts::configure_headless(settings.profile.headless);
+            http::configure(settings.network.client_settings());
+        }
+        Err(e) => eprintln!("{}", t!("headless-settings-unreadable", error = e.to_string())),
+    }
+
+    // Dispatch to an external `cyber-toolkit-<name>` plugin if the first argument names one and
+    // no role, which the plugin would otherwise shadow
+    if let Some(name) = args.get(1) {
+        let is_builtin = Cli::command().get_subcommands().any(|sub| sub.get_name() == name);
+        if !is_builtin {
+            if let Some(plugin) = plugins::find_plugin(name) {
+                if !plugins::is_role(name).await {
+                    return Err(plugins::exec_plugin(&plugin, &args[2..]));
+                }
+                eprintln!("{}", t!("plugin-shadowed-by-role", name = name.as_str()));
+            }
+        }
+    }
+
+    // Guide newcomers through the setup when called without arguments on the first run
+    if args.len() == 1 && wizard::should_run() {
+        if let Some(roles) = wizard::run().await? {
+            let _lock = lock_run(run_lock::Access::Write);
+            handle_add_command(&roles, settings::load()?.profile.user_mode, false).await?;
+            info!("{}", t!("operation-finished"));
+        }
+        return Ok(());
+    }
+
     let cli = Cli::parse(); // Parse command-line arguments
+    JSON_OUTPUT.store(cli.json, Ordering::Relaxed);
+    http::set_offline(cli.offline);
+    platform::set_plain(cli.plain);
+    if cli.yes {
+        let _ = USER_INTERFACE.set(Box::new(NonInteractive { assume_yes: true }));
+    }
+    checksums::set_strict(cli.strict);
+    ownership::set_remove_user_installed(cli.remove_user_installed);
+    if let Some(path) = &cli.config {
+        let _ = CONFIG_STORE.set(ConfigStore::new(path));
+    }
+    if cli.system {
+        SYSTEM_MODE.store(true, Ordering::Relaxed);
+        let _ = CONFIG_STORE.set(ConfigStore::new(config::system_config_path()));
+    }
+    if let Some(Commands::Config { action }) = &cli.command {
+        // Runs before the settings are loaded below, so a broken config.toml can still be fixed
+        return handle_config_command(action, cli.json);
+    }
+    let user_mode = cli.user || settings::load()?.profile.user_mode;
+    let role_files = aliases::expand_configured(&cli.role_files)?;
+    // Changing the system-wide roles is what root is for
+    if platform::is_root() && !cli.system {
+        eprintln!("{}", t!("running-as-root"));
+    }
 
-    // Dispatch based on the --remove flag
-    if cli.remove {
-        println!("Executing REMOVE command for roles: {:?}", cli.role_files);
-        handle_remove_command(&cli.role_files).await?;
-    } else {
-        println!("Executing ADD/SYNC command for roles: {:?}", cli.role_files);
-        handle_add_command(&cli.role_files).await?;
+    let _lock = lock_run(access(&cli));
+    let notified = notified_run(&cli);
+    let command_roles = if cli.command.is_none() { role_files.clone() } else { Vec::new() };
+    let started_at = chrono::Local::now();
+    let result = dispatch(cli, user_mode, &role_files).await;
+    if let Some((command, installs)) = notified {
+        notify_run(command, installs, &command_roles, started_at, &result).await;
     }
+    result?;
 
-    println!("\n--- Operation finished ---");
+    info!("{}", t!("operation-finished"));
+    Ok(())
+}
+
+/// Runs the command `cli` asks for; see `main`.
+async fn dispatch(cli: Cli, user_mode: bool, role_files: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(command) = cli.command {
+        match command {
+            Commands::Du => handle_du_command().await?,
+            Commands::Stats => handle_stats_command(cli.json).await?,
+            Commands::Metrics { textfile } => handle_metrics_command(textfile.as_deref()).await?,
+            Commands::Sync { action } => {
+                let settings = settings::load()?;
+                match action {
+                    SyncAction::Push => sync::push(&settings)?,
+                    SyncAction::Pull => sync::pull(&settings)?,
+                }
+            }
+            Commands::RefreshData { role_files } => {
+                handle_refresh_data_command(&aliases::expand_configured(&role_files)?, user_mode).await?
+            }
+            Commands::Harden { role_file, undo } => handle_harden_command(&aliases::expand_one(&role_file)?, undo).await?,
+            Commands::Wordlists { action } => handle_wordlists_command(action, user_mode).await?,
+            Commands::CheckUpdates { notify } => handle_check_updates_command(notify, cli.json).await?,
+            Commands::Freeze => handle_freeze_command().await?,
+            Commands::RetryFailed => handle_retry_failed_command(user_mode).await?,
+            Commands::Install { locked: false } => handle_add_command(&[], user_mode, cli.refreeze).await?,
+            #[cfg(unix)]
+            Commands::Install { locked: true } => handle_install_locked_command().await?,
+            #[cfg(windows)]
+            Commands::Install { locked: true } => return Err(Box::from(t!("install-locked-needs-pacman"))),
+            Commands::Changelog { role_file, limit } => {
+                handle_changelog_command(&aliases::expand_one(&role_file)?, limit, cli.json).await?
+            }
+            Commands::Preset { action } => match action {
+                PresetAction::List => handle_preset_list_command(cli.json).await?,
+                PresetAction::Apply { name } => {
+                    let preset = presets::fetch_preset(&name).await?;
+                    println!("{}", t!("preset-applying", name = name, roles = format!("{:?}", preset.roles)));
+                    handle_add_command(&preset.roles, user_mode, cli.refreeze).await?;
+                }
+            },
+            Commands::Engage { targets } => handle_engage_command(&targets, user_mode, cli.refreeze).await?,
+            Commands::Select => handle_select_command(user_mode, cli.refreeze, cli.force, cli.unpin).await?,
+            Commands::Batch { source } => handle_batch_command(&source, user_mode, cli.refreeze, cli.force, cli.unpin).await?,
+            Commands::WhichRole { tool } => handle_which_role_command(&tool, cli.json).await?,
+            Commands::WhyInstalled { package } => handle_why_installed_command(&package, cli.json).await?,
+            Commands::Expire => handle_expire_command(user_mode, cli.force).await?,
+            Commands::AutoSync => handle_auto_sync_command(user_mode).await?,
+            Commands::Apply => handle_apply_command(user_mode, cli.refreeze).await?,
+            Commands::Leave => handle_leave_command(user_mode, cli.force, cli.unpin).await?,
+            Commands::Config { .. } => {} // Handled before loading the settings
+            Commands::State { action } => handle_state_command(action)?,
+            Commands::Cache { action } => handle_cache_command(action, cli.json)?,
+            Commands::Alias { action } => handle_alias_command(action, cli.json)?,
+            Commands::Tool { action } => handle_tool_command(action, user_mode, cli.refreeze, cli.force, cli.unpin).await?,
+            Commands::Verify => handle_verify_command(cli.json)?,
+            Commands::Report { format, output } => handle_report_command(format, output).await?,
+            Commands::Learn { role, search } => handle_learn_command(&aliases::expand_one(&role)?, search.as_deref(), cli.json).await?,
+            Commands::Licenses { flagged } => handle_licenses_command(flagged, cli.json).await?,
+            Commands::MigrateRoles { repository, check } => handle_migrate_roles_command(&repository, check)?,
+            Commands::PinRole { roles } => handle_pin_role_command(&aliases::expand_configured(&roles)?, cli.json)?,
+            Commands::UnpinRole { roles } => {
+                let unpinned = pins::unpin(&aliases::expand_configured(&roles)?)?;
+                println!("{}", t!("pins-unpinned", roles = format!("{:?}", unpinned)));
+            }
+            Commands::Plugin { name, args } => return Err(handle_plugin_command(&name, &args)),
+            #[cfg(unix)]
+            Commands::Bundle { action } => match action {
+                BundleAction::Create { role_files, wordlists, output } => {
+                    handle_bundle_create_command(&aliases::expand_configured(&role_files)?, &wordlists, &output).await?
+                }
+                BundleAction::Install { file } => handle_bundle_install_command(&file, user_mode, cli.refreeze).await?,
+            },
+            #[cfg(windows)]
+            Commands::Bundle { .. } => return Err(Box::from(t!("bundle-needs-pacman"))),
+            Commands::Fleet { action } => match action {
+                FleetAction::Apply { hosts, copy_binary, canary_percent, canary_tag, smoke_test, max_failures, role_files } => {
+                    let rollout = fleet::Rollout {
+                        canary: canary_percent.map(fleet::Canary::Percent).or(canary_tag.map(fleet::Canary::Tag)),
+                        smoke_tests: smoke_test,
+                        max_failures,
+                    };
+                    handle_fleet_apply_command(&hosts, &aliases::expand_configured(&role_files)?, copy_binary, &rollout)?
+                }
+            },
+        }
+    } else if cli.current {
+        handle_current_command(ListFilter::from_flags(cli.installed_only, cli.missing_only), cli.json).await?;
+    } else if cli.list_all {
+        handle_list_all_command(cli.discover, ListFilter::from_flags(cli.installed_only, cli.missing_only), cli.json).await?;
+    } else if cli.update {
+        handle_update_command(role_files, user_mode, cli.refreeze, cli.force, cli.unpin, cli.keep_existing).await?;
+    } else if cli.remove {
+        println!("{}", t!("executing-remove", roles = format!("{:?}", role_files)));
+        handle_remove_command(role_files, &[], user_mode, cli.force, cli.purge, cli.unpin).await?;
+    } else {
+        println!("{}", t!("executing-add", roles = format!("{:?}", role_files)));
+        handle_add_command(role_files, user_mode, cli.refreeze).await?;
+        match cli.temp {
+            Some(duration) => {
+                let until = chrono::Utc::now() + duration;
+                expiry::set(role_files, until)?;
+                println!("{}", t!("roles-expire", roles = format!("{:?}", role_files), until = until.format("%Y-%m-%d %H:%M UTC").to_string()));
+            }
+            None => expiry::clear(role_files)?,
+        }
+    }
     Ok(())
 }
+
+/// What the command `cli` asks for needs to be protected from (see `run_lock`).
+fn access(cli: &Cli) -> run_lock::Access {
+    use run_lock::Access::{Exclusive, Read, Write};
+    let Some(command) = &cli.command else {
+        return if cli.current || cli.list_all { Read } else { Write };
+    };
+    match command {
+        Commands::Du
+        | Commands::Stats
+        | Commands::Metrics { .. }
+        | Commands::CheckUpdates { .. }
+        | Commands::Changelog { .. }
+        | Commands::Preset { action: PresetAction::List }
+        | Commands::Wordlists { action: WordlistsAction::List }
+        | Commands::WhichRole { .. }
+        | Commands::WhyInstalled { .. }
+        | Commands::State { action: StateAction::Export { .. } }
+        | Commands::Cache { action: CacheAction::Stats }
+        | Commands::Alias { action: AliasAction::List }
+        | Commands::Verify
+        | Commands::Report { .. }
+        | Commands::Learn { .. }
+        | Commands::Licenses { .. }
+        | Commands::MigrateRoles { check: true, .. }
+        | Commands::Fleet { .. }
+        | Commands::Plugin { .. } => Read,
+        Commands::State { action: StateAction::Import { .. } } | Commands::Cache { action: CacheAction::Prune { .. } | CacheAction::Clear } => {
+            Exclusive
+        }
+        _ => Write,
+    }
+}
+
+/// Takes the locks `access` needs for the rest of the run. Without them (e.g. on a read-only
+/// state directory) the run goes ahead unprotected.
+fn lock_run(access: run_lock::Access) -> Option<run_lock::RunLock> {
+    let command = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+    match run_lock::acquire(access, &command) {
+        Ok(lock) => Some(lock),
+        Err(e) => {
+            eprintln!("{}", t!("run-lock-failed", error = e.to_string()));
+            None
+        }
+    }
+}
+
+/// The name a run is reported under in the end-of-run summary (see `notify`) and whether it
+/// installs tools; `None` for commands that don't change the machine.
+fn notified_run(cli: &Cli) -> Option<(&'static str, bool)> {
+    let run = match &cli.command {
+        Some(Commands::RetryFailed) => ("retry-failed", true),
+        Some(Commands::Install { .. }) => ("install", true),
+        Some(Commands::Preset { action: PresetAction::Apply { .. } }) => ("preset apply", true),
+        Some(Commands::Engage { .. }) => ("engage", true),
+        Some(Commands::Select) => ("select", true),
+        Some(Commands::Batch { .. }) => ("batch", true),
+        Some(Commands::Expire) => ("expire", false),
+        Some(Commands::AutoSync) => ("auto-sync", true),
+        Some(Commands::Apply) => ("apply", true),
+        Some(Commands::Leave) => ("leave", false),
+        Some(Commands::Harden { .. }) => ("harden", false),
+        Some(Commands::Tool { action }) => ("tool", matches!(action, ToolAction::Add { .. })),
+        #[cfg(unix)]
+        Some(Commands::Bundle { action: BundleAction::Install { .. } }) => ("bundle install", true),
+        Some(_) => return None,
+        None if cli.current || cli.list_all => return None,
+        None if cli.update => ("update", true),
+        None if cli.remove => ("remove", false),
+        None => ("add", true),
+    };
+    Some(run)
+}
+
+/// Sends the end-of-run summary of `command` if `[notify]` asks for one.
+async fn notify_run(
+    command: &str,
+    installs: bool,
+    roles: &[String],
+    started_at: chrono::DateTime<chrono::Local>,
+    result: &Result<(), Box<dyn std::error::Error>>,
+) {
+    let Ok(settings) = settings::load() else {
+        return;
+    };
+    if !notify::wanted(&settings.notify, result.is_ok()) {
+        return;
+    }
+    let mut summary = notify::RunSummary::new(command, roles, started_at, result.as_ref().err().map(|e| e.to_string()));
+    summary.configured_roles = read_roles_from_config_file().unwrap_or_default();
+    if installs {
+        summary.failed_tools = failures::read().unwrap_or_default();
+    }
+    notify::send(&settings.notify, &summary).await;
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use cyber_toolkit::test_support::FakeBackend;
+    use cyber_toolkit::Backend;
+
+    fn tools(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn named_packages_reads_missing_targets() {
+        let stderr = "error: target not found: ffuf\nerror: target not found: nmap\n";
+        assert_eq!(named_packages(stderr, &tools(&["ffuf", "nmap", "sqlmap"])), ["ffuf", "nmap"]);
+    }
+
+    #[test]
+    fn named_packages_reads_file_conflicts() {
+        let stderr = "error: failed to commit transaction (conflicting files)\n\
+                      nmap: /usr/bin/ncat exists in filesystem\n\
+                      Errors occurred, no packages were upgraded.\n";
+        assert_eq!(named_packages(stderr, &tools(&["ncat", "nmap"])), ["nmap"]);
+    }
+
+    #[test]
+    fn named_packages_reads_preparation_problems() {
+        let stderr = "error: failed to prepare transaction (could not satisfy dependencies)\n\
+                      :: unable to satisfy dependency 'python-lxml' required by sqlmap\n";
+        assert_eq!(named_packages(stderr, &tools(&["python-lxml", "sqlmap"])), ["sqlmap"]);
+        let stderr = "error: failed to prepare transaction (conflicting dependencies)\n\
+                      :: wireshark-cli-4.2.0-1 and wireshark-qt-4.2.0-1 are in conflict\n";
+        assert_eq!(named_packages(stderr, &tools(&["wireshark", "wireshark-cli", "wireshark-qt"])), ["wireshark-cli", "wireshark-qt"]);
+    }
+
+    #[test]
+    fn named_packages_ignores_other_words() {
+        let stderr = "warning: nmap-7.94-1 is up to date -- reinstalling\n\
+                      error: failed to commit transaction (invalid or corrupted package)\n\
+                      error: could not open file /var/cache/pacman/pkg/sqlmap-1.8-1-any.pkg.tar.zst\n";
+        assert!(named_packages(stderr, &tools(&["nmap", "sqlmap"])).is_empty());
+    }
+
+    /// Installs with `backend`, failing like pacman does with a corrupted package: the error
+    /// names none of the packages.
+    async fn install_unnamed(backend: &FakeBackend, chunk: Vec<String>) -> Result<PackageRun, Box<dyn std::error::Error>> {
+        match backend.install(&chunk).await {
+            Ok(()) => Ok(PackageRun::Completed),
+            Err(e) => Err(Box::new(corrupted_package(e.to_string()))),
+        }
+    }
+
+    fn corrupted_package(reason: String) -> failures::CommandFailure {
+        let stderr = "error: failed to commit transaction (invalid or corrupted package)\nErrors occurred, no packages were upgraded.".to_string();
+        failures::CommandFailure::new(reason, &std::process::Command::new("pacman"), Some(1), stderr)
+    }
+
+    #[tokio::test]
+    async fn isolate_failures_bisects_when_the_error_names_no_package() {
+        let backend = FakeBackend::new().fail_on("sqlmap");
+        let tools = tools(&["burpsuite", "ffuf", "nmap", "sqlmap"]);
+        let error = corrupted_package("pacman -S failed".to_string());
+        let failed = isolate_failures(tools, &error, |chunk| install_unnamed(&backend, chunk)).await;
+        assert_eq!(failed.iter().map(|failure| failure.tool.as_str()).collect::<Vec<_>>(), ["sqlmap"]);
+        assert!(failed[0].reason.contains("invalid or corrupted package"));
+        assert_eq!(backend.installed_tools(), ["burpsuite", "ffuf", "nmap"]);
+        assert_eq!(
+            backend.transcript(),
+            "install burpsuite ffuf nmap sqlmap\ninstall burpsuite ffuf\ninstall nmap sqlmap\ninstall nmap\ninstall sqlmap\n"
+        );
+    }
+}
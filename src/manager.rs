@@ -0,0 +1,259 @@
+//! `RoleManager`, the high-level library API: manages the configured roles and their tools.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use cyber_toolkit::RoleManager;
+//!
+//! let manager = RoleManager::builder().dry_run(true).build()?;
+//! let report = manager.add(&["blue".to_string()]).await?;
+//! println!("Would install {:?}", report.tools_installed);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use crate::backend::{Backend, PackageOperation, PacmanBackend};
+use crate::config::ConfigStore;
+#[cfg(feature = "remote")]
+use crate::fetcher::HttpFetcher;
+use crate::fetcher::RoleFetcher;
+use crate::report::{Reporter, SilentReporter};
+use crate::roles::{self, FetchReport, RoleFetchError};
+
+/// Outcome of `add`, `remove` or `set`.
+#[derive(Debug, Default, Clone)]
+pub struct ChangeReport {
+    pub roles_added: Vec<String>,
+    pub roles_removed: Vec<String>,
+    /// Tools installed or updated (or that would be, in a dry run).
+    pub tools_installed: Vec<String>,
+    /// Tools removed because no remaining role needs them.
+    pub tools_removed: Vec<String>,
+    /// Roles that could not be fetched. New roles among them are not added.
+    pub failed: Vec<RoleFetchError>,
+    /// Nothing was installed, removed or written.
+    pub dry_run: bool,
+}
+
+/// Installation state of a configured role.
+#[derive(Debug, Clone)]
+pub struct RoleStatus {
+    pub role: String,
+    pub tools: Vec<String>,
+    pub installed: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+/// Manages the configured roles: fetches their tool lists and installs or removes the tools with
+/// a backend. Build one with `RoleManager::builder()`.
+pub struct RoleManager {
+    fetcher: Box<dyn RoleFetcher>,
+    backend: Box<dyn Backend>,
+    config: ConfigStore,
+    dry_run: bool,
+    reporter: Box<dyn Reporter + Send + Sync>,
+}
+
+/// Configures a `RoleManager`. Unset options default to the official repository (which needs
+/// the `remote` feature), pacman,
+/// `~/.config/cyber-toolkit/roles.cnf`, real changes and no reporting.
+#[derive(Default)]
+pub struct RoleManagerBuilder {
+    repo_url: Option<String>,
+    fetcher: Option<Box<dyn RoleFetcher>>,
+    backend: Option<Box<dyn Backend>>,
+    config_path: Option<PathBuf>,
+    dry_run: bool,
+    reporter: Option<Box<dyn Reporter + Send + Sync>>,
+}
+
+impl RoleManagerBuilder {
+    /// Fetches role files from this base URL instead of the official repository.
+    pub fn repo_url(mut self, url: &str) -> Self {
+        self.repo_url = Some(url.to_string());
+        self
+    }
+
+    /// Fetches role files with a custom fetcher. Takes precedence over `repo_url`.
+    pub fn fetcher(mut self, fetcher: impl RoleFetcher + 'static) -> Self {
+        self.fetcher = Some(Box::new(fetcher));
+        self
+    }
+
+    /// Installs and removes tools with this backend instead of pacman.
+    pub fn backend(mut self, backend: impl Backend + 'static) -> Self {
+        self.backend = Some(Box::new(backend));
+        self
+    }
+
+    /// Keeps the configured roles in this file instead of `~/.config/cyber-toolkit/roles.cnf`.
+    pub fn config_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    /// Computes the changes without installing, removing or writing anything.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Receives progress events.
+    pub fn reporter(mut self, reporter: impl Reporter + Send + Sync + 'static) -> Self {
+        self.reporter = Some(Box::new(reporter));
+        self
+    }
+
+    pub fn build(self) -> Result<RoleManager, Box<dyn std::error::Error>> {
+        let fetcher = match self.fetcher {
+            Some(fetcher) => fetcher,
+            None => default_fetcher(self.repo_url)?,
+        };
+        let config = match self.config_path {
+            Some(path) => ConfigStore::new(path),
+            None => ConfigStore::open_default()?,
+        };
+        Ok(RoleManager {
+            fetcher,
+            backend: self.backend.unwrap_or_else(|| Box::new(PacmanBackend)),
+            config,
+            dry_run: self.dry_run,
+            reporter: self.reporter.unwrap_or_else(|| Box::new(SilentReporter)),
+        })
+    }
+}
+
+/// Fetches over HTTP from `repo_url`, or the official repository.
+#[cfg(feature = "remote")]
+fn default_fetcher(repo_url: Option<String>) -> Result<Box<dyn RoleFetcher>, Box<dyn std::error::Error>> {
+    Ok(match repo_url {
+        Some(url) => Box::new(HttpFetcher::with_base_url(&url)),
+        None => Box::new(HttpFetcher::new()),
+    })
+}
+
+/// Without the `remote` feature there is no HTTP fetcher to fall back to.
+#[cfg(not(feature = "remote"))]
+fn default_fetcher(_repo_url: Option<String>) -> Result<Box<dyn RoleFetcher>, Box<dyn std::error::Error>> {
+    Err(Box::from("No role fetcher configured: set one with `fetcher`, or enable the `remote` feature."))
+}
+
+impl RoleManager {
+    pub fn builder() -> RoleManagerBuilder {
+        RoleManagerBuilder::default()
+    }
+
+    /// Returns the configured roles.
+    pub fn list(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(self.config.read_roles()?)
+    }
+
+    /// Returns which tools of each configured role are installed. Roles that can't be fetched
+    /// are left out.
+    pub async fn status(&self) -> Result<Vec<RoleStatus>, Box<dyn std::error::Error>> {
+        let report = self.fetch(&self.list()?).await;
+        let mut statuses = Vec::new();
+        for role in report.roles {
+            let installed = self.backend.installed(&role.tools).await?;
+            let missing = role.tools.iter().filter(|tool| !installed.contains(tool)).cloned().collect();
+            statuses.push(RoleStatus { role: role.name, tools: role.tools, installed, missing });
+        }
+        Ok(statuses)
+    }
+
+    /// Adds roles to the configuration and installs the tools of all configured roles.
+    pub async fn add(&self, roles: &[String]) -> Result<ChangeReport, Box<dyn std::error::Error>> {
+        let mut desired = self.list()?;
+        for role in roles::canonical_names(roles) {
+            if !desired.contains(&role) {
+                desired.push(role);
+            }
+        }
+        self.apply(desired, true).await
+    }
+
+    /// Removes roles from the configuration and the tools no remaining role needs.
+    pub async fn remove(&self, roles: &[String]) -> Result<ChangeReport, Box<dyn std::error::Error>> {
+        let removed: BTreeSet<String> = roles::canonical_names(roles).into_iter().collect();
+        let desired = self.list()?.into_iter().filter(|role| !removed.contains(role)).collect();
+        self.apply(desired, false).await
+    }
+
+    /// Makes exactly these roles the configured ones: adds the new ones, removes the others and
+    /// their tools, and installs the tools of all of them.
+    pub async fn set(&self, roles: &[String]) -> Result<ChangeReport, Box<dyn std::error::Error>> {
+        self.apply(roles::canonical_names(roles), true).await
+    }
+
+    async fn fetch(&self, roles: &[String]) -> FetchReport {
+        roles::fetch_roles(roles, self.fetcher.as_ref(), self.reporter.as_ref()).await
+    }
+
+    /// Moves the configuration from the current roles to `desired`.
+    ///
+    /// Tools of dropped roles are only removed if every kept role could be fetched; otherwise
+    /// shared tools could be removed by mistake.
+    ///
+    /// The change is a transaction: the tools are installed first and the dropped roles' tools
+    /// removed after that, and `roles.cnf` is only written once both succeeded. A failed install
+    /// removes nothing, and after a failed removal the file still lists the dropped roles, whose
+    /// tools are still there.
+    async fn apply(&self, desired: Vec<String>, install: bool) -> Result<ChangeReport, Box<dyn std::error::Error>> {
+        let configured = self.list()?;
+        let dropped: Vec<String> = configured.iter().filter(|role| !desired.contains(role)).cloned().collect();
+        let desired_report = self.fetch(&desired).await;
+        let dropped_report = self.fetch(&dropped).await;
+
+        let failed_new: Vec<&String> = desired_report
+            .failed
+            .iter()
+            .map(|failure| &failure.role)
+            .filter(|role| !configured.contains(role))
+            .collect();
+        let desired: Vec<String> = desired.into_iter().filter(|role| !failed_new.contains(&role)).collect();
+        let failed_kept = desired_report.failed.iter().any(|failure| configured.contains(&failure.role));
+        if failed_kept && !dropped.is_empty() {
+            return Err(Box::from(
+                "Some configured roles could not be fetched, so the tools they share with removed roles are unknown.",
+            ));
+        }
+
+        let kept_tools: BTreeSet<String> = desired_report.tools().into_iter().collect();
+        let report = ChangeReport {
+            roles_added: desired.iter().filter(|role| !configured.contains(role)).cloned().collect(),
+            roles_removed: dropped,
+            tools_installed: if install { kept_tools.iter().cloned().collect() } else { Vec::new() },
+            tools_removed: dropped_report.tools().into_iter().filter(|tool| !kept_tools.contains(tool)).collect(),
+            failed: desired_report.failed.into_iter().chain(dropped_report.failed).collect(),
+            dry_run: self.dry_run,
+        };
+        if self.dry_run {
+            return Ok(report);
+        }
+
+        if !report.tools_installed.is_empty() {
+            self.reporter.installing_tools(&report.tools_installed);
+            if self.reporter.captures_package_output() {
+                self.backend
+                    .install_with_output(&report.tools_installed, &|stream, line| self.reporter.package_output(PackageOperation::Install, stream, line))
+                    .await?;
+            } else {
+                self.backend.install(&report.tools_installed).await?;
+            }
+        }
+        if !report.tools_removed.is_empty() {
+            self.reporter.removing_tools(&report.tools_removed);
+            if self.reporter.captures_package_output() {
+                self.backend
+                    .remove_with_output(&report.tools_removed, &|stream, line| self.reporter.package_output(PackageOperation::Remove, stream, line))
+                    .await?;
+            } else {
+                self.backend.remove(&report.tools_removed).await?;
+            }
+        }
+        self.config.write_roles(&desired)?;
+        Ok(report)
+    }
+}
@@ -0,0 +1,189 @@
+//! Optional per-role metadata, stored as `meta/<role>.toml` in the repository.
+//!
+//! Role files stay plain tool lists; anything more structured about a role lives here.
+//! A role without a metadata file simply has default (empty) metadata.
+//!
+//! ```toml
+//! format = 1                               # Metadata format; files without it are format 1
+//! description = "Web application testing"  # One-line summary shown by `--list-all`
+//! installed_size_mb = 2300                 # Approximate size of the installed tools
+//!
+//! [[hooks]]
+//! tool = "exploitdb"        # Only runs if this tool is installed
+//! command = "searchsploit -u"
+//! root = true               # Run through pkexec
+//!
+//! [sandbox]
+//! firejail = ["sqlmap"]      # Network-facing tools confined by `cyber-toolkit harden`
+//!
+//! [[dotfiles]]
+//! source = "msfconsole.rc"   # Path under `dotfiles/` in the repository
+//! target = "~/.msf4/msfconsole.rc"
+//!
+//! [python]
+//! requirements = ["bloodhound==1.7.2"]  # Installed into a per-role virtualenv
+//! entry_points = ["bloodhound-python"]  # Optional; defaults to every script pip installs
+//!
+//! [purge]
+//! metasploit = ["~/.msf4"]  # Data deleted by `--remove --purge` after confirmation
+//!
+//! [licenses]
+//! burpsuite = { license = "proprietary", vendor = "PortSwigger" }  # Shown by `licenses`
+//!
+//! [learn]
+//! sqlmap = "Automates finding and exploiting SQL injection..."  # Shown by `learn`
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use cyber_toolkit::http;
+
+use crate::i18n::t;
+use crate::state;
+
+/// Base URL from which role metadata files are fetched.
+const META_RAW_URL: &str = "https://raw.githubusercontent.com/jakubGodula/cyber-toolkit/main/meta/";
+
+/// Newest metadata format this version understands. Metadata in a newer format is refused, since
+/// sections it doesn't know would silently be ignored.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Structured information about a role.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct RoleMetadata {
+    /// Format of the metadata (see `FORMAT_VERSION`).
+    pub format: Option<u32>,
+    /// One-line summary of the role, shown by `--list-all`.
+    pub description: Option<String>,
+    /// Approximate installed size of the role's tools in MiB, shown by `--list-all`.
+    pub installed_size_mb: Option<u64>,
+    /// Data refresh commands run after installs/updates or via `refresh-data`.
+    pub hooks: Vec<UpdateHook>,
+    pub sandbox: SandboxMetadata,
+    /// Configuration files deployed to the user's home with the role.
+    pub dotfiles: Vec<DotfileSpec>,
+    pub python: PythonMetadata,
+    /// Configuration and data paths of each tool (keyed by role entry), deleted by `--purge`.
+    pub purge: BTreeMap<String, Vec<String>>,
+    /// License and vendor of tools (keyed by role entry), overriding what the package declares.
+    pub licenses: BTreeMap<String, LicenseMetadata>,
+    /// Longer explanations of tools for students (keyed by role entry), shown by `learn`.
+    pub learn: BTreeMap<String, String>,
+}
+
+/// License information about a tool, for tools whose package declares none or only `custom`
+/// (e.g. wrappers of commercial tools).
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct LicenseMetadata {
+    /// License identifier, e.g. `proprietary` or `GPL-2.0-or-later`.
+    pub license: Option<String>,
+    /// Who makes the tool, e.g. `PortSwigger`.
+    pub vendor: Option<String>,
+}
+
+/// `[python]` section: Python tools installed into the role's own virtualenv.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct PythonMetadata {
+    /// pip requirement specifiers.
+    pub requirements: Vec<String>,
+    /// Commands to expose on `PATH`. When empty, every script pip installs is exposed.
+    pub entry_points: Vec<String>,
+}
+
+/// A configuration file carried by a role.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DotfileSpec {
+    /// Template path relative to `dotfiles/` in the repository.
+    pub source: String,
+    /// Destination path; a leading `~/` refers to the user's home.
+    pub target: String,
+}
+
+/// `[sandbox]` section: tools to confine when the role is hardened.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct SandboxMetadata {
+    /// Tools to run under firejail.
+    pub firejail: Vec<String>,
+}
+
+/// A command that refreshes a tool's data (e.g. `searchsploit -u`, `nuclei -update-templates`).
+#[derive(Deserialize, Debug, Clone)]
+pub struct UpdateHook {
+    /// Tool the hook belongs to; the hook is skipped if it isn't installed.
+    pub tool: String,
+    /// Shell command to run.
+    pub command: String,
+    /// Whether the command needs root privileges (run through `pkexec`).
+    #[serde(default)]
+    pub root: bool,
+}
+
+/// Fetches the raw TOML metadata of a role. A missing metadata file (HTTP 404) yields an empty
+/// document, which parses to default metadata.
+///
+/// The metadata is cached next to the role file (`<role>.toml`); in offline mode, or while the
+/// repository rate-limits us, the cached copy is used instead.
+pub async fn fetch_role_metadata_source(role_file_name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let cache_name = format!("{}.toml", role_file_name.trim());
+    if http::is_offline() {
+        return Ok(state::read_cached_role(&cache_name)?
+            .ok_or_else(|| t!("metadata-not-cached", role = role_file_name.trim()))?);
+    }
+    let content = state::or_cached_when_rate_limited(fetch_source(role_file_name).await, &cache_name)?;
+    if let Err(e) = state::write_cached_role(&cache_name, &content) {
+        eprintln!("{}", t!("metadata-cache-failed", role = role_file_name.trim(), error = e.to_string()));
+    }
+    Ok(content)
+}
+
+async fn fetch_source(role_file_name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let url = format!("{}{}.toml", META_RAW_URL, role_file_name.trim());
+    let response = http::get(&url)?.send().await?;
+    http::check_response(&response)?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(String::new());
+    }
+    if !response.status().is_success() {
+        return Err(Box::from(t!("metadata-fetch-failed", url = url, status = response.status().to_string())));
+    }
+    Ok(response.text().await?)
+}
+
+/// Format a metadata document declares with its top-level `format` key, or 1 if it declares none.
+pub fn format_version(content: &str) -> Result<u32, Box<dyn std::error::Error>> {
+    let document: toml::Table = toml::from_str(content)?;
+    match document.get("format") {
+        None => Ok(1),
+        Some(toml::Value::Integer(version)) => u32::try_from(*version).map_err(|_| Box::from(t!("metadata-format-invalid", format = version.to_string()))),
+        Some(value) => Err(Box::from(t!("metadata-format-invalid", format = value.to_string()))),
+    }
+}
+
+/// Fetches and parses the metadata of a role (see `fetch_role_metadata_source`). Metadata in a
+/// newer format than `FORMAT_VERSION` is refused.
+pub async fn fetch_role_metadata(role_file_name: &str) -> Result<RoleMetadata, Box<dyn std::error::Error>> {
+    let content = fetch_role_metadata_source(role_file_name).await?;
+    let version = format_version(&content).map_err(|e| t!("metadata-invalid", role = role_file_name.trim(), error = e.to_string()))?;
+    if version > FORMAT_VERSION {
+        return Err(Box::from(t!("metadata-format-too-new", role = role_file_name.trim(), format = version, known = FORMAT_VERSION)));
+    }
+    toml::from_str(&content).map_err(|e| Box::from(t!("metadata-invalid", role = role_file_name.trim(), error = e.to_string())))
+}
+
+/// Fetches the metadata of each role. Roles whose metadata can't be fetched are reported and skipped.
+pub async fn fetch_all(roles: &[String]) -> Vec<(String, RoleMetadata)> {
+    let mut all = Vec::with_capacity(roles.len());
+    for role in roles {
+        match fetch_role_metadata(role).await {
+            Ok(role_metadata) => all.push((role.clone(), role_metadata)),
+            Err(e) => eprintln!("{}", t!("metadata-fetch-warning", role = role.as_str(), error = e.to_string())),
+        }
+    }
+    all
+}
@@ -0,0 +1,90 @@
+//! Health metrics of the toolkit in the Prometheus text format, for monitoring a lab fleet.
+//!
+//! `cyber-toolkit metrics` prints them, and `--textfile` writes them for node_exporter's textfile
+//! collector. Nothing is sent anywhere and no network is used: the metrics are computed from the
+//! cached role files and the local state only, so they are cheap enough to refresh from a timer.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Tools of one configured role.
+#[derive(Debug)]
+pub struct RoleMetrics {
+    pub role: String,
+    pub tools: usize,
+    pub installed: usize,
+}
+
+/// Everything `render` exports.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub roles: Vec<RoleMetrics>,
+    /// Configured roles whose role file isn't cached (never fetched) or can't be read.
+    pub unavailable_roles: usize,
+    /// Unique tools across the roles.
+    pub tools: usize,
+    pub installed_tools: usize,
+    /// Unix time of the last successful add/sync.
+    pub last_sync: Option<i64>,
+    /// Tools that failed to install in the last run.
+    pub last_run_failures: usize,
+    pub cache_size_bytes: u64,
+}
+
+/// Renders `metrics` in the Prometheus text exposition format.
+pub fn render(metrics: &Metrics) -> String {
+    let mut text = String::new();
+    let configured = metrics.roles.len() + metrics.unavailable_roles;
+    gauge(&mut text, "configured_roles", "Roles configured on this host.", &[(None, configured as u64)]);
+    gauge(&mut text, "unavailable_roles", "Configured roles whose role file isn't cached.", &[(None, metrics.unavailable_roles as u64)]);
+    gauge(&mut text, "tools", "Unique tools of the configured roles.", &[(None, metrics.tools as u64)]);
+    gauge(&mut text, "installed_tools", "Tools of the configured roles that are installed.", &[(None, metrics.installed_tools as u64)]);
+    gauge(
+        &mut text,
+        "missing_tools",
+        "Tools of the configured roles that aren't installed.",
+        &[(None, metrics.tools.saturating_sub(metrics.installed_tools) as u64)],
+    );
+    let per_role = |count: fn(&RoleMetrics) -> usize| -> Vec<(Option<&str>, u64)> {
+        metrics.roles.iter().map(|role| (Some(role.role.as_str()), count(role) as u64)).collect()
+    };
+    gauge(&mut text, "role_tools", "Tools of each configured role.", &per_role(|role| role.tools));
+    gauge(&mut text, "role_missing_tools", "Tools of each configured role that aren't installed.", &per_role(|role| role.tools - role.installed));
+    gauge(
+        &mut text,
+        "last_sync_timestamp_seconds",
+        "Unix time of the last successful add or sync, 0 if there was none.",
+        &[(None, metrics.last_sync.unwrap_or(0).max(0) as u64)],
+    );
+    gauge(&mut text, "last_run_failures", "Tools that failed to install in the last run.", &[(None, metrics.last_run_failures as u64)]);
+    gauge(&mut text, "cache_size_bytes", "Size of the role cache.", &[(None, metrics.cache_size_bytes)]);
+    text
+}
+
+/// Appends a gauge with its `HELP` and `TYPE` lines, one sample per value; a value with a role
+/// gets a `role` label.
+fn gauge(text: &mut String, name: &str, help: &str, values: &[(Option<&str>, u64)]) {
+    let name = format!("cyber_toolkit_{}", name);
+    text.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+    for (role, value) in values {
+        match role {
+            Some(role) => text.push_str(&format!("{}{{role=\"{}\"}} {}\n", name, escape_label(role), value)),
+            None => text.push_str(&format!("{} {}\n", name, value)),
+        }
+    }
+}
+
+/// Escapes a label value as the text format requires.
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Writes `text` to `path` for the textfile collector. The file is written under a temporary
+/// name next to it and renamed, so the collector never reads half a file.
+pub fn write_textfile(path: &Path, text: &str) -> Result<(), io::Error> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&temporary, text)?;
+    fs::rename(&temporary, path)
+}
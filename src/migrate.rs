@@ -0,0 +1,226 @@
+//! `migrate-roles`: brings the role files and metadata of a repository checkout to the formats
+//! this version writes, for maintainers.
+//!
+//! Every role file gets a `#!format <N>` first line and every metadata file a top-level
+//! `format = <N>`, replacing an older declaration. Format 1 is the first versioned format, so
+//! there is nothing else to convert yet; later formats add their conversion steps to
+//! `migrate_role` and `migrate_metadata`. Files in a newer format than this version understands
+//! are reported and left alone. If `roles/MANIFEST.sha256` exists, the checksums of the rewritten
+//! role files are updated in it, so clients keep verifying them.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use cyber_toolkit::role_file;
+
+use crate::checksums;
+use crate::i18n::t;
+use crate::lock;
+use crate::metadata;
+
+/// A file that needs (or got) a new format.
+#[derive(Debug)]
+pub struct Migration {
+    pub path: PathBuf,
+    /// Format the file was in.
+    pub from: u32,
+    pub to: u32,
+}
+
+/// What `run` found.
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub migrations: Vec<Migration>,
+    /// Files that can't be migrated, with the reason.
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+/// Migrates the role files under `roles/` and the metadata under `meta/` of `repository`.
+/// With `check`, nothing is written; the outcome lists what would change.
+pub fn run(repository: &Path, check: bool) -> Result<Outcome, Box<dyn std::error::Error>> {
+    let roles_dir = repository.join("roles");
+    let meta_dir = repository.join("meta");
+    if !roles_dir.is_dir() && !meta_dir.is_dir() {
+        return Err(Box::from(t!("migrate-not-a-repository", path = repository.display().to_string())));
+    }
+
+    let mut outcome = Outcome::default();
+    let mut checksums = HashMap::new();
+    for path in files(&roles_dir)? {
+        let name = file_name(&path);
+        if name == "role_names" || name == checksums::MANIFEST_FILE {
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        match role_file::format_version(&content) {
+            Ok(version) if version > role_file::FORMAT_VERSION => outcome.errors.push((path, newer_format(version, role_file::FORMAT_VERSION))),
+            Ok(version) => {
+                let migrated = migrate_role(&content);
+                if migrated != content {
+                    if !check {
+                        fs::write(&path, &migrated)?;
+                        checksums.insert(name, lock::hash_content(&migrated));
+                    }
+                    outcome.migrations.push(Migration { path, from: version, to: role_file::FORMAT_VERSION });
+                }
+            }
+            Err(e) => outcome.errors.push((path, e.to_string())),
+        }
+    }
+    for path in files(&meta_dir)?.into_iter().filter(|path| path.extension().is_some_and(|extension| extension == "toml")) {
+        let content = fs::read_to_string(&path)?;
+        match metadata::format_version(&content) {
+            Ok(version) if version > metadata::FORMAT_VERSION => outcome.errors.push((path, newer_format(version, metadata::FORMAT_VERSION))),
+            Ok(version) => {
+                let migrated = migrate_metadata(&content);
+                if migrated != content {
+                    if !check {
+                        fs::write(&path, &migrated)?;
+                    }
+                    outcome.migrations.push(Migration { path, from: version, to: metadata::FORMAT_VERSION });
+                }
+            }
+            Err(e) => outcome.errors.push((path, e.to_string())),
+        }
+    }
+
+    let manifest = roles_dir.join(checksums::MANIFEST_FILE);
+    if !checksums.is_empty() && manifest.is_file() {
+        let content = fs::read_to_string(&manifest)?;
+        fs::write(&manifest, update_manifest(&content, &checksums))?;
+    }
+    Ok(outcome)
+}
+
+/// Files directly in `dir`, sorted; none if it doesn't exist. Hidden files are skipped.
+fn files(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && !entry.file_name().to_string_lossy().starts_with('.') {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn newer_format(version: u32, supported: u32) -> String {
+    t!("migrate-newer-format", version = version, supported = supported)
+}
+
+/// Declares the current format on the first line of a role file, replacing an older marker and
+/// dropping a byte order mark. Keeps the file's line endings.
+fn migrate_role(content: &str) -> String {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let body = match content.lines().next() {
+        Some(first) if first.trim().starts_with(role_file::FORMAT_MARKER) => content[first.len()..].trim_start_matches(['\r', '\n']),
+        _ => content,
+    };
+    format!("{} {}{}{}", role_file::FORMAT_MARKER, role_file::FORMAT_VERSION, newline, body)
+}
+
+/// Declares the current format with a top-level `format` key, replacing an older one. The key is
+/// added as the first line, where it is outside of any table.
+fn migrate_metadata(content: &str) -> String {
+    let declaration = format!("format = {}", metadata::FORMAT_VERSION);
+    let mut lines: Vec<&str> = content.lines().collect();
+    // Only keys before the first table header are top-level
+    let top_level = lines.iter().position(|line| line.trim_start().starts_with('[')).unwrap_or(lines.len());
+    match lines[..top_level].iter().position(|line| line.split_once('=').is_some_and(|(key, _)| key.trim() == "format")) {
+        Some(index) if lines[index].trim() == declaration => return content.to_string(),
+        Some(index) => lines[index] = &declaration,
+        None => lines.insert(0, &declaration),
+    }
+    let mut migrated = lines.join("\n");
+    migrated.push('\n');
+    migrated
+}
+
+/// Replaces the checksums of the role files in `checksums` in the `sha256sum` output `manifest`.
+fn update_manifest(manifest: &str, checksums: &HashMap<String, String>) -> String {
+    let mut updated = String::with_capacity(manifest.len());
+    for line in manifest.lines() {
+        let entry = checksums::parse(line).into_iter().next();
+        match entry.as_ref().and_then(|(name, _)| checksums.get(name).map(|hash| (name, hash))) {
+            Some((name, hash)) => updated.push_str(&format!("{}  {}", hash, name)),
+            None => updated.push_str(line),
+        }
+        updated.push('\n');
+    }
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_role_declares_the_format_once() {
+        assert_eq!(migrate_role("nmap\nsqlmap\n"), "#!format 1\nnmap\nsqlmap\n");
+        assert_eq!(migrate_role("#!format 0\n\nnmap\n"), "#!format 1\nnmap\n");
+        assert_eq!(migrate_role("#!format 1\nnmap\n"), "#!format 1\nnmap\n");
+    }
+
+    #[test]
+    fn migrate_role_drops_the_byte_order_mark_and_keeps_crlf() {
+        assert_eq!(migrate_role("\u{feff}# Web\r\nffuf\r\n"), "#!format 1\r\n# Web\r\nffuf\r\n");
+    }
+
+    #[test]
+    fn migrate_metadata_sets_the_top_level_format() {
+        assert_eq!(migrate_metadata("description = \"Web\"\n"), "format = 1\ndescription = \"Web\"\n");
+        assert_eq!(migrate_metadata("format = 0\n[tools]\nformat = 3\n"), "format = 1\n[tools]\nformat = 3\n");
+        // A `format` inside a table isn't the file's
+        assert_eq!(migrate_metadata("[tools]\nformat = 1\n"), "format = 1\n[tools]\nformat = 1\n");
+        assert_eq!(migrate_metadata("format = 1\n[tools]\n"), "format = 1\n[tools]\n");
+    }
+
+    #[test]
+    fn run_migrates_old_files_and_leaves_newer_ones_alone() {
+        let repository = tempfile::tempdir().unwrap();
+        let (roles, meta) = (repository.path().join("roles"), repository.path().join("meta"));
+        fs::create_dir_all(&roles).unwrap();
+        fs::create_dir_all(&meta).unwrap();
+        fs::write(roles.join("web"), "ffuf\n").unwrap();
+        fs::write(roles.join("red"), "#!format 1\nnmap\n").unwrap();
+        fs::write(roles.join("future"), "#!format 99\nnmap\n").unwrap();
+        fs::write(roles.join("broken"), "#!format one\nnmap\n").unwrap();
+        fs::write(meta.join("web.toml"), "description = \"Web\"\n").unwrap();
+        let manifest = format!("{}  web\n{}  red\n", lock::hash_content("ffuf\n"), lock::hash_content("#!format 1\nnmap\n"));
+        fs::write(roles.join(checksums::MANIFEST_FILE), &manifest).unwrap();
+
+        let checked = run(repository.path(), true).unwrap();
+        let migrated: Vec<String> = checked.migrations.iter().map(|migration| file_name(&migration.path)).collect();
+        assert_eq!(migrated, ["web", "web.toml"]);
+        let failed: Vec<String> = checked.errors.iter().map(|(path, _)| file_name(path)).collect();
+        assert_eq!(failed, ["broken", "future"]);
+        assert_eq!(fs::read_to_string(roles.join("web")).unwrap(), "ffuf\n");
+
+        let outcome = run(repository.path(), false).unwrap();
+        assert_eq!(outcome.migrations.len(), 2);
+        assert_eq!(fs::read_to_string(roles.join("web")).unwrap(), "#!format 1\nffuf\n");
+        assert_eq!(fs::read_to_string(roles.join("future")).unwrap(), "#!format 99\nnmap\n");
+        assert_eq!(fs::read_to_string(meta.join("web.toml")).unwrap(), "format = 1\ndescription = \"Web\"\n");
+        let manifest = checksums::parse(&fs::read_to_string(roles.join(checksums::MANIFEST_FILE)).unwrap());
+        assert_eq!(manifest["web"], lock::hash_content("#!format 1\nffuf\n"));
+        assert_eq!(manifest["red"], lock::hash_content("#!format 1\nnmap\n"));
+
+        assert!(run(repository.path(), false).unwrap().migrations.is_empty());
+    }
+
+    #[test]
+    fn run_refuses_a_directory_without_roles_or_metadata() {
+        let repository = tempfile::tempdir().unwrap();
+        assert!(run(repository.path(), true).is_err());
+    }
+}
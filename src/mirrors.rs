@@ -0,0 +1,62 @@
+//! Refreshing the pacman mirrorlist before large installs.
+//!
+//! Fresh machines often ship a stale mirrorlist, which makes multi-gigabyte role installs slow.
+//! When enabled in the `[mirrors]` settings and the pending download is large enough, `reflector`
+//! ranks the most recently synced HTTPS mirrors by speed and rewrites the mirrorlist.
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use cyber_toolkit::http;
+
+use crate::i18n::t;
+use crate::settings::MirrorSettings;
+use crate::{pacman, platform};
+
+/// The system mirrorlist rewritten by `reflector`.
+const MIRRORLIST: &str = "/etc/pacman.d/mirrorlist";
+
+/// Ranks mirrors if the settings ask for it and installing `packages` downloads enough.
+///
+/// Failures are reported as warnings; the install proceeds with the existing mirrorlist.
+/// Offline, nothing is ranked: reflector needs the network.
+pub fn rank_if_needed(settings: &MirrorSettings, packages: &[String]) {
+    if !settings.rank_before_install || packages.is_empty() || http::is_offline() {
+        return;
+    }
+    let installed = pacman::query_installed(packages).unwrap_or_default();
+    let missing: Vec<String> = packages.iter().filter(|p| !installed.contains_key(*p)).cloned().collect();
+    let download_size = pacman::query_download_size(&missing).unwrap_or(0);
+    if download_size < settings.threshold_mb * 1024 * 1024 {
+        return;
+    }
+
+    let max_age = Duration::from_secs(settings.max_age_days * 24 * 60 * 60);
+    let age = fs::metadata(MIRRORLIST)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    if matches!(age, Some(age) if age < max_age) {
+        return;
+    }
+    if !Path::new("/usr/bin/reflector").exists() {
+        eprintln!("{}", t!("mirrors-no-reflector"));
+        return;
+    }
+
+    println!("{}", t!("mirrors-ranking", size = pacman::format_size(download_size)));
+    let mut args: Vec<String> = ["--latest", "20", "--protocol", "https", "--sort", "rate", "--save", MIRRORLIST]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    if !settings.countries.is_empty() {
+        args.push("--country".to_string());
+        args.push(settings.countries.join(","));
+    }
+    match platform::elevated("/usr/bin/reflector", &args).status() {
+        Ok(status) if status.success() => println!("{}", t!("mirrors-updated")),
+        Ok(status) => eprintln!("{}", t!("mirrors-reflector-failed", code = format!("{:?}", status.code()))),
+        Err(e) => eprintln!("{}", t!("mirrors-reflector-not-run", error = e.to_string())),
+    }
+}
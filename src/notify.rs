@@ -0,0 +1,266 @@
+//! End-of-run summaries of commands that change the machine (add/sync, remove, update, auto-sync,
+//! ...), for labs that want to hear about unattended runs. With `[notify]` configured, the
+//! summary is POSTed as JSON to the `webhook` and mailed to the `email` addresses, with the same
+//! JSON attached:
+//!
+//! ```toml
+//! [notify]
+//! webhook = "https://chat.example.org/hooks/lab"
+//! email = ["lab-admins@example.org"]
+//! smtp_url = "smtp://relay.lab:25"
+//! failures_only = true
+//! ```
+//!
+//! Mail goes to the local `sendmail` (`sendmail -t -i`, which Postfix, exim and msmtp all
+//! provide), or with `smtp_url` straight to a relay through curl, for labs where chat integrations
+//! aren't allowed but a mail relay is reachable. Sending is best effort: failures are reported as
+//! warnings and never change the outcome of the run.
+
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use cyber_toolkit::http;
+use cyber_toolkit::paths;
+
+use crate::audit;
+use crate::failures::FailedTool;
+use crate::i18n::t;
+use crate::settings::NotifySettings;
+
+/// Environment variable holding the password for `smtp_user`, kept out of `config.toml`.
+pub const SMTP_PASSWORD_VAR: &str = "CYBER_TOOLKIT_SMTP_PASSWORD";
+
+const ATTACHMENT_NAME: &str = "cyber-toolkit-summary.json";
+
+/// What a run did, as sent to the webhook and attached to the mail.
+#[derive(Serialize, Debug)]
+pub struct RunSummary {
+    pub command: String,
+    /// Roles named on the command line.
+    pub roles: Vec<String>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub host: String,
+    pub user: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub duration_secs: i64,
+    /// Roles configured when the run finished.
+    pub configured_roles: Vec<String>,
+    /// Tools that failed to install, for runs that install tools.
+    pub failed_tools: Vec<FailedTool>,
+    pub version: String,
+}
+
+impl RunSummary {
+    pub fn new(command: &str, roles: &[String], started_at: DateTime<Local>, error: Option<String>) -> Self {
+        let finished_at = Local::now();
+        RunSummary {
+            command: command.to_string(),
+            roles: roles.to_vec(),
+            success: error.is_none(),
+            error,
+            host: audit::hostname().to_string(),
+            user: audit::user().to_string(),
+            started_at: started_at.to_rfc3339(),
+            finished_at: finished_at.to_rfc3339(),
+            duration_secs: (finished_at - started_at).num_seconds(),
+            configured_roles: Vec::new(),
+            failed_tools: Vec::new(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    fn subject(&self) -> String {
+        let outcome = if !self.success {
+            t!("notify-outcome-failed")
+        } else if !self.failed_tools.is_empty() {
+            t!("notify-outcome-failures")
+        } else {
+            t!("notify-outcome-succeeded")
+        };
+        t!("notify-subject", host = self.host.as_str(), command = self.command.as_str(), outcome = outcome)
+    }
+
+    /// The summary for people, the body of the mail.
+    fn text(&self) -> String {
+        let mut text = t!("notify-text-header", command = self.command.as_str(), host = self.host.as_str(), user = self.user.as_str()) + "\n";
+        if !self.roles.is_empty() {
+            text.push_str(&(t!("notify-text-roles", roles = self.roles.join(", ")) + "\n"));
+        }
+        text.push_str(&(t!("notify-text-times", started = self.started_at.as_str(), finished = self.finished_at.as_str(), seconds = self.duration_secs) + "\n"));
+        match &self.error {
+            Some(error) => text.push_str(&(t!("notify-text-failed", error = error.as_str()) + "\n")),
+            None => text.push_str(&(t!("notify-text-succeeded") + "\n")),
+        }
+        text.push_str(&(t!("notify-text-configured", roles = self.configured_roles.join(", ")) + "\n"));
+        if !self.failed_tools.is_empty() {
+            text.push_str(&(t!("notify-text-failed-tools", count = self.failed_tools.len()) + "\n"));
+            for failure in &self.failed_tools {
+                text.push_str(&format!("  - {}: {}\n", failure.tool, failure.reason));
+            }
+        }
+        text.push_str(&(t!("notify-text-attachment", file = ATTACHMENT_NAME) + "\n"));
+        text
+    }
+}
+
+/// Whether `settings` send anything about a run that succeeded (`success`) or not.
+pub fn wanted(settings: &NotifySettings, success: bool) -> bool {
+    (settings.webhook.is_some() || !settings.email.is_empty()) && !(success && settings.failures_only)
+}
+
+/// Sends `summary` to the configured webhook and addresses, warning about what couldn't be sent.
+pub async fn send(settings: &NotifySettings, summary: &RunSummary) {
+    let json = match serde_json::to_string_pretty(summary) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("{}", t!("notify-serialize-failed", error = e.to_string()));
+            return;
+        }
+    };
+    if let Some(url) = &settings.webhook {
+        if let Err(e) = post_webhook(url, &json).await {
+            eprintln!("{}", t!("notify-webhook-failed", url = url.as_str(), error = e.to_string()));
+        }
+    }
+    if !settings.email.is_empty() {
+        if let Err(e) = send_mail(settings, summary, &json) {
+            eprintln!("{}", t!("notify-mail-failed", recipients = settings.email.join(", "), error = e.to_string()));
+        }
+    }
+}
+
+async fn post_webhook(url: &str, json: &str) -> Result<(), Box<dyn std::error::Error>> {
+    http::check_request(url)?;
+    let response = http::client()
+        .post(url)
+        .timeout(http::settings().request_timeout)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(json.to_string())
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(Box::from(format!("HTTP {}", response.status())));
+    }
+    Ok(())
+}
+
+fn send_mail(settings: &NotifySettings, summary: &RunSummary, json: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let from = settings.from.clone().unwrap_or_else(|| format!("cyber-toolkit@{}", summary.host));
+    let message = message(&from, &settings.email, summary, json);
+    match &settings.smtp_url {
+        Some(url) => send_smtp(settings, url, &from, &message),
+        None => send_sendmail(&message),
+    }
+}
+
+/// Builds the mail: the summary as text, and the JSON as an attachment.
+fn message(from: &str, to: &[String], summary: &RunSummary, json: &str) -> String {
+    let boundary = format!("cyber-toolkit-{}-{}", std::process::id(), Local::now().timestamp_nanos_opt().unwrap_or_default());
+    let mut message = String::new();
+    message.push_str(&format!("From: {}\n", from));
+    message.push_str(&format!("To: {}\n", to.join(", ")));
+    message.push_str(&format!("Subject: {}\n", summary.subject()));
+    message.push_str(&format!("Date: {}\n", Local::now().to_rfc2822()));
+    message.push_str("MIME-Version: 1.0\n");
+    message.push_str(&format!("Content-Type: multipart/mixed; boundary=\"{}\"\n\n", boundary));
+    message.push_str(&format!("--{}\n", boundary));
+    message.push_str("Content-Type: text/plain; charset=utf-8\nContent-Transfer-Encoding: 8bit\n\n");
+    message.push_str(&summary.text());
+    message.push_str(&format!("\n--{}\n", boundary));
+    message.push_str(&format!("Content-Type: application/json; name=\"{}\"\n", ATTACHMENT_NAME));
+    message.push_str(&format!("Content-Disposition: attachment; filename=\"{}\"\n", ATTACHMENT_NAME));
+    message.push_str("Content-Transfer-Encoding: base64\n\n");
+    let encoded = base64(json.as_bytes());
+    for line in encoded.as_bytes().chunks(76) {
+        message.push_str(&String::from_utf8_lossy(line));
+        message.push('\n');
+    }
+    message.push_str(&format!("--{}--\n", boundary));
+    message
+}
+
+fn send_sendmail(message: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new("sendmail")
+        .args(["-t", "-i"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| t!("notify-sendmail-missing", error = e.to_string()))?;
+    child.stdin.take().ok_or_else(|| t!("notify-no-stdin", program = "sendmail"))?.write_all(message.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(Box::from(t!("notify-program-failed", program = "sendmail", code = format!("{:?}", status.code()))));
+    }
+    Ok(())
+}
+
+/// Sends the mail to the relay at `url` with curl. The options, including the credentials, go
+/// to curl as a config file on stdin, so the password doesn't show up in the process list.
+fn send_smtp(settings: &NotifySettings, url: &str, from: &str, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = paths::state_dir()?.join(format!("summary-{}.eml", std::process::id()));
+    fs::write(&path, message)?;
+    let mut config = vec![
+        format!("url = {}", quote(url)),
+        format!("mail-from = {}", quote(from)),
+        format!("upload-file = {}", quote(&path.to_string_lossy())),
+        // Mail lines end in CRLF on the wire
+        "crlf".to_string(),
+        "silent".to_string(),
+        "show-error".to_string(),
+        if settings.require_tls { "ssl-reqd" } else { "ssl" }.to_string(),
+    ];
+    for address in &settings.email {
+        config.push(format!("mail-rcpt = {}", quote(address)));
+    }
+    if let Some(user) = &settings.smtp_user {
+        let password = std::env::var(SMTP_PASSWORD_VAR).map_err(|_| t!("notify-smtp-password-missing", variable = SMTP_PASSWORD_VAR))?;
+        config.push(format!("user = {}", quote(&format!("{}:{}", user, password))));
+    }
+
+    let result = run_curl(&config.join("\n"));
+    let _ = fs::remove_file(&path);
+    result
+}
+
+fn run_curl(config: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new("curl")
+        .args(["--config", "-"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| t!("notify-curl-missing", error = e.to_string()))?;
+    child.stdin.take().ok_or_else(|| t!("notify-no-stdin", program = "curl"))?.write_all(config.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(Box::from(t!("notify-program-failed", program = "curl", code = format!("{:?}", status.code()))));
+    }
+    Ok(())
+}
+
+/// Quotes a value for a curl config file.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Standard base64 with padding, for the attachment.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let group = (u32::from(bytes[0]) << 16) | (u32::from(bytes[1]) << 8) | u32::from(bytes[2]);
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * index) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
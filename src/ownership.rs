@@ -0,0 +1,114 @@
+//! Who put a tool on the machine, for `cyber-toolkit why-installed` and for removal, which leaves
+//! tools the user had installed themselves alone (unless `--remove-user-installed` is given).
+//!
+//! pacman only knows whether a package was explicitly installed or pulled in as a dependency.
+//! The packages the toolkit installs are explicitly installed too (and dependencies a role lists
+//! are marked so with `pacman -D --asexplicit`); what tells them apart from the user's own is the
+//! origin in the install records (see `lock`), taken from pacman's install reason before the
+//! toolkit touched the package.
+//!
+//! Records written before origins were recorded, and packages without a record, are judged by
+//! dates instead: an explicitly installed package whose install date is older than the toolkit's
+//! first trace of it (its install record, or the first install event in the audit log) was there
+//! before any role asked for it. pacman's date is that of the installed version, so a package the
+//! user upgraded since then can't be told apart this way and counts as the toolkit's.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone};
+use serde::Serialize;
+
+use crate::i18n::t;
+use crate::lock::{InstalledTool, Origin};
+use crate::pacman::InstalledPackage;
+
+static REMOVE_USER_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Lets removal uninstall (`true`) tools the user had installed themselves.
+pub fn set_remove_user_installed(remove: bool) {
+    REMOVE_USER_INSTALLED.store(remove, Ordering::Relaxed);
+}
+
+pub fn removes_user_installed() -> bool {
+    REMOVE_USER_INSTALLED.load(Ordering::Relaxed)
+}
+
+/// Why a tool is installed, as `why-installed` reports it.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Attribution {
+    /// The toolkit installed it for a role.
+    Toolkit,
+    /// The user installed it themselves.
+    User,
+    /// Another package needs it.
+    Dependency,
+    NotInstalled,
+}
+
+impl Attribution {
+    pub fn describe(self) -> String {
+        match self {
+            Attribution::Toolkit => t!("attribution-toolkit"),
+            Attribution::User => t!("attribution-user"),
+            Attribution::Dependency => t!("attribution-dependency"),
+            Attribution::NotInstalled => t!("attribution-not-installed"),
+        }
+    }
+}
+
+/// Attributes an installed tool. `package` is pacman's entry for it, `None` for tools pacman
+/// doesn't manage; `first_install` the time of its first install event in the audit log.
+pub fn attribute(installed: bool, package: Option<&InstalledPackage>, record: Option<&InstalledTool>, first_install: Option<&str>) -> Attribution {
+    if !installed {
+        return Attribution::NotInstalled;
+    }
+    if user_installed(package, record, first_install) {
+        return Attribution::User;
+    }
+    match (record.and_then(|record| record.origin), package) {
+        (Some(Origin::Dependency), _) => Attribution::Dependency,
+        (None, Some(package)) if !package.explicit => Attribution::Dependency,
+        _ => Attribution::Toolkit,
+    }
+}
+
+/// Whether the user had installed a tool themselves before a role listed it. The recorded origin
+/// decides if there is one; otherwise only explicitly installed pacman packages qualify, by their
+/// install date (see the module documentation). Containers and Go tools are always the toolkit's.
+pub fn user_installed(package: Option<&InstalledPackage>, record: Option<&InstalledTool>, first_install: Option<&str>) -> bool {
+    if let Some(origin) = record.and_then(|record| record.origin) {
+        return origin == Origin::User;
+    }
+    let Some(package) = package.filter(|package| package.explicit) else {
+        return false;
+    };
+    let first_trace = record.map(|record| record.installed_at.as_str()).into_iter().chain(first_install).filter_map(parse_time).min();
+    let Some(first_trace) = first_trace else {
+        // The toolkit never installed it
+        return true;
+    };
+    package.install_date.as_deref().and_then(parse_install_date).is_some_and(|installed| installed < first_trace)
+}
+
+/// Origins of the native packages in `installed`, queried before add/sync installs anything:
+/// explicitly installed ones are the user's, the others dependencies.
+pub fn preinstalled(installed: &HashMap<String, InstalledPackage>) -> HashMap<String, Origin> {
+    installed
+        .iter()
+        .map(|(name, package)| (name.clone(), if package.explicit { Origin::User } else { Origin::Dependency }))
+        .collect()
+}
+
+fn parse_time(timestamp: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(timestamp).ok()
+}
+
+/// Parses pacman's install date, local time in the C locale (`Tue Oct  1 10:00:00 2024`).
+fn parse_install_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    // pacman pads single-digit days with a space
+    let date = date.split_whitespace().collect::<Vec<_>>().join(" ");
+    let local = NaiveDateTime::parse_from_str(&date, "%a %b %d %H:%M:%S %Y").ok()?;
+    Local.from_local_datetime(&local).earliest().map(|time| time.fixed_offset())
+}
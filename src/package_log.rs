@@ -0,0 +1,89 @@
+//! Log of the package manager's output, `~/.local/state/cyber-toolkit/package-manager.log`.
+//!
+//! pacman runs as a child process, so without this its output only ever reached the terminal.
+//! Every line it prints is appended with the time, the operation and the stream it came from:
+//!
+//! ```text
+//! 2026-10-16T09:12:03+00:00 [-Syu] stdout: (1/1) installing nmap
+//! 2026-10-16T09:12:04+00:00 [-Syu] stderr: error: target not found: nmapp
+//! ```
+//!
+//! Each run starts with a line naming the command and ends with its exit code. When the log
+//! grows beyond `MAX_SIZE`, it is moved to `package-manager.log.old` and a new one is started.
+//! Failing to write the log never fails the operation; a warning is printed once instead.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use cyber_toolkit::OutputStream;
+
+use crate::i18n::t;
+use crate::state;
+
+/// Size in bytes beyond which the log is rotated.
+const MAX_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Path of the log, `~/.local/state/cyber-toolkit/package-manager.log`.
+pub fn log_path() -> Result<PathBuf, io::Error> {
+    Ok(state::state_dir()?.join("package-manager.log"))
+}
+
+/// The log of one package manager run.
+pub struct PackageLog {
+    operation: String,
+    file: Option<File>,
+}
+
+impl PackageLog {
+    /// Opens the log for a run of `command` (as shown to the user), tagging its lines with
+    /// `operation`, e.g. `-Syu`.
+    pub fn start(operation: &str, command: &str) -> Self {
+        let mut log = PackageLog { operation: operation.to_string(), file: None };
+        match open() {
+            Ok(file) => log.file = Some(file),
+            Err(e) => eprintln!("{}", t!("package-log-open-failed", error = e.to_string())),
+        }
+        log.write(&format!("started {}", command));
+        log
+    }
+
+    /// Appends a line the package manager printed on `stream`.
+    pub fn line(&mut self, stream: OutputStream, line: &str) {
+        let stream = match stream {
+            OutputStream::Stdout => "stdout",
+            OutputStream::Stderr => "stderr",
+        };
+        self.write(&format!("{}: {}", stream, line.trim_end()));
+    }
+
+    /// Appends how the run ended.
+    pub fn finish(mut self, exit_code: Option<i32>) {
+        match exit_code {
+            Some(code) => self.write(&format!("exited with code {}", code)),
+            None => self.write("ended by a signal"),
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        let entry = format!("{} [{}] {}\n", chrono::Utc::now().to_rfc3339(), self.operation, text);
+        if let Err(e) = file.write_all(entry.as_bytes()) {
+            eprintln!("{}", t!("package-log-write-failed", error = e.to_string()));
+            self.file = None;
+        }
+    }
+}
+
+fn open() -> Result<File, io::Error> {
+    let path = log_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_SIZE) {
+        fs::rename(&path, path.with_extension("log.old"))?;
+    }
+    fs::OpenOptions::new().create(true).append(true).open(path)
+}
@@ -0,0 +1,353 @@
+//! Read-only queries against the local pacman database.
+//!
+//! These helpers never modify the system, so they run `pacman` directly without `pkexec`.
+//! `pacman -Qi` and `pacman -Si` answers are cached until the database changes (see `pacman_cache`).
+//! With the `pacman-db` feature, the databases are read directly where possible (see `pacman_db`).
+
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::t;
+use crate::pacman_cache;
+
+/// Information about an installed package, as reported by `pacman -Qi`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstalledPackage {
+    pub name: String,
+    /// One-line summary of the package.
+    #[serde(default)]
+    pub description: String,
+    /// Full version including the release, e.g. `7.95-1`.
+    pub version: String,
+    /// Package architecture, e.g. `x86_64` or `any`.
+    pub architecture: String,
+    /// Installed size in bytes.
+    pub installed_size: u64,
+    /// Installed packages that depend on this one.
+    pub required_by: Vec<String>,
+    /// License identifiers, e.g. `GPL-2.0-or-later` or `custom:PortSwigger`.
+    #[serde(default)]
+    pub licenses: Vec<String>,
+    /// Upstream project URL, if the package names one.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// When the package was installed or last upgraded, as pacman prints it in the C locale
+    /// (e.g. `Tue Oct  1 10:00:00 2024`).
+    #[serde(default)]
+    pub install_date: Option<String>,
+    /// Whether pacman's install reason is "Explicitly installed" rather than "Installed as a
+    /// dependency for another package".
+    #[serde(default)]
+    pub explicit: bool,
+}
+
+/// Queries `pacman -Qi` for the given tools.
+///
+/// Returns a map from package name to its details. Tools that are not installed
+/// are simply absent from the map (pacman reports them on stderr, which is ignored).
+pub fn query_installed(tools: &[String]) -> Result<HashMap<String, InstalledPackage>, Box<dyn std::error::Error>> {
+    if tools.is_empty() {
+        return Ok(HashMap::new());
+    }
+    pacman_cache::installed(tools, |tools| {
+        #[cfg(feature = "pacman-db")]
+        if let Ok(installed) = crate::pacman_db::installed(tools) {
+            return Ok(installed);
+        }
+        let output = Command::new("pacman")
+            .env("LC_ALL", "C") // Stable field names and number formatting
+            .arg("-Qi")
+            .arg("--")
+            .args(tools)
+            .output()?;
+
+        // pacman exits non-zero if any package is missing, but still prints the ones it found.
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(parse_qi_output(&stdout)
+            .into_iter()
+            .map(|pkg| (pkg.name.clone(), pkg))
+            .collect())
+    })
+}
+
+/// Sums the download sizes reported by `pacman -Si` for the given packages, as a rough estimate
+/// of how much an install will download. Packages unknown to the sync databases count as 0.
+pub fn query_download_size(packages: &[String]) -> Result<u64, Box<dyn std::error::Error>> {
+    Ok(query_sync_packages(packages)?.values().map(|package| package.download_size).sum())
+}
+
+/// Lists the files owned by the given installed packages with `pacman -Ql`.
+///
+/// Returns `(package, path)` pairs. Packages that are not installed are ignored.
+pub fn query_files(packages: &[String]) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    if packages.is_empty() {
+        return Ok(Vec::new());
+    }
+    #[cfg(feature = "pacman-db")]
+    if let Ok(files) = crate::pacman_db::files(packages) {
+        return Ok(files);
+    }
+    let output = Command::new("pacman").env("LC_ALL", "C").arg("-Ql").arg("--").args(packages).output()?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(package, path)| (package.to_string(), path.to_string()))
+        .collect())
+}
+
+/// What is installed locally, from one `pacman -Q` and one `pacman -Qu` run.
+#[derive(Debug, Default)]
+pub struct InstallState {
+    /// Installed package names and their versions.
+    pub installed: HashMap<String, String>,
+    /// Installed packages with a newer version in the sync databases.
+    pub outdated: HashSet<String>,
+}
+
+impl InstallState {
+    /// Whether `package` is installed and no newer version is known.
+    pub fn is_current(&self, package: &str) -> bool {
+        self.installed.contains_key(package) && !self.outdated.contains(package)
+    }
+}
+
+/// Lists all installed packages and the outdated ones, running both queries in parallel.
+///
+/// "Outdated" is relative to the local sync databases, as last refreshed by `pacman -Sy`.
+/// Packages from the AUR or other foreign sources are never reported as outdated.
+pub fn query_install_state() -> Result<InstallState, Box<dyn std::error::Error>> {
+    #[cfg(feature = "pacman-db")]
+    if let Ok(installed) = crate::pacman_db::installed_versions() {
+        if let Ok(outdated) = crate::pacman_db::outdated(&installed) {
+            return Ok(InstallState { installed, outdated: outdated.into_iter().collect() });
+        }
+    }
+    let query = |flag: &str| Command::new("pacman").env("LC_ALL", "C").arg(flag).output();
+    let (installed, outdated) = std::thread::scope(|scope| {
+        let outdated = scope.spawn(|| query("-Qu"));
+        (query("-Q"), outdated.join().expect("pacman -Qu query thread panicked"))
+    });
+    let installed = installed?;
+    if !installed.status.success() {
+        return Err(Box::from(t!("pacman-query-failed", flag = "-Q", error = String::from_utf8_lossy(&installed.stderr).trim().to_string())));
+    }
+    // `pacman -Qu` exits with 1 when nothing is outdated
+    let outdated = outdated?;
+    Ok(InstallState {
+        installed: String::from_utf8_lossy(&installed.stdout)
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, version)| (name.to_string(), version.trim().to_string()))
+            .collect(),
+        outdated: String::from_utf8_lossy(&outdated.stdout)
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+/// Lists what the removal `operation` (e.g. `Runs` for `pacman -Runs`) would remove for the given
+/// packages, including the dependencies (and with `c` the dependents) it takes along, by running
+/// it with `-p`. This needs no root.
+pub fn preview_removal(packages: &[String], operation: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if packages.is_empty() {
+        return Ok(Vec::new());
+    }
+    let flag = format!("-{}p", operation);
+    let output = Command::new("pacman").env("LC_ALL", "C").args([flag.as_str(), "--print-format", "%n"]).arg("--").args(packages).output()?;
+    if !output.status.success() {
+        return Err(Box::from(t!("pacman-query-failed", flag = flag.as_str(), error = String::from_utf8_lossy(&output.stderr).trim().to_string())));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect())
+}
+
+/// Lists the member packages of the given groups in the sync databases with `pacman -Sg`.
+///
+/// Returns a map from group name to its members. Unknown groups are absent from the map.
+pub fn query_group_members(groups: &[String]) -> Result<HashMap<String, Vec<String>>, Box<dyn std::error::Error>> {
+    if groups.is_empty() {
+        return Ok(HashMap::new());
+    }
+    #[cfg(feature = "pacman-db")]
+    if let Ok(members) = crate::pacman_db::group_members(groups) {
+        return Ok(members);
+    }
+    // pacman exits non-zero if any group is unknown, but still lists the members of the others.
+    let output = Command::new("pacman").env("LC_ALL", "C").arg("-Sg").arg("--").args(groups).output()?;
+    let mut members: HashMap<String, Vec<String>> = HashMap::new();
+    for (group, package) in String::from_utf8_lossy(&output.stdout).lines().filter_map(|line| line.split_once(' ')) {
+        members.entry(group.to_string()).or_default().push(package.trim().to_string());
+    }
+    Ok(members)
+}
+
+/// A package in the sync databases, as reported by `pacman -Si`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SyncPackage {
+    pub name: String,
+    /// One-line summary of the package.
+    #[serde(default)]
+    pub description: String,
+    /// Download size in bytes.
+    pub download_size: u64,
+    /// Names the package provides besides its own, without versions.
+    pub provides: Vec<String>,
+    /// Names the package conflicts with, without version constraints.
+    pub conflicts: Vec<String>,
+}
+
+/// Queries `pacman -Si` for the given packages. Packages unknown to the sync databases
+/// (e.g. AUR packages) are absent from the map.
+pub fn query_sync_packages(packages: &[String]) -> Result<HashMap<String, SyncPackage>, Box<dyn std::error::Error>> {
+    if packages.is_empty() {
+        return Ok(HashMap::new());
+    }
+    pacman_cache::sync(packages, |packages| {
+        #[cfg(feature = "pacman-db")]
+        if let Ok(found) = crate::pacman_db::sync(packages) {
+            return Ok(found);
+        }
+        let output = Command::new("pacman").env("LC_ALL", "C").arg("-Si").arg("--").args(packages).output()?;
+        Ok(parse_si_output(&String::from_utf8_lossy(&output.stdout)))
+    })
+}
+
+/// Parses the block-per-package output of `pacman -Si`.
+fn parse_si_output(output: &str) -> HashMap<String, SyncPackage> {
+    let mut packages = HashMap::new();
+    for block in output.split("\n\n") {
+        let mut package = SyncPackage::default();
+        let mut current_key = String::new();
+        for line in block.lines() {
+            // Long lists continue on indented lines
+            let value = match line.split_once(':') {
+                Some((key, value)) if !line.starts_with(char::is_whitespace) => {
+                    current_key = key.trim().to_string();
+                    value
+                }
+                _ => line,
+            };
+            let names = value.split_whitespace().filter(|name| *name != "None").map(strip_version);
+            match current_key.as_str() {
+                "Name" => package.name = value.trim().to_string(),
+                "Description" => package.description = value.trim().to_string(),
+                "Download Size" => package.download_size = parse_size(value.trim()).unwrap_or(0),
+                "Provides" => package.provides.extend(names),
+                "Conflicts With" => package.conflicts.extend(names),
+                _ => {}
+            }
+        }
+        // With several repositories, the first one listing a package wins, as for installs
+        if !package.name.is_empty() && !packages.contains_key(&package.name) {
+            packages.insert(package.name.clone(), package);
+        }
+    }
+    packages
+}
+
+/// Strips a version or version constraint from a dependency, e.g. `iptables=1.8` to `iptables`.
+fn strip_version(dependency: &str) -> String {
+    dependency.split(['<', '>', '=']).next().unwrap_or(dependency).to_string()
+}
+
+/// Splits a list value of `pacman -Qi`, whose items are separated by two spaces since a single
+/// item may contain spaces (e.g. the license `GPL-2.0-or-later WITH Classpath-exception-2.0`).
+fn split_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value.split("  ").map(str::trim).filter(|item| !item.is_empty()).map(str::to_string)
+}
+
+/// Parses the block-per-package output of `pacman -Qi`.
+fn parse_qi_output(output: &str) -> Vec<InstalledPackage> {
+    let mut packages = Vec::new();
+    for block in output.split("\n\n") {
+        let mut name = None;
+        let mut description = String::new();
+        let mut version = String::new();
+        let mut architecture = String::new();
+        let mut installed_size = 0;
+        let mut required_by = Vec::new();
+        let mut licenses = Vec::new();
+        let mut url = None;
+        let mut install_date = None;
+        let mut explicit = false;
+        // Field whose values continue on indented lines
+        let mut field = "";
+        for line in block.lines() {
+            if line.starts_with(char::is_whitespace) {
+                // Continuation lines of multi-value fields
+                match field {
+                    "Required By" => required_by.extend(line.split_whitespace().map(str::to_string)),
+                    "Licenses" => licenses.extend(split_list(line)),
+                    _ => {}
+                }
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            field = key.trim();
+            match field {
+                "Name" => name = Some(value.to_string()),
+                "Description" => description = value.to_string(),
+                "Version" => version = value.to_string(),
+                "Architecture" => architecture = value.to_string(),
+                "Installed Size" => installed_size = parse_size(value).unwrap_or(0),
+                "Required By" => required_by.extend(value.split_whitespace().filter(|name| *name != "None").map(str::to_string)),
+                "Licenses" => licenses.extend(split_list(value).filter(|license| license != "None")),
+                "URL" if value != "None" => url = Some(value.to_string()),
+                "Install Date" => install_date = Some(value.to_string()),
+                "Install Reason" => explicit = value.starts_with("Explicitly"),
+                _ => {}
+            }
+        }
+        if let Some(name) = name {
+            packages.push(InstalledPackage { name, description, version, architecture, installed_size, required_by, licenses, url, install_date, explicit });
+        }
+    }
+    packages
+}
+
+/// URL of a package version in the Arch Linux Archive, which keeps every official package
+/// version ever released.
+pub fn archive_url(name: &str, version: &str, architecture: &str) -> String {
+    let first = name.chars().next().unwrap_or('_');
+    format!(
+        "https://archive.archlinux.org/packages/{}/{}/{}-{}-{}.pkg.tar.zst",
+        first, name, name, version, architecture
+    )
+}
+
+/// Parses a pacman size string such as `12.34 MiB` into bytes.
+fn parse_size(value: &str) -> Option<u64> {
+    let (number, unit) = value.split_once(' ')?;
+    let number: f64 = number.parse().ok()?;
+    let multiplier = match unit.trim() {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * multiplier) as u64)
+}
+
+/// Formats a byte count using binary units, matching pacman's own output style.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.2} {}", size, UNITS[unit])
+    }
+}
@@ -0,0 +1,164 @@
+//! Cache of `pacman -Qi` and `pacman -Si` answers in `~/.cache/cyber-toolkit/pacman-queries.json`.
+//!
+//! pacman takes a long time to answer for hundreds of packages, and `du`, `--current` or an
+//! add/sync with nothing to do ask the same questions every time. Answers are kept per package,
+//! including "not found", for as long as the database they came from is unchanged: local answers
+//! until a package is installed, upgraded or removed (which can also change another package's
+//! `Required By`), sync answers until the sync databases are refreshed. Each database is
+//! identified by the modification times of its directory and the files in it.
+//!
+//! Without a readable database directory (e.g. on Windows) nothing is cached.
+//!
+//! The answers are a cache, not state, so they live in the cache directory rather than in
+//! `state.db` (see `state_db`): `cache prune` and `cache clear` remove them like any other cached
+//! file, and `state export` doesn't carry them to another machine. Answers aren't keyed by
+//! version either: finding out the version a sync database has for a package means reading that
+//! database, which is the cost the cache saves, and a local answer can go stale without its
+//! package changing version, as `Required By` and the install reason belong to the whole database.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::t;
+use crate::pacman::{InstalledPackage, SyncPackage};
+use crate::state;
+
+/// pacman's database directory.
+const DB_PATH: &str = "/var/lib/pacman";
+
+/// Version of the cached answers' format, part of every stamp so answers cached by a version
+/// that recorded fewer fields are queried again.
+const FORMAT: u32 = 5;
+
+/// Cached answers from one database.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+struct Section<T> {
+    /// Identifies the database state the answers belong to (see `db_stamp`).
+    stamp: String,
+    found: HashMap<String, T>,
+    /// Packages the database doesn't know.
+    missing: HashSet<String>,
+}
+
+impl<T> Default for Section<T> {
+    fn default() -> Self {
+        Section { stamp: String::new(), found: HashMap::new(), missing: HashSet::new() }
+    }
+}
+
+/// Content of `pacman-queries.json`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+struct QueryCache {
+    /// `pacman -Qi` answers.
+    local: Section<InstalledPackage>,
+    /// `pacman -Si` answers.
+    sync: Section<SyncPackage>,
+}
+
+/// Name of the cached answers in the cache directory.
+pub const CACHE_FILE: &str = "pacman-queries.json";
+
+/// Returns the path of `pacman-queries.json` in the cache directory.
+fn cache_path() -> Result<PathBuf, io::Error> {
+    Ok(state::cache_dir()?.join(CACHE_FILE))
+}
+
+/// Returns the latest modification time of `dir` and its entries, or `None` if `dir` can't be read.
+fn db_stamp(dir: &Path) -> Option<String> {
+    let modified = |metadata: fs::Metadata| metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok();
+    let latest = fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok().and_then(modified))
+        .chain(fs::metadata(dir).ok().and_then(modified))
+        .max()?;
+    Some(format!("{}.{:09}", latest.as_secs(), latest.subsec_nanos()))
+}
+
+/// Reads the cache. A missing or unreadable cache is empty.
+fn read() -> QueryCache {
+    cache_path()
+        .and_then(fs::read_to_string)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Replaces the cache file atomically, so concurrent runs never see a partial file. Each run
+/// writes its own temporary file, so concurrent writers can't clobber each other's; the last
+/// rename wins.
+fn write(cache: &QueryCache) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = state::cache_dir()?;
+    fs::create_dir_all(&dir)?;
+    let mut temporary = tempfile::Builder::new().prefix(".pacman-queries-").suffix(".tmp").tempfile_in(&dir)?;
+    serde_json::to_writer(&mut temporary, cache)?;
+    temporary.as_file().sync_all()?;
+    temporary.persist(cache_path()?)?;
+    Ok(())
+}
+
+/// Answers `packages` from one section of the cache, running `query` only for the packages
+/// without a valid cached answer, and caches its answers.
+fn lookup<T: Clone>(
+    packages: &[String],
+    db_dir: &Path,
+    section: fn(&mut QueryCache) -> &mut Section<T>,
+    query: impl FnOnce(&[String]) -> Result<HashMap<String, T>, Box<dyn std::error::Error>>,
+) -> Result<HashMap<String, T>, Box<dyn std::error::Error>> {
+    let Some(stamp) = db_stamp(db_dir).map(|stamp| format!("{}/{}", FORMAT, stamp)) else {
+        return query(packages);
+    };
+    let mut cache = read();
+    let cached = section(&mut cache);
+    if cached.stamp != stamp {
+        *cached = Section { stamp, ..Section::default() };
+    }
+
+    let mut uncached: Vec<String> =
+        packages.iter().filter(|package| !cached.found.contains_key(*package) && !cached.missing.contains(*package)).cloned().collect();
+    uncached.sort_unstable();
+    uncached.dedup();
+    if !uncached.is_empty() {
+        let mut answers = query(&uncached)?;
+        for package in uncached {
+            match answers.remove(&package) {
+                Some(answer) => {
+                    cached.found.insert(package, answer);
+                }
+                None => {
+                    cached.missing.insert(package);
+                }
+            }
+        }
+        // Only lookups from here on depend on the cache, so failing to save it isn't an error
+        if let Err(e) = write(&cache) {
+            eprintln!("{}", t!("pacman-cache-save-failed", error = e.to_string()));
+        }
+    }
+
+    let cached = section(&mut cache);
+    Ok(packages.iter().filter_map(|package| Some((package.clone(), cached.found.get(package)?.clone()))).collect())
+}
+
+/// Answers a `pacman -Qi` query from the cache where possible, running `query` for the rest.
+pub fn installed(
+    packages: &[String],
+    query: impl FnOnce(&[String]) -> Result<HashMap<String, InstalledPackage>, Box<dyn std::error::Error>>,
+) -> Result<HashMap<String, InstalledPackage>, Box<dyn std::error::Error>> {
+    lookup(packages, &Path::new(DB_PATH).join("local"), |cache| &mut cache.local, query)
+}
+
+/// Answers a `pacman -Si` query from the cache where possible, running `query` for the rest.
+pub fn sync(
+    packages: &[String],
+    query: impl FnOnce(&[String]) -> Result<HashMap<String, SyncPackage>, Box<dyn std::error::Error>>,
+) -> Result<HashMap<String, SyncPackage>, Box<dyn std::error::Error>> {
+    lookup(packages, &Path::new(DB_PATH).join("sync"), |cache| &mut cache.sync, query)
+}
@@ -0,0 +1,61 @@
+//! Download tuning for the pacman transactions the toolkit starts.
+//!
+//! pacman reads `ParallelDownloads` and `XferCommand` only from its configuration file, so when
+//! the `[pacman]` settings tune downloads, a copy of `/etc/pacman.conf` with those options
+//! replaced is written to `~/.local/state/cyber-toolkit/pacman.conf` and passed with `--config`. The system
+//! configuration itself is never changed.
+//!
+//! A bandwidth cap uses curl as `XferCommand`. pacman downloads sequentially through an
+//! `XferCommand`, so the cap takes precedence over `parallel_downloads`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::i18n::t;
+use crate::settings::PacmanSettings;
+use crate::state;
+
+/// The system pacman configuration the tuned copy is based on.
+const SYSTEM_CONFIG: &str = "/etc/pacman.conf";
+
+/// Writes the tuned configuration if the settings ask for any tuning, returning its path.
+pub fn write_tuned_config(settings: &PacmanSettings) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    let option = match (&settings.bandwidth_limit, settings.parallel_downloads) {
+        (Some(limit), _) => {
+            if !limit.chars().all(|c| c.is_ascii_alphanumeric() || c == '.') {
+                return Err(Box::from(t!("pacman-bandwidth-limit-invalid", limit = format!("{:?}", limit))));
+            }
+            format!("XferCommand = /usr/bin/curl --limit-rate {} -L -C - -f -o %o %u", limit)
+        }
+        (None, Some(parallel)) => format!("ParallelDownloads = {}", parallel.max(1)),
+        (None, None) => return Ok(None),
+    };
+
+    let system_config = fs::read_to_string(SYSTEM_CONFIG)?;
+    let mut tuned = String::with_capacity(system_config.len() + option.len());
+    let mut in_options = false;
+    for line in system_config.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_options = trimmed == "[options]";
+            tuned.push_str(line);
+            tuned.push('\n');
+            if in_options {
+                tuned.push_str(&option);
+                tuned.push('\n');
+            }
+            continue;
+        }
+        let key = trimmed.split('=').next().unwrap_or_default().trim();
+        if in_options && (key == "ParallelDownloads" || key == "XferCommand") {
+            continue; // Replaced by the option above
+        }
+        tuned.push_str(line);
+        tuned.push('\n');
+    }
+
+    let path = state::state_dir()?.join("pacman.conf");
+    fs::create_dir_all(state::state_dir()?)?;
+    fs::write(&path, tuned)?;
+    Ok(Some(path))
+}
@@ -0,0 +1,453 @@
+//! Package queries read straight from pacman's databases (the `pacman-db` feature), instead of
+//! running `pacman` and parsing its output.
+//!
+//! The local database is a directory per installed package with plain-text `desc` and `files`
+//! entries; the sync databases are gzip-compressed tar archives of the same `desc` entries, one
+//! per repository in `/etc/pacman.conf`. Reading them in-process answers queries about hundreds
+//! of packages without a pacman run per question. This reads the on-disk format libalpm writes
+//! instead of linking libalpm (there are no Rust bindings we could build against everywhere), so
+//! version comparison is reimplemented here after libalpm's `alpm_pkg_vercmp`.
+//!
+//! Every function fails rather than guessing when a database can't be read (e.g. a repository
+//! compressed with zstd), and `pacman` answers the query instead.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::pacman::{InstalledPackage, SyncPackage};
+
+/// pacman's database directory.
+const DB_PATH: &str = "/var/lib/pacman";
+
+const PACMAN_CONFIG: &str = "/etc/pacman.conf";
+
+/// The entries of a `desc` file: `%NAME%`, `%VERSION%`, ... (without the `%`) and their lines.
+type Desc = HashMap<String, Vec<String>>;
+
+/// Parses a `desc` (or `files`) entry: a `%KEY%` line, then its values up to the next blank line.
+fn parse_desc(text: &str) -> Desc {
+    let mut desc = Desc::new();
+    let mut key: Option<String> = None;
+    for line in text.lines() {
+        if line.is_empty() {
+            key = None;
+        } else if let Some(name) = line.strip_prefix('%').and_then(|line| line.strip_suffix('%')).filter(|_| key.is_none()) {
+            key = Some(name.to_string());
+            desc.entry(name.to_string()).or_default();
+        } else if let Some(key) = &key {
+            desc.entry(key.clone()).or_default().push(line.to_string());
+        }
+    }
+    desc
+}
+
+fn first<'a>(desc: &'a Desc, key: &str) -> Option<&'a str> {
+    desc.get(key).and_then(|values| values.first()).map(String::as_str)
+}
+
+fn list<'a>(desc: &'a Desc, key: &str) -> impl Iterator<Item = &'a String> {
+    desc.get(key).into_iter().flatten()
+}
+
+/// Strips a version constraint or a `: description` from a dependency, e.g. `openssl>=3` to
+/// `openssl`.
+fn dependency_name(dependency: &str) -> &str {
+    dependency.split(['<', '>', '=', ':']).next().unwrap_or(dependency).trim()
+}
+
+/// The installed packages: their directory in the local database and their `desc`.
+fn local_packages() -> Result<Vec<(PathBuf, Desc)>, Box<dyn std::error::Error>> {
+    let mut packages = Vec::new();
+    for entry in fs::read_dir(Path::new(DB_PATH).join("local"))? {
+        let dir = entry?.path();
+        let desc_path = dir.join("desc");
+        if !desc_path.is_file() {
+            continue; // ALPM_DB_VERSION and the like
+        }
+        let desc = parse_desc(&fs::read_to_string(&desc_path)?);
+        if first(&desc, "NAME").is_some() {
+            packages.push((dir, desc));
+        }
+    }
+    Ok(packages)
+}
+
+/// The answer of `pacman -Qi` for the given packages; packages that aren't installed are absent.
+pub fn installed(packages: &[String]) -> Result<HashMap<String, InstalledPackage>, Box<dyn std::error::Error>> {
+    let local = local_packages()?;
+    let wanted: HashSet<&str> = packages.iter().map(String::as_str).collect();
+
+    // "Required By" is the reverse of the other packages' dependencies, which may name the
+    // package itself or something it provides
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (_, desc) in &local {
+        let name = first(desc, "NAME").unwrap_or_default();
+        for dependency in list(desc, "DEPENDS") {
+            dependents.entry(dependency_name(dependency)).or_default().push(name);
+        }
+    }
+
+    let mut found = HashMap::new();
+    for (_, desc) in &local {
+        let name = first(desc, "NAME").unwrap_or_default();
+        if !wanted.contains(name) {
+            continue;
+        }
+        let mut required_by: Vec<String> = std::iter::once(name)
+            .chain(list(desc, "PROVIDES").map(|provided| dependency_name(provided)))
+            .flat_map(|provided| dependents.get(provided).into_iter().flatten())
+            .map(|dependent| dependent.to_string())
+            .collect();
+        required_by.sort();
+        required_by.dedup();
+        let install_date = first(desc, "INSTALLDATE")
+            .and_then(|date| date.parse::<i64>().ok())
+            .and_then(|date| DateTime::from_timestamp(date, 0))
+            // As pacman prints it in the C locale
+            .map(|date| date.with_timezone(&Local).format("%a %b %e %H:%M:%S %Y").to_string());
+        found.insert(
+            name.to_string(),
+            InstalledPackage {
+                name: name.to_string(),
+                description: first(desc, "DESC").unwrap_or_default().to_string(),
+                version: first(desc, "VERSION").unwrap_or_default().to_string(),
+                architecture: first(desc, "ARCH").unwrap_or_default().to_string(),
+                installed_size: first(desc, "SIZE").and_then(|size| size.parse().ok()).unwrap_or(0),
+                required_by,
+                licenses: list(desc, "LICENSE").cloned().collect(),
+                url: first(desc, "URL").map(str::to_string),
+                install_date,
+                // No reason is recorded for explicitly installed packages; 1 means "as a dependency"
+                explicit: first(desc, "REASON").is_none_or(|reason| reason == "0"),
+            },
+        );
+    }
+    Ok(found)
+}
+
+/// Installed package names and their versions, as `pacman -Q` lists them.
+pub fn installed_versions() -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    Ok(local_packages()?
+        .iter()
+        .map(|(_, desc)| (first(desc, "NAME").unwrap_or_default().to_string(), first(desc, "VERSION").unwrap_or_default().to_string()))
+        .collect())
+}
+
+/// The installed packages with a newer version in the sync databases, as `pacman -Qu` lists them.
+pub fn outdated(installed: &HashMap<String, String>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut versions: HashMap<String, String> = HashMap::new();
+    for desc in sync_packages()? {
+        let (Some(name), Some(version)) = (first(&desc, "NAME"), first(&desc, "VERSION")) else {
+            continue;
+        };
+        // The first repository listing a package wins, as for upgrades
+        versions.entry(name.to_string()).or_insert_with(|| version.to_string());
+    }
+    Ok(installed
+        .iter()
+        .filter(|(name, version)| versions.get(*name).is_some_and(|available| vercmp(version, available) == Ordering::Less))
+        .map(|(name, _)| name.clone())
+        .collect())
+}
+
+/// The files owned by the given installed packages as `(package, path)` pairs, as `pacman -Ql`
+/// lists them.
+pub fn files(packages: &[String]) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let wanted: HashSet<&str> = packages.iter().map(String::as_str).collect();
+    let mut files = Vec::new();
+    for (dir, desc) in local_packages()? {
+        let name = first(&desc, "NAME").unwrap_or_default();
+        if !wanted.contains(name) {
+            continue;
+        }
+        let entry = parse_desc(&fs::read_to_string(dir.join("files"))?);
+        files.extend(list(&entry, "FILES").map(|path| (name.to_string(), format!("/{}", path))));
+    }
+    Ok(files)
+}
+
+/// The answer of `pacman -Si` for the given packages; packages no repository has are absent.
+pub fn sync(packages: &[String]) -> Result<HashMap<String, SyncPackage>, Box<dyn std::error::Error>> {
+    let wanted: HashSet<&str> = packages.iter().map(String::as_str).collect();
+    let mut found = HashMap::new();
+    for desc in sync_packages()? {
+        let Some(name) = first(&desc, "NAME").filter(|name| wanted.contains(name)) else {
+            continue;
+        };
+        if found.contains_key(name) {
+            continue;
+        }
+        let names = |key| list(&desc, key).map(|entry| dependency_name(entry).to_string()).collect();
+        let package = SyncPackage {
+            name: name.to_string(),
+            description: first(&desc, "DESC").unwrap_or_default().to_string(),
+            download_size: first(&desc, "CSIZE").and_then(|size| size.parse().ok()).unwrap_or(0),
+            provides: names("PROVIDES"),
+            conflicts: names("CONFLICTS"),
+        };
+        found.insert(name.to_string(), package);
+    }
+    Ok(found)
+}
+
+/// The members of the given groups in the sync databases, as `pacman -Sg` lists them.
+pub fn group_members(groups: &[String]) -> Result<HashMap<String, Vec<String>>, Box<dyn std::error::Error>> {
+    let mut members: HashMap<String, Vec<String>> = HashMap::new();
+    for desc in sync_packages()? {
+        let name = first(&desc, "NAME").unwrap_or_default();
+        for group in list(&desc, "GROUPS").filter(|group| groups.contains(group)) {
+            let packages = members.entry(group.clone()).or_default();
+            if !packages.iter().any(|member| member == name) {
+                packages.push(name.to_string());
+            }
+        }
+    }
+    for packages in members.values_mut() {
+        packages.sort();
+    }
+    Ok(members)
+}
+
+/// The repositories of `/etc/pacman.conf`, in order: every section but `[options]`.
+fn repositories() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    Ok(fs::read_to_string(PACMAN_CONFIG)?
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix('[')?.strip_suffix(']').map(str::to_string))
+        .filter(|section| section != "options")
+        .collect())
+}
+
+/// The `desc` entries of every package in the sync databases, repository by repository.
+fn sync_packages() -> Result<Vec<Desc>, Box<dyn std::error::Error>> {
+    let mut packages = Vec::new();
+    for repository in repositories()? {
+        let path = Path::new(DB_PATH).join("sync").join(format!("{}.db", repository));
+        if !path.exists() {
+            continue; // Not refreshed yet, as pacman -S sees it
+        }
+        packages.extend(read_sync_db(&path)?);
+    }
+    Ok(packages)
+}
+
+/// Reads the `desc` entries of a sync database.
+fn read_sync_db(path: &Path) -> Result<Vec<Desc>, Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    let mut magic = [0; 2];
+    file.read_exact(&mut magic)?;
+    if magic != [0x1f, 0x8b] {
+        return Err(Box::from(format!("{} isn't gzip-compressed", path.display())));
+    }
+    let mut packages = Vec::new();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(File::open(path)?));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.path()?.ends_with("desc") {
+            continue;
+        }
+        let mut text = String::new();
+        entry.read_to_string(&mut text)?;
+        packages.push(parse_desc(&text));
+    }
+    Ok(packages)
+}
+
+/// Compares two package versions (`[epoch:]version[-release]`) the way pacman's `vercmp` does.
+fn vercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let split = |full: &str| -> (String, String, Option<String>) {
+        let (epoch, rest) = match full.split_once(':') {
+            Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => (if epoch.is_empty() { "0" } else { epoch }, rest),
+            _ => ("0", full),
+        };
+        match rest.rsplit_once('-') {
+            Some((version, release)) => (epoch.to_string(), version.to_string(), Some(release.to_string())),
+            None => (epoch.to_string(), rest.to_string(), None),
+        }
+    };
+    let (epoch_a, version_a, release_a) = split(a);
+    let (epoch_b, version_b, release_b) = split(b);
+    segments_cmp(&epoch_a, &epoch_b).then_with(|| segments_cmp(&version_a, &version_b)).then_with(|| match (release_a, release_b) {
+        (Some(release_a), Some(release_b)) => segments_cmp(&release_a, &release_b),
+        _ => Ordering::Equal,
+    })
+}
+
+/// rpm's segment-wise comparison, which libalpm uses for each part of a version: runs of digits
+/// compare as numbers and beat runs of letters, which compare as strings.
+fn segments_cmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut one, mut two) = (0, 0);
+    while one < a.len() && two < b.len() {
+        let (separator_one, separator_two) = (one, two);
+        while one < a.len() && !a[one].is_ascii_alphanumeric() {
+            one += 1;
+        }
+        while two < b.len() && !b[two].is_ascii_alphanumeric() {
+            two += 1;
+        }
+        if one == a.len() || two == b.len() {
+            break;
+        }
+        // More separators, e.g. `1..1` against `1.1`, make the newer version
+        if one - separator_one != two - separator_two {
+            return (one - separator_one).cmp(&(two - separator_two));
+        }
+        let numeric = a[one].is_ascii_digit();
+        let in_segment = |c: &u8| if numeric { c.is_ascii_digit() } else { c.is_ascii_alphabetic() };
+        let end_one = one + a[one..].iter().take_while(|c| in_segment(c)).count();
+        let end_two = two + b[two..].iter().take_while(|c| in_segment(c)).count();
+        if end_two == two {
+            // A number against letters: the number is newer
+            return if numeric { Ordering::Greater } else { Ordering::Less };
+        }
+        let (mut segment_one, mut segment_two) = (&a[one..end_one], &b[two..end_two]);
+        if numeric {
+            while segment_one.first() == Some(&b'0') {
+                segment_one = &segment_one[1..];
+            }
+            while segment_two.first() == Some(&b'0') {
+                segment_two = &segment_two[1..];
+            }
+            let by_length = segment_one.len().cmp(&segment_two.len());
+            if by_length != Ordering::Equal {
+                return by_length;
+            }
+        }
+        let by_content = segment_one.cmp(segment_two);
+        if by_content != Ordering::Equal {
+            return by_content;
+        }
+        (one, two) = (end_one, end_two);
+    }
+    let (rest_one, rest_two) = (a.get(one).copied(), b.get(two).copied());
+    match (rest_one, rest_two) {
+        (None, None) => Ordering::Equal,
+        // What is left decides: `1.0` < `1.0.1`, but `1.0a` < `1.0`
+        (None, Some(c)) if !c.is_ascii_alphabetic() => Ordering::Less,
+        (Some(c), _) if c.is_ascii_alphabetic() => Ordering::Less,
+        _ => Ordering::Greater,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::{dependency_name, parse_desc, vercmp};
+
+    /// The cases of pacman's `test/util/vercmptest.sh`: both versions and what `vercmp` prints.
+    const VERCMP_CASES: &[(&str, &str, i8)] = &[
+        // All similar length, no pkgrel
+        ("1.5.0", "1.5.0", 0),
+        ("1.5.1", "1.5.0", 1),
+        // Mixed length
+        ("1.5.1", "1.5", 1),
+        // With pkgrel, simple
+        ("1.5.0-1", "1.5.0-1", 0),
+        ("1.5.0-1", "1.5.0-2", -1),
+        ("1.5.0-1", "1.5.1-1", -1),
+        ("1.5.0-2", "1.5.1-1", -1),
+        // With pkgrel, mixed lengths
+        ("1.5-1", "1.5.1-1", -1),
+        ("1.5-2", "1.5.1-1", -1),
+        ("1.5-2", "1.5.1-2", -1),
+        // Mixed pkgrel inclusion
+        ("1.5", "1.5-1", 0),
+        ("1.5-1", "1.5", 0),
+        ("1.1-1", "1.1", 0),
+        ("1.0-1", "1.1", -1),
+        ("1.1-1", "1.0", 1),
+        // Alphanumeric versions
+        ("1.5b-1", "1.5-1", -1),
+        ("1.5b", "1.5", -1),
+        ("1.5b-1", "1.5", -1),
+        ("1.5b", "1.5.1", -1),
+        // From the manpage
+        ("1.0a", "1.0alpha", -1),
+        ("1.0alpha", "1.0b", -1),
+        ("1.0b", "1.0beta", -1),
+        ("1.0beta", "1.0rc", -1),
+        ("1.0rc", "1.0", -1),
+        // Alpha-dotted versions
+        ("1.5.a", "1.5", 1),
+        ("1.5.b", "1.5.a", 1),
+        ("1.5.1", "1.5.b", 1),
+        // Alpha dots and dashes
+        ("1.5.b-1", "1.5.b", 0),
+        ("1.5-1", "1.5.b", -1),
+        // Same or similar content, differing separators
+        ("2.0", "2_0", 0),
+        ("2.0_a", "2_0.a", 0),
+        ("2.0a", "2.0.a", -1),
+        ("2___a", "2_a", 1),
+        // Epoch included
+        ("0:1.0", "0:1.0", 0),
+        ("0:1.0", "0:1.1", -1),
+        ("1:1.0", "0:1.0", 1),
+        ("1:1.0", "0:1.1", 1),
+        ("1:1.0", "2:1.1", -1),
+        // Epoch and sometimes a pkgrel
+        ("1:1.0", "0:1.0-1", 1),
+        ("1:1.0-1", "0:1.1-1", 1),
+        // Epoch on one side only
+        ("0:1.0", "1.0", 0),
+        ("0:1.0", "1.1", -1),
+        ("0:1.1", "1.0", 1),
+        ("1:1.0", "1.0", 1),
+        ("1:1.0", "1.1", 1),
+        ("1:1.1", "1.1", 1),
+    ];
+
+    #[test]
+    fn vercmp_matches_pacman() {
+        for &(a, b, expected) in VERCMP_CASES {
+            let expected = expected.cmp(&0);
+            assert_eq!(vercmp(a, b), expected, "vercmp {} {}", a, b);
+            assert_eq!(vercmp(b, a), expected.reverse(), "vercmp {} {}", b, a);
+        }
+    }
+
+    #[test]
+    fn vercmp_compares_numbers_by_value() {
+        assert_eq!(vercmp("1.10-1", "1.9-1"), Ordering::Greater);
+        assert_eq!(vercmp("1.010", "1.10"), Ordering::Equal);
+        assert_eq!(vercmp("7.95-1", "7.95-10"), Ordering::Less);
+        assert_eq!(vercmp("20240101.r5.g1a2b3c-1", "20231231.r90.gffffff-1"), Ordering::Greater);
+    }
+
+    #[test]
+    fn parse_desc_reads_keys_and_values() {
+        let desc = parse_desc("%NAME%\nnmap\n\n%DEPENDS%\nglibc\nopenssl>=3\n\n%REASON%\n1\n");
+        assert_eq!(desc["NAME"], ["nmap"]);
+        assert_eq!(desc["DEPENDS"], ["glibc", "openssl>=3"]);
+        assert_eq!(desc["REASON"], ["1"]);
+    }
+
+    #[test]
+    fn parse_desc_ignores_values_without_a_key() {
+        let desc = parse_desc("stray\n%NAME%\nnmap\n\nmore stray\n%EMPTY%\n\n");
+        assert_eq!(desc.len(), 2);
+        assert_eq!(desc["NAME"], ["nmap"]);
+        assert!(desc["EMPTY"].is_empty());
+    }
+
+    #[test]
+    fn dependency_name_drops_constraints_and_descriptions() {
+        assert_eq!(dependency_name("openssl>=3"), "openssl");
+        assert_eq!(dependency_name("python<3.13"), "python");
+        assert_eq!(dependency_name("sh=5.2"), "sh");
+        assert_eq!(dependency_name("ruby: for the scripts"), "ruby");
+        assert_eq!(dependency_name("glibc"), "glibc");
+    }
+}
@@ -0,0 +1,156 @@
+//! Where the toolkit keeps its files, following the XDG base directory specification:
+//!
+//! - configuration (`roles.cnf`, `config.toml`, `wordlists.cnf`): `$XDG_CONFIG_HOME/cyber-toolkit/`
+//! - cached role files: `$XDG_CACHE_HOME/cyber-toolkit/`
+//! - state (history, lockfiles, logs, virtualenvs, ...): `$XDG_STATE_HOME/cyber-toolkit/`
+//!
+//! Setting `CYBER_TOOLKIT_HOME` puts all three into that one directory instead.
+//!
+//! Machine-wide configuration, set by an administrator for all users, is in
+//! `/etc/cyber-toolkit/` (`%ProgramData%\cyber-toolkit\` on Windows), or in
+//! `CYBER_TOOLKIT_SYSTEM_DIR` if set.
+//!
+//! Older versions kept everything in `~/.roles/`; `migrate_legacy_dir` moves it over.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Environment variable overriding all three directories.
+pub const HOME_ENV: &str = "CYBER_TOOLKIT_HOME";
+
+/// Environment variable overriding the system configuration directory.
+pub const SYSTEM_DIR_ENV: &str = "CYBER_TOOLKIT_SYSTEM_DIR";
+
+/// Name of the toolkit's subdirectory in each base directory.
+const APP_DIR_NAME: &str = "cyber-toolkit";
+
+/// Entries of the legacy directory that belong in the configuration directory. `.git` and
+/// `.gitignore` come from `cyber-toolkit sync`, which now versions the configuration directory.
+const LEGACY_CONFIG_ENTRIES: &[&str] = &["roles.cnf", "config.toml", "wordlists.cnf", ".git", ".gitignore"];
+
+/// Entries of the legacy directory that are left in place: virtualenvs and Go binaries contain
+/// absolute paths, so they are rebuilt in the state directory on the next add/sync instead.
+const LEGACY_KEPT_ENTRIES: &[&str] = &["venvs", "go", "go.lock"];
+
+fn home_override() -> Option<PathBuf> {
+    std::env::var_os(HOME_ENV).filter(|dir| !dir.is_empty()).map(PathBuf::from)
+}
+
+fn base_dir(xdg_dir: Option<PathBuf>, kind: &str) -> Result<PathBuf, io::Error> {
+    if let Some(dir) = home_override() {
+        return Ok(dir);
+    }
+    xdg_dir
+        .map(|dir| dir.join(APP_DIR_NAME))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} directory not found.", kind)))
+}
+
+/// The configuration directory, `$XDG_CONFIG_HOME/cyber-toolkit`.
+pub fn config_dir() -> Result<PathBuf, io::Error> {
+    base_dir(dirs::config_dir(), "Configuration")
+}
+
+/// The system configuration directory, shared by all users: `/etc/cyber-toolkit`.
+pub fn system_config_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(SYSTEM_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    if cfg!(windows) {
+        let program_data = std::env::var_os("ProgramData").unwrap_or_else(|| r"C:\ProgramData".into());
+        return PathBuf::from(program_data).join(APP_DIR_NAME);
+    }
+    PathBuf::from("/etc").join(APP_DIR_NAME)
+}
+
+/// The cache directory, `$XDG_CACHE_HOME/cyber-toolkit`.
+pub fn cache_dir() -> Result<PathBuf, io::Error> {
+    base_dir(dirs::cache_dir(), "Cache")
+}
+
+/// The state directory, `$XDG_STATE_HOME/cyber-toolkit`. Platforms without a state directory
+/// (Windows, macOS) use the local data directory.
+pub fn state_dir() -> Result<PathBuf, io::Error> {
+    base_dir(dirs::state_dir().or_else(dirs::data_local_dir), "State")
+}
+
+/// The directory used by older versions, `~/.roles`.
+pub fn legacy_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home_dir| home_dir.join(".roles"))
+}
+
+/// What `migrate_legacy_dir` did.
+#[derive(Debug, Default)]
+pub struct Migration {
+    pub from: PathBuf,
+    /// Entries moved, with their new paths.
+    pub moved: Vec<PathBuf>,
+    /// Entries left in the legacy directory (see `LEGACY_KEPT_ENTRIES`).
+    pub kept: Vec<PathBuf>,
+}
+
+/// Moves the contents of `~/.roles` into the XDG directories, once.
+///
+/// Nothing happens if `CYBER_TOOLKIT_HOME` is set, `~/.roles` doesn't exist, or the new
+/// configuration directory already exists. `~/.roles` is removed if it ends up empty.
+pub fn migrate_legacy_dir() -> Result<Option<Migration>, io::Error> {
+    let Some(legacy) = legacy_dir().filter(|dir| dir.is_dir()) else {
+        return Ok(None);
+    };
+    if home_override().is_some() || config_dir()?.exists() {
+        return Ok(None);
+    }
+
+    let mut migration = Migration { from: legacy.clone(), ..Migration::default() };
+    for entry in fs::read_dir(&legacy)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        if LEGACY_KEPT_ENTRIES.contains(&name.as_str()) {
+            migration.kept.push(path);
+            continue;
+        }
+        if name == "cache" {
+            // Cached role files now live directly in the cache directory
+            for cached in fs::read_dir(&path)? {
+                let cached = cached?.path();
+                migration.moved.push(move_entry(&cached, &cache_dir()?)?);
+            }
+            fs::remove_dir(&path)?;
+            continue;
+        }
+        let is_config = LEGACY_CONFIG_ENTRIES.contains(&name.as_str()) || name.ends_with(".bak");
+        let target_dir = if is_config { config_dir()? } else { state_dir()? };
+        migration.moved.push(move_entry(&path, &target_dir)?);
+    }
+    if migration.kept.is_empty() {
+        fs::remove_dir(&legacy)?;
+    }
+    Ok(Some(migration))
+}
+
+fn move_entry(path: &Path, target_dir: &Path) -> Result<PathBuf, io::Error> {
+    fs::create_dir_all(target_dir)?;
+    let target = target_dir.join(path.file_name().unwrap_or_default());
+    match fs::rename(path, &target) {
+        // e.g. ~/.cache on a separate tmpfs
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_recursively(path, &target)?;
+            if path.is_dir() { fs::remove_dir_all(path)? } else { fs::remove_file(path)? }
+        }
+        result => result?,
+    }
+    Ok(target)
+}
+
+fn copy_recursively(from: &Path, to: &Path) -> Result<(), io::Error> {
+    if !from.is_dir() {
+        fs::copy(from, to)?;
+        return Ok(());
+    }
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_recursively(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! Pinned roles, listed as `pinned` in the `[roles]` section of `config.toml`.
+//!
+//! A pinned role is never dropped from the configuration by accident: removing it (with `-r`,
+//! `select`, `leave` or `tool remove`) is refused unless `--unpin` is given, which also unpins
+//! it. `expire` leaves pinned temporary roles in place.
+
+use cyber_toolkit::roles;
+
+use crate::i18n::t;
+use crate::settings;
+
+/// Key of the pinned roles in `config.toml`.
+const PINNED_KEY: &str = "roles.pinned";
+
+/// Returns the pinned roles.
+pub fn pinned() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    Ok(roles::canonical_names(&settings::load()?.roles.pinned))
+}
+
+/// Replaces the pinned roles in `config.toml`, dropping the key once none are left.
+fn write(pinned: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut table = settings::load_table()?;
+    if pinned.is_empty() {
+        settings::unset_key(&mut table, PINNED_KEY);
+    } else {
+        let values = pinned.iter().map(|role| toml::Value::String(role.clone())).collect();
+        settings::set_key(&mut table, PINNED_KEY, toml::Value::Array(values))?;
+    }
+    settings::save_table(&table)
+}
+
+/// Pins `names`. Returns the roles that weren't pinned yet.
+pub fn pin(names: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut pinned = pinned()?;
+    let added: Vec<String> = roles::canonical_names(names).into_iter().filter(|role| !pinned.contains(role)).collect();
+    if !added.is_empty() {
+        pinned.extend(added.iter().cloned());
+        write(&pinned)?;
+    }
+    Ok(added)
+}
+
+/// Unpins `names`. Returns the roles that were pinned.
+pub fn unpin(names: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let names = roles::canonical_names(names);
+    let (removed, kept): (Vec<String>, Vec<String>) = pinned()?.into_iter().partition(|role| names.contains(role));
+    if !removed.is_empty() {
+        write(&kept)?;
+    }
+    Ok(removed)
+}
+
+/// Returns the pinned roles among `removing`. Fails if there are any and `unpin` isn't set.
+pub fn check_removal(removing: &[String], unpin: bool) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let pinned: Vec<String> = pinned()?.into_iter().filter(|role| removing.contains(role)).collect();
+    if !pinned.is_empty() && !unpin {
+        return Err(Box::from(t!("pins-refusing-removal", roles = format!("{:?}", pinned))));
+    }
+    Ok(pinned)
+}
@@ -0,0 +1,297 @@
+//! The few operations that differ between Unix and Windows.
+//!
+//! Privileged commands go through `pkexec` on Unix and through a UAC prompt
+//! (`Start-Process -Verb RunAs`) on Windows. Shell commands run with `sh -c` and `cmd /C`.
+//!
+//! The toolkit itself always runs unprivileged; elevation is limited to short-lived child
+//! processes for the steps that need it, such as the package manager.
+//!
+//! With `set_plain` (`--plain`), child processes are asked for plain, line-oriented output as
+//! well (see `plain_output`), for screen readers and log collectors.
+
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+#[cfg(unix)]
+use std::sync::OnceLock;
+use std::thread;
+
+use cyber_toolkit::OutputStream;
+
+#[cfg(unix)]
+use crate::i18n::t;
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Asks (`true`) for plain output: no colors, progress bars, spinners or menus, only lines of text.
+pub fn set_plain(plain: bool) {
+    PLAIN.store(plain, Ordering::Relaxed);
+}
+
+/// Returns true if plain output was asked for (see `set_plain`).
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// With plain output, sets `NO_COLOR=1` and `TERM=dumb` for `command`, which most tools honour
+/// by dropping colors and progress bars. Elevated commands lose their environment through
+/// `pkexec`, so pacman gets `PLAIN_PACMAN_FLAGS` instead.
+pub fn plain_output(command: &mut Command) -> &mut Command {
+    if is_plain() {
+        command.env("NO_COLOR", "1").env("TERM", "dumb");
+    }
+    command
+}
+
+/// pacman flags for plain output, also understood by the AUR helpers.
+#[cfg(unix)]
+pub const PLAIN_PACMAN_FLAGS: [&str; 2] = ["--color=never", "--noprogressbar"];
+
+/// Returns true if the toolkit itself runs with root privileges (never on Windows).
+pub fn is_root() -> bool {
+    #[cfg(unix)]
+    {
+        static IS_ROOT: OnceLock<bool> = OnceLock::new();
+        *IS_ROOT.get_or_init(|| {
+            Command::new("id")
+                .arg("-u")
+                .output()
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "0")
+                .unwrap_or(false)
+        })
+    }
+    #[cfg(windows)]
+    {
+        false
+    }
+}
+
+/// Builds a command running `program` with root privileges through `pkexec`, or directly if
+/// the toolkit already runs as root.
+#[cfg(unix)]
+pub fn elevated(program: &str, args: &[String]) -> Command {
+    let words = std::iter::once(program).chain(args.iter().map(String::as_str));
+    crate::audit::record_elevation(&shlex::try_join(words).unwrap_or_default());
+    let mut command = if is_root() {
+        Command::new(program)
+    } else {
+        let mut pkexec = Command::new("pkexec");
+        pkexec.arg(program);
+        pkexec
+    };
+    command.args(args);
+    command
+}
+
+/// Exit codes of `timeout` when the command ran out of time (124) or had to be killed (137).
+#[cfg(unix)]
+const TIMEOUT_EXIT_CODES: [i32; 2] = [124, 137];
+
+/// Prefixes a command with coreutils' `timeout` if `minutes` is set, returning the program and
+/// arguments to run instead.
+///
+/// `timeout` runs the command in its own process group and signals the whole group, so hung
+/// children such as post-install scripts are stopped too; it is SIGKILLed 30 seconds after SIGTERM.
+/// An interactive command must stay in the terminal's foreground group to read input, so with
+/// `foreground` only the command itself is signalled.
+#[cfg(unix)]
+pub fn with_timeout(program: &str, args: Vec<String>, minutes: Option<u64>, foreground: bool) -> (String, Vec<String>) {
+    let Some(minutes) = minutes else {
+        return (program.to_string(), args);
+    };
+    let mut timeout_args = Vec::with_capacity(args.len() + 5);
+    if foreground {
+        timeout_args.push("--foreground".to_string());
+    }
+    timeout_args.push("--kill-after=30s".to_string());
+    timeout_args.push(format!("{}m", minutes));
+    timeout_args.push(program.to_string());
+    timeout_args.extend(args);
+    ("/usr/bin/timeout".to_string(), timeout_args)
+}
+
+/// Returns true if the exit code means `with_timeout` stopped the command.
+#[cfg(unix)]
+pub fn timed_out(code: Option<i32>) -> bool {
+    code.is_some_and(|code| TIMEOUT_EXIT_CODES.contains(&code))
+}
+
+/// Explains why an elevated child process failed, based on `pkexec`'s exit codes.
+#[cfg(unix)]
+pub fn elevation_failure(code: Option<i32>) -> Option<String> {
+    if is_root() {
+        return None;
+    }
+    match code {
+        Some(126) => Some(t!("elevation-dismissed")),
+        Some(127) => Some(t!("elevation-not-authorized")),
+        _ => None,
+    }
+}
+
+/// Builds a command running `command` in the platform shell.
+pub fn shell(command: &str) -> Command {
+    #[cfg(unix)]
+    {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+    #[cfg(windows)]
+    {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    }
+}
+
+/// Number of trailing stderr lines kept by `status_with_stderr`.
+const STDERR_EXCERPT_LINES: usize = 20;
+
+/// Runs a command like `Command::status`, passing its stderr through unchanged while keeping the
+/// last lines of it for failure reports.
+///
+/// stderr is forwarded as it arrives rather than line by line, since prompts such as pacman's
+/// "Proceed with installation?" are written there without a trailing newline. The command gets
+/// `plain_output`.
+pub fn status_with_stderr(command: &mut Command) -> io::Result<(ExitStatus, String)> {
+    status_with_lines(command, &mut |_, _| {})
+}
+
+/// Runs a command like `status_with_stderr`, also passing each line it prints to `on_line` as it
+/// arrives, e.g. for a log.
+///
+/// stderr is always captured. stdout is captured too unless it is a terminal: then it stays
+/// attached, so progress bars keep drawing in place, and only the stderr lines reach `on_line`.
+/// Captured output is still forwarded to the toolkit's own stdout and stderr unchanged.
+pub fn status_with_lines(command: &mut Command, on_line: &mut dyn FnMut(OutputStream, &str)) -> io::Result<(ExitStatus, String)> {
+    plain_output(command).stderr(Stdio::piped());
+    if !io::stdout().is_terminal() {
+        command.stdout(Stdio::piped());
+    }
+    let mut child = command.spawn()?;
+    let streams: [(OutputStream, Option<Box<dyn Read + Send>>); 2] = [
+        (OutputStream::Stdout, child.stdout.take().map(|stdout| Box::new(stdout) as _)),
+        (OutputStream::Stderr, child.stderr.take().map(|stderr| Box::new(stderr) as _)),
+    ];
+    let (sender, receiver) = mpsc::channel();
+    for (name, stream) in streams {
+        let Some(mut stream) = stream else {
+            continue;
+        };
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let mut buffer = [0; 4096];
+            while let Ok(read @ 1..) = stream.read(&mut buffer) {
+                if sender.send((name, buffer[..read].to_vec())).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    let mut captured = Vec::new();
+    // Unfinished last line of each stream
+    let mut partial: [Vec<u8>; 2] = Default::default();
+    for (stream, chunk) in receiver {
+        match stream {
+            OutputStream::Stdout => {
+                io::stdout().write_all(&chunk)?;
+                io::stdout().flush()?;
+            }
+            OutputStream::Stderr => {
+                io::stderr().write_all(&chunk)?;
+                captured.extend_from_slice(&chunk);
+            }
+        }
+        let pending = &mut partial[match stream {
+            OutputStream::Stdout => 0,
+            OutputStream::Stderr => 1,
+        }];
+        pending.extend_from_slice(&chunk);
+        // Progress output rewrites its line with `\r`, so that ends a line too
+        while let Some(end) = pending.iter().position(|byte| *byte == b'\n' || *byte == b'\r') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..end]);
+            if !line.trim().is_empty() {
+                on_line(stream, &line);
+            }
+        }
+    }
+    for (stream, pending) in [OutputStream::Stdout, OutputStream::Stderr].into_iter().zip(partial) {
+        let line = String::from_utf8_lossy(&pending);
+        if !line.trim().is_empty() {
+            on_line(stream, &line);
+        }
+    }
+
+    let status = child.wait()?;
+    let captured = String::from_utf8_lossy(&captured);
+    let lines: Vec<&str> = captured.lines().collect();
+    let excerpt = lines[lines.len().saturating_sub(STDERR_EXCERPT_LINES)..].join("\n");
+    Ok((status, excerpt))
+}
+
+/// Builds a command running `command` in the platform shell with administrator privileges.
+///
+/// On Windows the elevated process runs in its own console; its exit code is passed through.
+pub fn elevated_shell(command: &str) -> Command {
+    #[cfg(unix)]
+    {
+        elevated("sh", &["-c".to_string(), command.to_string()])
+    }
+    #[cfg(windows)]
+    {
+        crate::audit::record_elevation(command);
+        let script = format!(
+            "$p = Start-Process -FilePath cmd.exe -ArgumentList '/C', '{}' -Verb RunAs -Wait -PassThru; exit $p.ExitCode",
+            command.replace('\'', "''")
+        );
+        let mut shell = Command::new("powershell");
+        shell.arg("-NoProfile").arg("-NonInteractive").arg("-Command").arg(script);
+        shell
+    }
+}
+
+/// Marks a generated script as executable. Windows has no executable bit, so this is a no-op there.
+pub fn make_executable(path: &Path) -> Result<(), io::Error> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+    }
+    #[cfg(windows)]
+    {
+        let _ = path;
+        Ok(())
+    }
+}
+
+/// Returns true if the file can be executed directly.
+pub fn is_executable(metadata: &fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(windows)]
+    {
+        metadata.is_file() // Lookup already appended `.exe`
+    }
+}
+
+/// Creates a symbolic link at `link` pointing to the file `target`.
+pub fn symlink_file(target: &Path, link: &Path) -> Result<(), io::Error> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, link)
+    }
+    #[cfg(windows)]
+    {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
@@ -0,0 +1,89 @@
+//! Git-style external subcommands.
+//!
+//! `cyber-toolkit foo ...` runs `cyber-toolkit-foo ...` from `PATH` when `foo` is not a built-in
+//! subcommand or a role and such an executable exists. A role in the role index (or a configured
+//! one) takes precedence, so a plugin can't take over `cyber-toolkit red`; `cyber-toolkit plugin
+//! foo ...` runs the plugin regardless. The plugin receives context through environment variables:
+//!
+//! - `CYBER_TOOLKIT_ROLES_DIR`: the toolkit's state directory (`~/.local/state/cyber-toolkit`).
+//! - `CYBER_TOOLKIT_CONFIG`: path of the roles list (`roles.cnf`).
+//! - `CYBER_TOOLKIT_SETTINGS`: path of the settings file (`config.toml`).
+//! - `CYBER_TOOLKIT_REPO_URL`: base URL role files are fetched from.
+//! - `CYBER_TOOLKIT_ROLES`: JSON array of the currently configured roles.
+//! - `CYBER_TOOLKIT_VERSION`: version of the invoking toolkit.
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
+
+use cyber_toolkit::roles;
+
+use crate::discovery::{self, ROLE_INDEX_FILE};
+use crate::i18n::t;
+use crate::{platform, settings, state, BASE_RAW_URL};
+
+/// Prefix of plugin executable names.
+const PLUGIN_PREFIX: &str = "cyber-toolkit-";
+
+/// Searches `PATH` for the executable implementing plugin `name`.
+pub fn find_plugin(name: &str) -> Option<PathBuf> {
+    if name.is_empty() || name.starts_with('-') || name.contains('/') {
+        return None;
+    }
+    let executable_name = format!("{}{}{}", PLUGIN_PREFIX, name, std::env::consts::EXE_SUFFIX);
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var)
+        .map(|dir| dir.join(&executable_name))
+        .find(|candidate| {
+            candidate.metadata().map(|metadata| platform::is_executable(&metadata)).unwrap_or(false)
+        })
+}
+
+/// Whether `name` is a role, which takes precedence over a plugin of the same name: one in the
+/// role index, or a configured one. The index is fetched, or read from the cache if that fails.
+pub async fn is_role(name: &str) -> bool {
+    let role = roles::canonical_name(name);
+    let index = match discovery::fetch_role_index().await {
+        Ok(index) => index,
+        Err(_) => state::read_cached_role(ROLE_INDEX_FILE)
+            .ok()
+            .flatten()
+            .map(|text| text.lines().map(roles::canonical_name).collect())
+            .unwrap_or_default(),
+    };
+    index.iter().any(|indexed| roles::canonical_name(indexed) == role)
+        || crate::read_roles_from_config_file().unwrap_or_default().contains(&role)
+}
+
+/// Replaces the current process with the plugin, passing `args` and the context environment variables.
+///
+/// Only returns if the plugin could not be executed. Windows can't replace the process, so
+/// there the plugin runs as a child and its exit code is passed through.
+pub fn exec_plugin(plugin: &PathBuf, args: &[String]) -> Box<dyn std::error::Error> {
+    let (state_dir, config_path) = match (state::state_dir(), crate::config_store()) {
+        (Ok(dir), Ok(store)) => (dir, store.path().to_path_buf()),
+        (Err(e), _) | (_, Err(e)) => return Box::new(e),
+    };
+    let configured_roles = crate::read_roles_from_config_file().unwrap_or_default();
+    let roles_json = serde_json::to_string(&configured_roles).unwrap_or_else(|_| "[]".to_string());
+
+    let mut command = Command::new(plugin);
+    command
+        .args(args)
+        .env("CYBER_TOOLKIT_ROLES_DIR", &state_dir)
+        .env("CYBER_TOOLKIT_CONFIG", config_path)
+        .env("CYBER_TOOLKIT_SETTINGS", settings::settings_path().unwrap_or_default())
+        .env("CYBER_TOOLKIT_REPO_URL", BASE_RAW_URL)
+        .env("CYBER_TOOLKIT_ROLES", roles_json)
+        .env("CYBER_TOOLKIT_VERSION", env!("CARGO_PKG_VERSION"));
+
+    #[cfg(unix)]
+    let error = command.exec();
+    #[cfg(windows)]
+    let error = match command.status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => e,
+    };
+    Box::from(t!("plugin-exec-failed", path = format!("{:?}", plugin), error = error.to_string()))
+}
@@ -0,0 +1,56 @@
+//! Power and network state, so `auto-sync` can hold back large downloads on laptops.
+//!
+//! Both are read over D-Bus with `busctl`: UPower's `OnBattery` property, and NetworkManager's
+//! `Metered` property for the primary connection. Without UPower, the power supplies in
+//! `/sys/class/power_supply` are checked instead. If neither service answers, the machine counts
+//! as on mains power and unmetered.
+
+use std::fs;
+use std::process::Command;
+
+/// `NMMetered` values meaning the connection is metered: `YES` (1) and `GUESS_YES` (3), the
+/// latter e.g. for phone hotspots.
+const NM_METERED: [u32; 2] = [1, 3];
+
+/// Reads a property from the system bus with `busctl get-property`, returning its value without
+/// the type signature (`b true` -> `true`).
+fn get_property(service: &str, path: &str, interface: &str, property: &str) -> Option<String> {
+    let output = Command::new("busctl").args(["--system", "get-property", service, path, interface, property]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    text.trim().split_once(' ').map(|(_, value)| value.to_string())
+}
+
+/// Returns true if the machine runs on battery.
+pub fn on_battery() -> bool {
+    match get_property("org.freedesktop.UPower", "/org/freedesktop/UPower", "org.freedesktop.UPower", "OnBattery") {
+        Some(value) => value == "true",
+        None => on_battery_sysfs(),
+    }
+}
+
+/// Without UPower: on battery if there is a battery and no mains supply is online.
+fn on_battery_sysfs() -> bool {
+    let Ok(entries) = fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    let mut has_battery = false;
+    for entry in entries.flatten() {
+        let read = |name: &str| fs::read_to_string(entry.path().join(name)).map(|value| value.trim().to_string()).unwrap_or_default();
+        match read("type").as_str() {
+            "Mains" | "USB" if read("online") == "1" => return false,
+            "Battery" => has_battery = true,
+            _ => {}
+        }
+    }
+    has_battery
+}
+
+/// Returns true if NetworkManager considers the primary connection metered.
+pub fn on_metered_connection() -> bool {
+    get_property("org.freedesktop.NetworkManager", "/org/freedesktop/NetworkManager", "org.freedesktop.NetworkManager", "Metered")
+        .and_then(|value| value.parse::<u32>().ok())
+        .is_some_and(|metered| NM_METERED.contains(&metered))
+}
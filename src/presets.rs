@@ -0,0 +1,52 @@
+//! Presets: curated groups of roles for common goals such as certifications.
+//!
+//! Presets are defined in `presets/presets.toml` in the repository, one table per preset:
+//!
+//! ```toml
+//! [oscp]
+//! description = "OSCP preparation"
+//! roles = ["red", "network", "web", "cracker"]
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use cyber_toolkit::http;
+
+use crate::i18n::t;
+
+/// URL of the preset definitions.
+const PRESETS_URL: &str = "https://raw.githubusercontent.com/jakubGodula/cyber-toolkit/main/presets/presets.toml";
+
+/// A named group of roles.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Preset {
+    #[serde(default)]
+    pub description: String,
+    pub roles: Vec<String>,
+}
+
+/// Fetches all presets, keyed by name.
+pub async fn fetch_presets() -> Result<BTreeMap<String, Preset>, Box<dyn std::error::Error>> {
+    let response = http::get(PRESETS_URL)?.send().await?;
+    http::check_response(&response)?;
+    if !response.status().is_success() {
+        return Err(Box::from(t!("presets-fetch-failed", url = PRESETS_URL, status = response.status().to_string())));
+    }
+    let content = response.text().await?;
+    toml::from_str(&content).map_err(|e| Box::from(t!("presets-invalid", url = PRESETS_URL, error = e.to_string())))
+}
+
+/// Fetches a single preset by name (case-insensitive).
+pub async fn fetch_preset(name: &str) -> Result<Preset, Box<dyn std::error::Error>> {
+    let presets = fetch_presets().await?;
+    presets
+        .iter()
+        .find(|(preset_name, _)| preset_name.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, preset)| preset.clone())
+        .ok_or_else(|| {
+            let names: Vec<&str> = presets.keys().map(String::as_str).collect();
+            Box::from(t!("preset-unknown", preset = format!("{:?}", name), presets = names.join(", ")))
+        })
+}
@@ -0,0 +1,110 @@
+//! Project-local roles declared in a `.cyber-toolkit.toml` file, like direnv's `.envrc`.
+//!
+//! An engagement or CTF directory lists the roles it needs:
+//!
+//! ```toml
+//! roles = ["web", "wireless"]
+//! ```
+//!
+//! `cyber-toolkit apply` adds them and remembers which of them weren't configured before in the
+//! `projects.json` state document; `cyber-toolkit leave` removes just those again,
+//! unless another applied project still declares them.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::t;
+use crate::state_db;
+
+/// Name of the project file looked up in the current directory and its parents.
+pub const PROJECT_FILE_NAME: &str = ".cyber-toolkit.toml";
+
+/// Contents of a `.cyber-toolkit.toml` file.
+#[derive(Deserialize, Debug)]
+pub struct ProjectFile {
+    /// Roles needed in the project.
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+/// A project whose roles were applied on this machine.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct AppliedProject {
+    /// Roles the project file declared when it was last applied.
+    pub roles: Vec<String>,
+    /// Roles that were added for the project, i.e. weren't configured before; `leave` removes these.
+    pub added: Vec<String>,
+}
+
+/// Returns the directory containing the nearest `.cyber-toolkit.toml`, starting at `start`.
+pub fn find(start: &Path) -> Option<PathBuf> {
+    start.ancestors().find(|dir| dir.join(PROJECT_FILE_NAME).is_file()).map(Path::to_path_buf)
+}
+
+/// Reads the project file in `dir`.
+pub fn load(dir: &Path) -> Result<ProjectFile, Box<dyn std::error::Error>> {
+    let path = dir.join(PROJECT_FILE_NAME);
+    let content = fs::read_to_string(&path)?;
+    toml::from_str(&content).map_err(|e| Box::from(t!("project-file-invalid", path = format!("{:?}", path), error = e.to_string())))
+}
+
+/// Parses the `projects.json` state document.
+fn parse_applied(content: Option<String>) -> Result<BTreeMap<String, AppliedProject>, Box<dyn std::error::Error>> {
+    Ok(content.map(|content| serde_json::from_str(&content)).transpose()?.unwrap_or_default())
+}
+
+/// Reads the applied projects, keyed by project directory.
+pub fn read_applied() -> Result<BTreeMap<String, AppliedProject>, Box<dyn std::error::Error>> {
+    parse_applied(state_db::read_document("projects.json")?)
+}
+
+/// Changes the applied projects with `change`, in one state database transaction.
+fn update_applied(change: impl FnOnce(&mut BTreeMap<String, AppliedProject>)) -> Result<(), Box<dyn std::error::Error>> {
+    state_db::update_document("projects.json", |content| {
+        let mut projects = parse_applied(content)?;
+        change(&mut projects);
+        Ok(Some(serde_json::to_string_pretty(&projects)?))
+    })
+}
+
+/// Records that `project` was applied: its declared `roles`, of which `newly_added` weren't configured before.
+pub fn record_apply(project: &Path, roles: &[String], newly_added: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    update_applied(|projects| {
+        let entry = projects.entry(project.to_string_lossy().into_owned()).or_default();
+        entry.roles = roles.to_vec();
+        entry.added.extend(newly_added.iter().cloned());
+        entry.added.sort_unstable();
+        entry.added.dedup();
+    })
+}
+
+/// Returns the roles to remove when leaving `project`, or `None` if it wasn't applied.
+///
+/// Roles added for the project that another applied project also declares are kept.
+pub fn roles_to_leave(project: &Path) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>> {
+    let projects = read_applied()?;
+    let key = project.to_string_lossy();
+    let Some(left) = projects.get(key.as_ref()) else {
+        return Ok(None);
+    };
+    let still_declared = |role: &String| projects.iter().any(|(other, applied)| *other != key && applied.roles.contains(role));
+    Ok(Some(left.added.iter().filter(|role| !still_declared(role)).cloned().collect()))
+}
+
+/// Forgets `project`. Its added roles that another applied project declares are handed over to
+/// that project, so leaving it later removes them.
+pub fn forget(project: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    update_applied(|projects| {
+        let Some(left) = projects.remove(project.to_string_lossy().as_ref()) else {
+            return;
+        };
+        for role in left.added {
+            if let Some(other) = projects.values_mut().find(|other| other.roles.contains(&role)) {
+                other.added.push(role);
+            }
+        }
+    })
+}
@@ -0,0 +1,107 @@
+//! Purging the data uninstalled tools leave behind, for `--remove --purge`.
+//!
+//! Role metadata lists the configuration and data directories of its tools in a `[purge]`
+//! section, keyed by role entry:
+//!
+//! ```toml
+//! [purge]
+//! metasploit = ["~/.msf4"]
+//! sqlmap = ["~/.local/share/sqlmap"]
+//! ```
+//!
+//! Only paths inside the home directory are purged, each after its own confirmation.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::i18n::t;
+use crate::metadata::RoleMetadata;
+use crate::{pacman, ui};
+
+/// An existing path left behind by an uninstalled tool.
+#[derive(Debug, Clone)]
+pub struct PurgePath {
+    pub tool: String,
+    pub path: PathBuf,
+    /// Total size of the files under the path in bytes.
+    pub size: u64,
+}
+
+/// Expands a leading `~/` to the home directory. Only paths inside the home directory are accepted.
+fn resolve(path: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let home = dirs::home_dir().ok_or_else(|| t!("home-not-found"))?;
+    let relative = path.strip_prefix("~/").ok_or_else(|| t!("purge-path-not-home-relative", path = format!("{:?}", path)))?;
+    if relative.is_empty() || relative.split('/').any(|part| part == ".." || part == ".") {
+        return Err(Box::from(t!("purge-path-outside-home", path = format!("{:?}", path))));
+    }
+    Ok(home.join(relative))
+}
+
+/// Returns the total size of the files under `path`, without following symlinks.
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.filter_map(Result::ok).map(|entry| disk_usage(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Collects the existing purge paths of `tools` from the metadata of their roles.
+pub fn collect(tools: &[String], roles_metadata: &[(String, RoleMetadata)]) -> Vec<PurgePath> {
+    let mut paths: Vec<PurgePath> = Vec::new();
+    for (role, role_metadata) in roles_metadata {
+        for (tool, tool_paths) in role_metadata.purge.iter().filter(|(tool, _)| tools.contains(tool)) {
+            for path in tool_paths {
+                match resolve(path) {
+                    Ok(path) if path.exists() && !paths.iter().any(|known| known.path == path) => {
+                        let size = disk_usage(&path);
+                        paths.push(PurgePath { tool: tool.clone(), path, size });
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("{}", t!("purge-path-ignored", role = role.as_str(), error = e.to_string())),
+                }
+            }
+        }
+    }
+    paths
+}
+
+/// Lists `paths` and deletes each one the user confirms. Without a terminal, nothing is deleted
+/// unless `force` or `--yes` is set; `force` also skips the confirmations.
+pub fn run(paths: &[PurgePath], force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if paths.is_empty() {
+        println!("{}", t!("purge-nothing"));
+        return Ok(());
+    }
+    println!("{}", t!("purge-list"));
+    for purge_path in paths {
+        println!("  {:>10}  {} ({})", pacman::format_size(purge_path.size), purge_path.path.display(), purge_path.tool);
+    }
+    for purge_path in paths {
+        if !force {
+            let prompt = t!("purge-confirm", path = purge_path.path.display().to_string(), size = pacman::format_size(purge_path.size));
+            match ui().confirm(&prompt, false)? {
+                Some(true) => {}
+                Some(false) => continue,
+                None => {
+                    eprintln!("{}", t!("purge-no-terminal"));
+                    return Ok(());
+                }
+            }
+        }
+        let removed = if purge_path.path.is_dir() && !purge_path.path.is_symlink() {
+            fs::remove_dir_all(&purge_path.path)
+        } else {
+            fs::remove_file(&purge_path.path)
+        };
+        match removed {
+            Ok(()) => println!("{}", t!("purge-done", path = purge_path.path.display().to_string())),
+            Err(e) => eprintln!("{}", t!("purge-failed", path = purge_path.path.display().to_string(), error = e.to_string())),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,359 @@
+//! A queue of `RoleManager` operations for long-running embedders such as a provisioning daemon
+//! (`queue` feature).
+//!
+//! A daemon gets requests from several places (its API, D-Bus, timers) that may overlap. Running
+//! them concurrently would race pacman and the roles file, and rejecting them loses work. An
+//! [`OperationQueue`] instead runs one operation at a time, in order. Every submission gets a
+//! [`JobId`] that the daemon can hand out and look up later ([`OperationQueue::job`],
+//! [`OperationQueue::wait`]), and [`OperationQueue::status`] shows what is running and waiting.
+//!
+//! Submitting an operation identical to the last one still waiting (same kind, same roles in any
+//! order) doesn't queue it again: the submission joins that job and gets its ID. An earlier
+//! waiting job is not joined, since the jobs queued after it could undo it (add `a`, remove `a`,
+//! add `a` must end with `a` added), and neither is a running one, since it may have read the
+//! configuration before the request.
+//!
+//! `OperationQueue::run` works through the queue and never returns. Like the `RoleManager`
+//! futures it awaits, it isn't `Send`, so run it next to the code receiving requests, e.g. with
+//! `tokio::select!` in the daemon's main task (or on a `LocalSet`):
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use cyber_toolkit::queue::{Operation, OperationQueue};
+//! use cyber_toolkit::RoleManager;
+//!
+//! let queue = OperationQueue::new(RoleManager::builder().build()?);
+//! let requests = async {
+//!     // From the API, D-Bus or a timer
+//!     let id = queue.submit(Operation::Add(vec!["web".to_string()]));
+//!     println!("{:?}", queue.status().queued);
+//!     queue.wait(id).await
+//! };
+//! tokio::select! {
+//!     () = queue.run() => {}
+//!     job = requests => println!("{:?}", job.map(|job| job.state)),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use tokio::sync::Notify;
+
+use crate::manager::{ChangeReport, RoleManager};
+use crate::roles;
+
+/// Finished jobs kept for lookups by ID; older ones are forgotten.
+const FINISHED_JOBS_KEPT: usize = 100;
+
+/// Identifies a submitted job. IDs are never reused within a queue.
+pub type JobId = u64;
+
+/// An operation on the configured roles, see the `RoleManager` methods of the same names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Add(Vec<String>),
+    Remove(Vec<String>),
+    Set(Vec<String>),
+    /// Installs the tools of the configured roles without changing them.
+    Sync,
+}
+
+impl Operation {
+    /// The operation with canonical role names (see `roles::canonical_names`).
+    fn canonical(self) -> Self {
+        match self {
+            Operation::Add(roles) => Operation::Add(roles::canonical_names(&roles)),
+            Operation::Remove(roles) => Operation::Remove(roles::canonical_names(&roles)),
+            Operation::Set(roles) => Operation::Set(roles::canonical_names(&roles)),
+            Operation::Sync => Operation::Sync,
+        }
+    }
+
+    /// Whether both operations do the same, regardless of the order their roles are named in.
+    fn same_as(&self, other: &Operation) -> bool {
+        let roles = |operation: &Operation| -> BTreeSet<String> {
+            match operation {
+                Operation::Add(roles) | Operation::Remove(roles) | Operation::Set(roles) => roles.iter().cloned().collect(),
+                Operation::Sync => BTreeSet::new(),
+            }
+        };
+        std::mem::discriminant(self) == std::mem::discriminant(other) && roles(self) == roles(other)
+    }
+}
+
+/// Where a job is.
+#[derive(Debug, Clone)]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded(ChangeReport),
+    Failed(String),
+}
+
+impl JobState {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobState::Succeeded(_) | JobState::Failed(_))
+    }
+}
+
+/// A submitted operation.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: JobId,
+    pub operation: Operation,
+    pub state: JobState,
+    /// How many submissions this job serves: 1, plus the identical ones coalesced into it.
+    pub submissions: usize,
+    pub submitted_at: SystemTime,
+    pub started_at: Option<SystemTime>,
+    pub finished_at: Option<SystemTime>,
+}
+
+/// What the queue is doing, see `OperationQueue::status`.
+#[derive(Debug, Clone)]
+pub struct QueueStatus {
+    pub running: Option<Job>,
+    /// Waiting jobs, next first.
+    pub queued: Vec<Job>,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    next_id: JobId,
+    /// The running job and the queued ones, in submission order. Finished jobs move to `finished`.
+    active: VecDeque<Job>,
+    /// Finished jobs, oldest first.
+    finished: VecDeque<Job>,
+}
+
+/// Runs `RoleManager` operations one at a time, in submission order. Jobs are submitted with
+/// `&self`, so the code receiving requests can share the queue with `run`.
+pub struct OperationQueue {
+    manager: RoleManager,
+    state: Mutex<QueueState>,
+    /// Signaled when a job is queued.
+    work: Notify,
+    /// Signaled when a job changes state.
+    changed: Notify,
+}
+
+impl OperationQueue {
+    pub fn new(manager: RoleManager) -> Self {
+        OperationQueue { manager, state: Mutex::new(QueueState::default()), work: Notify::new(), changed: Notify::new() }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queues `operation` and returns its job ID, or the ID of the last waiting job if it is
+    /// identical, which then serves both submissions.
+    pub fn submit(&self, operation: Operation) -> JobId {
+        let operation = operation.canonical();
+        let mut state = self.lock();
+        let last = state.active.back_mut().filter(|job| matches!(job.state, JobState::Queued));
+        if let Some(job) = last.filter(|job| job.operation.same_as(&operation)) {
+            job.submissions += 1;
+            return job.id;
+        }
+        state.next_id += 1;
+        let id = state.next_id;
+        state.active.push_back(Job {
+            id,
+            operation,
+            state: JobState::Queued,
+            submissions: 1,
+            submitted_at: SystemTime::now(),
+            started_at: None,
+            finished_at: None,
+        });
+        drop(state);
+        self.work.notify_one();
+        id
+    }
+
+    /// The job with `id`, if it is active or among the recently finished ones.
+    pub fn job(&self, id: JobId) -> Option<Job> {
+        let state = self.lock();
+        state.active.iter().chain(state.finished.iter()).find(|job| job.id == id).cloned()
+    }
+
+    /// The running job and the waiting ones.
+    pub fn status(&self) -> QueueStatus {
+        let state = self.lock();
+        QueueStatus {
+            running: state.active.iter().find(|job| matches!(job.state, JobState::Running)).cloned(),
+            queued: state.active.iter().filter(|job| matches!(job.state, JobState::Queued)).cloned().collect(),
+        }
+    }
+
+    /// Recently finished jobs, oldest first.
+    pub fn finished(&self) -> Vec<Job> {
+        self.lock().finished.iter().cloned().collect()
+    }
+
+    /// Waits until the job with `id` has finished and returns it; `None` if there is no such job
+    /// (or it finished so long ago that it was forgotten).
+    pub async fn wait(&self, id: JobId) -> Option<Job> {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            // Registered before looking, so a change in between isn't missed
+            changed.as_mut().enable();
+            match self.job(id) {
+                Some(job) if job.state.is_finished() => return Some(job),
+                Some(_) => changed.await,
+                None => return None,
+            }
+        }
+    }
+
+    /// Runs jobs as they are submitted. Never returns.
+    pub async fn run(&self) {
+        loop {
+            if !self.run_next().await {
+                self.work.notified().await;
+            }
+        }
+    }
+
+    /// Runs the jobs waiting now and those submitted meanwhile, until the queue is empty.
+    pub async fn run_pending(&self) {
+        while self.run_next().await {}
+    }
+
+    /// Runs the next waiting job, returning false if there was none.
+    async fn run_next(&self) -> bool {
+        let operation = {
+            let mut state = self.lock();
+            let Some(job) = state.active.iter_mut().find(|job| matches!(job.state, JobState::Queued)) else {
+                return false;
+            };
+            job.state = JobState::Running;
+            job.started_at = Some(SystemTime::now());
+            job.operation.clone()
+        };
+        self.changed.notify_waiters();
+
+        let result = match &operation {
+            Operation::Add(roles) => self.manager.add(roles).await,
+            Operation::Remove(roles) => self.manager.remove(roles).await,
+            Operation::Set(roles) => self.manager.set(roles).await,
+            Operation::Sync => self.manager.add(&[]).await,
+        };
+        let outcome = match result {
+            Ok(report) => JobState::Succeeded(report),
+            Err(e) => JobState::Failed(e.to_string()),
+        };
+
+        {
+            let mut state = self.lock();
+            if let Some(index) = state.active.iter().position(|job| matches!(job.state, JobState::Running)) {
+                if let Some(mut job) = state.active.remove(index) {
+                    job.state = outcome;
+                    job.finished_at = Some(SystemTime::now());
+                    state.finished.push_back(job);
+                    while state.finished.len() > FINISHED_JOBS_KEPT {
+                        state.finished.pop_front();
+                    }
+                }
+            }
+        }
+        self.changed.notify_waiters();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{fixtures, FakeBackend, TempConfig};
+
+    fn roles(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn queue(config: &TempConfig, backend: &FakeBackend) -> OperationQueue {
+        OperationQueue::new(RoleManager::builder().fetcher(fixtures::fetcher()).backend(backend.clone()).config_path(config.path()).build().unwrap())
+    }
+
+    #[tokio::test]
+    async fn identical_submissions_join_the_last_waiting_job() {
+        let config = TempConfig::new().unwrap();
+        let backend = FakeBackend::new();
+        let queue = queue(&config, &backend);
+
+        let first = queue.submit(Operation::Add(roles(&["web", "blue"])));
+        let second = queue.submit(Operation::Add(roles(&["Blue.txt", "web"])));
+
+        assert_eq!(first, second);
+        assert_eq!(queue.status().queued.len(), 1);
+        let (_, job) = tokio::join!(queue.run_pending(), queue.wait(first));
+        let job = job.unwrap();
+        assert_eq!(job.submissions, 2);
+        assert!(matches!(job.state, JobState::Succeeded(_)));
+        assert_eq!(backend.transcript(), "install burpsuite ffuf nmap sqlmap volatility3 wireshark-cli\n");
+    }
+
+    #[tokio::test]
+    async fn submissions_are_not_joined_across_a_different_job() {
+        let config = TempConfig::new().unwrap();
+        let backend = FakeBackend::new();
+        let queue = queue(&config, &backend);
+
+        let add = queue.submit(Operation::Add(roles(&["blue"])));
+        let remove = queue.submit(Operation::Remove(roles(&["blue"])));
+        let add_again = queue.submit(Operation::Add(roles(&["blue"])));
+
+        assert_ne!(add, add_again);
+        assert_eq!(queue.status().queued.iter().map(|job| job.id).collect::<Vec<_>>(), [add, remove, add_again]);
+        queue.run_pending().await;
+        assert_eq!(queue.finished().iter().map(|job| job.id).collect::<Vec<_>>(), [add, remove, add_again]);
+        assert_eq!(queue.manager.list().unwrap(), ["blue"]);
+        assert_eq!(backend.installed_tools(), ["nmap", "volatility3", "wireshark-cli"]);
+    }
+
+    #[tokio::test]
+    async fn wait_returns_finished_jobs_at_once_and_none_for_unknown_ones() {
+        let config = TempConfig::new().unwrap();
+        let backend = FakeBackend::new();
+        let queue = queue(&config, &backend);
+
+        let id = queue.submit(Operation::Add(roles(&["blue", "missing"])));
+        queue.run_pending().await;
+
+        let job = queue.wait(id).await.unwrap();
+        let JobState::Succeeded(report) = job.state else {
+            panic!("job failed: {:?}", job.state);
+        };
+        assert_eq!(report.roles_added, ["blue"]);
+        assert_eq!(report.failed.len(), 1);
+        assert!(job.finished_at.is_some());
+        assert!(queue.wait(id + 1).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn only_the_latest_finished_jobs_are_kept() {
+        let config = TempConfig::new().unwrap();
+        let backend = FakeBackend::new();
+        let queue = queue(&config, &backend);
+
+        // Run one at a time, so the identical submissions aren't joined
+        let mut ids = Vec::new();
+        for _ in 0..=FINISHED_JOBS_KEPT {
+            ids.push(queue.submit(Operation::Sync));
+            queue.run_pending().await;
+        }
+
+        let finished = queue.finished();
+        assert_eq!(finished.len(), FINISHED_JOBS_KEPT);
+        assert_eq!(finished.first().map(|job| job.id), Some(ids[1]));
+        assert!(queue.job(ids[0]).is_none());
+        assert!(queue.wait(ids[0]).await.is_none());
+        assert!(queue.wait(ids[FINISHED_JOBS_KEPT]).await.is_some());
+    }
+}
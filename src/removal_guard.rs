@@ -0,0 +1,61 @@
+//! Safety backstop for removals: uninstalling many packages at once, or packages matching the
+//! protected patterns of the `[removal]` settings, needs an explicit confirmation.
+//!
+//! The confirmation is asked through `ui()` (so `--yes` gives it); without a terminal, `--force`
+//! or `--yes` is required.
+
+use crate::i18n::t;
+use crate::settings::RemovalSettings;
+use crate::ui;
+
+/// Returns true if `name` matches `pattern`, where `*` matches any run of characters.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty(); // No `*` at all: exact match
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Checks the packages about to be uninstalled against the `[removal]` settings.
+///
+/// If there are more than `confirm_above` of them, or any matches a protected pattern, the user
+/// has to confirm the removal unless `force` is set. Returns an error if the removal must not go ahead.
+pub fn check(packages: &[String], settings: &RemovalSettings, force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let protected: Vec<&String> =
+        packages.iter().filter(|package| settings.protected.iter().any(|pattern| matches_pattern(pattern, package))).collect();
+    let too_many = packages.len() > settings.confirm_above;
+    if !too_many && protected.is_empty() {
+        return Ok(());
+    }
+
+    let mut reasons = Vec::new();
+    if too_many {
+        reasons.push(t!("removal-guard-too-many", count = packages.len(), limit = settings.confirm_above));
+    }
+    if !protected.is_empty() {
+        reasons.push(t!("removal-guard-protected", packages = format!("{:?}", protected)));
+    }
+    let reason = reasons.join("; ");
+    if force {
+        eprintln!("{}", t!("removal-guard-forced", reason = reason.as_str()));
+        return Ok(());
+    }
+    println!("{}", t!("removal-guard-packages", packages = format!("{:?}", packages)));
+    match ui().confirm(&t!("removal-guard-confirm", reason = reason.as_str()), false)? {
+        Some(true) => Ok(()),
+        Some(false) => Err(Box::from(t!("removal-guard-cancelled"))),
+        None => Err(Box::from(t!("removal-guard-refused", reason = reason))),
+    }
+}
@@ -0,0 +1,41 @@
+//! Progress reporting for library callers.
+//!
+//! Library functions report what happens through a `Reporter` instead of printing. Every method
+//! has an empty default, so implementors only override the events they care about.
+
+use crate::backend::{OutputStream, PackageOperation};
+use crate::roles::Role;
+
+/// Receives progress events from library operations.
+pub trait Reporter {
+    /// A role file is about to be fetched from `location` (usually a URL).
+    fn fetching_role(&self, _role: &str, _location: &str) {}
+
+    /// A role file was fetched and resolved for this host.
+    fn role_fetched(&self, _role: &Role) {}
+
+    /// A role file could not be fetched; the operation continues with the other roles.
+    fn role_failed(&self, _role: &str, _error: &str) {}
+
+    /// Tools are about to be installed or updated.
+    fn installing_tools(&self, _tools: &[String]) {}
+
+    /// Tools are about to be removed.
+    fn removing_tools(&self, _tools: &[String]) {}
+
+    /// Whether the package manager's output should be captured and passed to `package_output`
+    /// instead of going to the inherited terminal.
+    fn captures_package_output(&self) -> bool {
+        false
+    }
+
+    /// The package manager printed `line` on `stream` during `operation`, e.g. to forward it to
+    /// a log. Only called if `captures_package_output` returns true.
+    fn package_output(&self, _operation: PackageOperation, _stream: OutputStream, _line: &str) {}
+}
+
+/// A reporter that discards all events.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SilentReporter;
+
+impl Reporter for SilentReporter {}
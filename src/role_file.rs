@@ -0,0 +1,285 @@
+//! Parsing of role files (tool lists) into tool names.
+//!
+//! Role files may come from Windows editors or be annotated by maintainers: a UTF-8 byte order
+//! mark and CRLF line endings are accepted, and `#` starts a comment, either on a line of its own
+//! or after an entry (`nmap  # port scanner`).
+//!
+//! Besides one tool per line, a role file may contain rhai script blocks (see `scripting`)
+//! between a `#!rhai` line and a `#!end` line; they are evaluated against the host facts.
+//!
+//! A tool line may also be prefixed with one or more `@condition` markers (e.g. `@wifi aircrack-ng`
+//! or `@baremetal @!vm hashcat`); the tool is only included if every condition holds on this host.
+//! See `HostFacts::satisfies` for the supported conditions. GUI tools are marked with `@gui`
+//! (e.g. `@gui wireshark-qt`) so headless machines skip them, and `@!gui tshark` installs a tool
+//! only on headless machines.
+//!
+//! Entries can be grouped into architecture sections: a `[x86_64]` or `[aarch64, armv7h]` line starts
+//! a section whose entries only apply on those architectures, and `[all]` returns to the common section.
+//!
+//! The first line may declare the format of the file, e.g. `#!format 1`; files without it are
+//! format 1. Files in a newer format than `FORMAT_VERSION` are refused by `check_format` rather
+//! than half-understood, since a misread role could install or remove the wrong tools.
+
+use crate::{facts, scripting};
+
+/// Newest role file format this version understands.
+pub const FORMAT_VERSION: u32 = 1;
+/// Start of the line declaring the format of a role file.
+pub const FORMAT_MARKER: &str = "#!format";
+
+/// Line starting a script block.
+const SCRIPT_START: &str = "#!rhai";
+/// Line ending a script block.
+const SCRIPT_END: &str = "#!end";
+
+/// A role file entry that was left out on this host.
+#[derive(Debug, Clone)]
+pub struct SkippedTool {
+    pub tool: String,
+    /// Human-readable reason, e.g. `condition @wifi not met`.
+    pub reason: String,
+}
+
+/// Result of parsing a role file.
+#[derive(Debug, Default)]
+pub struct ParsedRoleFile {
+    /// Tools to install on this host, in file order.
+    pub tools: Vec<String>,
+    /// Entries skipped because of their section or conditions.
+    pub skipped: Vec<SkippedTool>,
+    /// Problems found while parsing: failing or unterminated script blocks, unknown conditions.
+    pub warnings: Vec<String>,
+}
+
+/// Format a role file declares on its first line, or 1 if it declares none.
+pub fn format_version(content: &str) -> Result<u32, Box<dyn std::error::Error>> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let Some(version) = content.lines().next().and_then(|line| line.trim().strip_prefix(FORMAT_MARKER)) else {
+        return Ok(1);
+    };
+    version.trim().parse().map_err(|_| Box::from(format!("invalid format marker `{} {}`", FORMAT_MARKER, version.trim())))
+}
+
+/// Refuses role files in a format newer than `FORMAT_VERSION`, telling the user to update.
+pub fn check_format(content: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let version = format_version(content)?;
+    if version > FORMAT_VERSION {
+        return Err(Box::from(format!(
+            "it uses role file format {}, but this version of cyber-toolkit only understands format {}; update cyber-toolkit to use it",
+            version, FORMAT_VERSION
+        )));
+    }
+    Ok(())
+}
+
+/// Parses the content of a role file into tool names.
+///
+/// - Ignores a leading byte order mark and the `\r` of CRLF line endings.
+/// - Removes `#` comments: whole-line ones and inline ones after whitespace.
+/// - Trims whitespace, removes trailing commas and strips matching surrounding quotes.
+/// - Skips empty lines.
+/// - Tracks architecture sections; entries in sections for other architectures are skipped.
+/// - Drops tools whose `@condition` markers don't hold on this host (unknown conditions count as unmet).
+/// - Evaluates script blocks; a failing script is recorded as a warning and contributes no tools.
+/// - Skips entries starting with `-` with a warning, since they would reach pacman as options.
+pub fn parse_tool_list(content: &str, source: &str) -> ParsedRoleFile {
+    let mut parsed = ParsedRoleFile::default();
+    let mut script: Option<String> = None;
+    let mut section_archs: Option<Vec<String>> = None; // None means the common section
+
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    for line in content.lines().map(|line| line.trim_end_matches('\r')) {
+        let in_foreign_section = section_archs
+            .as_ref()
+            .is_some_and(|archs| !archs.iter().any(|arch| arch_matches(arch, &facts::current().arch)));
+
+        if let Some(body) = script.as_mut() {
+            if line.trim() == SCRIPT_END {
+                if in_foreign_section {
+                    parsed.skipped.push(SkippedTool { tool: "(script block)".to_string(), reason: foreign_section_reason(&section_archs) });
+                } else {
+                    match scripting::evaluate(body, facts::current()) {
+                        Ok(script_tools) => parsed.tools.extend(script_tools.iter().map(|t| normalize_tool_name(t)).filter(|t| !t.is_empty())),
+                        Err(e) => parsed.warnings.push(format!("Script block in {} failed: {}. Skipping it.", source, e)),
+                    }
+                }
+                script = None;
+            } else {
+                body.push_str(line);
+                body.push('\n');
+            }
+            continue;
+        }
+        if line.trim() == SCRIPT_START {
+            script = Some(String::new());
+            continue;
+        }
+        let line = strip_comment(line);
+        if let Some(archs) = parse_section_header(line) {
+            section_archs = archs;
+            continue;
+        }
+
+        let (conditions, entry) = split_conditions(line);
+        let tool = normalize_tool_name(entry);
+        if tool.is_empty() {
+            continue;
+        }
+        if in_foreign_section {
+            parsed.skipped.push(SkippedTool { tool, reason: foreign_section_reason(&section_archs) });
+            continue;
+        }
+        if let Some(unmet) = conditions.iter().find(|c| facts::current().satisfies(c) != Some(true)) {
+            if facts::current().satisfies(unmet).is_none() {
+                parsed.warnings.push(format!("Unknown condition @{} for {} in {}.", unmet, tool, source));
+            }
+            parsed.skipped.push(SkippedTool { tool, reason: format!("condition @{} not met", unmet) });
+            continue;
+        }
+        parsed.tools.push(tool);
+    }
+
+    if script.is_some() {
+        parsed.warnings.push(format!("Unterminated script block in {} (missing `{}`). Skipping it.", source, SCRIPT_END));
+    }
+    // Entries end up as package manager arguments, where one starting with `-` would be an option
+    parsed.tools.retain(|tool| {
+        if tool.starts_with('-') {
+            parsed.warnings.push(format!("Invalid entry {:?} in {}: tool names can't start with '-'. Skipping it.", tool, source));
+        }
+        !tool.starts_with('-')
+    });
+    parsed
+}
+
+/// Parses an architecture section header such as `[x86_64]` or `[x86_64, aarch64]`.
+///
+/// Returns `Some(None)` for `[all]`, which starts the common section again, and `None` if the
+/// line is not a section header.
+fn parse_section_header(line: &str) -> Option<Option<Vec<String>>> {
+    let inner = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let archs: Vec<String> = inner
+        .split(',')
+        .map(|arch| arch.trim().to_string())
+        .filter(|arch| !arch.is_empty())
+        .collect();
+    if archs.iter().any(|arch| arch == "all") {
+        Some(None)
+    } else {
+        Some(Some(archs))
+    }
+}
+
+/// Compares a section architecture with the local one, accepting Arch Linux names (e.g. `armv7h`).
+fn arch_matches(section_arch: &str, local_arch: &str) -> bool {
+    section_arch == local_arch || (section_arch == "armv7h" && local_arch == "arm")
+}
+
+fn foreign_section_reason(section_archs: &Option<Vec<String>>) -> String {
+    format!(
+        "only for {} (this host is {})",
+        section_archs.as_deref().unwrap_or_default().join(", "),
+        facts::current().arch
+    )
+}
+
+/// Removes a `#` comment from a line: the whole line if it starts with `#`, otherwise from a `#`
+/// that follows whitespace, so `#` inside an entry is kept.
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('#') {
+        return "";
+    }
+    let mut previous = ' ';
+    for (index, c) in line.char_indices() {
+        if c == '#' && previous.is_whitespace() {
+            return &line[..index];
+        }
+        previous = c;
+    }
+    line
+}
+
+/// Splits leading `@condition` markers off a role file line, returning the conditions and the rest of the line.
+fn split_conditions(line: &str) -> (Vec<&str>, &str) {
+    let mut conditions = Vec::new();
+    let mut rest = line.trim_start();
+    while let Some(marker) = rest.strip_prefix('@') {
+        let end = marker.find(char::is_whitespace).unwrap_or(marker.len());
+        conditions.push(&marker[..end]);
+        rest = marker[end..].trim_start();
+    }
+    (conditions, rest)
+}
+
+/// Normalizes a tool name: trims whitespace, removes trailing commas, and strips surrounding quotes.
+fn normalize_tool_name(line: &str) -> String {
+    let s = line.trim();
+    let s_no_comma = s.trim_end_matches(',');
+    let mut final_s = s_no_comma.trim(); // Trim again after comma removal
+    if final_s.len() >= 2 &&
+       ((final_s.starts_with('"') && final_s.ends_with('"')) ||
+        (final_s.starts_with('\'') && final_s.ends_with('\''))) {
+        final_s = &final_s[1..final_s.len() - 1]; // Strip quotes
+    }
+    final_s.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_tool_list, strip_comment};
+
+    #[test]
+    fn strip_comment_removes_comment_lines_and_trailing_comments() {
+        assert_eq!(strip_comment("# Network tools"), "");
+        assert_eq!(strip_comment("   # indented"), "");
+        assert_eq!(strip_comment("nmap # port scanner"), "nmap ");
+        assert_eq!(strip_comment("nmap\t# tab before the comment"), "nmap\t");
+    }
+
+    #[test]
+    fn strip_comment_keeps_hashes_inside_entries() {
+        assert_eq!(strip_comment("c#-tool"), "c#-tool");
+        assert_eq!(strip_comment("c#-tool # but not this"), "c#-tool ");
+        assert_eq!(strip_comment("nmap"), "nmap");
+    }
+
+    #[test]
+    fn parse_tool_list_accepts_a_byte_order_mark_and_crlf_line_endings() {
+        let parsed = parse_tool_list("\u{feff}# Windows notepad\r\nnmap\r\n\r\nsqlmap # injection\r\n", "web");
+        assert_eq!(parsed.tools, ["nmap", "sqlmap"]);
+        assert!(parsed.warnings.is_empty());
+    }
+
+    #[test]
+    fn parse_tool_list_cleans_up_sloppy_entries() {
+        let parsed = parse_tool_list("  nmap,\n\"ffuf\"\n'sqlmap',\n,\n   \t\n", "web");
+        assert_eq!(parsed.tools, ["nmap", "ffuf", "sqlmap"]);
+    }
+
+    #[test]
+    fn parse_tool_list_skips_entries_that_would_be_options() {
+        let parsed = parse_tool_list("nmap\n--noconfirm\n-Rns\n", "web");
+        assert_eq!(parsed.tools, ["nmap"]);
+        assert_eq!(parsed.warnings.len(), 2);
+        assert!(parsed.warnings[0].contains("\"--noconfirm\" in web"));
+    }
+
+    #[test]
+    fn parse_tool_list_warns_about_unknown_conditions_and_unterminated_scripts() {
+        let parsed = parse_tool_list("@no-such-condition hashcat\nnmap\n#!rhai\nffuf\n", "red");
+        assert_eq!(parsed.tools, ["nmap"]);
+        assert_eq!(parsed.skipped.len(), 1);
+        assert_eq!(parsed.skipped[0].tool, "hashcat");
+        assert_eq!(parsed.warnings.len(), 2);
+        assert!(parsed.warnings[0].contains("@no-such-condition"));
+        assert!(parsed.warnings[1].contains("Unterminated script block in red"));
+    }
+
+    #[test]
+    fn parse_tool_list_skips_sections_for_other_architectures() {
+        let parsed = parse_tool_list("[no-such-arch]\nhashcat\n[]\nffuf\n[all]\nnmap\n", "red");
+        assert_eq!(parsed.tools, ["nmap"]);
+        let skipped: Vec<&str> = parsed.skipped.iter().map(|skipped| skipped.tool.as_str()).collect();
+        assert_eq!(skipped, ["hashcat", "ffuf"]);
+    }
+}
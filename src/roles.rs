@@ -0,0 +1,138 @@
+//! Fetching role files and resolving them into tool lists.
+
+use crate::fetcher::RoleFetcher;
+use crate::report::Reporter;
+use crate::role_file::{self, SkippedTool};
+
+/// Base URL from which role files (tool lists) are fetched.
+pub const BASE_RAW_URL: &str = "https://raw.githubusercontent.com/jakubGodula/cyber-toolkit/main/roles/";
+
+/// Extension some people give role names, after the files' old naming (`blue.txt`).
+const ROLE_FILE_EXTENSION: &str = ".txt";
+
+/// Returns the canonical form of a role name, which is also the name of its file in the
+/// repository: trimmed, lowercase and without a `.txt` extension. `Blue-Teamer`, `blue-teamer`
+/// and `blue-teamer.txt` are all the role `blue-teamer`.
+pub fn canonical_name(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    match name.strip_suffix(ROLE_FILE_EXTENSION) {
+        Some(stem) if !stem.is_empty() => stem.to_string(),
+        _ => name,
+    }
+}
+
+/// Canonicalizes `names` (see `canonical_name`), dropping empty names and all but the first of
+/// names that are the same role.
+pub fn canonical_names<S: AsRef<str>>(names: &[S]) -> Vec<String> {
+    let mut canonical: Vec<String> = Vec::new();
+    for name in names.iter().map(|name| canonical_name(name.as_ref())).filter(|name| !name.is_empty()) {
+        if !canonical.contains(&name) {
+            canonical.push(name);
+        }
+    }
+    canonical
+}
+
+/// A role file fetched from the repository and resolved for this host.
+#[derive(Debug, Clone)]
+pub struct Role {
+    pub name: String,
+    /// Where the role file was fetched from, as described by the fetcher (usually a URL).
+    pub location: String,
+    /// Raw content of the role file, e.g. for caching.
+    pub content: String,
+    /// Tools to install on this host, in file order.
+    pub tools: Vec<String>,
+    /// Entries left out on this host (other architectures, unmet conditions).
+    pub skipped: Vec<SkippedTool>,
+    /// Problems found while resolving the file, such as failing script blocks.
+    pub warnings: Vec<String>,
+}
+
+/// A role file that could not be fetched.
+#[derive(Debug, Clone)]
+pub struct RoleFetchError {
+    pub role: String,
+    pub error: String,
+}
+
+/// Result of fetching several role files.
+#[derive(Debug, Default)]
+pub struct FetchReport {
+    pub roles: Vec<Role>,
+    pub failed: Vec<RoleFetchError>,
+}
+
+impl FetchReport {
+    /// All unique tools of the fetched roles, sorted.
+    pub fn tools(&self) -> Vec<String> {
+        let mut tools: Vec<String> = self.roles.iter().flat_map(|role| role.tools.iter().cloned()).collect();
+        tools.sort_unstable();
+        tools.dedup();
+        tools
+    }
+}
+
+/// Fetches one role file with `fetcher` and resolves it for this host. `name` is canonicalized
+/// first (see `canonical_name`). Role files in a newer format are refused (see
+/// `role_file::check_format`).
+pub async fn fetch_role(name: &str, fetcher: &dyn RoleFetcher) -> Result<Role, Box<dyn std::error::Error>> {
+    let name = canonical_name(name);
+    let name = name.as_str();
+    let content = fetcher.fetch(name).await?;
+    role_file::check_format(&content)?;
+    let parsed = role_file::parse_tool_list(&content, name);
+    Ok(Role {
+        name: name.to_string(),
+        location: fetcher.location(name),
+        content,
+        tools: parsed.tools,
+        skipped: parsed.skipped,
+        warnings: parsed.warnings,
+    })
+}
+
+/// Fetches the given role files. Roles that can't be fetched are reported and recorded in
+/// `FetchReport::failed`; the others are still fetched. Names are canonicalized, so each role is
+/// fetched once however it is spelled; empty names are ignored.
+pub async fn fetch_roles(names: &[String], fetcher: &dyn RoleFetcher, reporter: &dyn Reporter) -> FetchReport {
+    let mut report = FetchReport::default();
+    for name in canonical_names(names).iter().map(String::as_str) {
+        reporter.fetching_role(name, &fetcher.location(name));
+        match fetch_role(name, fetcher).await {
+            Ok(role) => {
+                reporter.role_fetched(&role);
+                report.roles.push(role);
+            }
+            Err(e) => {
+                reporter.role_failed(name, &e.to_string());
+                report.failed.push(RoleFetchError { role: name.to_string(), error: e.to_string() });
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonical_name, canonical_names};
+
+    #[test]
+    fn canonical_name_lowercases_and_drops_the_extension() {
+        assert_eq!(canonical_name("Blue-Teamer"), "blue-teamer");
+        assert_eq!(canonical_name(" blue-teamer.txt "), "blue-teamer");
+        assert_eq!(canonical_name("Blue-Teamer.TXT"), "blue-teamer");
+        assert_eq!(canonical_name("web/api"), "web/api");
+    }
+
+    #[test]
+    fn canonical_name_keeps_a_bare_extension() {
+        assert_eq!(canonical_name(".txt"), ".txt");
+        assert_eq!(canonical_name(""), "");
+    }
+
+    #[test]
+    fn canonical_names_drops_duplicates_and_empty_names() {
+        assert_eq!(canonical_names(&["Red", "blue", "red.txt", " ", "BLUE"]), ["red", "blue"]);
+    }
+}
@@ -0,0 +1,91 @@
+//! Locks that keep concurrent runs of the toolkit out of each other's way without serializing
+//! everything: a 30-minute role install shouldn't block `--current` or `which-role`.
+//!
+//! Each run holds, for its whole duration, the locks its kind of command needs (see `Access`),
+//! as advisory file locks on two files in the state directory:
+//!
+//! - `operation.lock`, held exclusively by every command that changes the machine or the
+//!   configuration, so only one of them runs at a time. It also names the run holding it, for
+//!   the message of a run that has to wait.
+//! - `state.lock`, held shared by every run, and exclusively by the few commands that replace
+//!   what the others read (`state import`, `cache clear`), which therefore run alone.
+//!
+//! Read-only commands only take `state.lock` shared, so they run alongside an install. Locks are
+//! always taken in this order, and released by the operating system when the run ends, however
+//! it ends.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::i18n::t;
+use crate::state;
+
+const OPERATION_LOCK: &str = "operation.lock";
+const STATE_LOCK: &str = "state.lock";
+
+/// What a command needs to be protected from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Only reads the configuration and state: runs alongside anything but `Exclusive`.
+    Read,
+    /// Changes the machine or the configuration: one at a time, alongside readers.
+    Write,
+    /// Replaces state others read: runs alone.
+    Exclusive,
+}
+
+/// The locks of a run, released when dropped.
+#[derive(Debug)]
+pub struct RunLock {
+    _operation: Option<File>,
+    _state: File,
+}
+
+/// Takes the locks for `access`, waiting (with a message) while another run holds them.
+/// `command` names this run to the runs that have to wait for it.
+pub fn acquire(access: Access, command: &str) -> Result<RunLock, io::Error> {
+    let dir = state::state_dir()?;
+    fs::create_dir_all(&dir)?;
+    let operation = if access == Access::Read {
+        None
+    } else {
+        let path = dir.join(OPERATION_LOCK);
+        let mut file = open(&path)?;
+        lock(&file, true, Some(&path))?;
+        file.set_len(0)?;
+        write!(file, "{} {}", std::process::id(), command)?;
+        Some(file)
+    };
+    let state = open(&dir.join(STATE_LOCK))?;
+    // Only an `Exclusive` run keeps a reader waiting here, and it holds `operation.lock` too;
+    // an `Exclusive` run waits for readers, which don't name themselves
+    let holder = (access == Access::Read).then(|| dir.join(OPERATION_LOCK));
+    lock(&state, access == Access::Exclusive, holder.as_deref())?;
+    Ok(RunLock { _operation: operation, _state: state })
+}
+
+fn open(path: &Path) -> Result<File, io::Error> {
+    OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)
+}
+
+/// Locks `file`, exclusively or shared. If another run holds a conflicting lock, says so before
+/// waiting for it, naming the run from the `holder` lock file if given.
+fn lock(file: &File, exclusive: bool, holder: Option<&Path>) -> Result<(), io::Error> {
+    let attempt = if exclusive { file.try_lock() } else { file.try_lock_shared() };
+    match attempt {
+        Ok(()) => return Ok(()),
+        Err(fs::TryLockError::WouldBlock) => {}
+        Err(fs::TryLockError::Error(e)) => return Err(e),
+    }
+    let holder = holder.and_then(|path| fs::read_to_string(path).ok()).unwrap_or_default();
+    match holder.trim().split_once(' ') {
+        Some((pid, command)) => eprintln!("{}", t!("run-lock-waiting-for", pid = pid, command = command)),
+        None => eprintln!("{}", t!("run-lock-waiting")),
+    }
+    if exclusive {
+        file.lock()
+    } else {
+        file.lock_shared()
+    }
+}
@@ -0,0 +1,55 @@
+//! Evaluation of rhai script blocks embedded in role files.
+//!
+//! A script sees the host facts as variables and returns the tools to include:
+//!
+//! ```text
+//! #!rhai
+//! if gpu == "nvidia" { ["hashcat", "hashcat-nvidia"] } else { ["hashcat"] }
+//! #!end
+//! ```
+//!
+//! Available variables: `arch`, `gpu`, `ram_mb`, `virtualization`, `is_vm`, `wifi`, `headless`.
+//! The script may evaluate to an array of strings, a single string, or `()` for no tools.
+
+use rhai::{Array, Dynamic, Engine, Scope};
+
+use crate::facts::HostFacts;
+
+/// Upper bound on script operations, so a buggy role file can't hang the toolkit.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Evaluates a script block and returns the tool names it produced.
+pub fn evaluate(script: &str, facts: &HostFacts) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let mut scope = Scope::new();
+    scope.push_constant("arch", facts.arch.clone());
+    scope.push_constant("gpu", facts.gpu.clone());
+    scope.push_constant("ram_mb", facts.ram_mb as i64);
+    scope.push_constant("virtualization", facts.virtualization.clone());
+    scope.push_constant("is_vm", facts.is_vm());
+    scope.push_constant("wifi", facts.wifi);
+    scope.push_constant("headless", facts.headless);
+
+    let result: Dynamic = engine
+        .eval_with_scope(&mut scope, script)
+        .map_err(|e| format!("role script failed: {}", e))?;
+
+    if result.is_unit() {
+        return Ok(Vec::new());
+    }
+    if result.is_string() {
+        return Ok(vec![result.into_string()?]);
+    }
+    let array: Array = result
+        .try_cast()
+        .ok_or("role script must evaluate to an array of tool names, a string or ()")?;
+    array
+        .into_iter()
+        .map(|item| {
+            item.into_string()
+                .map_err(|type_name| Box::from(format!("role script returned a {} instead of a tool name", type_name)))
+        })
+        .collect()
+}
@@ -0,0 +1,443 @@
+//! User settings read from `~/.config/cyber-toolkit/config.toml`.
+//!
+//! Every setting is optional; a missing file or missing keys fall back to defaults.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use cyber_toolkit::http::ClientSettings;
+use cyber_toolkit::paths;
+
+use crate::cache::Policy;
+use crate::i18n::t;
+
+/// Top-level structure of `config.toml`.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub sync: SyncSettings,
+    pub profile: ProfileSettings,
+    pub wordlists: WordlistSettings,
+    pub hooks: HookSettings,
+    pub pacman: PacmanSettings,
+    pub mirrors: MirrorSettings,
+    pub removal: RemovalSettings,
+    pub network: NetworkSettings,
+    pub auto_sync: AutoSyncSettings,
+    pub roles: RoleSettings,
+    pub notify: NotifySettings,
+    pub cache: CacheSettings,
+    /// `[aliases]` section: alias name to the roles it stands for, joined with `+` (see `aliases`).
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// `[network]` section: timeouts and keep-alive of the HTTP client.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Longest wait for a connection to the repository or GitHub.
+    pub connect_timeout_secs: u64,
+    /// Longest time fetching a role file, metadata or API response may take.
+    pub request_timeout_secs: u64,
+    /// Longest wait for the next data of a large download (wordlists); the download itself may take longer.
+    pub read_timeout_secs: u64,
+    /// TCP keep-alive interval, and how long idle connections are kept for reuse.
+    pub keepalive_secs: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        let defaults = ClientSettings::default();
+        NetworkSettings {
+            connect_timeout_secs: defaults.connect_timeout.as_secs(),
+            request_timeout_secs: defaults.request_timeout.as_secs(),
+            read_timeout_secs: defaults.read_timeout.as_secs(),
+            keepalive_secs: defaults.keepalive.as_secs(),
+        }
+    }
+}
+
+impl NetworkSettings {
+    /// Returns these settings for `http::configure`.
+    pub fn client_settings(&self) -> ClientSettings {
+        ClientSettings {
+            connect_timeout: Duration::from_secs(self.connect_timeout_secs),
+            request_timeout: Duration::from_secs(self.request_timeout_secs),
+            read_timeout: Duration::from_secs(self.read_timeout_secs),
+            keepalive: Duration::from_secs(self.keepalive_secs),
+        }
+    }
+}
+
+/// `[roles]` section: roles that need more than the role name to be removed.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct RoleSettings {
+    /// Roles that are only removed with `--unpin` (see `pins`).
+    pub pinned: Vec<String>,
+}
+
+/// `[auto_sync]` section: when `auto-sync` holds back.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct AutoSyncSettings {
+    /// Skip while the machine runs on battery.
+    pub skip_on_battery: bool,
+    /// Skip while the network connection is metered (e.g. a phone hotspot).
+    pub skip_on_metered: bool,
+}
+
+impl Default for AutoSyncSettings {
+    fn default() -> Self {
+        AutoSyncSettings { skip_on_battery: true, skip_on_metered: true }
+    }
+}
+
+/// `[cache]` section: what `cache prune` keeps. 0 means no limit.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct CacheSettings {
+    /// Prune entries that weren't refreshed for this many days.
+    pub max_age_days: u64,
+    /// Then prune the oldest entries until the cache is at most this large.
+    pub max_size_mb: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        CacheSettings { max_age_days: 30, max_size_mb: 0 }
+    }
+}
+
+impl CacheSettings {
+    /// Returns these settings for `cache::to_prune`.
+    pub fn policy(&self) -> Policy {
+        Policy {
+            max_age: (self.max_age_days > 0).then(|| Duration::from_secs(self.max_age_days * 24 * 60 * 60)),
+            max_size: (self.max_size_mb > 0).then_some(self.max_size_mb * 1024 * 1024),
+        }
+    }
+}
+
+/// `[notify]` section: where the summary of a run that changes the machine is sent (see `notify`).
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct NotifySettings {
+    /// URL the summary is POSTed to as JSON.
+    pub webhook: Option<String>,
+    /// Addresses the summary is mailed to, with the JSON attached.
+    pub email: Vec<String>,
+    /// Sender of the mail (default: `cyber-toolkit@<hostname>`).
+    pub from: Option<String>,
+    /// Relay to send the mail through with curl (`smtp://relay:25`, `smtps://relay:465`); without
+    /// one, it goes to the local `sendmail`.
+    pub smtp_url: Option<String>,
+    /// User to log in to the relay as; the password comes from `CYBER_TOOLKIT_SMTP_PASSWORD`.
+    pub smtp_user: Option<String>,
+    /// Refuse to send when the relay doesn't offer STARTTLS, instead of sending unencrypted.
+    pub require_tls: bool,
+    /// Only send summaries of failed runs.
+    pub failures_only: bool,
+}
+
+/// `[removal]` section: how native packages are uninstalled, and when uninstalling needs an
+/// explicit confirmation (or `--force`).
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct RemovalSettings {
+    /// Ask before uninstalling more than this many packages at once.
+    pub confirm_above: usize,
+    /// Package name patterns (`*` matches anything) that are never uninstalled without confirmation.
+    pub protected: Vec<String>,
+    /// How the native packages of removed roles are uninstalled.
+    pub strategy: RemovalStrategy,
+    /// `[removal.roles]`: strategies for single roles, overriding `strategy`.
+    pub roles: BTreeMap<String, RemovalStrategy>,
+}
+
+/// How pacman uninstalls the native packages of removed roles. Ordered from the most to the
+/// least conservative.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RemovalStrategy {
+    /// `pacman -R`: only the tools themselves; their dependencies stay installed.
+    Conservative,
+    /// `pacman -Runs`: the tools and the dependencies nothing else needs.
+    #[default]
+    Unneeded,
+    /// `pacman -Rcns`: the tools, the packages depending on them, and the dependencies nothing
+    /// else needs.
+    Cascade,
+}
+
+impl RemovalStrategy {
+    pub fn name(self) -> &'static str {
+        match self {
+            RemovalStrategy::Conservative => "conservative",
+            RemovalStrategy::Unneeded => "unneeded",
+            RemovalStrategy::Cascade => "cascade",
+        }
+    }
+
+    /// The pacman operation, as `run_pacman_command` takes it.
+    pub fn operation(self) -> &'static str {
+        match self {
+            RemovalStrategy::Conservative => "R",
+            RemovalStrategy::Unneeded => "Runs",
+            RemovalStrategy::Cascade => "Rcns",
+        }
+    }
+}
+
+impl RemovalSettings {
+    /// The strategy for removing `roles` together: the most conservative of their strategies, so
+    /// no role's packages are removed more eagerly than configured for it.
+    pub fn strategy_for(&self, roles: &[String]) -> RemovalStrategy {
+        roles.iter().map(|role| self.roles.get(role).copied().unwrap_or(self.strategy)).min().unwrap_or(self.strategy)
+    }
+}
+
+impl Default for RemovalSettings {
+    fn default() -> Self {
+        RemovalSettings {
+            confirm_above: 25,
+            protected: ["base", "base-devel", "linux", "linux-*", "glibc", "systemd", "pacman", "sudo", "polkit"]
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+            strategy: RemovalStrategy::default(),
+            roles: BTreeMap::new(),
+        }
+    }
+}
+
+/// `[mirrors]` section: refreshing the pacman mirrorlist with `reflector` before large installs.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct MirrorSettings {
+    /// Rank mirrors before installs that download more than `threshold_mb`.
+    pub rank_before_install: bool,
+    /// Countries passed to `reflector --country` (e.g. `["Germany", "Poland"]`). Empty means all.
+    pub countries: Vec<String>,
+    /// Minimum download size in MiB that triggers ranking.
+    pub threshold_mb: u64,
+    /// Skip ranking if the mirrorlist was updated less than this many days ago.
+    pub max_age_days: u64,
+}
+
+impl Default for MirrorSettings {
+    fn default() -> Self {
+        MirrorSettings { rank_before_install: false, countries: Vec::new(), threshold_mb: 1024, max_age_days: 7 }
+    }
+}
+
+/// `[pacman]` section: how native packages are installed.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct PacmanSettings {
+    /// AUR helper (e.g. `paru` or `yay`) used instead of `pkexec pacman`, so role files can
+    /// list AUR packages. The helper runs unprivileged and elevates by itself.
+    pub aur_helper: Option<String>,
+    /// Number of packages pacman downloads in parallel (`ParallelDownloads`).
+    pub parallel_downloads: Option<u32>,
+    /// Download rate cap in curl's `--limit-rate` syntax, e.g. `2M`. Downloads become sequential.
+    pub bandwidth_limit: Option<String>,
+    /// Abort a pacman or AUR helper run after this many minutes (e.g. a hung post-install script).
+    pub timeout_minutes: Option<u64>,
+}
+
+/// `[hooks]` section: when data refresh hooks from role metadata run.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct HookSettings {
+    /// Run the configured roles' hooks after every successful add/sync.
+    pub run_on_sync: bool,
+}
+
+/// `[profile]` section: describes the kind of machine the toolkit runs on.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct ProfileSettings {
+    /// Skip GUI tools (`@gui` entries). Detected automatically when unset.
+    pub headless: Option<bool>,
+    /// Always run in user mode, as with `--user` (for machines where users lack root).
+    pub user_mode: bool,
+}
+
+/// `[sync]` section: where `cyber-toolkit sync` pushes to and pulls from.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct SyncSettings {
+    /// Git remote URL for the configuration directory's repository.
+    pub remote: Option<String>,
+    /// Branch used on the remote.
+    pub branch: String,
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        SyncSettings { remote: None, branch: "main".to_string() }
+    }
+}
+
+/// `[wordlists]` section: where wordlist sets are stored.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct WordlistSettings {
+    /// Download directory. Defaults to `~/.local/share/wordlists`.
+    pub dir: Option<PathBuf>,
+    /// Symlink installed sets into `/usr/share/wordlists` (requires `pkexec`).
+    pub link_system: bool,
+}
+
+impl Default for WordlistSettings {
+    fn default() -> Self {
+        WordlistSettings { dir: None, link_system: cfg!(unix) }
+    }
+}
+
+impl WordlistSettings {
+    /// Returns the configured download directory, or the default one.
+    pub fn dir(&self) -> Result<PathBuf, std::io::Error> {
+        match &self.dir {
+            Some(dir) => Ok(dir.clone()),
+            None => dirs::data_dir()
+                .map(|dir| dir.join("wordlists"))
+                .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, t!("data-dir-not-found"))),
+        }
+    }
+}
+
+/// Returns the path of the settings file, `~/.config/cyber-toolkit/config.toml`.
+pub fn settings_path() -> Result<PathBuf, std::io::Error> {
+    Ok(paths::config_dir()?.join("config.toml"))
+}
+
+/// Writes settings to `~/.config/cyber-toolkit/config.toml`, replacing the file.
+pub fn save(settings: &Settings) -> Result<(), Box<dyn std::error::Error>> {
+    write_atomically(&toml::to_string_pretty(settings)?)
+}
+
+/// Replaces `config.toml` through a temporary file, so an interrupted write never leaves half a file.
+fn write_atomically(content: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = settings_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("toml.tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Loads settings from `~/.config/cyber-toolkit/config.toml`, returning defaults if the file doesn't exist.
+pub fn load() -> Result<Settings, Box<dyn std::error::Error>> {
+    let path = settings_path()?;
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+    let content = fs::read_to_string(&path)?;
+    toml::from_str(&content).map_err(|e| Box::from(t!("settings-invalid", path = format!("{:?}", path), error = e.to_string())))
+}
+
+/// Reads `config.toml` as a plain TOML table, keeping only the keys set in the file.
+/// Returns an empty table if the file doesn't exist.
+pub fn load_table() -> Result<toml::Table, Box<dyn std::error::Error>> {
+    let path = settings_path()?;
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+    let content = fs::read_to_string(&path)?;
+    content.parse().map_err(|e: toml::de::Error| Box::from(t!("settings-toml-invalid", path = format!("{:?}", path), error = e.to_string())))
+}
+
+/// Checks that `table` is valid settings and writes it to `config.toml`.
+pub fn save_table(table: &toml::Table) -> Result<(), Box<dyn std::error::Error>> {
+    validate(table)?;
+    write_atomically(&toml::to_string_pretty(table)?)
+}
+
+/// Parses `table` as settings. Fails on values of the wrong type, but not on unknown keys;
+/// see `unknown_keys` for those.
+pub fn validate(table: &toml::Table) -> Result<Settings, Box<dyn std::error::Error>> {
+    Settings::deserialize(toml::Value::Table(table.clone())).map_err(|e| Box::from(e.to_string().trim().replace('\n', " ")))
+}
+
+/// Returns the dotted keys of `table` that aren't settings (e.g. typos), which serde would otherwise ignore.
+pub fn unknown_keys(table: &toml::Table) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let known = toml::Value::try_from(validate(table)?)?;
+    let mut unknown = Vec::new();
+    collect_unknown_keys(table, known.as_table().expect("settings serialize to a table"), "", &mut unknown);
+    Ok(unknown)
+}
+
+/// Adds the keys of `table` missing from `known` to `unknown`, recursing into sections.
+fn collect_unknown_keys(table: &toml::Table, known: &toml::Table, prefix: &str, unknown: &mut Vec<String>) {
+    for (key, value) in table {
+        let dotted = format!("{}{}", prefix, key);
+        match (value, known.get(key)) {
+            (toml::Value::Table(section), Some(toml::Value::Table(known_section))) => {
+                collect_unknown_keys(section, known_section, &format!("{}.", dotted), unknown)
+            }
+            (_, Some(_)) => {}
+            (_, None) => unknown.push(dotted),
+        }
+    }
+}
+
+/// Looks up a dotted key such as `pacman.aur_helper` in `table`.
+pub fn get_key<'a>(table: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let mut parts = key.split('.');
+    let mut value = table.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_table()?.get(part)?;
+    }
+    Some(value)
+}
+
+/// Sets a dotted key in `table`, creating the sections on the way. The result isn't validated.
+pub fn set_key(table: &mut toml::Table, key: &str, value: toml::Value) -> Result<(), Box<dyn std::error::Error>> {
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(Box::from(t!("settings-key-invalid", key = key)));
+    }
+    let (last, sections) = parts.split_last().expect("split always yields a part");
+    let mut current = table;
+    for section in sections {
+        let entry = current.entry(section.to_string()).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        current = entry.as_table_mut().ok_or_else(|| t!("settings-not-a-section", section = *section))?;
+    }
+    current.insert(last.to_string(), value);
+    Ok(())
+}
+
+/// Removes a dotted key from `table`, dropping sections left empty. Returns whether the key was set.
+pub fn unset_key(table: &mut toml::Table, key: &str) -> bool {
+    match key.split_once('.') {
+        None => table.remove(key).is_some(),
+        Some((section, rest)) => {
+            let Some(toml::Value::Table(inner)) = table.get_mut(section) else {
+                return false;
+            };
+            let removed = unset_key(inner, rest);
+            if inner.is_empty() {
+                table.remove(section);
+            }
+            removed
+        }
+    }
+}
+
+/// Parses a value given on the command line as TOML (`true`, `4`, `["Germany", "Poland"]`),
+/// falling back to a plain string (`paru`, `2M`).
+pub fn parse_value(raw: &str) -> toml::Value {
+    format!("value = {}", raw)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
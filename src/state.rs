@@ -0,0 +1,122 @@
+//! Local state kept in the state directory (`~/.local/state/cyber-toolkit`, see `paths`):
+//! the time of the last successful sync and a history of add/remove operations (both in the
+//! state database, see `state_db`), and the native packages skipped in user mode. Cached copies
+//! of fetched role files go to the cache directory.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Utc};
+use cyber_toolkit::{http, paths};
+
+use crate::i18n::t;
+use crate::state_db;
+
+/// Returns the toolkit's state directory, `$XDG_STATE_HOME/cyber-toolkit`.
+pub fn state_dir() -> Result<PathBuf, io::Error> {
+    paths::state_dir()
+}
+
+/// Returns the directory holding cached role files, `$XDG_CACHE_HOME/cyber-toolkit`.
+pub fn cache_dir() -> Result<PathBuf, io::Error> {
+    paths::cache_dir()
+}
+
+/// Stores the raw content of a fetched role file in the cache, overwriting any previous copy.
+/// The copy is replaced atomically, since read-only commands may read it during an install.
+pub fn write_cached_role(role_file_name: &str, content: &str) -> Result<(), io::Error> {
+    let dir = cache_dir()?;
+    fs::create_dir_all(&dir)?;
+    // Role names map to flat file names inside the cache directory
+    let path = dir.join(role_file_name.replace('/', "_"));
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&temporary, content)?;
+    fs::rename(&temporary, path)
+}
+
+/// Returns the cached content of a role file, if it was fetched before.
+pub fn read_cached_role(role_file_name: &str) -> Result<Option<String>, io::Error> {
+    let path = cache_dir()?.join(role_file_name.replace('/', "_"));
+    if !path.exists() {
+        return Ok(None);
+    }
+    fs::read_to_string(path).map(Some)
+}
+
+/// Falls back to the cached copy `cache_name` if `fetched` failed because the repository
+/// rate-limits us (see `http::RateLimited`). The rate limit is reported once per run, not for
+/// every file read from the cache.
+pub fn or_cached_when_rate_limited(
+    fetched: Result<String, Box<dyn std::error::Error>>,
+    cache_name: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    static REPORTED: AtomicBool = AtomicBool::new(false);
+    match fetched {
+        Err(e) if http::is_rate_limited(e.as_ref()) => match read_cached_role(cache_name)? {
+            Some(cached) => {
+                if !REPORTED.swap(true, Ordering::Relaxed) {
+                    eprintln!("{}", t!("rate-limited-using-cache", error = e.to_string()));
+                }
+                Ok(cached)
+            }
+            None => Err(e),
+        },
+        result => result,
+    }
+}
+
+/// Returns the total size in bytes of all files in the cache directory (0 if it doesn't exist).
+pub fn cache_size() -> Result<u64, io::Error> {
+    let dir = cache_dir()?;
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut total = 0;
+    for entry in fs::read_dir(dir)? {
+        let metadata = entry?.metadata()?;
+        if metadata.is_file() {
+            total += metadata.len();
+        }
+    }
+    Ok(total)
+}
+
+/// Records the current time as the last successful sync.
+pub fn record_sync() -> Result<(), Box<dyn std::error::Error>> {
+    state_db::write_document("last_sync", Some(&Utc::now().to_rfc3339()))
+}
+
+/// Reads the time of the last successful sync, if one was ever recorded.
+pub fn last_sync() -> Result<Option<DateTime<Utc>>, Box<dyn std::error::Error>> {
+    Ok(state_db::read_document("last_sync")?
+        .and_then(|content| DateTime::parse_from_rfc3339(content.trim()).ok())
+        .map(|time| time.with_timezone(&Utc)))
+}
+
+/// Appends an entry for `operation` on `roles` to the history.
+pub fn append_history(operation: &str, roles: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    state_db::append_history(&Utc::now().to_rfc3339(), operation, roles)
+}
+
+/// Records the native packages that the last add/sync in user mode (`--user`) skipped,
+/// one per line in `skipped_native` in the state directory. An empty list removes the file.
+pub fn write_skipped_native(tools: &[String]) -> Result<(), io::Error> {
+    let path = state_dir()?.join("skipped_native");
+    if tools.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    fs::create_dir_all(state_dir()?)?;
+    fs::write(path, tools.join("\n") + "\n")
+}
+
+/// Counts history entries per operation (e.g. `add`, `remove`).
+pub fn history_counts() -> Result<BTreeMap<String, usize>, Box<dyn std::error::Error>> {
+    state_db::history_counts()
+}
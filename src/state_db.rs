@@ -0,0 +1,218 @@
+//! The state database, `~/.local/state/cyber-toolkit/state.db` (SQLite).
+//!
+//! The toolkit's own bookkeeping lives here instead of in a set of separate files, so every
+//! change is transactional: a crash or a concurrent run never leaves half-written state behind.
+//!
+//! - `documents`: named state documents with the content the former state files had, e.g. the
+//!   TOML of `roles.lock` or the JSON of `expiry.json` (see `DOCUMENTS`).
+//! - `history`: one row per add/remove operation.
+//!
+//! The schema is versioned with `PRAGMA user_version` and upgraded by `MIGRATIONS` on open.
+//! `export` and `import` move the whole state in and out as JSON, e.g. to inspect it or to
+//! carry it to another machine.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::t;
+use crate::state;
+
+/// Schema migrations, applied in order. `PRAGMA user_version` counts the applied ones.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE documents (name TEXT PRIMARY KEY, content TEXT NOT NULL);
+     CREATE TABLE history (id INTEGER PRIMARY KEY AUTOINCREMENT, time TEXT NOT NULL, operation TEXT NOT NULL, roles TEXT NOT NULL);",
+];
+
+/// The state documents. Earlier versions kept each of them as a file of the same name in the
+/// state directory; the first open imports those files and removes them.
+pub const DOCUMENTS: &[&str] =
+    &["last_sync", "roles.lock", "last-failures.json", "expiry.json", "projects.json", "dotfiles.json", "hardened.json", "hooks.json"];
+
+/// An entry of the `history` table.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    /// RFC 3339 timestamp.
+    pub time: String,
+    /// `add`, `remove` or `timeout`.
+    pub operation: String,
+    pub roles: Vec<String>,
+}
+
+/// The whole state, as written by `export` and read by `import`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Export {
+    /// Schema version of the database the state was exported from.
+    pub schema_version: usize,
+    pub documents: BTreeMap<String, String>,
+    pub history: Vec<HistoryEntry>,
+}
+
+/// Returns the path of `state.db` in the state directory.
+pub fn db_path() -> Result<PathBuf, std::io::Error> {
+    Ok(state::state_dir()?.join("state.db"))
+}
+
+/// Opens the state database, creating it and applying pending migrations as needed.
+fn open() -> Result<Connection, Box<dyn std::error::Error>> {
+    fs::create_dir_all(state::state_dir()?)?;
+    let mut connection = Connection::open(db_path()?)?;
+    // Concurrent runs wait for each other's transactions instead of failing
+    connection.busy_timeout(Duration::from_secs(10))?;
+    migrate(&mut connection)?;
+    Ok(connection)
+}
+
+/// Applies the migrations the database hasn't seen yet, each in its own transaction.
+fn migrate(connection: &mut Connection) -> Result<(), Box<dyn std::error::Error>> {
+    let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version == MIGRATIONS.len() {
+        return Ok(());
+    }
+    let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    // Another run may have migrated the database in the meantime
+    let version: usize = transaction.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        return Err(Box::from(t!("state-db-too-new", path = format!("{:?}", db_path()?), version = version, known = MIGRATIONS.len())));
+    }
+    if version == MIGRATIONS.len() {
+        return Ok(());
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", index + 1)?;
+    }
+    let imported = if version == 0 { import_state_files(&transaction)? } else { Vec::new() };
+    transaction.commit()?;
+    // Only remove the files once their content is safely in the database
+    for path in imported {
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("{}", t!("state-db-import-cleanup-failed", path = format!("{:?}", path), error = e.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Imports the state files of earlier versions as documents. Returns the imported files.
+fn import_state_files(transaction: &rusqlite::Transaction) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let dir = state::state_dir()?;
+    let mut imported = Vec::new();
+    for name in DOCUMENTS {
+        let path = dir.join(name);
+        if !path.exists() {
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        transaction.execute("INSERT OR REPLACE INTO documents (name, content) VALUES (?1, ?2)", params![name, content])?;
+        imported.push(path);
+    }
+
+    // The history file had one `<time>\t<operation>\t<comma-separated roles>` line per entry
+    let history_path = dir.join("history");
+    if history_path.exists() {
+        for line in fs::read_to_string(&history_path)?.lines() {
+            let mut fields = line.splitn(3, '\t');
+            if let (Some(time), Some(operation), roles) = (fields.next(), fields.next(), fields.next().unwrap_or_default()) {
+                transaction.execute("INSERT INTO history (time, operation, roles) VALUES (?1, ?2, ?3)", params![time, operation, roles])?;
+            }
+        }
+        imported.push(history_path);
+    }
+    Ok(imported)
+}
+
+/// Reads a state document, or `None` if there is none.
+pub fn read_document(name: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    Ok(open()?.query_row("SELECT content FROM documents WHERE name = ?1", [name], |row| row.get(0)).optional()?)
+}
+
+/// Replaces a state document. `None` deletes it.
+pub fn write_document(name: &str, content: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let connection = open()?;
+    match content {
+        Some(content) => connection.execute("INSERT OR REPLACE INTO documents (name, content) VALUES (?1, ?2)", params![name, content])?,
+        None => connection.execute("DELETE FROM documents WHERE name = ?1", [name])?,
+    };
+    Ok(())
+}
+
+/// Reads a state document, changes it with `change` and writes the result back (`None` deletes
+/// it), all in one transaction, so concurrent runs can't lose each other's changes.
+pub fn update_document(
+    name: &str,
+    change: impl FnOnce(Option<String>) -> Result<Option<String>, Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut connection = open()?;
+    let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let current: Option<String> =
+        transaction.query_row("SELECT content FROM documents WHERE name = ?1", [name], |row| row.get(0)).optional()?;
+    match change(current)? {
+        Some(content) => transaction.execute("INSERT OR REPLACE INTO documents (name, content) VALUES (?1, ?2)", params![name, content])?,
+        None => transaction.execute("DELETE FROM documents WHERE name = ?1", [name])?,
+    };
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Appends an entry to the history.
+pub fn append_history(time: &str, operation: &str, roles: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    open()?.execute("INSERT INTO history (time, operation, roles) VALUES (?1, ?2, ?3)", params![time, operation, roles.join(",")])?;
+    Ok(())
+}
+
+/// Counts history entries per operation.
+pub fn history_counts() -> Result<BTreeMap<String, usize>, Box<dyn std::error::Error>> {
+    let connection = open()?;
+    let mut statement = connection.prepare("SELECT operation, COUNT(*) FROM history GROUP BY operation")?;
+    let counts = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<_, _>>()?;
+    Ok(counts)
+}
+
+/// Returns the whole state.
+pub fn export() -> Result<Export, Box<dyn std::error::Error>> {
+    let connection = open()?;
+    let documents = connection
+        .prepare("SELECT name, content FROM documents ORDER BY name")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    let history = connection
+        .prepare("SELECT time, operation, roles FROM history ORDER BY id")?
+        .query_map([], |row| {
+            let roles: String = row.get(2)?;
+            Ok(HistoryEntry {
+                time: row.get(0)?,
+                operation: row.get(1)?,
+                roles: roles.split(',').filter(|role| !role.is_empty()).map(str::to_string).collect(),
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(Export { schema_version: MIGRATIONS.len(), documents, history })
+}
+
+/// Replaces the whole state with `state`, in one transaction.
+pub fn import(state: &Export) -> Result<(), Box<dyn std::error::Error>> {
+    if state.schema_version > MIGRATIONS.len() {
+        return Err(Box::from(t!("state-import-too-new", version = state.schema_version)));
+    }
+    if let Some(unknown) = state.documents.keys().find(|name| !DOCUMENTS.contains(&name.as_str())) {
+        return Err(Box::from(t!("state-import-unknown-document", name = format!("{:?}", unknown))));
+    }
+    let mut connection = open()?;
+    let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    transaction.execute_batch("DELETE FROM documents; DELETE FROM history;")?;
+    for (name, content) in &state.documents {
+        transaction.execute("INSERT INTO documents (name, content) VALUES (?1, ?2)", params![name, content])?;
+    }
+    for entry in &state.history {
+        transaction.execute(
+            "INSERT INTO history (time, operation, roles) VALUES (?1, ?2, ?3)",
+            params![entry.time, entry.operation, entry.roles.join(",")],
+        )?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
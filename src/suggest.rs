@@ -0,0 +1,61 @@
+//! "Did you mean" suggestions for role names that don't exist, built on `fuzzy`.
+//!
+//! A mistyped role is usually a typo of another role (`wirless`) or a subcommand taken as a
+//! role (`statz`, since any unknown word is a role to add). Both are suggested as complete
+//! invocations: the command line as given, with the unknown name replaced.
+
+use crate::fuzzy;
+use crate::i18n::t;
+
+/// Maximum number of suggestions per unknown name.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Returns the candidates closest to `name`, best first.
+pub fn nearest<'a>(name: &str, candidates: &'a [String]) -> Vec<&'a str> {
+    let mut matches: Vec<(fuzzy::Match, &str)> = candidates
+        .iter()
+        .filter(|candidate| candidate.as_str() != name)
+        .filter_map(|candidate| fuzzy::matches(name, candidate).map(|quality| (quality, candidate.as_str())))
+        .collect();
+    matches.sort();
+    matches.into_iter().take(MAX_SUGGESTIONS).map(|(_, candidate)| candidate).collect()
+}
+
+/// Returns the command line this process was started with, with `name` replaced by `replacement`,
+/// or `None` if `name` wasn't given on it (e.g. it came from an alias or a project file).
+fn invocation_with(name: &str, replacement: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.iter().any(|arg| arg == name) {
+        return None;
+    }
+    let words = args.iter().map(|arg| if arg == name { replacement } else { arg.as_str() });
+    Some(format!("cyber-toolkit {}", shlex::try_join(words).ok()?))
+}
+
+/// Describes the unknown role `name` with the closest of `roles` and `subcommands`, as lines
+/// to print.
+pub fn unknown_role(name: &str, roles: &[String], subcommands: &[String]) -> Vec<String> {
+    let mut lines = vec![t!("role-unknown", role = name)];
+    for role in nearest(name, roles) {
+        lines.push(match invocation_with(name, role) {
+            Some(invocation) => t!("suggest-role-invocation", role = role, invocation = invocation),
+            None => t!("suggest-role", role = role),
+        });
+    }
+    for subcommand in nearest(name, subcommands) {
+        lines.push(t!("suggest-subcommand", subcommand = subcommand));
+    }
+    lines
+}
+
+/// Describes `names`, none of which is configured, with the closest configured roles.
+pub fn not_configured(names: &[String], configured: &[String]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for name in names {
+        let similar = nearest(name, configured);
+        if !similar.is_empty() {
+            lines.push(t!("suggest-configured", role = name.as_str(), similar = similar.join(", ")));
+        }
+    }
+    lines
+}
@@ -0,0 +1,152 @@
+//! Synchronization of the configuration directory (`~/.config/cyber-toolkit`) across machines,
+//! using it as a git repository.
+//!
+//! Role selection (`roles.cnf`) and settings are versioned. Machine-local state lives in the
+//! cache and state directories; when `CYBER_TOOLKIT_HOME` puts everything into one directory,
+//! it is excluded through a `.gitignore`, which every sync completes with the entries newer
+//! versions added, untracking files an older version committed.
+
+use std::path::Path;
+use std::process::Command;
+
+use cyber_toolkit::paths;
+
+use crate::i18n::t;
+use crate::platform;
+use crate::settings::Settings;
+
+/// Entries kept out of the synced repository because they describe only the local machine.
+const LOCAL_ONLY_ENTRIES: &[&str] = &["cache/", "last_sync", "history", "venvs/", "go/", "go.lock", "skipped_native", "pacman.conf", "last-failures.json", "audit.jsonl", "*.bak", "*.tmp", "config.edit.toml", "projects.json", "expiry.json", "pacman-queries.json", "state.db*", "operation.lock", "state.lock", "roles.cnf.lock"];
+
+/// Name of the git remote managed by the toolkit.
+const REMOTE_NAME: &str = "origin";
+
+/// Runs a git command inside the given directory, returning an error if it fails.
+fn git(dir: &Path, args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let status = platform::plain_output(Command::new("git").arg("-C").arg(dir).args(args)).status()?;
+    if !status.success() {
+        return Err(Box::from(t!("git-failed-code", command = args.join(" "), code = format!("{:?}", status.code()))));
+    }
+    Ok(())
+}
+
+/// Runs a git command inside the given directory and returns its trimmed stdout.
+fn git_output(dir: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(Box::from(t!("git-failed", command = args.join(" "), error = String::from_utf8_lossy(&output.stderr).trim().to_string())));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Makes sure the configuration directory is a git repository whose `origin` points at the configured remote.
+///
+/// Initializes the repository on first use.
+fn prepare_repository(settings: &Settings) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+    let remote = settings.sync.remote.as_deref().ok_or_else(|| {
+        t!("sync-no-remote", path = format!("{:?}", crate::settings::settings_path().unwrap_or_default()))
+    })?;
+
+    let dir = paths::config_dir()?;
+    std::fs::create_dir_all(&dir)?;
+
+    if !dir.join(".git").exists() {
+        println!("{}", t!("sync-init", path = format!("{:?}", dir)));
+        git(&dir, &["init", "--quiet", "--initial-branch", &settings.sync.branch])?;
+    }
+
+    match git_output(&dir, &["remote", "get-url", REMOTE_NAME]) {
+        Ok(current) if current == remote => {}
+        Ok(_) => git(&dir, &["remote", "set-url", REMOTE_NAME, remote])?,
+        Err(_) => git(&dir, &["remote", "add", REMOTE_NAME, remote])?,
+    }
+    Ok(dir)
+}
+
+/// Adds the `LOCAL_ONLY_ENTRIES` missing from the `.gitignore` in `dir`, keeping the user's own
+/// entries, and removes files it ignores from the index (not from the disk), e.g. the state
+/// database committed before it was listed.
+///
+/// The removal is committed like any change. Machines pulling it untrack the same files before
+/// rebasing, so their copies stay too.
+fn ignore_local_only(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let path = dir.join(".gitignore");
+    let mut content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Box::new(e)),
+    };
+    let missing: Vec<&str> = LOCAL_ONLY_ENTRIES.iter().copied().filter(|entry| !content.lines().any(|line| line.trim() == *entry)).collect();
+    if !missing.is_empty() {
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&(missing.join("\n") + "\n"));
+        std::fs::write(&path, content)?;
+    }
+
+    let tracked = git_output(dir, &["ls-files", "-z", "--cached", "--ignored", "--exclude-standard"])?;
+    let tracked: Vec<&str> = tracked.split('\0').filter(|file| !file.is_empty()).collect();
+    if !tracked.is_empty() {
+        let mut args = vec!["rm", "--cached", "--quiet", "--"];
+        args.extend(tracked);
+        git(dir, &args)?;
+    }
+    Ok(())
+}
+
+/// Commits any local changes in the configuration directory. Returns `true` if a commit was created.
+fn commit_local_changes(dir: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    ignore_local_only(dir)?;
+    git(dir, &["add", "--all"])?;
+    if git_output(dir, &["status", "--porcelain"])?.is_empty() {
+        return Ok(false);
+    }
+    let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+    let message = format!("cyber-toolkit sync from {} at {}", hostname.trim(), chrono::Utc::now().to_rfc3339());
+    git(dir, &["commit", "--quiet", "-m", &message])?;
+    Ok(true)
+}
+
+/// Commits local changes and pushes them to the configured remote branch.
+pub fn push(settings: &Settings) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = prepare_repository(settings)?;
+    if commit_local_changes(&dir)? {
+        println!("{}", t!("sync-committed", path = format!("{:?}", dir)));
+    } else {
+        println!("{}", t!("sync-nothing-to-commit"));
+    }
+    let refspec = format!("HEAD:{}", settings.sync.branch);
+    git(&dir, &["push", REMOTE_NAME, &refspec])?;
+    println!("{}", t!("sync-pushed", remote = settings.sync.remote.as_deref().unwrap_or_default()));
+    Ok(())
+}
+
+/// Commits local changes, then pulls the configured remote branch with a rebase.
+///
+/// On a machine that has never synced, the remote files are taken as-is unless a local
+/// `roles.cnf` already exists, in which case the local files are committed on top.
+/// On conflicts git leaves the repository mid-rebase; the user resolves them in the configuration directory.
+pub fn pull(settings: &Settings) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = prepare_repository(settings)?;
+    let had_commits = git_output(&dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok();
+    git(&dir, &["fetch", "--quiet", REMOTE_NAME, &settings.sync.branch])?;
+
+    if had_commits {
+        commit_local_changes(&dir)?;
+        if let Err(e) = git(&dir, &["rebase", "--quiet", "FETCH_HEAD"]) {
+            return Err(Box::from(t!("sync-rebase-conflict", error = e.to_string(), path = format!("{:?}", dir))));
+        }
+    } else {
+        // First sync on this machine: adopt the remote history, then decide whose files win
+        let has_local_roles = dir.join("roles.cnf").exists();
+        git(&dir, &["reset", "--quiet", "FETCH_HEAD"])?;
+        if has_local_roles {
+            commit_local_changes(&dir)?; // Keep this machine's roles on top of the remote state
+        } else {
+            git(&dir, &["checkout", "--quiet", "--", "."])?;
+        }
+    }
+    println!("{}", t!("sync-pulled", remote = settings.sync.remote.as_deref().unwrap_or_default()));
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! The `UserInterface` of the command line: questions as dialoguer prompts and menus, progress
+//! as a single line that is rewritten in place.
+//!
+//! With `--plain`, progress isn't shown (the line-oriented messages around it remain).
+
+use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+
+use cyber_toolkit::UserInterface;
+use dialoguer::{Confirm, Input, MultiSelect, Select};
+
+use crate::platform;
+
+/// Asks in the terminal.
+#[derive(Default)]
+pub struct TerminalUi {
+    /// Task and percentage (or MiB, without a total) of the progress line last drawn.
+    progress: Mutex<Option<(String, u64)>>,
+}
+
+impl UserInterface for TerminalUi {
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    fn prompt(&self, message: &str, default: Option<&str>) -> Result<Option<String>, Box<dyn Error>> {
+        let mut input = Input::<String>::new().with_prompt(message).allow_empty(true);
+        if let Some(default) = default {
+            input = input.default(default.to_string());
+        }
+        Ok(Some(input.interact_text()?))
+    }
+
+    fn confirm(&self, message: &str, default: bool) -> Result<Option<bool>, Box<dyn Error>> {
+        Ok(Confirm::new().with_prompt(message).default(default).interact_opt()?)
+    }
+
+    fn select(&self, message: &str, items: &[String], default: usize) -> Result<Option<usize>, Box<dyn Error>> {
+        Ok(Select::new().with_prompt(message).items(items).default(default).interact_opt()?)
+    }
+
+    fn multi_select(&self, message: &str, items: &[String], checked: &[bool]) -> Result<Option<Vec<usize>>, Box<dyn Error>> {
+        Ok(MultiSelect::new().with_prompt(message).items(items).defaults(checked).interact_opt()?)
+    }
+
+    fn progress(&self, task: &str, done: u64, total: Option<u64>) {
+        if platform::is_plain() || !io::stdout().is_terminal() {
+            return;
+        }
+        let finished = total == Some(done);
+        let step = match total {
+            Some(total) if total > 0 => done * 100 / total,
+            _ => done >> 20,
+        };
+        let mut last = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+        // Only redraw when the shown number changes
+        if !finished && last.as_ref().is_some_and(|(last_task, last_step)| last_task == task && *last_step == step) {
+            return;
+        }
+        let line = match total {
+            Some(_) => format!("{}% {}", step, task),
+            None => format!("{} MiB {}", step, task),
+        };
+        print!("\r    {}{}", line, if finished { "\n" } else { "" });
+        let _ = io::stdout().flush();
+        *last = (!finished).then(|| (task.to_string(), step));
+    }
+}
@@ -0,0 +1,334 @@
+//! Hermetic test doubles for code built on the library (`test-support` feature): exercise add,
+//! remove and update logic without network access or pacman.
+//!
+//! - `MemoryFetcher` serves role files from memory; they can be changed between operations to
+//!   simulate upstream edits.
+//! - `FakeBackend` records the commands it receives and keeps a set of installed tools instead of
+//!   running a package manager. `fail_on` makes installs fail like a missing package would.
+//! - `TempConfig` is a `roles.cnf` in a fresh temporary directory, deleted on drop.
+//! - `fixtures` has a few small roles with overlapping tools.
+//! - `describe` and `FakeBackend::transcript` render outcomes as stable text, and
+//!   `assert_golden` compares such text with a file.
+//!
+//! Both doubles are handles to shared state: pass a clone to `RoleManagerBuilder` and inspect
+//! or change the original.
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+//! use cyber_toolkit::test_support::{fixtures, FakeBackend, TempConfig};
+//! use cyber_toolkit::RoleManager;
+//!
+//! let config = TempConfig::new()?;
+//! let backend = FakeBackend::new();
+//! let manager = RoleManager::builder()
+//!     .fetcher(fixtures::fetcher())
+//!     .backend(backend.clone())
+//!     .config_path(config.path())
+//!     .build()?;
+//! manager.add(&["blue".to_string(), "red".to_string()]).await?;
+//! manager.remove(&["red".to_string()]).await?;
+//! assert_eq!(
+//!     backend.transcript(),
+//!     "install metasploit nmap sqlmap volatility3 wireshark-cli\nremove metasploit sqlmap\n"
+//! );
+//! assert_eq!(manager.list()?, ["blue"]);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! # })
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use async_trait::async_trait;
+
+use crate::backend::{Backend, OutputSink, OutputStream, PackageOperation};
+use crate::config::CONFIG_FILE_NAME;
+use crate::fetcher::RoleFetcher;
+use crate::manager::ChangeReport;
+use crate::roles;
+
+/// Environment variable that makes `assert_golden` write the actual text instead of comparing.
+pub const UPDATE_GOLDEN_VAR: &str = "CYBER_TOOLKIT_UPDATE_GOLDEN";
+
+/// Serves role files from memory. Unknown roles fail to fetch, like a 404 from the repository.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFetcher {
+    roles: Arc<RwLock<BTreeMap<String, String>>>,
+}
+
+impl MemoryFetcher {
+    pub fn new() -> Self {
+        MemoryFetcher::default()
+    }
+
+    /// Adds a role file with `content`.
+    pub fn with_role(self, name: &str, content: &str) -> Self {
+        self.set_role(name, content);
+        self
+    }
+
+    /// Adds or replaces a role file, e.g. to simulate an upstream change between operations.
+    pub fn set_role(&self, name: &str, content: &str) {
+        self.roles.write().unwrap_or_else(|e| e.into_inner()).insert(roles::canonical_name(name), content.to_string());
+    }
+
+    /// Removes a role file, so fetching it fails.
+    pub fn remove_role(&self, name: &str) {
+        self.roles.write().unwrap_or_else(|e| e.into_inner()).remove(&roles::canonical_name(name));
+    }
+}
+
+#[async_trait]
+impl RoleFetcher for MemoryFetcher {
+    async fn fetch(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let roles = self.roles.read().unwrap_or_else(|e| e.into_inner());
+        roles.get(&roles::canonical_name(name)).cloned().ok_or_else(|| Box::from(format!("role {} doesn't exist", name.trim())))
+    }
+
+    fn location(&self, name: &str) -> String {
+        format!("memory:{}", name.trim())
+    }
+}
+
+/// A command `FakeBackend` received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendCommand {
+    pub operation: PackageOperation,
+    pub tools: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    installed: BTreeSet<String>,
+    failing: BTreeSet<String>,
+    failing_removals: BTreeSet<String>,
+    commands: Vec<BackendCommand>,
+}
+
+/// Records install and remove commands and tracks the installed tools in memory.
+///
+/// Installs and removals are transactions like pacman's: if any tool is set to fail (`fail_on`,
+/// `fail_removal_on`), the command fails and nothing is installed or removed. Failed commands
+/// are recorded too.
+#[derive(Debug, Clone, Default)]
+pub struct FakeBackend {
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl FakeBackend {
+    pub fn new() -> Self {
+        FakeBackend::default()
+    }
+
+    /// Starts with these tools installed.
+    pub fn with_installed(self, tools: &[&str]) -> Self {
+        self.lock().installed.extend(tools.iter().map(|tool| tool.to_string()));
+        self
+    }
+
+    /// Makes installing `tool` fail with pacman's "target not found" error.
+    pub fn fail_on(self, tool: &str) -> Self {
+        self.lock().failing.insert(tool.to_string());
+        self
+    }
+
+    /// Makes removing `tool` fail like pacman does when another package still requires it.
+    pub fn fail_removal_on(self, tool: &str) -> Self {
+        self.lock().failing_removals.insert(tool.to_string());
+        self
+    }
+
+    /// The commands received so far, oldest first.
+    pub fn commands(&self) -> Vec<BackendCommand> {
+        self.lock().commands.clone()
+    }
+
+    /// The tools installed now, sorted.
+    pub fn installed_tools(&self) -> Vec<String> {
+        self.lock().installed.iter().cloned().collect()
+    }
+
+    /// The commands received so far, one per line: `install nmap sqlmap`, `remove sqlmap`.
+    pub fn transcript(&self) -> String {
+        self.lock()
+            .commands
+            .iter()
+            .map(|command| {
+                let operation = match command.operation {
+                    PackageOperation::Install => "install",
+                    PackageOperation::Remove => "remove",
+                };
+                format!("{} {}\n", operation, command.tools.join(" "))
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Runs `operation`, passing the lines pacman would print to `output`.
+    fn run(&self, operation: PackageOperation, tools: &[String], output: Option<OutputSink<'_>>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut state = self.lock();
+        state.commands.push(BackendCommand { operation, tools: tools.to_vec() });
+        let emit = |stream, line: String| {
+            if let Some(output) = output {
+                output(stream, &line);
+            }
+        };
+        match operation {
+            PackageOperation::Install => {
+                let missing: Vec<&String> = tools.iter().filter(|tool| state.failing.contains(*tool)).collect();
+                if !missing.is_empty() {
+                    for tool in &missing {
+                        emit(OutputStream::Stderr, format!("error: target not found: {}", tool));
+                    }
+                    return Err(Box::from(format!("pacman -S --needed failed. Exit code: Some(1) (target not found: {:?})", missing)));
+                }
+                for (index, tool) in tools.iter().enumerate() {
+                    emit(OutputStream::Stdout, format!("({}/{}) installing {}", index + 1, tools.len(), tool));
+                }
+                state.installed.extend(tools.iter().cloned());
+            }
+            PackageOperation::Remove => {
+                let required: Vec<&String> = tools.iter().filter(|tool| state.failing_removals.contains(*tool)).collect();
+                if !required.is_empty() {
+                    emit(OutputStream::Stderr, "error: failed to prepare transaction (could not satisfy dependencies)".to_string());
+                    for tool in &required {
+                        emit(OutputStream::Stderr, format!(":: removing {} breaks dependency '{}' required by some-package", tool, tool));
+                    }
+                    return Err(Box::from(format!("pacman -Rns failed. Exit code: Some(1) (required: {:?})", required)));
+                }
+                for (index, tool) in tools.iter().enumerate() {
+                    emit(OutputStream::Stdout, format!("({}/{}) removing {}", index + 1, tools.len(), tool));
+                }
+                state.installed.retain(|tool| !tools.contains(tool));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Backend for FakeBackend {
+    async fn install(&self, tools: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        self.run(PackageOperation::Install, tools, None)
+    }
+
+    async fn remove(&self, tools: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        self.run(PackageOperation::Remove, tools, None)
+    }
+
+    async fn installed(&self, tools: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let state = self.lock();
+        Ok(tools.iter().filter(|tool| state.installed.contains(*tool)).cloned().collect())
+    }
+
+    async fn install_with_output(&self, tools: &[String], output: OutputSink<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        self.run(PackageOperation::Install, tools, Some(output))
+    }
+
+    async fn remove_with_output(&self, tools: &[String], output: OutputSink<'_>) -> Result<(), Box<dyn std::error::Error>> {
+        self.run(PackageOperation::Remove, tools, Some(output))
+    }
+}
+
+/// A `roles.cnf` in a new temporary directory, which is deleted with everything in it on drop.
+#[derive(Debug)]
+pub struct TempConfig {
+    dir: PathBuf,
+}
+
+impl TempConfig {
+    /// Creates the directory; the roles file itself doesn't exist until it is written.
+    pub fn new() -> Result<Self, io::Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!("cyber-toolkit-test-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?; // Left over from an earlier process with the same id
+        }
+        fs::create_dir_all(&dir)?;
+        Ok(TempConfig { dir })
+    }
+
+    /// Creates the roles file with these roles configured.
+    pub fn with_roles(roles: &[&str]) -> Result<Self, io::Error> {
+        let config = TempConfig::new()?;
+        fs::write(config.path(), roles.iter().map(|role| format!("{}\n", role)).collect::<String>())?;
+        Ok(config)
+    }
+
+    /// Path of the roles file, for `RoleManagerBuilder::config_path`.
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(CONFIG_FILE_NAME)
+    }
+}
+
+impl Drop for TempConfig {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Small roles for tests. `nmap` is shared by `blue` and `red`, `sqlmap` by `red` and `web`.
+pub mod fixtures {
+    use super::MemoryFetcher;
+
+    pub const BLUE: &str = "wireshark-cli\nnmap\nvolatility3\n";
+    pub const RED: &str = "nmap\nmetasploit\nsqlmap\n";
+    pub const WEB: &str = "# Web application testing\nsqlmap\nburpsuite\nffuf\n";
+
+    /// A fetcher serving `blue`, `red` and `web`.
+    pub fn fetcher() -> MemoryFetcher {
+        MemoryFetcher::new().with_role("blue", BLUE).with_role("red", RED).with_role("web", WEB)
+    }
+}
+
+/// Renders a `ChangeReport` as stable text for golden files, one field per line.
+pub fn describe(report: &ChangeReport) -> String {
+    let mut text = String::new();
+    for (label, items) in [
+        ("roles added", &report.roles_added),
+        ("roles removed", &report.roles_removed),
+        ("tools installed", &report.tools_installed),
+        ("tools removed", &report.tools_removed),
+    ] {
+        text.push_str(&format!("{}: {}\n", label, items.join(" ")));
+    }
+    for failure in &report.failed {
+        text.push_str(&format!("failed: {}: {}\n", failure.role, failure.error));
+    }
+    if report.dry_run {
+        text.push_str("dry run\n");
+    }
+    text
+}
+
+/// Compares `actual` with the golden file at `path`, panicking with both texts if they differ.
+/// With `CYBER_TOOLKIT_UPDATE_GOLDEN=1` set, (re)writes the file with `actual` instead.
+pub fn assert_golden(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_GOLDEN_VAR).is_some_and(|value| value == "1") {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap_or_else(|e| panic!("could not create {}: {}", parent.display(), e));
+        }
+        fs::write(path, actual).unwrap_or_else(|e| panic!("could not write golden file {}: {}", path.display(), e));
+        return;
+    }
+    let expected = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("could not read golden file {} ({}); run with {}=1 to create it", path.display(), e, UPDATE_GOLDEN_VAR));
+    assert!(
+        expected == actual,
+        "{} doesn't match (run with {}=1 to update it)\n--- expected\n{}--- actual\n{}",
+        path.display(),
+        UPDATE_GOLDEN_VAR,
+        expected,
+        actual
+    );
+}
@@ -0,0 +1,67 @@
+//! Interaction with the person running an operation.
+//!
+//! Questions (a line of text, yes/no, a choice from a menu) and progress go through a
+//! `UserInterface`, so the caller decides how they are presented: the command-line interface
+//! asks in the terminal, an embedder or a TUI can supply its own implementation, and
+//! `NonInteractive` answers without asking (e.g. for `--yes`, or when there is no terminal).
+//!
+//! Every question returns `Ok(None)` when it wasn't answered: the person cancelled it, or the
+//! interface can't ask. Callers treat that as "no" and usually explain how to proceed without a
+//! question, e.g. with `--yes`.
+
+use std::error::Error;
+
+/// Asks questions and shows progress.
+pub trait UserInterface {
+    /// Whether questions reach a person. Tools run by the caller (e.g. pacman) may then ask their
+    /// own questions; otherwise they must be told not to.
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    /// Asks for a line of text, offering `default`.
+    fn prompt(&self, message: &str, default: Option<&str>) -> Result<Option<String>, Box<dyn Error>>;
+
+    /// Asks a yes/no question, offering `default`.
+    fn confirm(&self, message: &str, default: bool) -> Result<Option<bool>, Box<dyn Error>>;
+
+    /// Asks for one of `items`, offering the one at `default`. Returns its index.
+    fn select(&self, message: &str, items: &[String], default: usize) -> Result<Option<usize>, Box<dyn Error>>;
+
+    /// Asks for any number of `items`, offering the ones `checked` is true for. Returns their indices.
+    fn multi_select(&self, message: &str, items: &[String], checked: &[bool]) -> Result<Option<Vec<usize>>, Box<dyn Error>>;
+
+    /// `done` units of the task `task` (e.g. bytes of a download) are finished, out of `total` if
+    /// known. Called repeatedly; `done == total` ends the task.
+    fn progress(&self, _task: &str, _done: u64, _total: Option<u64>) {}
+}
+
+/// Answers every question without asking.
+///
+/// With `assume_yes`, confirmations are answered with yes and all other questions with their
+/// default; without it, nothing is answered.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NonInteractive {
+    pub assume_yes: bool,
+}
+
+impl UserInterface for NonInteractive {
+    fn prompt(&self, _message: &str, default: Option<&str>) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(default.filter(|_| self.assume_yes).map(str::to_string))
+    }
+
+    fn confirm(&self, _message: &str, _default: bool) -> Result<Option<bool>, Box<dyn Error>> {
+        Ok(self.assume_yes.then_some(true))
+    }
+
+    fn select(&self, _message: &str, items: &[String], default: usize) -> Result<Option<usize>, Box<dyn Error>> {
+        Ok((self.assume_yes && default < items.len()).then_some(default))
+    }
+
+    fn multi_select(&self, _message: &str, items: &[String], checked: &[bool]) -> Result<Option<Vec<usize>>, Box<dyn Error>> {
+        if !self.assume_yes {
+            return Ok(None);
+        }
+        Ok(Some((0..items.len()).filter(|index| checked.get(*index).copied().unwrap_or(false)).collect()))
+    }
+}
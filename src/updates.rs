@@ -0,0 +1,92 @@
+//! Detecting upstream changes to configured roles (`cyber-toolkit check-updates`).
+//!
+//! The current role files are fetched and compared with the copies cached at the last sync, so
+//! tools added or removed upstream can be reviewed before syncing. The cache is not updated.
+
+use std::collections::BTreeSet;
+use std::process::Command;
+
+use serde::Serialize;
+
+use cyber_toolkit::RoleFetcher;
+
+use crate::i18n::t;
+use crate::{checksums, role_file, state};
+
+/// Upstream changes to one role since it was last synced.
+#[derive(Serialize, Debug)]
+pub struct RoleUpdate {
+    pub role: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// The role was never synced on this machine, so there is nothing to compare against.
+    pub not_cached: bool,
+}
+
+impl RoleUpdate {
+    pub fn has_changes(&self) -> bool {
+        !self.added.is_empty() || !self.removed.is_empty()
+    }
+}
+
+/// Fetches the raw content of a role file, verified against the repository's manifest (see
+/// `checksums`), without touching the cache.
+pub async fn fetch_role_content(role: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let content = checksums::repository_fetcher().fetch(role).await?;
+    role_file::check_format(&content)?;
+    Ok(content)
+}
+
+fn tool_set(content: &str, role: &str) -> BTreeSet<String> {
+    role_file::parse_tool_list(content, role).tools.into_iter().collect()
+}
+
+/// Compares each role's current upstream tool list with its cached copy.
+/// Roles that can't be fetched are reported and skipped.
+pub async fn check(roles: &[String]) -> Result<Vec<RoleUpdate>, Box<dyn std::error::Error>> {
+    let mut updates = Vec::new();
+    for role in roles {
+        let current = match fetch_role_content(role).await {
+            Ok(content) => tool_set(&content, role),
+            Err(e) => {
+                eprintln!("{}", t!("updates-check-failed", error = e.to_string()));
+                continue;
+            }
+        };
+        let update = match state::read_cached_role(role)? {
+            Some(cached) => {
+                let cached = tool_set(&cached, role);
+                RoleUpdate {
+                    role: role.clone(),
+                    added: current.difference(&cached).cloned().collect(),
+                    removed: cached.difference(&current).cloned().collect(),
+                    not_cached: false,
+                }
+            }
+            None => RoleUpdate { role: role.clone(), added: current.into_iter().collect(), removed: Vec::new(), not_cached: true },
+        };
+        updates.push(update);
+    }
+    Ok(updates)
+}
+
+/// Shows a desktop notification summarizing the changes with `notify-send`.
+pub fn notify(updates: &[RoleUpdate]) -> Result<(), Box<dyn std::error::Error>> {
+    let changed: Vec<&RoleUpdate> = updates.iter().filter(|update| update.has_changes()).collect();
+    if changed.is_empty() {
+        return Ok(());
+    }
+    let body: Vec<String> = changed
+        .iter()
+        .map(|update| format!("{}: +{} -{}", update.role, update.added.len(), update.removed.len()))
+        .collect();
+    let status = Command::new("notify-send")
+        .arg("--app-name=cyber-toolkit")
+        .arg(t!("updates-notification-title"))
+        .arg(body.join("\n"))
+        .status()?;
+    if !status.success() {
+        return Err(Box::from(t!("updates-notify-send-failed", code = format!("{:?}", status.code()))));
+    }
+    Ok(())
+}
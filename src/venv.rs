@@ -0,0 +1,139 @@
+//! Per-role Python virtualenvs for tools distributed through pip.
+//!
+//! A role's `[python]` metadata lists pip requirements. They are installed into
+//! `~/.local/state/cyber-toolkit/venvs/<role>`, isolated from the system Python and from other roles, and each entry
+//! point gets a shim in `~/.local/bin` that runs it from the virtualenv. The requirements are
+//! only reinstalled when they change. Removing the role deletes the virtualenv and its shims.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use cyber_toolkit::http;
+
+use crate::i18n::t;
+use crate::metadata::PythonMetadata;
+use crate::{platform, state};
+
+/// File inside a virtualenv recording the requirements it was built from.
+const REQUIREMENTS_FILE: &str = "cyber-toolkit-requirements.txt";
+
+/// Marker identifying shims generated for a role; the role name follows it.
+const SHIM_MARKER: &str = "# Generated by cyber-toolkit for the Python virtualenv of role ";
+
+fn venv_dir(role: &str) -> Result<PathBuf, io::Error> {
+    Ok(state::state_dir()?.join("venvs").join(role.replace('/', "_")))
+}
+
+fn shim_dir() -> Result<PathBuf, io::Error> {
+    dirs::home_dir()
+        .map(|home_dir| home_dir.join(".local").join("bin"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, t!("home-not-found")))
+}
+
+/// Lists the executable names in the virtualenv's `bin` directory.
+fn bin_entries(venv: &Path) -> BTreeSet<String> {
+    fs::read_dir(venv.join("bin"))
+        .map(|entries| entries.flatten().map(|entry| entry.file_name().to_string_lossy().into_owned()).collect())
+        .unwrap_or_default()
+}
+
+fn run(command: &mut Command, what: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let status = platform::plain_output(command).status()?;
+    if !status.success() {
+        return Err(Box::from(t!("step-failed", step = what, code = format!("{:?}", status.code()))));
+    }
+    Ok(())
+}
+
+/// Creates or updates the virtualenv of a role and its shims. Returns the exposed commands.
+///
+/// Does nothing if the requirements are unchanged since the last run.
+pub fn sync_role(role: &str, python: &PythonMetadata) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let venv = venv_dir(role)?;
+    let requirements = python.requirements.join("\n") + "\n";
+    let recorded = fs::read_to_string(venv.join(REQUIREMENTS_FILE)).ok();
+    if recorded.as_deref() == Some(requirements.as_str()) {
+        return Ok(Vec::new()); // Up to date
+    }
+
+    // Rebuild from scratch so removed requirements don't linger
+    remove_role(role)?;
+    fs::create_dir_all(venv.parent().unwrap_or(&venv))?;
+    println!("{}", t!("venv-creating", role = role, path = format!("{:?}", venv)));
+    run(Command::new("python3").arg("-m").arg("venv").arg(&venv), &t!("venv-create-step"))?;
+
+    let before = bin_entries(&venv);
+    fs::write(venv.join(REQUIREMENTS_FILE), &requirements)?;
+    let mut pip = Command::new(venv.join("bin").join("pip"));
+    pip.arg("install").arg("--requirement").arg(venv.join(REQUIREMENTS_FILE));
+    if http::is_offline() {
+        pip.arg("--no-index"); // Only local packages, e.g. from `find-links` in pip.conf
+    }
+    if platform::is_plain() {
+        pip.arg("--progress-bar=off").arg("--no-color");
+    }
+    run(&mut pip, "pip install").inspect_err(|_| {
+        let _ = fs::remove_file(venv.join(REQUIREMENTS_FILE)); // Retry on the next sync
+    })?;
+
+    let entry_points: Vec<String> = if python.entry_points.is_empty() {
+        bin_entries(&venv).difference(&before).cloned().collect()
+    } else {
+        python.entry_points.clone()
+    };
+
+    let shims = shim_dir()?;
+    fs::create_dir_all(&shims)?;
+    let mut exposed = Vec::new();
+    for entry_point in entry_points {
+        let target = venv.join("bin").join(&entry_point);
+        if !target.exists() {
+            eprintln!("{}", t!("venv-entry-point-missing", entry_point = entry_point.as_str(), role = role));
+            continue;
+        }
+        let shim_path = shims.join(&entry_point);
+        if shim_path.exists() && shim_owner(&shim_path).as_deref() != Some(role) {
+            eprintln!("{}", t!("venv-shim-exists", path = format!("{:?}", shim_path)));
+            continue;
+        }
+        let script = format!(
+            "#!/bin/sh\n{}{}\nexec {} \"$@\"\n",
+            SHIM_MARKER,
+            role,
+            shlex::try_quote(&target.to_string_lossy())?
+        );
+        fs::write(&shim_path, script)?;
+        platform::make_executable(&shim_path)?;
+        exposed.push(entry_point);
+    }
+    Ok(exposed)
+}
+
+/// Returns the role a shim was generated for, or `None` for other files.
+fn shim_owner(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix(SHIM_MARKER))
+        .map(|role| role.trim().to_string())
+}
+
+/// Deletes the virtualenv of a role and the shims pointing into it. Missing ones are not errors.
+pub fn remove_role(role: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if let Ok(entries) = fs::read_dir(shim_dir()?) {
+        for entry in entries.flatten() {
+            if shim_owner(&entry.path()).as_deref() == Some(role) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+    }
+    let venv = venv_dir(role)?;
+    if venv.exists() {
+        fs::remove_dir_all(&venv)?;
+        println!("{}", t!("venv-removed", role = role));
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! Native package management on Windows.
+//!
+//! Plain role entries are winget package identifiers (e.g. `WiresharkFoundation.Wireshark`), and
+//! `choco:<package>` entries are installed with chocolatey. winget asks for elevation per package
+//! when an installer needs it; chocolatey always runs elevated, with a single UAC prompt per
+//! operation.
+
+use crate::i18n::t;
+use crate::platform;
+use std::process::Command;
+
+/// Installs or upgrades winget packages one at a time, so one failure doesn't hide the others.
+pub fn winget_install(ids: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut failed = Vec::new();
+    for id in ids {
+        println!("{}", t!("winget-installing", package = id.as_str()));
+        let status = Command::new("winget")
+            .args(["install", "--id", id, "--exact", "--silent"])
+            .args(["--accept-package-agreements", "--accept-source-agreements"])
+            .args(if platform::is_plain() { &["--disable-interactivity"][..] } else { &[] })
+            .status()?;
+        if !status.success() {
+            eprintln!("{}", t!("winget-install-failed", package = id.as_str(), code = format!("{:?}", status.code())));
+            failed.push(id.clone());
+        }
+    }
+    if !failed.is_empty() {
+        return Err(Box::from(t!("winget-failed", packages = format!("{:?}", failed))));
+    }
+    Ok(())
+}
+
+/// Uninstalls winget packages. Packages that aren't installed are reported but not errors.
+pub fn winget_uninstall(ids: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    for id in ids {
+        println!("{}", t!("winget-uninstalling", package = id.as_str()));
+        let status = Command::new("winget").args(["uninstall", "--id", id, "--exact", "--silent"]).status()?;
+        if !status.success() {
+            eprintln!("{}", t!("winget-uninstall-failed", package = id.as_str(), code = format!("{:?}", status.code())));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the winget packages that are installed.
+pub fn winget_installed(ids: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut installed = Vec::new();
+    for id in ids {
+        let output = Command::new("winget")
+            .args(["list", "--id", id, "--exact", "--accept-source-agreements"])
+            .output()?;
+        if output.status.success() {
+            installed.push(id.clone());
+        }
+    }
+    Ok(installed)
+}
+
+/// Runs `choco <operation> -y <packages>` elevated.
+pub fn choco(operation: &str, packages: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if packages.is_empty() {
+        return Ok(());
+    }
+    let command_str = format!("choco {} -y {}", operation, packages.join(" "));
+    println!("{}", t!("executing-command", command = command_str.as_str()));
+    let status = platform::elevated_shell(&command_str).status()?;
+    if !status.success() {
+        return Err(Box::from(t!("choco-failed", operation = operation, code = format!("{:?}", status.code()))));
+    }
+    Ok(())
+}
+
+/// Returns the chocolatey packages that are installed.
+pub fn choco_installed(packages: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let output = Command::new("choco").args(["list", "--limit-output"]).output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let listed: Vec<&str> = stdout.lines().filter_map(|line| line.split('|').next()).collect();
+    Ok(packages
+        .iter()
+        .filter(|package| listed.iter().any(|name| name.eq_ignore_ascii_case(package)))
+        .cloned()
+        .collect())
+}
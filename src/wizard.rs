@@ -0,0 +1,85 @@
+//! Guided setup on the first run (`cyber-toolkit` without arguments and without a roles list).
+//!
+//! The wizard asks how tools should be installed (system packages or user mode), which AUR helper
+//! to use if any is installed, and which roles to add. It then shows the install plan and, once
+//! confirmed, saves the choices to `~/.config/cyber-toolkit/config.toml` and returns the roles to add.
+
+use std::io::{self, IsTerminal};
+
+use crate::i18n::t;
+use crate::{backends, discovery, settings, ui};
+
+/// AUR helpers offered by the wizard, if found on `PATH`.
+const AUR_HELPERS: &[&str] = &["paru", "yay", "pikaur"];
+
+/// Returns true if this is the first run (no roles list yet) in an interactive terminal.
+pub fn should_run() -> bool {
+    let configured = crate::config_store().map(|store| store.path().exists()).unwrap_or(true);
+    !configured && io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// Runs the wizard. Returns the roles to add, or `None` if the user cancelled.
+pub async fn run() -> Result<Option<Vec<String>>, Box<dyn std::error::Error>> {
+    println!("{}", t!("wizard-welcome"));
+    let mut settings = settings::load()?;
+
+    let modes = [
+        t!("wizard-mode-system"),
+        t!("wizard-mode-user"),
+    ];
+    let Some(mode) = ui().select(&t!("wizard-mode-prompt"), &modes, if settings.profile.user_mode { 1 } else { 0 })? else {
+        return Ok(None);
+    };
+    settings.profile.user_mode = mode == 1;
+
+    let helpers: Vec<&str> = AUR_HELPERS.iter().copied().filter(|helper| on_path(helper)).collect();
+    if !settings.profile.user_mode && !helpers.is_empty() {
+        let mut choices = vec![t!("wizard-helper-none")];
+        choices.extend(helpers.iter().map(|helper| helper.to_string()));
+        let Some(choice) = ui().select(&t!("wizard-helper-prompt"), &choices, 0)? else {
+            return Ok(None);
+        };
+        settings.pacman.aur_helper = (choice > 0).then(|| helpers[choice - 1].to_string());
+    }
+
+    println!("{}", t!("wizard-fetching"));
+    let available = discovery::fetch_role_index().await?;
+    if available.is_empty() {
+        return Err(Box::from(t!("wizard-no-roles")));
+    }
+    let Some(selected) = ui().multi_select(&t!("wizard-roles-prompt"), &available, &[])? else {
+        return Ok(None);
+    };
+    if selected.is_empty() {
+        println!("{}", t!("wizard-no-selection"));
+        return Ok(None);
+    }
+    let roles: Vec<String> = selected.into_iter().map(|index| available[index].clone()).collect();
+
+    println!("{}", t!("wizard-resolving"));
+    let tools = crate::fetch_tools_for_role_files(&roles).await?;
+    let grouped = backends::split_by_backend(&tools);
+    println!("{}", t!("wizard-plan-roles", roles = roles.join(", ")));
+    if settings.profile.user_mode {
+        println!("{}", t!("wizard-plan-native-skipped", count = grouped.native.len()));
+    } else {
+        let via = settings.pacman.aur_helper.as_deref().unwrap_or("pacman");
+        println!("{}", t!("wizard-plan-native", count = grouped.native.len(), via = via));
+    }
+    println!("{}", t!("wizard-plan-isolated", count = grouped.isolated.len()));
+    println!("{}", t!("wizard-plan-go", count = grouped.go.len()));
+
+    let confirmed = ui().confirm(&t!("wizard-confirm"), true)?;
+    if confirmed != Some(true) {
+        println!("{}", t!("nothing-changed"));
+        return Ok(None);
+    }
+    settings::save(&settings)?;
+    Ok(Some(roles))
+}
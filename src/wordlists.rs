@@ -0,0 +1,338 @@
+//! Wordlist management: role-like sets of wordlists downloaded into a local directory.
+//!
+//! Wordlist sets are defined by files under `wordlists/` in the repository (indexed by
+//! `wordlists/set_names`). Each non-comment line of a set reads `<file name> <url> [sha256]`.
+//! Files are downloaded to `<dir>/<set>/<file name>`, where `<dir>` comes from the `[wordlists]`
+//! settings, and installed sets are tracked in `~/.config/cyber-toolkit/wordlists.cnf`.
+//!
+//! Downloads resume from a `.part` file after interruptions, are verified against the
+//! checksum when one is given, and remember the server's ETag so `update` can detect changes.
+//! Optionally, each set is symlinked into `/usr/share/wordlists/<set>` where tools expect it.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use reqwest::header::{ETAG, IF_NONE_MATCH, RANGE};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+
+use cyber_toolkit::{http, paths};
+
+use crate::i18n::t;
+use crate::{platform, ui};
+
+/// Base URL from which wordlist set definitions are fetched.
+const WORDLISTS_RAW_URL: &str = "https://raw.githubusercontent.com/jakubGodula/cyber-toolkit/main/wordlists/";
+
+/// Name of the index file listing available wordlist sets.
+const SET_INDEX_FILE: &str = "set_names";
+
+/// System-wide wordlist location expected by many tools.
+pub const SYSTEM_WORDLISTS_DIR: &str = "/usr/share/wordlists";
+
+/// One downloadable file of a wordlist set.
+#[derive(Debug, Clone)]
+pub struct WordlistEntry {
+    pub file_name: String,
+    pub url: String,
+    /// Expected SHA-256 of the file (lowercase hex), if the set definition provides one.
+    pub sha256: Option<String>,
+}
+
+/// Result of checking or updating a single wordlist file.
+#[derive(Debug)]
+pub enum FileStatus {
+    Downloaded,
+    UpToDate,
+    UpdateAvailable,
+}
+
+/// Fetches the names of all available wordlist sets from the index.
+pub async fn fetch_set_names() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let content = fetch_text(&format!("{}{}", WORDLISTS_RAW_URL, SET_INDEX_FILE)).await?;
+    Ok(content.lines().map(str::trim).filter(|l| !l.is_empty()).map(String::from).collect())
+}
+
+/// Fetches and parses the definition of a wordlist set.
+pub async fn fetch_set(set: &str) -> Result<Vec<WordlistEntry>, Box<dyn std::error::Error>> {
+    let content = fetch_text(&format!("{}{}", WORDLISTS_RAW_URL, set)).await?;
+    parse_set(&content, set)
+}
+
+async fn fetch_text(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let response = http::get(url)?.send().await?;
+    http::check_response(&response)?;
+    if !response.status().is_success() {
+        return Err(Box::from(t!("wordlists-fetch-failed", url = url, status = response.status().to_string())));
+    }
+    Ok(response.text().await?)
+}
+
+/// Checks that `set` names a single directory below the wordlist directory: set names become
+/// paths there and in `/usr/share/wordlists`, and may come from a bundle's manifest.
+pub fn check_set_name(set: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if set.is_empty() || set.contains('/') || set.starts_with('.') {
+        return Err(Box::from(t!("wordlists-set-name-invalid", set = format!("{:?}", set))));
+    }
+    Ok(())
+}
+
+/// Parses a set definition: `<file name> <url> [sha256]` per line, `#` starts a comment line.
+fn parse_set(content: &str, set: &str) -> Result<Vec<WordlistEntry>, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (file_name, url, sha256) = match fields.as_slice() {
+            [file_name, url] => (file_name, url, None),
+            [file_name, url, sha256] => (file_name, url, Some(sha256.to_lowercase())),
+            _ => return Err(Box::from(t!("wordlists-line-invalid", line_number = index + 1, set = set, line = format!("{:?}", line)))),
+        };
+        if file_name.contains('/') || file_name.starts_with('.') {
+            return Err(Box::from(t!("wordlists-file-name-invalid", file = format!("{:?}", file_name), set = set)));
+        }
+        entries.push(WordlistEntry { file_name: file_name.to_string(), url: url.to_string(), sha256 });
+    }
+    Ok(entries)
+}
+
+fn installed_sets_path() -> Result<PathBuf, io::Error> {
+    Ok(paths::config_dir()?.join("wordlists.cnf"))
+}
+
+/// Reads the installed wordlist sets from `~/.config/cyber-toolkit/wordlists.cnf`.
+pub fn read_installed_sets() -> Result<Vec<String>, io::Error> {
+    let path = installed_sets_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    BufReader::new(fs::File::open(path)?)
+        .lines()
+        .map(|line| line.map(|s| s.trim().to_string()))
+        .filter(|res| !matches!(res, Ok(s) if s.is_empty()))
+        .collect()
+}
+
+/// Writes the installed wordlist sets to `~/.config/cyber-toolkit/wordlists.cnf`, one per line.
+pub fn write_installed_sets(sets: &[String]) -> Result<(), io::Error> {
+    let path = installed_sets_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::File::create(path)?;
+    for set in sets {
+        writeln!(file, "{}", set)?;
+    }
+    Ok(())
+}
+
+/// Path of the sidecar file remembering the ETag a wordlist file was downloaded with.
+fn etag_path(file: &Path) -> PathBuf {
+    let name = file.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    file.with_file_name(format!(".{}.etag", name))
+}
+
+/// Downloads every file of a set into `<dir>/<set>`, skipping files that are already present.
+pub async fn install_set(client: &reqwest::Client, set: &str, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = fetch_set(set).await?;
+    let set_dir = dir.join(set);
+    fs::create_dir_all(&set_dir)?;
+    for entry in &entries {
+        let destination = set_dir.join(&entry.file_name);
+        if destination.exists() {
+            println!("{}", t!("wordlists-file-present", file = entry.file_name.as_str()));
+            continue;
+        }
+        println!("{}", t!("wordlists-downloading", file = entry.file_name.as_str(), url = entry.url.as_str()));
+        download(client, entry, &destination).await?;
+    }
+    Ok(())
+}
+
+/// Checks each file of an installed set against the server (by ETag) and, unless `check_only`
+/// is set, re-downloads the ones that changed or are missing.
+pub async fn update_set(
+    client: &reqwest::Client,
+    set: &str,
+    dir: &Path,
+    check_only: bool,
+) -> Result<Vec<(String, FileStatus)>, Box<dyn std::error::Error>> {
+    let entries = fetch_set(set).await?;
+    let set_dir = dir.join(set);
+    fs::create_dir_all(&set_dir)?;
+    let mut results = Vec::new();
+
+    for entry in &entries {
+        let destination = set_dir.join(&entry.file_name);
+        let known_etag = fs::read_to_string(etag_path(&destination)).ok();
+        let changed = if !destination.exists() {
+            true
+        } else if let Some(etag) = &known_etag {
+            let request = client.head(&entry.url).timeout(http::settings().request_timeout);
+            let response = request.header(IF_NONE_MATCH, etag.trim()).send().await?;
+            response.status() != StatusCode::NOT_MODIFIED
+                && response.headers().get(ETAG).and_then(|v| v.to_str().ok()) != Some(etag.trim())
+        } else {
+            false // Without an ETag there's nothing to compare against; keep the existing file
+        };
+
+        let status = match (changed, check_only) {
+            (false, _) => FileStatus::UpToDate,
+            (true, true) => FileStatus::UpdateAvailable,
+            (true, false) => {
+                // Start over rather than resuming a partial download of the old version
+                let _ = fs::remove_file(part_path(&destination));
+                download(client, entry, &destination).await?;
+                FileStatus::Downloaded
+            }
+        };
+        results.push((entry.file_name.clone(), status));
+    }
+    Ok(results)
+}
+
+fn part_path(destination: &Path) -> PathBuf {
+    let name = destination.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    destination.with_file_name(format!("{}.part", name))
+}
+
+/// Downloads a file, resuming a previous partial download if a `.part` file exists.
+///
+/// The file only gets its final name after the checksum (if any) was verified.
+async fn download(client: &reqwest::Client, entry: &WordlistEntry, destination: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let part = part_path(destination);
+    let resume_from = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = client.get(&entry.url);
+    if resume_from > 0 {
+        println!("{}", t!("wordlists-resuming", bytes = resume_from));
+        request = request.header(RANGE, format!("bytes={}-", resume_from));
+    }
+    let read_timeout = http::read_timeout();
+    let mut response = tokio::time::timeout(read_timeout, request.send())
+        .await
+        .map_err(|_| t!("download-no-answer", url = entry.url.as_str(), seconds = read_timeout.as_secs()))??;
+
+    let status = response.status();
+    let mut file = if status == StatusCode::PARTIAL_CONTENT {
+        fs::OpenOptions::new().append(true).open(&part)?
+    } else if status == StatusCode::RANGE_NOT_SATISFIABLE && resume_from > 0 {
+        // The partial file already holds the whole content
+        fs::OpenOptions::new().append(true).open(&part)?
+    } else if status.is_success() {
+        fs::File::create(&part)? // Server ignored the range; start from scratch
+    } else {
+        return Err(Box::from(t!("wordlists-download-failed", url = entry.url.as_str(), status = status.to_string())));
+    };
+    let etag = response.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(String::from);
+
+    if status != StatusCode::RANGE_NOT_SATISFIABLE {
+        let mut done = if status == StatusCode::PARTIAL_CONTENT { resume_from } else { 0 };
+        let total = response.content_length().map(|length| done + length);
+        // No total timeout for large files, but a stalled server must not hang the download forever
+        while let Some(chunk) = tokio::time::timeout(read_timeout, response.chunk())
+            .await
+            .map_err(|_| t!("download-stalled", url = entry.url.as_str(), seconds = read_timeout.as_secs()))??
+        {
+            file.write_all(&chunk)?;
+            done += chunk.len() as u64;
+            ui().progress(&entry.file_name, done, total);
+        }
+        if total != Some(done) {
+            ui().progress(&entry.file_name, done, Some(done));
+        }
+    }
+    file.flush()?;
+    drop(file);
+
+    if let Some(expected) = &entry.sha256 {
+        let actual = sha256_file(&part)?;
+        if &actual != expected {
+            fs::remove_file(&part)?;
+            return Err(Box::from(t!("wordlists-checksum-mismatch", file = entry.file_name.as_str(), expected = expected.as_str(), actual = actual)));
+        }
+    }
+
+    fs::rename(&part, destination)?;
+    match etag {
+        Some(etag) => fs::write(etag_path(destination), etag)?,
+        None => {
+            let _ = fs::remove_file(etag_path(destination));
+        }
+    }
+    Ok(())
+}
+
+/// Computes the SHA-256 of a file as lowercase hex.
+fn sha256_file(path: &Path) -> Result<String, io::Error> {
+    let mut hasher = Sha256::new();
+    let mut file = fs::File::open(path)?;
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Deletes the downloaded files of a set.
+pub fn remove_set_files(set: &str, dir: &Path) -> Result<(), io::Error> {
+    let set_dir = dir.join(set);
+    if set_dir.exists() {
+        fs::remove_dir_all(set_dir)?;
+    }
+    Ok(())
+}
+
+/// Creates (`link = true`) or removes symlinks `/usr/share/wordlists/<set>` for the given sets
+/// in a single `pkexec` invocation. Only symlinks are ever removed. Fails without changing
+/// anything if a set name isn't valid (see `check_set_name`).
+pub fn update_system_links(sets: &[String], dir: &Path, link: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if sets.is_empty() {
+        return Ok(());
+    }
+    for set in sets {
+        check_set_name(set)?;
+    }
+    let mut commands = Vec::new();
+    if link {
+        commands.push(format!("mkdir -p {}", SYSTEM_WORDLISTS_DIR));
+    }
+    for set in sets {
+        let link_path = shlex::try_quote(&format!("{}/{}", SYSTEM_WORDLISTS_DIR, set))?.into_owned();
+        if link {
+            let target = shlex::try_quote(&dir.join(set).to_string_lossy())?.into_owned();
+            commands.push(format!("ln -sfn {} {}", target, link_path));
+        } else {
+            commands.push(format!("if [ -L {0} ]; then rm {0}; fi", link_path));
+        }
+    }
+    let command_str = commands.join(" && ");
+    println!("{}", t!("executing-command", command = command_str.as_str()));
+    let status = platform::elevated_shell(&command_str).status()?;
+    if !status.success() {
+        return Err(Box::from(t!("wordlists-links-failed", dir = SYSTEM_WORDLISTS_DIR, code = format!("{:?}", status.code()))));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_set_name;
+
+    #[test]
+    fn check_set_name_rejects_paths() {
+        assert!(check_set_name("rockyou").is_ok());
+        assert!(check_set_name("seclists-web").is_ok());
+        for set in ["", ".", "..", ".hidden", "../etc", "a/b", "/etc"] {
+            assert!(check_set_name(set).is_err(), "{:?} was accepted", set);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Adding, removing and setting roles end to end, with the in-memory doubles of `test_support`
+//! instead of the repository and pacman.
+
+use std::fs;
+
+use cyber_toolkit::config::{ConfigStore, ExternalChanges};
+use cyber_toolkit::test_support::{self, fixtures, FakeBackend, MemoryFetcher, TempConfig};
+use cyber_toolkit::{ChangeReport, RoleManager};
+
+fn roles(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+fn manager(config: &TempConfig, fetcher: MemoryFetcher, backend: &FakeBackend) -> RoleManager {
+    RoleManager::builder().fetcher(fetcher).backend(backend.clone()).config_path(config.path()).build().unwrap()
+}
+
+#[tokio::test]
+async fn add_installs_the_tools_and_configures_the_roles() {
+    let config = TempConfig::new().unwrap();
+    let backend = FakeBackend::new();
+    let manager = manager(&config, fixtures::fetcher(), &backend);
+
+    let report = manager.add(&roles(&["Blue", "web.txt"])).await.unwrap();
+
+    assert_eq!(report.roles_added, ["blue", "web"]);
+    assert_eq!(backend.transcript(), "install burpsuite ffuf nmap sqlmap volatility3 wireshark-cli\n");
+    assert_eq!(manager.list().unwrap(), ["blue", "web"]);
+}
+
+#[tokio::test]
+async fn add_skips_roles_that_cannot_be_fetched() {
+    let config = TempConfig::new().unwrap();
+    let backend = FakeBackend::new();
+    let manager = manager(&config, fixtures::fetcher(), &backend);
+
+    let report = manager.add(&roles(&["blue", "missing"])).await.unwrap();
+
+    assert_eq!(report.roles_added, ["blue"]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(manager.list().unwrap(), ["blue"]);
+}
+
+#[tokio::test]
+async fn remove_keeps_tools_shared_with_remaining_roles() {
+    let config = TempConfig::with_roles(&["blue", "red"]).unwrap();
+    let backend = FakeBackend::new().with_installed(&["metasploit", "nmap", "sqlmap", "volatility3", "wireshark-cli"]);
+    let manager = manager(&config, fixtures::fetcher(), &backend);
+
+    let report = manager.remove(&roles(&["red"])).await.unwrap();
+
+    // nmap is also a blue tool
+    assert_eq!(report.tools_removed, ["metasploit", "sqlmap"]);
+    assert_eq!(backend.transcript(), "remove metasploit sqlmap\n");
+    assert_eq!(backend.installed_tools(), ["nmap", "volatility3", "wireshark-cli"]);
+    assert_eq!(manager.list().unwrap(), ["blue"]);
+}
+
+#[tokio::test]
+async fn remove_refuses_when_a_kept_role_cannot_be_fetched() {
+    let config = TempConfig::with_roles(&["blue", "red"]).unwrap();
+    let backend = FakeBackend::new();
+    let fetcher = fixtures::fetcher();
+    let manager = manager(&config, fetcher.clone(), &backend);
+    fetcher.remove_role("blue");
+
+    assert!(manager.remove(&roles(&["red"])).await.is_err());
+    assert_eq!(backend.transcript(), "");
+    assert_eq!(manager.list().unwrap(), ["blue", "red"]);
+}
+
+#[tokio::test]
+async fn set_swaps_roles_as_one_net_change() {
+    let config = TempConfig::with_roles(&["red"]).unwrap();
+    let backend = FakeBackend::new().with_installed(&["metasploit", "nmap", "sqlmap"]);
+    let manager = manager(&config, fixtures::fetcher(), &backend);
+
+    let report = manager.set(&roles(&["blue", "web"])).await.unwrap();
+
+    assert_eq!(report.roles_added, ["blue", "web"]);
+    assert_eq!(report.roles_removed, ["red"]);
+    // nmap stays for blue and sqlmap for web, so neither is removed and reinstalled
+    assert_eq!(
+        backend.transcript(),
+        "install burpsuite ffuf nmap sqlmap volatility3 wireshark-cli\nremove metasploit\n"
+    );
+    assert_eq!(manager.list().unwrap(), ["blue", "web"]);
+}
+
+#[tokio::test]
+async fn set_removes_nothing_when_the_install_fails() {
+    let config = TempConfig::with_roles(&["red"]).unwrap();
+    let backend = FakeBackend::new().with_installed(&["metasploit", "nmap", "sqlmap"]).fail_on("volatility3");
+    let manager = manager(&config, fixtures::fetcher(), &backend);
+    let before = fs::read_to_string(config.path()).unwrap();
+
+    assert!(manager.set(&roles(&["blue"])).await.is_err());
+
+    assert_eq!(backend.transcript(), "install nmap volatility3 wireshark-cli\n");
+    assert_eq!(backend.installed_tools(), ["metasploit", "nmap", "sqlmap"]);
+    assert_eq!(fs::read_to_string(config.path()).unwrap(), before);
+}
+
+#[tokio::test]
+async fn set_keeps_the_configuration_when_the_removal_fails() {
+    let config = TempConfig::with_roles(&["red"]).unwrap();
+    fs::write(config.path(), "# lab machine\nred\n").unwrap();
+    let backend = FakeBackend::new().with_installed(&["metasploit", "nmap", "sqlmap"]).fail_removal_on("metasploit");
+    let manager = manager(&config, fixtures::fetcher(), &backend);
+
+    assert!(manager.set(&roles(&["blue"])).await.is_err());
+
+    assert_eq!(backend.transcript(), "install nmap volatility3 wireshark-cli\nremove metasploit sqlmap\n");
+    // The removed role's tools are all still there, so the file still lists it
+    assert_eq!(backend.installed_tools(), ["metasploit", "nmap", "sqlmap", "volatility3", "wireshark-cli"]);
+    assert_eq!(fs::read_to_string(config.path()).unwrap(), "# lab machine\nred\n");
+    assert_eq!(manager.list().unwrap(), ["red"]);
+}
+
+/// Compares the report and the backend's commands with `tests/golden/<name>.txt`.
+fn assert_golden(name: &str, report: &ChangeReport, backend: &FakeBackend) {
+    let actual = format!("{}--- commands\n{}", test_support::describe(report), backend.transcript());
+    test_support::assert_golden(format!("tests/golden/{}.txt", name), &actual);
+}
+
+#[tokio::test]
+async fn switching_roles_matches_the_golden_output() {
+    let config = TempConfig::with_roles(&["blue"]).unwrap();
+    let backend = FakeBackend::new().with_installed(&["nmap", "volatility3", "wireshark-cli"]);
+    let manager = manager(&config, fixtures::fetcher(), &backend);
+
+    let report = manager.set(&roles(&["red"])).await.unwrap();
+
+    assert_golden("switch-to-red", &report, &backend);
+}
+
+#[tokio::test]
+async fn adding_with_an_unavailable_role_matches_the_golden_output() {
+    let config = TempConfig::with_roles(&["red"]).unwrap();
+    let backend = FakeBackend::new().with_installed(&["metasploit", "nmap", "sqlmap"]);
+    let manager = manager(&config, fixtures::fetcher(), &backend);
+
+    let report = manager.add(&roles(&["web", "missing"])).await.unwrap();
+
+    assert_golden("add-with-missing-role", &report, &backend);
+}
+
+#[tokio::test]
+async fn a_dry_run_matches_the_golden_output() {
+    let config = TempConfig::with_roles(&["blue", "red"]).unwrap();
+    let backend = FakeBackend::new();
+    let manager = RoleManager::builder()
+        .fetcher(fixtures::fetcher())
+        .backend(backend.clone())
+        .config_path(config.path())
+        .dry_run(true)
+        .build()
+        .unwrap();
+
+    let report = manager.remove(&roles(&["red"])).await.unwrap();
+
+    assert_golden("dry-run-remove", &report, &backend);
+}
+
+#[tokio::test]
+async fn dry_run_changes_nothing() {
+    let config = TempConfig::with_roles(&["red"]).unwrap();
+    let backend = FakeBackend::new();
+    let manager = RoleManager::builder()
+        .fetcher(fixtures::fetcher())
+        .backend(backend.clone())
+        .config_path(config.path())
+        .dry_run(true)
+        .build()
+        .unwrap();
+
+    let report = manager.set(&roles(&["blue"])).await.unwrap();
+
+    assert!(report.dry_run);
+    assert_eq!(report.tools_removed, ["metasploit", "sqlmap"]);
+    assert_eq!(backend.transcript(), "");
+    assert_eq!(manager.list().unwrap(), ["red"]);
+}
+
+#[tokio::test]
+async fn roles_added_by_another_process_are_kept() {
+    let config = TempConfig::with_roles(&["blue"]).unwrap();
+    let backend = FakeBackend::new();
+    let manager = manager(&config, fixtures::fetcher(), &backend);
+    assert_eq!(manager.list().unwrap(), ["blue"]);
+
+    // E.g. a second terminal
+    fs::write(config.path(), "blue\n# Added by hand\nweb\n").unwrap();
+    manager.add(&roles(&["red"])).await.unwrap();
+
+    assert_eq!(fs::read_to_string(config.path()).unwrap(), "blue\n# Added by hand\nweb\nred\n");
+}
+
+#[test]
+fn concurrent_writes_are_merged() {
+    let config = TempConfig::with_roles(&["blue", "red"]).unwrap();
+    let first = ConfigStore::new(config.path());
+    let second = ConfigStore::new(config.path());
+    assert_eq!(first.read_roles().unwrap(), ["blue", "red"]);
+
+    assert_eq!(second.write_roles(&roles(&["blue", "red", "web"])).unwrap(), None);
+    let external = first.write_roles(&roles(&["blue"])).unwrap();
+
+    assert_eq!(external, Some(ExternalChanges { added: roles(&["web"]), removed: Vec::new() }));
+    assert_eq!(first.read_roles().unwrap(), ["blue", "web"]);
+}
+
+#[test]
+fn writes_without_concurrent_changes_replace_the_roles() {
+    let config = TempConfig::with_roles(&["blue"]).unwrap();
+    let store = ConfigStore::new(config.path());
+    assert_eq!(store.read_roles().unwrap(), ["blue"]);
+
+    assert_eq!(store.write_roles(&roles(&["red"])).unwrap(), None);
+    assert_eq!(store.read_roles().unwrap(), ["red"]);
+    assert!(store.backup_path().unwrap().is_some());
+}